@@ -0,0 +1,17 @@
+-- Failed-login tracking per username, and an audit log of login attempts.
+CREATE TABLE login_attempts (
+    username TEXT PRIMARY KEY NOT NULL,
+    failed_count INTEGER NOT NULL DEFAULT 0,
+    last_failed_at TEXT,
+    locked_until TEXT
+);
+
+CREATE TABLE login_events (
+    id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    username TEXT NOT NULL,
+    user_id INTEGER REFERENCES users(id),
+    succeeded BOOLEAN NOT NULL,
+    reason TEXT NOT NULL,
+    occurred_at TEXT NOT NULL
+);
+CREATE INDEX idx_login_events_user_id ON login_events(user_id);
@@ -0,0 +1,19 @@
+-- Per-user TOTP secret (encrypted with a key derived from the password) and one-time recovery codes.
+CREATE TABLE user_totp (
+    user_id INTEGER PRIMARY KEY NOT NULL REFERENCES users(id),
+    key_salt TEXT NOT NULL,
+    nonce TEXT NOT NULL,
+    ciphertext TEXT NOT NULL,
+    enabled BOOLEAN NOT NULL DEFAULT 0,
+    last_used_step BIGINT,
+    created_at TEXT NOT NULL
+);
+
+CREATE TABLE recovery_codes (
+    id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    user_id INTEGER NOT NULL REFERENCES users(id),
+    code_hash TEXT NOT NULL,
+    used_at TEXT
+);
+
+CREATE INDEX idx_recovery_codes_user_id ON recovery_codes(user_id);
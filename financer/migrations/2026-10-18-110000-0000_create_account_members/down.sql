@@ -0,0 +1,2 @@
+ALTER TABLE transactions DROP COLUMN created_by;
+DROP TABLE account_members;
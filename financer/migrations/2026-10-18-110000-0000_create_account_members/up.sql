@@ -0,0 +1,17 @@
+-- Lets several users share an account. Every existing account gets its creator as owner.
+CREATE TABLE account_members (
+    account_id INTEGER NOT NULL REFERENCES accounts(id),
+    user_id INTEGER NOT NULL REFERENCES users(id),
+    role TEXT NOT NULL CHECK (role IN ('owner', 'editor', 'viewer')),
+    PRIMARY KEY (account_id, user_id)
+);
+CREATE INDEX idx_account_members_user_id ON account_members(user_id);
+
+INSERT INTO account_members (account_id, user_id, role)
+SELECT id, user_id, 'owner' FROM accounts;
+
+-- Which member entered each transaction. Existing rows are credited to the account's creator.
+ALTER TABLE transactions ADD COLUMN created_by INTEGER REFERENCES users(id);
+
+UPDATE transactions
+SET created_by = (SELECT accounts.user_id FROM accounts WHERE accounts.id = transactions.user_account_id);
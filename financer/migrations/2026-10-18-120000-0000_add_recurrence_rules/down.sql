@@ -0,0 +1,2 @@
+ALTER TABLE recurring_transfers DROP COLUMN recurrence_rule;
+ALTER TABLE recurring_transactions DROP COLUMN recurrence_rule;
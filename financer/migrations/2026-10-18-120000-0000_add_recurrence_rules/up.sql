@@ -0,0 +1,19 @@
+-- RRULE-style schedules for recurring items. Existing rows get the rule equivalent to their frequency.
+ALTER TABLE recurring_transactions ADD COLUMN recurrence_rule TEXT NOT NULL DEFAULT 'FREQ=MONTHLY';
+ALTER TABLE recurring_transfers ADD COLUMN recurrence_rule TEXT NOT NULL DEFAULT 'FREQ=MONTHLY';
+
+UPDATE recurring_transactions
+SET recurrence_rule = CASE frequency
+    WHEN 'Daily' THEN 'FREQ=DAILY'
+    WHEN 'Weekly' THEN 'FREQ=WEEKLY'
+    WHEN 'Yearly' THEN 'FREQ=YEARLY'
+    ELSE 'FREQ=MONTHLY'
+END;
+
+UPDATE recurring_transfers
+SET recurrence_rule = CASE frequency
+    WHEN 'Daily' THEN 'FREQ=DAILY'
+    WHEN 'Weekly' THEN 'FREQ=WEEKLY'
+    WHEN 'Yearly' THEN 'FREQ=YEARLY'
+    ELSE 'FREQ=MONTHLY'
+END;
@@ -0,0 +1,6 @@
+ALTER TABLE recurring_transfers DROP COLUMN occurrences_run;
+ALTER TABLE recurring_transfers DROP COLUMN max_occurrences;
+ALTER TABLE recurring_transfers DROP COLUMN ends_at;
+ALTER TABLE recurring_transactions DROP COLUMN occurrences_run;
+ALTER TABLE recurring_transactions DROP COLUMN max_occurrences;
+ALTER TABLE recurring_transactions DROP COLUMN ends_at;
@@ -0,0 +1,8 @@
+-- Optional end date and occurrence limit for recurring items, and how many times each has run.
+ALTER TABLE recurring_transactions ADD COLUMN ends_at TEXT;
+ALTER TABLE recurring_transactions ADD COLUMN max_occurrences INTEGER CHECK (max_occurrences IS NULL OR max_occurrences > 0);
+ALTER TABLE recurring_transactions ADD COLUMN occurrences_run INTEGER NOT NULL DEFAULT 0;
+
+ALTER TABLE recurring_transfers ADD COLUMN ends_at TEXT;
+ALTER TABLE recurring_transfers ADD COLUMN max_occurrences INTEGER CHECK (max_occurrences IS NULL OR max_occurrences > 0);
+ALTER TABLE recurring_transfers ADD COLUMN occurrences_run INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,3 @@
+ALTER TABLE recurring_transfers DROP COLUMN requires_approval;
+ALTER TABLE recurring_transactions DROP COLUMN requires_approval;
+DROP TABLE recurring_runs;
@@ -0,0 +1,18 @@
+-- Log of every occurrence a recurring item has produced, so processing never posts one twice
+-- and single occurrences can be held for approval or skipped.
+CREATE TABLE recurring_runs (
+    id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    item_kind TEXT NOT NULL CHECK (item_kind IN ('transaction', 'transfer')),
+    item_id INTEGER NOT NULL,
+    scheduled_for TEXT NOT NULL,
+    status TEXT NOT NULL CHECK (status IN ('pending', 'posted', 'skipped')),
+    amount REAL NOT NULL,
+    decided_by INTEGER REFERENCES users(id),
+    decided_at TEXT,
+    UNIQUE (item_kind, item_id, scheduled_for)
+);
+CREATE INDEX idx_recurring_runs_status ON recurring_runs(status);
+
+-- Items with this set queue their occurrences as pending instead of posting them.
+ALTER TABLE recurring_transactions ADD COLUMN requires_approval BOOLEAN NOT NULL DEFAULT 0;
+ALTER TABLE recurring_transfers ADD COLUMN requires_approval BOOLEAN NOT NULL DEFAULT 0;
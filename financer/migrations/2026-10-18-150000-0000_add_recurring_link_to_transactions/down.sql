@@ -0,0 +1,3 @@
+DROP INDEX idx_transactions_recurring;
+ALTER TABLE transactions DROP COLUMN recurring_kind;
+ALTER TABLE transactions DROP COLUMN recurring_id;
@@ -0,0 +1,5 @@
+-- Which recurring item generated a transaction. The kind says whether `recurring_id` refers to
+-- recurring_transactions or recurring_transfers. Both stay set after the template is deleted.
+ALTER TABLE transactions ADD COLUMN recurring_id INTEGER;
+ALTER TABLE transactions ADD COLUMN recurring_kind TEXT CHECK (recurring_kind IS NULL OR recurring_kind IN ('transaction', 'transfer'));
+CREATE INDEX idx_transactions_recurring ON transactions(recurring_kind, recurring_id);
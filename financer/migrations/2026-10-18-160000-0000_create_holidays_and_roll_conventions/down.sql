@@ -0,0 +1,3 @@
+ALTER TABLE recurring_transfers DROP COLUMN roll_convention;
+ALTER TABLE recurring_transactions DROP COLUMN roll_convention;
+DROP TABLE holidays;
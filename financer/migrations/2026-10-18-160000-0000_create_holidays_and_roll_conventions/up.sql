@@ -0,0 +1,13 @@
+-- Per-user holiday calendars, and how each recurring item moves off weekends and holidays.
+CREATE TABLE holidays (
+    id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    user_id INTEGER NOT NULL REFERENCES users(id),
+    holiday_date TEXT NOT NULL,
+    name TEXT NOT NULL,
+    UNIQUE (user_id, holiday_date)
+);
+
+ALTER TABLE recurring_transactions ADD COLUMN roll_convention TEXT NOT NULL DEFAULT 'none'
+    CHECK (roll_convention IN ('none', 'following', 'preceding', 'modified_following'));
+ALTER TABLE recurring_transfers ADD COLUMN roll_convention TEXT NOT NULL DEFAULT 'none'
+    CHECK (roll_convention IN ('none', 'following', 'preceding', 'modified_following'));
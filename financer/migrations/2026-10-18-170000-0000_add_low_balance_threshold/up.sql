@@ -0,0 +1,2 @@
+-- Balance below which the dashboard forecast warns about an account.
+ALTER TABLE users ADD COLUMN low_balance_threshold REAL NOT NULL DEFAULT 0;
@@ -0,0 +1,2 @@
+ALTER TABLE budgets DROP COLUMN rollover_cap_cents;
+ALTER TABLE budgets DROP COLUMN rollover_mode;
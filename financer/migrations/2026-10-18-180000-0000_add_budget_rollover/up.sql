@@ -0,0 +1,4 @@
+-- How unspent (or overspent) money in one budget period carries into the next.
+ALTER TABLE budgets ADD COLUMN rollover_mode TEXT NOT NULL DEFAULT 'none'
+    CHECK (rollover_mode IN ('none', 'surplus', 'surplus_and_deficit', 'capped'));
+ALTER TABLE budgets ADD COLUMN rollover_cap_cents INTEGER CHECK (rollover_cap_cents IS NULL OR rollover_cap_cents >= 0);
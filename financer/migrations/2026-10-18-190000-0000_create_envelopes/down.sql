@@ -0,0 +1,2 @@
+DROP TABLE envelope_assignments;
+DROP TABLE envelopes;
@@ -0,0 +1,20 @@
+-- Zero-based envelope budgeting: category envelopes and the money assigned to them each month.
+CREATE TABLE envelopes (
+    id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    user_id INTEGER NOT NULL REFERENCES users(id),
+    category TEXT NOT NULL,
+    -- First day of the month from which the category's spending draws the envelope down.
+    starts_on TEXT NOT NULL,
+    UNIQUE (user_id, category)
+);
+
+-- Positive rows assign money from To Be Assigned, negative rows return it. A move between
+-- envelopes is a pair of rows in the same month.
+CREATE TABLE envelope_assignments (
+    id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    envelope_id INTEGER NOT NULL REFERENCES envelopes(id),
+    month TEXT NOT NULL,
+    amount_cents INTEGER NOT NULL,
+    created_at TEXT NOT NULL
+);
+CREATE INDEX idx_envelope_assignments_envelope ON envelope_assignments(envelope_id, month);
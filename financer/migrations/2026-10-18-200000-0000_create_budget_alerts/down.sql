@@ -0,0 +1,2 @@
+DROP TABLE notifications;
+ALTER TABLE budgets DROP COLUMN alert_thresholds;
@@ -0,0 +1,18 @@
+-- Per-budget alert thresholds, as percentages of the limit, and the notifications they raise.
+ALTER TABLE budgets ADD COLUMN alert_thresholds TEXT NOT NULL DEFAULT '80,100';
+
+CREATE TABLE notifications (
+    id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    user_id INTEGER NOT NULL REFERENCES users(id),
+    kind TEXT NOT NULL,
+    budget_id INTEGER REFERENCES budgets(id),
+    period_start TEXT,
+    threshold INTEGER,
+    message TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    is_read BOOLEAN NOT NULL DEFAULT 0,
+    dismissed BOOLEAN NOT NULL DEFAULT 0,
+    -- A threshold alerts once per budget period.
+    UNIQUE (budget_id, period_start, threshold)
+);
+CREATE INDEX idx_notifications_user ON notifications(user_id, dismissed);
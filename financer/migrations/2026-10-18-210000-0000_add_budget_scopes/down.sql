@@ -0,0 +1,6 @@
+DROP TABLE budget_accounts;
+DROP TABLE budget_categories;
+ALTER TABLE budgets DROP COLUMN ends_on;
+ALTER TABLE budgets DROP COLUMN starts_on;
+ALTER TABLE budgets DROP COLUMN include_subcategories;
+ALTER TABLE budgets DROP COLUMN name;
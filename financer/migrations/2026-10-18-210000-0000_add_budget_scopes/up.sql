@@ -0,0 +1,21 @@
+-- Budgets that cover more than one category, only some accounts, or their own date range.
+ALTER TABLE budgets ADD COLUMN name TEXT;
+-- Also count `Parent:Child` categories under each of the budget's categories.
+ALTER TABLE budgets ADD COLUMN include_subcategories BOOLEAN NOT NULL DEFAULT 0;
+-- A custom range, both days inclusive. When set the budget covers just these dates.
+ALTER TABLE budgets ADD COLUMN starts_on TEXT;
+ALTER TABLE budgets ADD COLUMN ends_on TEXT;
+
+-- Categories counted in addition to budgets.category.
+CREATE TABLE budget_categories (
+    budget_id INTEGER NOT NULL REFERENCES budgets(id),
+    category TEXT NOT NULL,
+    PRIMARY KEY (budget_id, category)
+);
+
+-- When a budget has rows here it only counts these accounts.
+CREATE TABLE budget_accounts (
+    budget_id INTEGER NOT NULL REFERENCES budgets(id),
+    account_id INTEGER NOT NULL REFERENCES accounts(id),
+    PRIMARY KEY (budget_id, account_id)
+);
@@ -0,0 +1,2 @@
+DROP TABLE budget_template_items;
+DROP TABLE budget_templates;
@@ -0,0 +1,21 @@
+-- Named sets of budgets a user can create again in one go.
+CREATE TABLE budget_templates (
+    id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    user_id INTEGER NOT NULL REFERENCES users(id),
+    name TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    UNIQUE (user_id, name)
+);
+
+CREATE TABLE budget_template_items (
+    id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    template_id INTEGER NOT NULL REFERENCES budget_templates(id),
+    name TEXT,
+    category TEXT NOT NULL,
+    -- JSON array of the other categories the budget covers.
+    extra_categories TEXT NOT NULL DEFAULT '[]',
+    limit_cents INTEGER NOT NULL,
+    period TEXT NOT NULL,
+    target_type TEXT NOT NULL
+);
+CREATE INDEX idx_budget_template_items_template ON budget_template_items(template_id);
@@ -0,0 +1,4 @@
+-- Marks both legs of a transfer between the user's own accounts, so reports can leave them
+-- out without relying on the category. Existing transfers are found by their category.
+ALTER TABLE transactions ADD COLUMN is_transfer BOOLEAN NOT NULL DEFAULT 0;
+UPDATE transactions SET is_transfer = 1 WHERE category = 'Transfer';
@@ -0,0 +1,2 @@
+DROP TABLE savings_goal_accounts;
+DROP TABLE savings_goals;
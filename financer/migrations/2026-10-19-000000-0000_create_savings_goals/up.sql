@@ -0,0 +1,19 @@
+-- Savings goals, tracked from the combined balance of their linked accounts.
+CREATE TABLE savings_goals (
+    id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    user_id INTEGER NOT NULL REFERENCES users(id),
+    name TEXT NOT NULL,
+    target_cents INTEGER NOT NULL CHECK (target_cents > 0),
+    -- YYYY-MM-DD.
+    target_date TEXT NOT NULL,
+    -- Expected annual return on the linked accounts, as a fraction (0.04 for 4%).
+    expected_rate REAL NOT NULL DEFAULT 0,
+    created_at TEXT NOT NULL,
+    UNIQUE (user_id, name)
+);
+
+CREATE TABLE savings_goal_accounts (
+    goal_id INTEGER NOT NULL REFERENCES savings_goals(id),
+    account_id INTEGER NOT NULL REFERENCES accounts(id),
+    PRIMARY KEY (goal_id, account_id)
+);
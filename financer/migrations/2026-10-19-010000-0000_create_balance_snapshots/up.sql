@@ -0,0 +1,8 @@
+-- End-of-day balance of each account, for the net worth history.
+CREATE TABLE balance_snapshots (
+    account_id INTEGER NOT NULL REFERENCES accounts(id),
+    -- YYYY-MM-DD.
+    day TEXT NOT NULL,
+    balance_cents INTEGER NOT NULL,
+    PRIMARY KEY (account_id, day)
+);
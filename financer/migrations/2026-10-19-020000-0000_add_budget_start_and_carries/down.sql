@@ -0,0 +1,2 @@
+DROP TABLE budget_carries;
+ALTER TABLE budgets DROP COLUMN created_at;
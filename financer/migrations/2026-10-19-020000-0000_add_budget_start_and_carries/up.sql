@@ -0,0 +1,14 @@
+-- When a budget was created, in local time. Rollover history starts from this period.
+-- Older budgets only have updated_at, which SQLite stamped in UTC.
+ALTER TABLE budgets ADD COLUMN created_at TEXT NOT NULL DEFAULT '';
+UPDATE budgets SET created_at = datetime(updated_at, 'localtime');
+
+-- What a budget carries into a period, saved once worked out so later periods can continue
+-- from it. Rows are dropped when the transactions or settings behind them change.
+CREATE TABLE budget_carries (
+    budget_id INTEGER NOT NULL REFERENCES budgets(id),
+    -- YYYY-MM-DD HH:MM:SS, the start of the period carried into.
+    period_start TEXT NOT NULL,
+    carried_cents BIGINT NOT NULL,
+    PRIMARY KEY (budget_id, period_start)
+);
@@ -2,9 +2,19 @@ use eframe::egui;
 use eframe::egui::Ui;
 use crate::db;
 use diesel::sqlite::SqliteConnection;
-use diesel::result::{Error, DatabaseErrorKind};
-use crate::models::{Account, Transaction, RecurringTransaction, RecurringTransfer};
-use crate::models::{Budget, Period};
+use crate::error::FinancerError;
+use crate::auth::{wipe_secret, AutoLockPolicy, LoginThrottlePolicy, PasswordPolicy, TotpSetupForm};
+use crate::recurrence::{self, RecurrenceRule};
+use crate::schedule::{self, Occurrence};
+use crate::totp;
+use crate::models::{Account, BalanceSnapshot, Transaction, RecurringTransaction, RecurringTransfer, LoginEvent};
+use crate::models::{AccountMember, AccountRole, Budget, BudgetTemplate, Holiday, Notification, Period, RecurringKind, RecurringRun, RollConvention, RolloverMode, TargetType};
+use crate::goals::GoalProgress;
+use crate::net_worth::{self, NetWorthPoint};
+use crate::reports::{self, CategoryTrend, IncomeStatement};
+use crate::budgeting::{self, BudgetDraft, BudgetScope, EnvelopeStatus, IncomeShare, MonthlySavings, PaceProjection, PaceStatus, PeriodPerformance};
+use crate::calendar::HolidayCalendar;
+use crate::forecast::{self, AccountForecast};
 use crate::finance_calculations::{
     real_rate,
     future_value,
@@ -19,8 +29,12 @@ use crate::finance_calculations::{
     compound_interest_future_value_with_contributions,
     ContributionFrequency,
     CompoundingFrequency,
+    simulate_debt_payoff,
+    Debt,
+    PayoffPlan,
+    PayoffStrategy,
 };
-use egui_plot::{Plot, Line, Text as PlotText};
+use egui_plot::{Bar, BarChart, HLine, Legend, Plot, Line, Text as PlotText};
 use std::collections::HashMap;
 use chrono::{NaiveDateTime,NaiveDate,Datelike};
 use eframe::egui::Color32;
@@ -32,6 +46,10 @@ use std::time::{Duration, Instant};
 const CASHFLOW_STATE_FILE: &str = "cashflow_state.json";
 const BOND_STATE_FILE: &str = "bond_state.json";
 const MORTGAGE_STATE_FILE: &str = "mortgage_state.json";
+/// How much history the forecast averages to estimate typical spending.
+const FORECAST_HISTORY_DAYS: i64 = 90;
+/// Months shown in the category trends report.
+const REPORT_TREND_MONTHS: usize = 12;
 
 const DEFAULT_CATEGORIES: &[&str] = &[
     "Food & Dining",
@@ -47,16 +65,155 @@ const DEFAULT_CATEGORIES: &[&str] = &[
     "Other",
 ];
 
+fn friendly_error(e: &FinancerError) -> String {
+    match e {
+        FinancerError::Validation(msg) => format!("{}.", msg),
+        FinancerError::NotFound(what) => format!("{} no longer exists. Try refreshing.", what),
+        FinancerError::PermissionDenied(_) => "You don't have access to that item.".to_string(),
+        FinancerError::Conflict(msg) => format!("{}.", msg),
+        FinancerError::InsufficientFunds { account, available, required } => format!(
+            "Insufficient funds in {}: ${:.2} available, ${:.2} needed.",
+            account, available, required
+        ),
+        FinancerError::Storage(err) => {
+            format!("Could not save your changes ({}). Please try again.", err)
+        }
+        FinancerError::CorruptData(msg) => {
+            format!("Some stored data is damaged and could not be read ({}).", msg)
+        }
+        FinancerError::LoginThrottled { retry_after_secs } => format!(
+            "Too many failed attempts. Try again in {} seconds.",
+            retry_after_secs
+        ),
+        FinancerError::TwoFactorRequired => {
+            "Enter the 6-digit code from your authenticator app, or a recovery code.".to_string()
+        }
+        FinancerError::Internal(msg) => format!("Something went wrong ({}). Please try again.", msg),
+    }
+}
+
+/// Paints a QR code as black squares on a white quiet zone.
+fn draw_qr_code(ui: &mut egui::Ui, width: usize, modules: &[bool]) {
+    const MODULE_PX: f32 = 4.0;
+    const QUIET_ZONE: usize = 4;
+    let side = (width + 2 * QUIET_ZONE) as f32 * MODULE_PX;
+    let (response, painter) = ui.allocate_painter(egui::vec2(side, side), egui::Sense::hover());
+    let origin = response.rect.min;
+
+    painter.rect_filled(response.rect, 0.0, egui::Color32::WHITE);
+    for (i, dark) in modules.iter().enumerate() {
+        if *dark {
+            let x = (i % width + QUIET_ZONE) as f32 * MODULE_PX;
+            let y = (i / width + QUIET_ZONE) as f32 * MODULE_PX;
+            let rect = egui::Rect::from_min_size(origin + egui::vec2(x, y), egui::vec2(MODULE_PX, MODULE_PX));
+            painter.rect_filled(rect, 0.0, egui::Color32::BLACK);
+        }
+    }
+}
+
+/// Schedule picker for recurring items: a preset menu plus the editable rule text, with a
+/// summary and the next few dates after `next_run_at` so users can check the rule.
+fn show_recurrence_picker(ui: &mut egui::Ui, rule_text: &mut String, next_run_at: &str, id_salt: &str) {
+    let parsed = RecurrenceRule::parse(rule_text);
+    ui.horizontal(|ui| {
+        ui.label("Repeats:");
+        let selected = parsed.as_ref().map(|r| r.describe()).unwrap_or_else(|_| "Custom".to_string());
+        egui::ComboBox::from_id_salt(id_salt)
+            .selected_text(selected)
+            .show_ui(ui, |ui| {
+                for (label, preset) in recurrence::presets() {
+                    let preset_text = preset.to_rule_string();
+                    if ui.selectable_label(*rule_text == preset_text, label).clicked() {
+                        *rule_text = preset_text;
+                    }
+                }
+            });
+    });
+    ui.horizontal(|ui| {
+        ui.label("Rule:");
+        ui.text_edit_singleline(rule_text);
+    });
+    match parsed {
+        Ok(rule) => {
+            if let Ok(start) = chrono::NaiveDateTime::parse_from_str(next_run_at, "%Y-%m-%d %H:%M:%S") {
+                let upcoming: Vec<String> = rule
+                    .occurrences_after(start, 3)
+                    .iter()
+                    .map(|d| d.format("%Y-%m-%d").to_string())
+                    .collect();
+                ui.weak(format!("{}; then {}", rule.describe(), upcoming.join(", ")));
+            }
+        }
+        Err(e) => {
+            ui.colored_label(egui::Color32::RED, friendly_error(&e));
+        }
+    }
+}
+
+fn show_roll_picker(ui: &mut egui::Ui, roll: &mut RollConvention, id_salt: &str) {
+    ui.horizontal(|ui| {
+        ui.label("On weekends and holidays:");
+        egui::ComboBox::from_id_salt(id_salt)
+            .selected_text(roll.label())
+            .show_ui(ui, |ui| {
+                for option in RollConvention::ALL {
+                    ui.selectable_value(roll, option, option.label());
+                }
+            });
+    });
+}
+
+/// Schedule part of a recurring item's list entry, e.g. "Monthly | 3 left | until 2026-06-30 | paused".
+fn schedule_status(rule_text: &str, frequency: &str, next_run_at: &str, ends_at: Option<&str>, remaining: Option<i32>, active: bool, needs_approval: bool) -> String {
+    let mut text = RecurrenceRule::parse(rule_text)
+        .map(|r| r.describe())
+        .unwrap_or_else(|_| frequency.to_string());
+    if let Some(left) = remaining {
+        text.push_str(&format!(" | {} left", left));
+    }
+    if let Some(end) = ends_at {
+        text.push_str(&format!(" | until {}", end.get(..10).unwrap_or(end)));
+    }
+    if needs_approval {
+        text.push_str(" | needs approval");
+    }
+    if !active {
+        let past_end = ends_at.is_some_and(|end| next_run_at > end);
+        let finished = remaining == Some(0) || past_end;
+        text.push_str(if finished { " | finished" } else { " | paused" });
+    }
+    text
+}
+
 pub enum AppState {
     Login,
     Register,
     Dashboard,
+    SecuritySettings,
+    /// Session paused after inactivity; the previous screen is kept in `screen_before_lock`.
+    Locked,
     Budgeting,
+    /// Budgeted against actual for every budget over recent periods.
+    BudgetHistory,
+    /// Saved budget sets and the income-split and average-spend planners.
+    BudgetTemplates,
+    /// Income, spending and the share of income kept, month by month.
+    SavingsRate,
     Transactions,
     Transfers,
+    /// Recurring items projected over the next 30/60/90 days.
+    Upcoming,
+    /// Zero-based envelope budgeting, alongside the limit-based budgets.
+    Envelopes,
+    /// Savings targets tracked from the balances of linked accounts.
+    SavingsGoals,
+    /// Monthly income statement and per-category trends.
+    Reports,
     CashflowTools,
     BondTools,
     MortgageTools,
+    /// Snowball, avalanche and custom-order plans for paying off debts.
+    DebtPlanner,
     SavingsCalculator,
 }
 
@@ -124,20 +281,146 @@ pub struct FinancerApp {
     email: String,
     message: String,
     confirm_delete_user: bool,
+    password_policy: PasswordPolicy,
+    throttle_policy: LoginThrottlePolicy,
+    login_events: Vec<LoginEvent>,
+    // Auto-lock fields
+    auto_lock_policy: AutoLockPolicy,
+    last_activity: Instant,
+    screen_before_lock: Option<AppState>,
+    unlock_password: String,
+    unlock_totp_code: String,
+    unlock_needs_code: bool,
+    unlock_message: String,
+    login_totp_code: String,
+    login_needs_code: bool,
+    // Two-factor settings fields
+    totp_enabled: bool,
+    totp_form: TotpSetupForm,
+    totp_recovery_remaining: i64,
+    // Security settings fields
+    settings_current_password: String,
+    settings_new_password: String,
+    settings_confirm_password: String,
+    settings_new_username: String,
+    settings_new_email: String,
     last_recurring_check: Option<Instant>,
     screen: AppState,
     conn: SqliteConnection,
     accounts_list: Vec<Account>,
+    account_roles: HashMap<i32, AccountRole>,
+    member_names: HashMap<i32, String>,
+    // Upcoming screen fields
+    upcoming_days: i64,
+    pending_runs: Vec<RecurringRun>,
+    /// Amounts edited in the approval queue, by run id, before confirming.
+    pending_run_amounts: HashMap<i32, f32>,
+    upcoming_selected_day: Option<chrono::NaiveDate>,
+    holidays_list: Vec<Holiday>,
+    /// Business-day calendars of the owners of the visible recurring items, by user id.
+    holiday_calendars: HashMap<i32, HolidayCalendar>,
+    new_holiday_date: String,
+    new_holiday_name: String,
+    holiday_file: String,
+    // Balance forecast fields
+    forecast_days: i64,
+    forecast_include_spending: bool,
+    low_balance_threshold: f32,
+    forecasts: Vec<AccountForecast>,
+    forecast_computed_at: Option<Instant>,
+    // Net worth fields
+    net_worth_snapshots: Vec<BalanceSnapshot>,
+    /// How many months of history the chart shows; 0 for all of it.
+    net_worth_months: u32,
+    net_worth_by_account: bool,
+    net_worth_loaded_at: Option<Instant>,
+    // Account sharing fields
+    sharing_account_id: Option<i32>,
+    sharing_members: Vec<(AccountMember, String)>,
+    share_username: String,
+    share_role: AccountRole,
     new_account_name: String,
     new_account_type: String,   
     new_account_balance: f32,
     budgets: Vec<Budget>,
     selected_budget_period: Period,
     budget_progress: HashMap<i32, (i64, i32)>,
+    /// Cents each budget carries into the period being viewed, by budget id.
+    budget_rollovers: HashMap<i32, i64>,
+    budget_scopes: HashMap<i32, BudgetScope>,
+    /// End-of-period projections for expense budgets in the running period, by budget id.
+    budget_paces: HashMap<i32, PaceProjection>,
+    /// How many periods back the budget history goes.
+    budget_history_periods: usize,
+    budget_history: Vec<(Budget, Vec<PeriodPerformance>)>,
+    // Budget template fields
+    template_income_months: u32,
+    /// Average monthly income over `template_income_months`, in cents.
+    template_income: i64,
+    template_shares: Vec<IncomeShare>,
+    template_spend_months: u32,
+    /// Drafts from average spending, with whether each is ticked.
+    template_spend_drafts: Vec<(BudgetDraft, bool)>,
+    budget_templates: Vec<BudgetTemplate>,
+    template_name: String,
+    /// How many months the savings-rate report covers.
+    savings_months: usize,
+    savings_report: Vec<MonthlySavings>,
+    // Reports fields
+    /// The first day of the month the reports cover.
+    report_month: NaiveDate,
+    income_statement: Option<IncomeStatement>,
+    category_trends: Vec<CategoryTrend>,
+    /// Category whose trend is charted.
+    trend_category: Option<String>,
+    // Savings goal fields
+    savings_goals: Vec<GoalProgress>,
+    goal_name: String,
+    goal_target: f32,
+    goal_date: String,
+    /// Expected annual return, in percent.
+    goal_rate_percent: f64,
+    goal_account_ids: Vec<i32>,
+    // Debt payoff planner fields
+    debts: Vec<Debt>,
+    debt_extra_payment: f64,
+    debt_strategy: PayoffStrategy,
+    /// Indexes into `debts`, first paid first, for `PayoffStrategy::Custom`.
+    debt_custom_order: Vec<usize>,
     editor_category: String,
     editor_limit_cents: i32,
     editor_period: Period,
     editor_target_is_expense: bool,
+    editor_rollover_mode: RolloverMode,
+    editor_rollover_cap_cents: i32,
+    editor_alert_thresholds: String,
+    editor_name: String,
+    /// Categories the budget covers besides `editor_category`.
+    editor_extra_categories: Vec<String>,
+    editor_include_subcategories: bool,
+    /// Empty means every account.
+    editor_account_ids: Vec<i32>,
+    editor_custom_dates: bool,
+    editor_starts_on: String,
+    editor_ends_on: String,
+    /// Shown under the transaction form when a new expense would exceed a budget, until the
+    /// user saves anyway or cancels.
+    tx_budget_warning: Option<String>,
+    // Notification fields
+    notifications: Vec<Notification>,
+    unread_notifications: i64,
+    notifications_open: bool,
+    // Envelope budgeting fields
+    /// First day of the month shown on the Envelopes screen.
+    envelope_month: NaiveDate,
+    envelope_statuses: Vec<EnvelopeStatus>,
+    /// `None` until the user creates an envelope.
+    available_to_assign: Option<i64>,
+    envelope_new_category: String,
+    envelope_assign_inputs: HashMap<i32, f32>,
+    envelope_move_from: i32,
+    envelope_move_to: i32,
+    envelope_move_amount: f32,
     current_editing: Option<i32>,
     period_offset: i32,
     editor_open: bool,
@@ -162,6 +445,8 @@ pub struct FinancerApp {
     // Transaction filter
     tx_filter_account_id: Option<i32>,
     tx_filter_category: Option<String>,
+    /// `Some(true)` shows only transactions generated by recurring items, `Some(false)` only the rest.
+    tx_filter_recurring: Option<bool>,
     tx_filter_start_date: String,
     tx_filter_end_date: String,
     // Transfer fields
@@ -182,7 +467,14 @@ pub struct FinancerApp {
     recurring_tx_custom_category: String,
     recurring_tx_show_category_input: bool,
     recurring_tx_next_run_at: String,
-    recurring_tx_frequency: Period,
+    recurring_tx_rule: String,
+    recurring_tx_has_end: bool,
+    recurring_tx_end_date: String,
+    recurring_tx_has_limit: bool,
+    recurring_tx_max_occurrences: i32,
+    recurring_tx_requires_approval: bool,
+    recurring_tx_rewrite_past: bool,
+    recurring_tx_roll: RollConvention,
 
     // Recurring transfers
     recurring_transfers_list: Vec<RecurringTransfer>,
@@ -191,7 +483,14 @@ pub struct FinancerApp {
     recurring_transfer_to_account_id: i32,
     recurring_transfer_amount: f32,
     recurring_transfer_next_run_at: String,
-    recurring_transfer_frequency: Period,
+    recurring_transfer_rule: String,
+    recurring_transfer_has_end: bool,
+    recurring_transfer_end_date: String,
+    recurring_transfer_has_limit: bool,
+    recurring_transfer_max_occurrences: i32,
+    recurring_transfer_requires_approval: bool,
+    recurring_transfer_rewrite_past: bool,
+    recurring_transfer_roll: RollConvention,
     // Cash-flow tools state (dynamic scenarios, dated entries)
     cf_nominal_rate_percent: f32,
     cf_inflation_rate_percent: f32,
@@ -436,27 +735,121 @@ impl FinancerApp {
     }
 
     pub fn new(conn: SqliteConnection) -> Self {
-        Self {
+        let mut app = Self {
             username: String::new(),
             password: String::new(),
             email: String::new(),
             message: String::new(),
             confirm_delete_user: false,
+            password_policy: PasswordPolicy::from_env(),
+            throttle_policy: LoginThrottlePolicy::from_env(),
+            login_events: Vec::new(),
+            auto_lock_policy: AutoLockPolicy::from_env(),
+            last_activity: Instant::now(),
+            screen_before_lock: None,
+            unlock_password: String::new(),
+            unlock_totp_code: String::new(),
+            unlock_needs_code: false,
+            unlock_message: String::new(),
+            login_totp_code: String::new(),
+            login_needs_code: false,
+            totp_enabled: false,
+            totp_form: TotpSetupForm::default(),
+            totp_recovery_remaining: 0,
+            settings_current_password: String::new(),
+            settings_new_password: String::new(),
+            settings_confirm_password: String::new(),
+            settings_new_username: String::new(),
+            settings_new_email: String::new(),
             last_recurring_check: None,
             screen: AppState::Login,
             conn,
             user_id: None,
             accounts_list: Vec::new(),
+            account_roles: HashMap::new(),
+            member_names: HashMap::new(),
+            upcoming_days: 30,
+            pending_runs: Vec::new(),
+            pending_run_amounts: HashMap::new(),
+            upcoming_selected_day: None,
+            holidays_list: Vec::new(),
+            holiday_calendars: HashMap::new(),
+            new_holiday_date: chrono::Local::now().format("%Y-%m-%d").to_string(),
+            new_holiday_name: String::new(),
+            holiday_file: "holidays.txt".to_string(),
+            forecast_days: 30,
+            forecast_include_spending: true,
+            low_balance_threshold: 0.0,
+            forecasts: Vec::new(),
+            forecast_computed_at: None,
+            net_worth_snapshots: Vec::new(),
+            net_worth_months: 12,
+            net_worth_by_account: false,
+            net_worth_loaded_at: None,
+            sharing_account_id: None,
+            sharing_members: Vec::new(),
+            share_username: String::new(),
+            share_role: AccountRole::Editor,
             new_account_name: String::new(),
             new_account_type: String::new(),
             new_account_balance: 0.0,
             budgets: Vec::new(),
             selected_budget_period: Period::Monthly,
             budget_progress: HashMap::new(),
+            budget_rollovers: HashMap::new(),
+            budget_scopes: HashMap::new(),
+            budget_paces: HashMap::new(),
+            budget_history_periods: 6,
+            budget_history: Vec::new(),
+            template_income_months: 3,
+            template_income: 0,
+            template_shares: budgeting::fifty_thirty_twenty(),
+            template_spend_months: 3,
+            template_spend_drafts: Vec::new(),
+            budget_templates: Vec::new(),
+            template_name: String::new(),
+            savings_months: 12,
+            savings_report: Vec::new(),
+            report_month: budgeting::period_range(Period::Monthly, chrono::Local::now().date_naive(), 0).0.date(),
+            income_statement: None,
+            category_trends: Vec::new(),
+            trend_category: None,
+            savings_goals: Vec::new(),
+            goal_name: String::new(),
+            goal_target: 0.0,
+            goal_date: chrono::Local::now().format("%Y-%m-%d").to_string(),
+            goal_rate_percent: 0.0,
+            goal_account_ids: Vec::new(),
+            debts: Vec::new(),
+            debt_extra_payment: 100.0,
+            debt_strategy: PayoffStrategy::Avalanche,
+            debt_custom_order: Vec::new(),
             editor_category: String::new(),
             editor_limit_cents: 0,
             editor_period: Period::Monthly,
             editor_target_is_expense: true,
+            editor_rollover_mode: RolloverMode::None,
+            editor_rollover_cap_cents: 0,
+            editor_alert_thresholds: budgeting::DEFAULT_ALERT_THRESHOLDS.to_string(),
+            editor_name: String::new(),
+            editor_extra_categories: Vec::new(),
+            editor_include_subcategories: false,
+            editor_account_ids: Vec::new(),
+            editor_custom_dates: false,
+            editor_starts_on: chrono::Local::now().format("%Y-%m-%d").to_string(),
+            editor_ends_on: chrono::Local::now().format("%Y-%m-%d").to_string(),
+            tx_budget_warning: None,
+            notifications: Vec::new(),
+            unread_notifications: 0,
+            notifications_open: false,
+            envelope_month: chrono::Local::now().date_naive().with_day(1).unwrap(),
+            envelope_statuses: Vec::new(),
+            available_to_assign: None,
+            envelope_new_category: String::new(),
+            envelope_assign_inputs: HashMap::new(),
+            envelope_move_from: 0,
+            envelope_move_to: 0,
+            envelope_move_amount: 0.0,
             current_editing: None,
             period_offset: 0,
             editor_open: false,
@@ -481,6 +874,7 @@ impl FinancerApp {
             // Transaction filter initialization
             tx_filter_account_id: None,
             tx_filter_category: None,
+            tx_filter_recurring: None,
             tx_filter_start_date: chrono::Local::now().date_naive().with_day(1).unwrap().format("%Y-%m-%d").to_string(),
             tx_filter_end_date: chrono::Local::now().date_naive().format("%Y-%m-%d").to_string(),
             // Transfer initialization
@@ -500,7 +894,14 @@ impl FinancerApp {
             recurring_tx_custom_category: String::new(),
             recurring_tx_show_category_input: false,
             recurring_tx_next_run_at: chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
-            recurring_tx_frequency: Period::Monthly,
+            recurring_tx_rule: "FREQ=MONTHLY".to_string(),
+            recurring_tx_has_end: false,
+            recurring_tx_end_date: chrono::Local::now().format("%Y-%m-%d").to_string(),
+            recurring_tx_has_limit: false,
+            recurring_tx_max_occurrences: 12,
+            recurring_tx_requires_approval: false,
+            recurring_tx_rewrite_past: false,
+            recurring_tx_roll: RollConvention::None,
 
             recurring_transfers_list: Vec::new(),
             recurring_transfer_editing_id: None,
@@ -508,7 +909,14 @@ impl FinancerApp {
             recurring_transfer_to_account_id: 0,
             recurring_transfer_amount: 0.0,
             recurring_transfer_next_run_at: chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
-            recurring_transfer_frequency: Period::Monthly,
+            recurring_transfer_rule: "FREQ=MONTHLY".to_string(),
+            recurring_transfer_has_end: false,
+            recurring_transfer_end_date: chrono::Local::now().format("%Y-%m-%d").to_string(),
+            recurring_transfer_has_limit: false,
+            recurring_transfer_max_occurrences: 12,
+            recurring_transfer_requires_approval: false,
+            recurring_transfer_rewrite_past: false,
+            recurring_transfer_roll: RollConvention::None,
             // Cash-flow tools initialization
             cf_nominal_rate_percent: 5.0,
             cf_inflation_rate_percent: 2.0,
@@ -575,7 +983,12 @@ impl FinancerApp {
             savings_comp_horizon_value: 5.0,
             savings_comp_horizon_unit: SavingsHorizonUnit::Years,
             savings_comp_fv: None,
+        };
+        // Days the app was closed get their balance snapshots before anyone logs in.
+        if let Err(e) = db::update_balance_snapshots(&mut app.conn, chrono::Local::now().date_naive()) {
+            app.message = format!("Failed to update balance history: {}", friendly_error(&e));
         }
+        app
     }
 
     fn show_expense_pie_chart(&mut self, ui: &mut egui::Ui) {
@@ -586,10 +999,8 @@ impl FinancerApp {
             let mut map = std::collections::HashMap::<String, f32>::new();
             for tx in &self.transactions_list {
                 // Parse date and filter by period
-                if let Ok(date) = chrono::NaiveDateTime::parse_from_str(&tx.date, "%Y-%m-%d %H:%M:%S") {
-                    if date >= start && date < end && tx.amount < 0.0 {
-                        *map.entry(tx.category.clone()).or_insert(0.0) += tx.amount.abs();
-                    }
+                if let Ok(date) = chrono::NaiveDateTime::parse_from_str(&tx.date, "%Y-%m-%d %H:%M:%S") && date >= start && date < end && tx.amount < 0.0 {
+                    *map.entry(tx.category.clone()).or_insert(0.0) += tx.amount.abs();
                 }
             }
             map.into_iter().collect()
@@ -718,14 +1129,12 @@ impl FinancerApp {
                 let mut income_by_month: Vec<((i32, u32), f32)> = Vec::new();
 
                 for tx in &self.transactions_list {
-                    if tx.amount > 0.0 {
-                        if let Ok(date) = NaiveDate::parse_from_str(&tx.date, "%Y-%m-%d %H:%M:%S") {
-                            let key = (date.year(), date.month());
-                            if let Some(entry) = income_by_month.iter_mut().find(|e| e.0 == key) {
-                                entry.1 += tx.amount;
-                            } else {
-                                income_by_month.push((key, tx.amount));
-                            }
+                    if tx.amount > 0.0 && let Ok(date) = NaiveDate::parse_from_str(&tx.date, "%Y-%m-%d %H:%M:%S") {
+                        let key = (date.year(), date.month());
+                        if let Some(entry) = income_by_month.iter_mut().find(|e| e.0 == key) {
+                            entry.1 += tx.amount;
+                        } else {
+                            income_by_month.push((key, tx.amount));
                         }
                     }
                 }
@@ -735,7 +1144,7 @@ impl FinancerApp {
                     return;
                 }
 
-                income_by_month.sort_by(|a, b| a.0.cmp(&b.0));
+                income_by_month.sort_by_key(|entry| entry.0);
 
                 let mut points: Vec<[f64; 2]> = Vec::new();
                 let mut x_labels: Vec<PlotText> = Vec::new();
@@ -767,11 +1176,142 @@ impl FinancerApp {
     fn load_user_transactions(&mut self) {
         if let Some(uid) = self.user_id {
             self.transactions_list = db::get_user_transactions(&mut self.conn, uid).unwrap_or_default();
+            let mut creator_ids: Vec<i32> = self.transactions_list.iter().filter_map(|tx| tx.created_by).collect();
+            creator_ids.sort_unstable();
+            creator_ids.dedup();
+            self.member_names = db::get_usernames(&mut self.conn, &creator_ids)
+                .unwrap_or_default()
+                .into_iter()
+                .collect();
         } else {
             self.transactions_list.clear();
+            self.member_names.clear();
+        }
+    }
+
+    fn refresh_accounts(&mut self, uid: i32) {
+        self.accounts_list = db::get_user_accounts(&mut self.conn, uid).unwrap_or_default();
+        self.account_roles = db::get_user_account_roles(&mut self.conn, uid)
+            .unwrap_or_default()
+            .into_iter()
+            .collect();
+    }
+
+    fn can_edit_account(&self, account_id: i32) -> bool {
+        self.account_roles.get(&account_id).is_some_and(|r| r.can_edit())
+    }
+
+    fn load_sharing_members(&mut self) {
+        let (Some(uid), Some(account_id)) = (self.user_id, self.sharing_account_id) else {
+            self.sharing_members.clear();
+            return;
+        };
+        match db::get_account_members(&mut self.conn, uid, account_id) {
+            Ok(members) => self.sharing_members = members,
+            Err(e) => {
+                self.sharing_members.clear();
+                self.message = format!("Failed to load members: {}", friendly_error(&e));
+            }
         }
     }
 
+    fn show_account_sharing(&mut self, ui: &mut egui::Ui, account_id: i32) {
+        let Some(uid) = self.user_id else {
+            return;
+        };
+        let is_owner = self.account_roles.get(&account_id) == Some(&AccountRole::Owner);
+        let account_name = self.accounts_list
+            .iter()
+            .find(|a| a.id == account_id)
+            .map(|a| a.name.clone())
+            .unwrap_or_default();
+
+        ui.group(|ui| {
+            ui.label(egui::RichText::new(format!("Members of {}", account_name)).strong());
+
+            let mut role_change: Option<(i32, AccountRole)> = None;
+            let mut removal: Option<i32> = None;
+            for (member, member_name) in &self.sharing_members {
+                let role = AccountRole::from_str(&member.role);
+                ui.horizontal(|ui| {
+                    ui.label(member_name);
+                    if is_owner && member.user_id != uid {
+                        let mut selected = role;
+                        egui::ComboBox::from_id_salt(("member_role", member.user_id))
+                            .selected_text(selected.to_str())
+                            .show_ui(ui, |ui| {
+                                for option in AccountRole::ALL {
+                                    ui.selectable_value(&mut selected, option, option.to_str());
+                                }
+                            });
+                        if selected != role {
+                            role_change = Some((member.user_id, selected));
+                        }
+                        if ui.button("Remove").clicked() {
+                            removal = Some(member.user_id);
+                        }
+                    } else {
+                        ui.label(format!("({})", role.to_str()));
+                        if member.user_id == uid && !is_owner && ui.button("Leave").clicked() {
+                            removal = Some(member.user_id);
+                        }
+                    }
+                });
+            }
+
+            if let Some((member_id, role)) = role_change {
+                match db::set_account_member_role(&mut self.conn, uid, account_id, member_id, role) {
+                    Ok(()) => self.message = "Member role updated.".to_string(),
+                    Err(e) => self.message = format!("Failed to change role: {}", friendly_error(&e)),
+                }
+                self.load_sharing_members();
+            }
+            if let Some(member_id) = removal {
+                match db::remove_account_member(&mut self.conn, uid, account_id, member_id) {
+                    Ok(()) if member_id == uid => {
+                        self.message = format!("You left {}.", account_name);
+                        self.sharing_account_id = None;
+                        self.refresh_accounts(uid);
+                    }
+                    Ok(()) => {
+                        self.message = "Member removed.".to_string();
+                        self.load_sharing_members();
+                    }
+                    Err(e) => self.message = format!("Failed to remove member: {}", friendly_error(&e)),
+                }
+            }
+
+            if is_owner {
+                ui.horizontal(|ui| {
+                    ui.label("Share with username:");
+                    ui.text_edit_singleline(&mut self.share_username);
+                    egui::ComboBox::from_id_salt("share_role")
+                        .selected_text(self.share_role.to_str())
+                        .show_ui(ui, |ui| {
+                            for option in AccountRole::ALL {
+                                ui.selectable_value(&mut self.share_role, option, option.to_str());
+                            }
+                        });
+                    if ui.button("Share").clicked() {
+                        match db::add_account_member(&mut self.conn, uid, account_id, &self.share_username, self.share_role) {
+                            Ok(()) => {
+                                self.message = format!("Shared {} with {}.", account_name, self.share_username.trim());
+                                self.share_username.clear();
+                                self.load_sharing_members();
+                            }
+                            Err(e) => self.message = format!("Failed to share account: {}", friendly_error(&e)),
+                        }
+                    }
+                });
+            }
+
+            if ui.button("Close").clicked() {
+                self.sharing_account_id = None;
+                self.sharing_members.clear();
+            }
+        });
+    }
+
     fn load_user_recurring_transactions(&mut self) {
         if let Some(uid) = self.user_id {
             self.recurring_transactions_list =
@@ -779,6 +1319,7 @@ impl FinancerApp {
         } else {
             self.recurring_transactions_list.clear();
         }
+        self.load_holidays();
     }
 
     fn load_user_recurring_transfers(&mut self) {
@@ -788,6 +1329,21 @@ impl FinancerApp {
         } else {
             self.recurring_transfers_list.clear();
         }
+        self.load_holidays();
+    }
+
+    fn load_holidays(&mut self) {
+        let Some(uid) = self.user_id else {
+            self.holidays_list.clear();
+            self.holiday_calendars.clear();
+            return;
+        };
+        self.holidays_list = db::get_user_holidays(&mut self.conn, uid).unwrap_or_default();
+        let owners: Vec<i32> = std::iter::once(uid)
+            .chain(self.recurring_transactions_list.iter().map(|i| i.user_id))
+            .chain(self.recurring_transfers_list.iter().map(|i| i.user_id))
+            .collect();
+        self.holiday_calendars = db::get_holiday_calendars(&mut self.conn, &owners).unwrap_or_default();
     }
 
     fn maybe_process_due_recurring(&mut self) {
@@ -805,13 +1361,63 @@ impl FinancerApp {
         let now = chrono::Local::now().naive_local();
         let processed = db::process_due_recurring(&mut self.conn, uid, now).unwrap_or(0);
         if processed > 0 {
-            self.accounts_list = db::get_user_accounts(&mut self.conn, uid).unwrap_or_default();
+            self.refresh_accounts(uid);
             self.load_user_transactions();
             self.load_user_budgets();
             self.compute_budget_progress(self.period_offset);
             self.load_user_recurring_transactions();
             self.load_user_recurring_transfers();
         }
+        self.pending_runs = db::get_pending_recurring_runs(&mut self.conn, uid).unwrap_or_default();
+        self.load_notifications();
+    }
+
+    fn load_notifications(&mut self) {
+        let Some(uid) = self.user_id else { return; };
+        self.notifications = db::get_notifications(&mut self.conn, uid).unwrap_or_default();
+        self.unread_notifications = db::count_unread_notifications(&mut self.conn, uid).unwrap_or(0);
+    }
+
+    /// The dashboard's budget alerts: a badge with the unread count that opens the list.
+    fn show_notifications(&mut self, ui: &mut egui::Ui) {
+        let Some(uid) = self.user_id else { return; };
+        if self.notifications.is_empty() {
+            return;
+        }
+        let badge = if self.unread_notifications > 0 {
+            egui::RichText::new(format!("Budget alerts ({} new)", self.unread_notifications)).color(Color32::from_rgb(220, 50, 50)).strong()
+        } else {
+            egui::RichText::new(format!("Budget alerts ({})", self.notifications.len()))
+        };
+        if ui.button(badge).clicked() {
+            self.notifications_open = !self.notifications_open;
+        }
+        if !self.notifications_open {
+            return;
+        }
+
+        let mut changed = false;
+        egui::Frame::group(ui.style()).show(ui, |ui| {
+            if self.unread_notifications > 0 && ui.small_button("Mark all read").clicked() {
+                changed |= db::mark_all_notifications_read(&mut self.conn, uid).is_ok();
+            }
+            for note in &self.notifications {
+                ui.horizontal(|ui| {
+                    let text = egui::RichText::new(&note.message);
+                    ui.label(if note.is_read { text.weak() } else { text.strong() });
+                    ui.label(egui::RichText::new(&note.created_at).small().weak());
+                    if !note.is_read && ui.small_button("Mark read").clicked() {
+                        changed |= db::mark_notification_read(&mut self.conn, uid, note.id).is_ok();
+                    }
+                    if ui.small_button("Dismiss").clicked() {
+                        changed |= db::dismiss_notification(&mut self.conn, uid, note.id).is_ok();
+                    }
+                });
+            }
+        });
+        if changed {
+            self.load_notifications();
+        }
     }
 
     fn load_user_categories(&mut self) {
@@ -836,6 +1442,28 @@ impl FinancerApp {
         all
     }
 
+    /// Optional end date and occurrence limit for a recurring item.
+    fn show_schedule_limits(
+        ui: &mut egui::Ui,
+        has_end: &mut bool,
+        end_date: &mut String,
+        has_limit: &mut bool,
+        max_occurrences: &mut i32,
+        id_prefix: &str,
+    ) {
+        ui.horizontal(|ui| {
+            ui.checkbox(has_end, "Ends on");
+            if *has_end {
+                Self::show_date_selector(ui, end_date, &format!("{}_end", id_prefix));
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.checkbox(has_limit, "Stop after");
+            ui.add_enabled(*has_limit, egui::DragValue::new(max_occurrences).range(1..=999));
+            ui.label("occurrences");
+        });
+    }
+
     fn show_date_selector(ui: &mut egui::Ui, date_string: &mut String, id_prefix: &str) {
         use chrono::Datelike;
         
@@ -898,10 +1526,8 @@ impl FinancerApp {
                 }
             });
         
-        if changed {
-            if let Some(new_date) = chrono::NaiveDate::from_ymd_opt(year, month, day) {
-                *date_string = new_date.format("%Y-%m-%d").to_string();
-            }
+        if changed && let Some(new_date) = chrono::NaiveDate::from_ymd_opt(year, month, day) {
+            *date_string = new_date.format("%Y-%m-%d").to_string();
         }
     }
 
@@ -915,23 +1541,49 @@ impl FinancerApp {
             ui.label("Password:");
             ui.add(egui::TextEdit::singleline(&mut self.password).password(true));
 
+            if self.login_needs_code {
+                ui.label("Authentication code:");
+                ui.text_edit_singleline(&mut self.login_totp_code);
+            }
+
             if ui.button("Login").clicked() {
-                match db::verify_user(&mut self.conn, &self.username, &self.password) {
+                let code = self.login_totp_code.trim();
+                match db::verify_user_at(
+                    &mut self.conn,
+                    &self.username,
+                    &self.password,
+                    (self.login_needs_code && !code.is_empty()).then_some(code),
+                    chrono::Local::now().naive_local(),
+                    &self.throttle_policy,
+                ) {
                     Ok(true) => {
+                        wipe_secret(&mut self.password);
+                        wipe_secret(&mut self.login_totp_code);
+                        self.login_needs_code = false;
+                        self.last_activity = Instant::now();
                         self.user_id = db::get_userid_by_username(&mut self.conn, &self.username).ok().map(|u| u.id);
                         if let Some(uid) = self.user_id {
                             let _ = db::process_due_recurring(&mut self.conn, uid, chrono::Local::now().naive_local());
-                            self.accounts_list = db::get_user_accounts(&mut self.conn, uid).unwrap_or_default();
+                            self.refresh_accounts(uid);
                             self.load_user_recurring_transactions();
                             self.load_user_recurring_transfers();
+                            self.low_balance_threshold = db::get_low_balance_threshold(&mut self.conn, uid).unwrap_or(0.0);
+                            self.forecast_computed_at = None;
+                            self.net_worth_loaded_at = None;
                         }
                         self.message.clear();
                         self.screen = AppState::Dashboard;
                     }
                     Ok(false) => {
+                        self.login_totp_code.clear();
                         self.message = "Invalid credentials. Please try again.".to_string();
                     }
-                    Err(e) => self.message = format!("Error during login: {}", e),
+                    Err(e @ FinancerError::TwoFactorRequired) => {
+                        self.login_needs_code = true;
+                        self.message = friendly_error(&e);
+                    }
+                    Err(e @ FinancerError::LoginThrottled { .. }) => self.message = friendly_error(&e),
+                    Err(e) => self.message = format!("Error during login: {}", friendly_error(&e)),
                 }
             }
 
@@ -961,6 +1613,7 @@ impl FinancerApp {
 
             ui.label("Password:");
             ui.add(egui::TextEdit::singleline(&mut self.password).password(true));
+            ui.label(egui::RichText::new(self.password_policy.describe()).small().italics());
 
             if ui.button("Create account").clicked() {
                 if self.username.is_empty() || self.password.is_empty() {
@@ -968,7 +1621,8 @@ impl FinancerApp {
                     return;
                 }
 
-                match db::create_user(&mut self.conn, &self.username, &self.password, Some(&self.email)) {
+                let email = if self.email.trim().is_empty() { None } else { Some(self.email.trim()) };
+                match db::register_user(&mut self.conn, &self.username, &self.password, email, &self.password_policy) {
                     Ok(_) => {
                         self.message = "Account created! Now you can login".to_string();
                         self.screen = AppState::Login;
@@ -976,15 +1630,11 @@ impl FinancerApp {
                         self.password.clear();
                         self.email.clear();
                     }
-                    Err(Error::DatabaseError(kind, _)) => {
-                        self.message = match kind {
-                            DatabaseErrorKind::UniqueViolation => "Username or email already exists.".to_string(),
-                            _ => "Database error occurred.".to_string(),
-                        }
-
+                    Err(FinancerError::Conflict(_)) => {
+                        self.message = "Username or email already exists.".to_string();
                     }
                     Err(e) => {
-                        self.message = format!("Failed to create account: {}", e);
+                        self.message = format!("Failed to create account: {}", friendly_error(&e));
                     }
                 }
             }
@@ -999,157 +1649,589 @@ impl FinancerApp {
         });
     }
 
-    fn show_dashboard(&mut self, ctx: &egui::Context) {
-        egui::CentralPanel::default().show(ctx, |ui| {
-            self.maybe_process_due_recurring();
-            ui.heading("FinanceR Dashboard");
-            ui.label(format!("Logged in as: {}", self.username));
-
-            ui.horizontal(|ui| {
-                if ui.button("Logout").clicked() {
-                    self.screen = AppState::Login;
-                    self.username.clear();
-                    self.password.clear();
-                    self.user_id = None;
-                    self.accounts_list.clear();
-                    self.message.clear();
-                    self.confirm_delete_user = false;
-                }
-
-                if ui.button("Delete User").clicked() {
-                    self.confirm_delete_user = true;
-                }
+    fn clear_settings_passwords(&mut self) {
+        wipe_secret(&mut self.settings_current_password);
+        wipe_secret(&mut self.settings_new_password);
+        wipe_secret(&mut self.settings_confirm_password);
+    }
 
-                if ui.button("Exit Program").clicked() {
-                    ctx.send_viewport_cmd(egui::ViewportCommand::Close);
-                }
-            });
+    fn lock_session(&mut self) {
+        wipe_secret(&mut self.password);
+        wipe_secret(&mut self.unlock_password);
+        wipe_secret(&mut self.unlock_totp_code);
+        self.unlock_needs_code = false;
+        self.clear_settings_passwords();
+        self.totp_form.wipe_typed_secrets();
+        self.unlock_message.clear();
+        let previous = std::mem::replace(&mut self.screen, AppState::Locked);
+        self.screen_before_lock = Some(previous);
+    }
 
-            if self.confirm_delete_user {
-                ui.horizontal(|ui| {
-                    ui.label("Confirm delete user?");
+    /// Locks the session once the idle timeout passes or the window is minimized.
+    /// Runs every frame, before the current screen is drawn.
+    fn maybe_auto_lock(&mut self, ctx: &egui::Context) {
+        if ctx.input(|i| !i.events.is_empty()) {
+            self.last_activity = Instant::now();
+        }
+        if self.user_id.is_none() || matches!(self.screen, AppState::Login | AppState::Register | AppState::Locked) {
+            return;
+        }
 
-                    if ui.button("Confirm Delete").clicked() {
-                        if let Some(uid) = self.user_id {
-                            match db::delete_user_and_all_data(&mut self.conn, uid) {
-                                Ok(()) => {
-                                    self.screen = AppState::Login;
-                                    self.username.clear();
-                                    self.password.clear();
-                                    self.email.clear();
-                                    self.user_id = None;
-                                    self.accounts_list.clear();
-                                    self.budgets.clear();
-                                    self.transactions_list.clear();
-                                    self.message = "User deleted.".to_string();
-                                }
-                                Err(e) => {
-                                    self.message = format!("Failed to delete user: {}", e);
-                                }
-                            }
-                        }
-                        self.confirm_delete_user = false;
-                    }
+        let minimized = ctx.input(|i| i.viewport().minimized.unwrap_or(false));
+        let idle_for = self.last_activity.elapsed();
+        if self.auto_lock_policy.should_lock(idle_for, minimized) {
+            self.lock_session();
+        } else if let Some(limit) = self.auto_lock_policy.idle_timeout {
+            // Wake up when the timer would expire even if no input arrives.
+            ctx.request_repaint_after(limit.saturating_sub(idle_for));
+        }
+    }
 
-                    if ui.button("Cancel").clicked() {
-                        self.confirm_delete_user = false;
-                    }
-                });
-            }
+    fn logout(&mut self) {
+        self.screen = AppState::Login;
+        self.username.clear();
+        wipe_secret(&mut self.password);
+        wipe_secret(&mut self.unlock_password);
+        wipe_secret(&mut self.unlock_totp_code);
+        self.unlock_needs_code = false;
+        wipe_secret(&mut self.login_totp_code);
+        self.login_needs_code = false;
+        self.clear_settings_passwords();
+        self.totp_form.clear();
+        self.screen_before_lock = None;
+        self.user_id = None;
+        self.accounts_list.clear();
+        self.account_roles.clear();
+        self.forecasts.clear();
+        self.forecast_computed_at = None;
+        self.net_worth_snapshots.clear();
+        self.net_worth_loaded_at = None;
+        self.available_to_assign = None;
+        self.envelope_statuses.clear();
+        self.notifications.clear();
+        self.unread_notifications = 0;
+        self.notifications_open = false;
+        self.tx_budget_warning = None;
+        self.sharing_account_id = None;
+        self.sharing_members.clear();
+        self.message.clear();
+        self.confirm_delete_user = false;
+    }
 
-            ui.separator();
-            ui.heading("Your Accounts:");
+    fn load_totp_status(&mut self, uid: i32) {
+        self.totp_enabled = db::is_totp_enabled(&mut self.conn, uid).unwrap_or(false);
+        self.totp_recovery_remaining = db::count_unused_recovery_codes(&mut self.conn, uid).unwrap_or(0);
+    }
 
-            if self.accounts_list.is_empty() {
-                ui.label("No accounts found. Please create one.");
-            } else {
-                let mut clicked_account_id: Option<i32> = None;
-                let mut delete_account_id: Option<i32> = None;
-                
-                for account in &self.accounts_list {
-                    ui.horizontal(|ui| {
-                        if ui.button(format!("{} - {}: ${:.2}", account.name, account.account_type, account.balance)).clicked() {
-                            clicked_account_id = Some(account.id);
-                        }
+    fn show_two_factor_settings(&mut self, ui: &mut egui::Ui) {
+        let Some(uid) = self.user_id else {
+            return;
+        };
+        ui.heading("Two-Factor Authentication");
 
-                        if ui.button("Delete").clicked() {
-                            delete_account_id = Some(account.id);
-                        }
-                    });
-                }
+        if !self.totp_form.new_recovery_codes.is_empty() {
+            ui.label("Two-factor authentication is on. Save these recovery codes somewhere safe. Each one works once if you lose your authenticator, and they won't be shown again:");
+            ui.monospace(self.totp_form.new_recovery_codes.join("\n"));
+            if ui.button("I've saved these codes").clicked() {
+                self.totp_form.clear();
+            }
+            return;
+        }
 
-                if let Some(account_id) = delete_account_id {
-                    if let Some(uid) = self.user_id {
-                        match db::delete_account(&mut self.conn, uid, account_id) {
-                            Ok(_) => {
-                                self.message = "Account deleted.".to_string();
-                                self.accounts_list = db::get_user_accounts(&mut self.conn, uid).unwrap_or_default();
-                                if self.tx_filter_account_id == Some(account_id) {
-                                    self.tx_filter_account_id = None;
-                                }
-                                if self.tx_account_id == account_id {
-                                    self.tx_account_id = 0;
-                                }
-                                if self.transfer_from_account_id == account_id {
-                                    self.transfer_from_account_id = 0;
-                                }
-                                if self.transfer_to_account_id == account_id {
-                                    self.transfer_to_account_id = 0;
-                                }
-                            }
-                            Err(e) => {
-                                self.message = format!("Failed to delete account: {}", e);
-                            }
+        if self.totp_enabled {
+            ui.label(format!(
+                "Enabled. {} unused recovery code(s) left.",
+                self.totp_recovery_remaining
+            ));
+            ui.horizontal(|ui| {
+                ui.label("Password:");
+                ui.add(egui::TextEdit::singleline(&mut self.totp_form.password).password(true));
+                if ui.button("Turn Off 2FA").clicked() {
+                    match db::disable_totp(&mut self.conn, uid, &self.totp_form.password) {
+                        Ok(()) => {
+                            self.totp_form.clear();
+                            self.load_totp_status(uid);
+                            self.message = "Two-factor authentication turned off.".to_string();
                         }
+                        Err(e) => self.message = format!("Failed to turn off 2FA: {}", friendly_error(&e)),
                     }
                 }
-                
-                if let Some(account_id) = clicked_account_id {
-                    self.tx_filter_account_id = Some(account_id);
-                    self.screen = AppState::Transactions;
-                    self.load_user_transactions();
-                    self.load_user_categories();
-                    self.load_user_recurring_transactions();
-                }
-            }
-
-            ui.separator();
-            ui.heading("Create New Account:");
-
-            ui.horizontal(|ui| {
-                ui.label("Name:");
-                ui.text_edit_singleline(&mut self.new_account_name);
             });
+            return;
+        }
 
+        if let Some(secret) = self.totp_form.pending_secret.clone() {
+            let uri = totp::provisioning_uri(&secret, &self.username);
+            ui.label("Scan this code with your authenticator app, or enter the key by hand:");
+            if let Some((width, modules)) = totp::qr_modules(&uri) {
+                draw_qr_code(ui, width, &modules);
+            }
             ui.horizontal(|ui| {
-                ui.label("Type:");
-                ui.text_edit_singleline(&mut self.new_account_type);
+                ui.label("Key:");
+                ui.monospace(&secret);
             });
-
-            ui.horizontal(|ui| {
-                ui.label("Balance:");
-                ui.add(egui::DragValue::new(&mut self.new_account_balance).speed(1.0));
+            ui.collapsing("Provisioning URI", |ui| {
+                ui.add(egui::TextEdit::multiline(&mut uri.as_str()).desired_rows(2));
             });
 
-            if ui.button("Create Account").clicked() {
-                if let Some(uid) = self.user_id {
-                    match db::create_account(&mut self.conn, &self.new_account_name, &self.new_account_type, self.new_account_balance, uid) {
-                        Ok(_) => {
-                            self.message = "Account created successfully.".to_string();
-                            self.accounts_list = db::get_user_accounts(&mut self.conn, uid).unwrap_or_default();
-                            self.new_account_name.clear();
-                            self.new_account_type.clear();
-                            self.new_account_balance = 0.0;
-                        }
-                        Err(e) => {
-                            self.message = format!("Failed to create account: {}", e);
+            ui.horizontal(|ui| {
+                ui.label("Code from the app:");
+                ui.text_edit_singleline(&mut self.totp_form.confirm_code);
+                if ui.button("Confirm").clicked() {
+                    match db::confirm_totp_enrollment(
+                        &mut self.conn,
+                        uid,
+                        &self.totp_form.password,
+                        &self.totp_form.confirm_code,
+                        chrono::Local::now().naive_local(),
+                    ) {
+                        Ok(codes) => {
+                            self.totp_form.clear();
+                            self.totp_form.new_recovery_codes = codes;
+                            self.load_totp_status(uid);
+                            self.message.clear();
                         }
+                        Err(e) => self.message = format!("Failed to enable 2FA: {}", friendly_error(&e)),
                     }
-                } 
-            }
+                }
+                if ui.button("Cancel").clicked() {
+                    self.totp_form.clear();
+                }
+            });
+            return;
+        }
 
-            ui.separator();
-            ui.heading("Accounts and Activity");
+        ui.label("Off. Turn it on to require a code from an authenticator app at login.");
+        ui.horizontal(|ui| {
+            ui.label("Password:");
+            ui.add(egui::TextEdit::singleline(&mut self.totp_form.password).password(true));
+            if ui.button("Set Up 2FA").clicked() {
+                match db::begin_totp_enrollment(&mut self.conn, uid, &self.totp_form.password, chrono::Local::now().naive_local()) {
+                    Ok(secret) => {
+                        self.totp_form.pending_secret = Some(secret);
+                        self.message.clear();
+                    }
+                    Err(e) => self.message = format!("Failed to start 2FA setup: {}", friendly_error(&e)),
+                }
+            }
+        });
+    }
+
+    fn show_lock_screen(&mut self, ctx: &egui::Context) {
+        egui::CentralPanel::default().show(ctx, |ui| {
+            ui.heading("FinanceR is locked");
+            ui.label(format!("Signed in as {}. Enter your password to continue.", self.username));
+
+            ui.label("Password:");
+            let response = ui.add(egui::TextEdit::singleline(&mut self.unlock_password).password(true));
+            let mut submitted = response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+
+            if self.unlock_needs_code {
+                ui.label("Authentication code:");
+                let response = ui.text_edit_singleline(&mut self.unlock_totp_code);
+                submitted |= response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+            }
+
+            ui.horizontal(|ui| {
+                if ui.button("Unlock").clicked() || submitted {
+                    let code = self.unlock_totp_code.trim();
+                    match db::verify_user_at(
+                        &mut self.conn,
+                        &self.username,
+                        &self.unlock_password,
+                        (self.unlock_needs_code && !code.is_empty()).then_some(code),
+                        chrono::Local::now().naive_local(),
+                        &self.throttle_policy,
+                    ) {
+                        Ok(true) => {
+                            wipe_secret(&mut self.unlock_password);
+                            wipe_secret(&mut self.unlock_totp_code);
+                            self.unlock_needs_code = false;
+                            self.unlock_message.clear();
+                            self.last_activity = Instant::now();
+                            self.screen = self.screen_before_lock.take().unwrap_or(AppState::Dashboard);
+                        }
+                        Ok(false) => {
+                            wipe_secret(&mut self.unlock_password);
+                            wipe_secret(&mut self.unlock_totp_code);
+                            self.unlock_message = if self.unlock_needs_code {
+                                "Incorrect password or code.".to_string()
+                            } else {
+                                "Incorrect password.".to_string()
+                            };
+                        }
+                        // Unlocking asks for the second factor just like logging in.
+                        Err(e @ FinancerError::TwoFactorRequired) => {
+                            self.unlock_needs_code = true;
+                            self.unlock_message = friendly_error(&e);
+                        }
+                        Err(e) => {
+                            wipe_secret(&mut self.unlock_password);
+                            self.unlock_message = friendly_error(&e);
+                        }
+                    }
+                }
+
+                if ui.button("Logout").clicked() {
+                    self.unlock_message.clear();
+                    self.logout();
+                }
+            });
+
+            ui.separator();
+            ui.label(&self.unlock_message);
+        });
+    }
+
+    fn show_security_settings(&mut self, ctx: &egui::Context) {
+        egui::CentralPanel::default().show(ctx, |ui| {
+            ui.heading("Security Settings");
+
+            ui.horizontal(|ui| {
+                if ui.button("Back to Dashboard").clicked() {
+                    self.clear_settings_passwords();
+                    self.totp_form.clear();
+                    self.message.clear();
+                    self.screen = AppState::Dashboard;
+                }
+            });
+
+            ui.separator();
+            ui.heading("Change Password");
+
+            ui.horizontal(|ui| {
+                ui.label("Current password:");
+                ui.add(egui::TextEdit::singleline(&mut self.settings_current_password).password(true));
+            });
+            ui.horizontal(|ui| {
+                ui.label("New password:");
+                ui.add(egui::TextEdit::singleline(&mut self.settings_new_password).password(true));
+            });
+            ui.horizontal(|ui| {
+                ui.label("Confirm new password:");
+                ui.add(egui::TextEdit::singleline(&mut self.settings_confirm_password).password(true));
+            });
+            ui.label(egui::RichText::new(self.password_policy.describe()).small().italics());
+
+            if ui.button("Change Password").clicked() && let Some(uid) = self.user_id {
+                if self.settings_new_password != self.settings_confirm_password {
+                    self.message = "New passwords do not match.".to_string();
+                } else {
+                    match db::change_password(
+                        &mut self.conn,
+                        uid,
+                        &self.settings_current_password,
+                        &self.settings_new_password,
+                        &self.password_policy,
+                    ) {
+                        Ok(()) => {
+                            self.message = "Password changed.".to_string();
+                            self.clear_settings_passwords();
+                        }
+                        Err(e) => {
+                            self.message = format!("Failed to change password: {}", friendly_error(&e));
+                        }
+                    }
+                }
+            }
+
+            ui.separator();
+            ui.heading("Change Username");
+
+            ui.horizontal(|ui| {
+                ui.label("Username:");
+                ui.text_edit_singleline(&mut self.settings_new_username);
+                if ui.button("Rename").clicked() && let Some(uid) = self.user_id {
+                    match db::change_username(&mut self.conn, uid, &self.settings_new_username) {
+                        Ok(()) => {
+                            self.username = self.settings_new_username.trim().to_string();
+                            self.message = format!("Username changed to {}.", self.username);
+                        }
+                        Err(e) => {
+                            self.message = format!("Failed to change username: {}", friendly_error(&e));
+                        }
+                    }
+                }
+            });
+
+            ui.separator();
+            ui.heading("Change Email");
+
+            ui.horizontal(|ui| {
+                ui.label("Email:");
+                ui.text_edit_singleline(&mut self.settings_new_email);
+                if ui.button("Update Email").clicked() && let Some(uid) = self.user_id {
+                    let new_email = self.settings_new_email.trim();
+                    let new_email = if new_email.is_empty() { None } else { Some(new_email) };
+                    match db::change_email(&mut self.conn, uid, new_email) {
+                        Ok(()) => {
+                            self.message = "Email updated.".to_string();
+                        }
+                        Err(e) => {
+                            self.message = format!("Failed to change email: {}", friendly_error(&e));
+                        }
+                    }
+                }
+            });
+
+            ui.separator();
+            self.show_two_factor_settings(ui);
+
+            ui.separator();
+            ui.heading("Auto-Lock");
+
+            let mut idle_minutes = self.auto_lock_policy.idle_timeout.map(|d| d.as_secs() / 60).unwrap_or(0);
+            ui.horizontal(|ui| {
+                ui.label("Lock after idle for (minutes, 0 = never):");
+                if ui.add(egui::DragValue::new(&mut idle_minutes).range(0..=240)).changed() {
+                    self.auto_lock_policy.idle_timeout =
+                        (idle_minutes > 0).then(|| Duration::from_secs(idle_minutes * 60));
+                }
+            });
+            ui.checkbox(&mut self.auto_lock_policy.lock_on_minimize, "Lock when the window is minimized");
+            ui.label(
+                egui::RichText::new("These settings last until the app is closed. Set IDLE_LOCK_MINUTES and LOCK_ON_MINIMIZE in .env to change the defaults.")
+                    .small()
+                    .italics(),
+            );
+
+            ui.separator();
+            ui.heading("Recent Sign-in Activity");
+
+            if self.login_events.is_empty() {
+                ui.label("No sign-in activity recorded yet.");
+            } else {
+                egui::Grid::new("login_events_grid").striped(true).show(ui, |ui| {
+                    ui.label("When");
+                    ui.label("Result");
+                    ui.label("Detail");
+                    ui.end_row();
+                    for event in &self.login_events {
+                        ui.label(&event.occurred_at);
+                        if event.succeeded {
+                            ui.label("Success");
+                        } else {
+                            ui.colored_label(egui::Color32::RED, "Failed");
+                        }
+                        ui.label(match event.reason.as_str() {
+                            "ok" => "Signed in",
+                            "bad_credentials" => "Wrong password",
+                            "locked_out" => "Wrong password, account locked",
+                            "throttled" => "Refused while locked",
+                            other => other,
+                        });
+                        ui.end_row();
+                    }
+                });
+            }
+
+            ui.separator();
+            ui.label(&self.message);
+        });
+    }
+
+    fn show_dashboard(&mut self, ctx: &egui::Context) {
+        egui::CentralPanel::default().show(ctx, |ui| {
+            self.maybe_process_due_recurring();
+            ui.heading("FinanceR Dashboard");
+            ui.label(format!("Logged in as: {}", self.username));
+            if !self.pending_runs.is_empty() {
+                let notice = format!("{} scheduled item(s) awaiting approval", self.pending_runs.len());
+                if ui.button(egui::RichText::new(notice).color(egui::Color32::from_rgb(200, 140, 0))).clicked() {
+                    self.screen = AppState::Upcoming;
+                    self.upcoming_selected_day = None;
+                    self.load_user_recurring_transactions();
+                    self.load_user_recurring_transfers();
+                }
+            }
+            if let Some(pool) = self.available_to_assign {
+                let color = if pool < 0 { Color32::RED } else { Color32::from_rgb(100, 200, 100) };
+                let label = egui::RichText::new(format!("To Be Assigned: ${:.2}", pool as f64 / 100.0)).color(color);
+                if ui.button(label).on_hover_text("Money not yet assigned to an envelope").clicked() {
+                    self.open_envelopes();
+                }
+            }
+            self.show_notifications(ui);
+
+            ui.horizontal(|ui| {
+                if ui.button("Logout").clicked() {
+                    self.logout();
+                }
+
+                if ui.button("Lock Now").clicked() {
+                    self.lock_session();
+                }
+
+                if ui.button("Security Settings").clicked() {
+                    self.settings_new_username = self.username.clone();
+                    self.settings_new_email = db::get_userid_by_username(&mut self.conn, &self.username)
+                        .ok()
+                        .and_then(|u| u.email)
+                        .unwrap_or_default();
+                    if let Some(uid) = self.user_id {
+                        self.login_events = db::get_login_events(&mut self.conn, uid, 20).unwrap_or_default();
+                        self.load_totp_status(uid);
+                    }
+                    self.message.clear();
+                    self.screen = AppState::SecuritySettings;
+                }
+
+                if ui.button("Delete User").clicked() {
+                    self.confirm_delete_user = true;
+                }
+
+                if ui.button("Exit Program").clicked() {
+                    ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+                }
+            });
+
+            if self.confirm_delete_user {
+                ui.horizontal(|ui| {
+                    ui.label("Confirm delete user?");
+
+                    if ui.button("Confirm Delete").clicked() {
+                        if let Some(uid) = self.user_id {
+                            match db::delete_user_and_all_data(&mut self.conn, uid) {
+                                Ok(()) => {
+                                    self.screen = AppState::Login;
+                                    self.username.clear();
+                                    self.password.clear();
+                                    self.email.clear();
+                                    self.user_id = None;
+                                    self.accounts_list.clear();
+                                    self.budgets.clear();
+                                    self.transactions_list.clear();
+                                    self.message = "User deleted.".to_string();
+                                }
+                                Err(e) => {
+                                    self.message = format!("Failed to delete user: {}", friendly_error(&e));
+                                }
+                            }
+                        }
+                        self.confirm_delete_user = false;
+                    }
+
+                    if ui.button("Cancel").clicked() {
+                        self.confirm_delete_user = false;
+                    }
+                });
+            }
+
+            ui.separator();
+            ui.heading("Your Accounts:");
+
+            if self.accounts_list.is_empty() {
+                ui.label("No accounts found. Please create one.");
+            } else {
+                let mut clicked_account_id: Option<i32> = None;
+                let mut delete_account_id: Option<i32> = None;
+                let mut sharing_account_id: Option<i32> = None;
+                
+                for account in &self.accounts_list {
+                    let role = self.account_roles.get(&account.id).copied().unwrap_or(AccountRole::Viewer);
+                    let shared_with_me = account.user_id != self.user_id.unwrap_or_default();
+                    ui.horizontal(|ui| {
+                        if ui.button(format!("{} - {}: ${:.2}", account.name, account.account_type, account.balance)).clicked() {
+                            clicked_account_id = Some(account.id);
+                        }
+
+                        if shared_with_me {
+                            ui.label(egui::RichText::new(format!("shared with you ({})", role.to_str())).italics());
+                        }
+
+                        if ui.button(if role == AccountRole::Owner { "Share" } else { "Members" }).clicked() {
+                            sharing_account_id = Some(account.id);
+                        }
+
+                        if role == AccountRole::Owner && ui.button("Delete").clicked() {
+                            delete_account_id = Some(account.id);
+                        }
+                    });
+                }
+
+                if let Some(account_id) = sharing_account_id {
+                    self.sharing_account_id = Some(account_id);
+                    self.share_username.clear();
+                    self.load_sharing_members();
+                }
+                if let Some(account_id) = self.sharing_account_id {
+                    self.show_account_sharing(ui, account_id);
+                }
+
+                if let Some(account_id) = delete_account_id && let Some(uid) = self.user_id {
+                    match db::delete_account(&mut self.conn, uid, account_id) {
+                        Ok(_) => {
+                            self.message = "Account deleted.".to_string();
+                            self.refresh_accounts(uid);
+                            if self.tx_filter_account_id == Some(account_id) {
+                                self.tx_filter_account_id = None;
+                            }
+                            if self.tx_account_id == account_id {
+                                self.tx_account_id = 0;
+                            }
+                            if self.transfer_from_account_id == account_id {
+                                self.transfer_from_account_id = 0;
+                            }
+                            if self.transfer_to_account_id == account_id {
+                                self.transfer_to_account_id = 0;
+                            }
+                        }
+                        Err(e) => {
+                            self.message = format!("Failed to delete account: {}", friendly_error(&e));
+                        }
+                    }
+                }
+                
+                if let Some(account_id) = clicked_account_id {
+                    self.tx_filter_account_id = Some(account_id);
+                    self.screen = AppState::Transactions;
+                    self.load_user_transactions();
+                    self.load_user_categories();
+                    self.load_user_recurring_transactions();
+                }
+            }
+
+            ui.separator();
+            self.show_balance_forecast(ui);
+
+            ui.separator();
+            self.show_net_worth(ui);
+
+            ui.separator();
+            ui.heading("Create New Account:");
+
+            ui.horizontal(|ui| {
+                ui.label("Name:");
+                ui.text_edit_singleline(&mut self.new_account_name);
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Type:");
+                ui.text_edit_singleline(&mut self.new_account_type);
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Balance:");
+                ui.add(egui::DragValue::new(&mut self.new_account_balance).speed(1.0));
+            });
+
+            if ui.button("Create Account").clicked() && let Some(uid) = self.user_id {
+                match db::create_account(&mut self.conn, &self.new_account_name, &self.new_account_type, self.new_account_balance, uid) {
+                    Ok(_) => {
+                        self.message = "Account created successfully.".to_string();
+                        self.refresh_accounts(uid);
+                        self.new_account_name.clear();
+                        self.new_account_type.clear();
+                        self.new_account_balance = 0.0;
+                    }
+                    Err(e) => {
+                        self.message = format!("Failed to create account: {}", friendly_error(&e));
+                    }
+                }
+            }
+
+            ui.separator();
+            ui.heading("Accounts and Activity");
 
             if ui.button("Transactions").clicked() {
                 self.tx_filter_account_id = None;
@@ -1165,6 +2247,13 @@ impl FinancerApp {
                 self.load_user_recurring_transfers();
             }
 
+            if ui.button("Upcoming").clicked() {
+                self.screen = AppState::Upcoming;
+                self.upcoming_selected_day = None;
+                self.load_user_recurring_transactions();
+                self.load_user_recurring_transfers();
+            }
+
             if ui.button("Budgets").clicked() {
                 self.screen = AppState::Budgeting;
                 self.load_user_budgets();
@@ -1172,95 +2261,1313 @@ impl FinancerApp {
                 self.compute_budget_progress(0);
             }
 
-            ui.separator();
-            ui.heading("Planning Tools");
+            if ui.button("Envelopes").clicked() {
+                self.open_envelopes();
+            }
+
+            if ui.button("Savings Goals").clicked() {
+                self.screen = AppState::SavingsGoals;
+                self.load_savings_goals();
+            }
+
+            if ui.button("Reports").clicked() {
+                self.screen = AppState::Reports;
+                self.report_month = budgeting::period_range(Period::Monthly, chrono::Local::now().date_naive(), 0).0.date();
+                self.load_reports();
+            }
+
+            ui.separator();
+            ui.heading("Planning Tools");
+            ui.horizontal(|ui| {
+                if ui.button("Savings Calculator").clicked() {
+                    self.screen = AppState::SavingsCalculator;
+                }
+                if ui.button("Bond Tools").clicked() {
+                    self.screen = AppState::BondTools;
+                }
+                if ui.button("Mortgage Tools").clicked() {
+                    self.screen = AppState::MortgageTools;
+                }
+                if ui.button("Debt Payoff").clicked() {
+                    self.screen = AppState::DebtPlanner;
+                    if self.debts.is_empty() {
+                        self.load_debts_from_accounts();
+                    }
+                }
+                if ui.button("Cash Flow Tools").clicked() {
+                    self.screen = AppState::CashflowTools;
+                }
+            });
+
+            ui.label(&self.message);
+        });
+    }
+
+    /// Recomputes the dashboard forecast and envelope pool at most every few seconds, like
+    /// recurring processing.
+    fn maybe_refresh_forecast(&mut self) {
+        let Some(uid) = self.user_id else { return; };
+        if self.forecast_computed_at.is_some_and(|t| t.elapsed() < Duration::from_secs(5)) {
+            return;
+        }
+        self.forecast_computed_at = Some(Instant::now());
+
+        let now = chrono::Local::now().naive_local();
+        let today = now.date();
+        let tomorrow = (today + chrono::Duration::days(1)).and_hms_opt(0, 0, 0).unwrap();
+        let end = tomorrow + chrono::Duration::days(self.forecast_days);
+
+        let scheduled = db::get_scheduled_transactions(&mut self.conn, uid, tomorrow).unwrap_or_default();
+        let occurrences = schedule::expand_occurrences(
+            &self.recurring_transactions_list,
+            &self.recurring_transfers_list,
+            &self.holiday_calendars,
+            now,
+            end,
+        );
+        let estimates = if self.forecast_include_spending {
+            db::get_spend_estimates(&mut self.conn, uid, tomorrow - chrono::Duration::days(FORECAST_HISTORY_DAYS), tomorrow)
+                .unwrap_or_default()
+        } else {
+            Vec::new()
+        };
+        self.forecasts = forecast::forecast_balances(&self.accounts_list, &scheduled, &occurrences, &estimates, today, self.forecast_days);
+        self.load_available_to_assign(uid);
+    }
+
+    fn load_available_to_assign(&mut self, uid: i32) {
+        let has_envelopes = db::get_user_envelopes(&mut self.conn, uid).is_ok_and(|e| !e.is_empty());
+        self.available_to_assign = if has_envelopes {
+            db::get_available_to_assign(&mut self.conn, uid, chrono::Local::now().date_naive()).ok()
+        } else {
+            None
+        };
+    }
+
+    fn show_balance_forecast(&mut self, ui: &mut egui::Ui) {
+        let Some(uid) = self.user_id else { return; };
+        self.maybe_refresh_forecast();
+
+        ui.heading("Balance Forecast");
+        ui.horizontal(|ui| {
+            for days in [30, 60, 90] {
+                if ui.radio_value(&mut self.forecast_days, days, format!("{} days", days)).clicked() {
+                    self.forecast_computed_at = None;
+                }
+            }
+            if ui
+                .checkbox(&mut self.forecast_include_spending, "Include typical spending")
+                .on_hover_text(format!(
+                    "Also subtract each category's average daily spending over the last {} days, leaving out recurring items and transfers",
+                    FORECAST_HISTORY_DAYS
+                ))
+                .changed()
+            {
+                self.forecast_computed_at = None;
+            }
+            ui.label("Warn below $");
+            if ui.add(egui::DragValue::new(&mut self.low_balance_threshold).speed(10.0)).changed() && let Err(e) = db::set_low_balance_threshold(&mut self.conn, uid, self.low_balance_threshold) {
+                self.message = format!("Failed to save threshold: {}", friendly_error(&e));
+            }
+        });
+
+        let threshold = self.low_balance_threshold;
+        let mut warned = false;
+        for account_forecast in &self.forecasts {
+            if let (Some(below), Some(lowest)) = (account_forecast.first_below(threshold), account_forecast.lowest()) {
+                warned = true;
+                ui.colored_label(
+                    Color32::RED,
+                    format!(
+                        "⚠ {} is projected to fall below ${:.2} on {} (lowest ${:.2} on {})",
+                        self.account_name(account_forecast.account_id),
+                        threshold,
+                        below.date.format("%Y-%m-%d"),
+                        lowest.balance,
+                        lowest.date.format("%Y-%m-%d")
+                    ),
+                );
+            }
+        }
+        if self.forecasts.is_empty() {
+            ui.label("Create an account to see a forecast.");
+            return;
+        }
+        if !warned {
+            ui.weak(format!(
+                "No account is projected to fall below ${:.2} in the next {} days.",
+                threshold, self.forecast_days
+            ));
+        }
+
+        let today = chrono::Local::now().date_naive();
+        let lines: Vec<(String, Vec<[f64; 2]>)> = self
+            .forecasts
+            .iter()
+            .map(|f| {
+                let points = f
+                    .points
+                    .iter()
+                    .map(|p| [(p.date - today).num_days() as f64, p.balance as f64])
+                    .collect();
+                (self.account_name(f.account_id), points)
+            })
+            .collect();
+        egui::CollapsingHeader::new("Forecast chart")
+            .default_open(true)
+            .show(ui, |ui| {
+                Plot::new("balance_forecast_plot")
+                    .height(200.0)
+                    .allow_scroll(false)
+                    .legend(Legend::default())
+                    .x_axis_formatter(move |mark, _range| {
+                        (today + chrono::Duration::days(mark.value.round() as i64)).format("%m-%d").to_string()
+                    })
+                    .label_formatter(move |name, point| {
+                        let day = today + chrono::Duration::days(point.x.round() as i64);
+                        format!("{}\n{}: ${:.2}", name, day.format("%Y-%m-%d"), point.y)
+                    })
+                    .show(ui, |plot_ui| {
+                        for (name, points) in lines {
+                            plot_ui.line(Line::new(points).name(name));
+                        }
+                        plot_ui.hline(HLine::new(threshold as f64).color(Color32::RED).name("Warning threshold"));
+                    });
+            });
+    }
+
+    /// Brings today's snapshots up to date and reloads the chart's range, at most once a
+    /// minute.
+    fn maybe_refresh_net_worth(&mut self) {
+        let Some(uid) = self.user_id else { return; };
+        if self.net_worth_loaded_at.is_some_and(|t| t.elapsed() < Duration::from_secs(60)) {
+            return;
+        }
+        self.net_worth_loaded_at = Some(Instant::now());
+
+        let today = chrono::Local::now().date_naive();
+        if let Err(e) = db::update_balance_snapshots(&mut self.conn, today) {
+            self.message = format!("Failed to update balance history: {}", friendly_error(&e));
+        }
+        let from = (self.net_worth_months > 0)
+            .then(|| today.checked_sub_months(chrono::Months::new(self.net_worth_months)))
+            .flatten();
+        self.net_worth_snapshots = db::get_balance_snapshots(&mut self.conn, uid, from).unwrap_or_default();
+    }
+
+    fn show_net_worth(&mut self, ui: &mut egui::Ui) {
+        let Some(uid) = self.user_id else { return; };
+        self.maybe_refresh_net_worth();
+        let dollars = |cents: i64| format!("${:.2}", cents as f64 / 100.0);
+
+        ui.heading("Net Worth");
+        ui.horizontal(|ui| {
+            for (months, label) in [(3, "3 months"), (12, "1 year"), (0, "All")] {
+                if ui.radio_value(&mut self.net_worth_months, months, label).clicked() {
+                    self.net_worth_loaded_at = None;
+                }
+            }
+            ui.separator();
+            ui.radio_value(&mut self.net_worth_by_account, false, "Assets and liabilities");
+            ui.radio_value(&mut self.net_worth_by_account, true, "By account");
+            ui.separator();
+            if ui
+                .button("Rebuild History")
+                .on_hover_text("Recalculate every past day's balances from your transactions")
+                .clicked()
+            {
+                match db::backfill_balance_snapshots(&mut self.conn, uid, chrono::Local::now().date_naive()) {
+                    Ok(days) => self.message = format!("Rebuilt {} days of balance history.", days),
+                    Err(e) => self.message = format!("Failed to rebuild balance history: {}", friendly_error(&e)),
+                }
+                self.net_worth_loaded_at = None;
+            }
+        });
+
+        let points = net_worth::net_worth_by_day(&self.net_worth_snapshots);
+        let (Some(first), Some(latest)) = (points.first().copied(), points.last().copied()) else {
+            ui.label("No balance history yet. Click Rebuild History to build it from your transactions.");
+            return;
+        };
+        ui.label(format!(
+            "Net worth {} on {}: assets {}, liabilities {}.",
+            dollars(latest.net()),
+            latest.day.format("%Y-%m-%d"),
+            dollars(latest.assets),
+            dollars(latest.liabilities)
+        ));
+        ui.label(egui::RichText::new("Accounts with a negative balance count as liabilities.").small().weak());
+
+        let start = first.day;
+        let x = move |day: NaiveDate| (day - start).num_days() as f64;
+        let lines: Vec<(String, Vec<[f64; 2]>)> = if self.net_worth_by_account {
+            net_worth::balances_by_account(&self.net_worth_snapshots)
+                .into_iter()
+                .map(|(account, balances)| {
+                    let line = balances.iter().map(|(day, cents)| [x(*day), *cents as f64 / 100.0]).collect();
+                    (self.account_name(account), line)
+                })
+                .collect()
+        } else {
+            let series = |value: fn(&NetWorthPoint) -> i64| -> Vec<[f64; 2]> {
+                points.iter().map(|p| [x(p.day), value(p) as f64 / 100.0]).collect()
+            };
+            vec![
+                ("Assets".to_string(), series(|p| p.assets)),
+                ("Liabilities".to_string(), series(|p| p.liabilities)),
+                ("Net worth".to_string(), series(|p| p.net())),
+            ]
+        };
+        egui::CollapsingHeader::new("Net worth chart")
+            .default_open(true)
+            .show(ui, |ui| {
+                Plot::new("net_worth_plot")
+                    .height(200.0)
+                    .allow_scroll(false)
+                    .legend(Legend::default())
+                    .x_axis_formatter(move |mark, _range| {
+                        (start + chrono::Duration::days(mark.value.round() as i64)).format("%Y-%m-%d").to_string()
+                    })
+                    .label_formatter(move |name, point| {
+                        let day = start + chrono::Duration::days(point.x.round() as i64);
+                        format!("{}\n{}: ${:.2}", name, day.format("%Y-%m-%d"), point.y)
+                    })
+                    .show(ui, |plot_ui| {
+                        for (name, line) in lines {
+                            plot_ui.line(Line::new(line).name(name));
+                        }
+                    });
+            });
+
+        for (title, format, ends) in [
+            ("Month-end figures", "%b %Y", net_worth::month_ends(&points)),
+            ("Year-end figures", "%Y", net_worth::year_ends(&points)),
+        ] {
+            egui::CollapsingHeader::new(title).show(ui, |ui| {
+                egui::Grid::new(title).striped(true).num_columns(5).show(ui, |ui| {
+                    ui.strong("Period");
+                    ui.strong("Assets");
+                    ui.strong("Liabilities");
+                    ui.strong("Net Worth");
+                    ui.strong("Change");
+                    ui.end_row();
+                    let mut previous: Option<i64> = None;
+                    let rows: Vec<(NetWorthPoint, Option<i64>)> = ends
+                        .iter()
+                        .map(|p| {
+                            let change = previous.map(|before| p.net() - before);
+                            previous = Some(p.net());
+                            (*p, change)
+                        })
+                        .collect();
+                    for (p, change) in rows.into_iter().rev() {
+                        ui.label(p.day.format(format).to_string());
+                        ui.label(dollars(p.assets));
+                        ui.label(dollars(p.liabilities));
+                        ui.label(dollars(p.net()));
+                        match change {
+                            Some(c) if c >= 0 => ui.colored_label(Color32::from_rgb(100, 200, 100), format!("+{}", dollars(c))),
+                            Some(c) => ui.colored_label(Color32::from_rgb(220, 50, 50), dollars(c)),
+                            None => ui.label("-"),
+                        };
+                        ui.end_row();
+                    }
+                });
+            });
+        }
+    }
+
+    fn load_savings_goals(&mut self) {
+        let Some(uid) = self.user_id else { return; };
+        let today = chrono::Local::now().date_naive();
+        self.savings_goals = db::get_savings_goals(&mut self.conn, uid, today).unwrap_or_default();
+    }
+
+    /// Fills in the recurring transfer form with the monthly contribution a goal needs, up to
+    /// its target date, and opens it so the user can pick the account to pay from.
+    fn offer_goal_transfer(&mut self, progress: &GoalProgress, monthly_cents: i64) {
+        let Some(&to_account) = progress.account_ids.first() else { return; };
+        let next_month = budgeting::period_range(Period::Monthly, chrono::Local::now().date_naive(), 1).0;
+        self.recurring_transfer_editing_id = None;
+        self.recurring_transfer_to_account_id = to_account;
+        if self.recurring_transfer_from_account_id == to_account {
+            self.recurring_transfer_from_account_id = 0;
+        }
+        self.recurring_transfer_amount = monthly_cents as f32 / 100.0;
+        self.recurring_transfer_next_run_at = (next_month + chrono::Duration::hours(9)).format("%Y-%m-%d %H:%M:%S").to_string();
+        self.recurring_transfer_rule = "FREQ=MONTHLY".to_string();
+        self.recurring_transfer_has_end = true;
+        self.recurring_transfer_end_date = progress.goal.target_date.clone();
+        self.recurring_transfer_has_limit = false;
+        self.screen = AppState::Transfers;
+        self.load_user_transactions();
+        self.load_user_recurring_transfers();
+        self.message = format!("Choose the account to pay into {} from, then click Add Recurring Transfer.", progress.goal.name);
+    }
+
+    fn show_savings_goals(&mut self, ctx: &egui::Context) {
+        let Some(uid) = self.user_id else { return; };
+        let dollars = |cents: i64| format!("${:.2}", cents as f64 / 100.0);
+        let today = chrono::Local::now().date_naive();
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+        egui::ScrollArea::vertical().auto_shrink([false; 2]).show(ui, |ui| {
+            ui.heading("Savings Goals");
+            if ui.button("Back to Dashboard").clicked() {
+                self.screen = AppState::Dashboard;
+            }
+            ui.separator();
+
+            if self.savings_goals.is_empty() {
+                ui.label("No goals yet. Add one below.");
+            }
+            let mut transfer: Option<(GoalProgress, i64)> = None;
+            let mut delete: Option<i32> = None;
+            for progress in &self.savings_goals {
+                let goal = &progress.goal;
+                ui.horizontal(|ui| {
+                    ui.strong(&goal.name);
+                    ui.label(format!("{} of {} by {}", dollars(progress.saved), dollars(goal.target_cents as i64), goal.target_date));
+                    if ui.small_button("Delete").clicked() {
+                        delete = Some(goal.id);
+                    }
+                });
+                let color = if progress.is_reached() { Color32::from_rgb(100, 200, 100) } else { Color32::from_rgb(90, 150, 230) };
+                ui.add_sized([300.0, 20.0], egui::ProgressBar::new(progress.fraction()).fill(color).show_percentage());
+                let accounts = progress.account_ids.iter().map(|a| self.account_name(*a)).collect::<Vec<_>>().join(", ");
+                ui.label(egui::RichText::new(format!("Tracking {}", if accounts.is_empty() { "no accounts".to_string() } else { accounts })).small());
+
+                if progress.is_reached() {
+                    ui.colored_label(color, "Goal reached!");
+                } else {
+                    if let Some(required) = progress.required_monthly(today) {
+                        ui.horizontal(|ui| {
+                            ui.label(format!(
+                                "Needs {}/month to finish on time, at {:.1}% expected return.",
+                                dollars(required),
+                                goal.expected_rate * 100.0
+                            ));
+                            if required > 0 && !progress.account_ids.is_empty() && ui.button("Set Up Monthly Transfer").clicked() {
+                                transfer = Some((progress.clone(), required));
+                            }
+                        });
+                    }
+                    let pace = format!("At your recent pace of {}/month", dollars(progress.monthly_pace));
+                    match progress.projected_completion(today) {
+                        Some(done) => {
+                            let late = progress.target_date().is_some_and(|target| done > target);
+                            let text = format!("{}, you'll get there around {}.", pace, done.format("%b %Y"));
+                            if late {
+                                ui.colored_label(Color32::from_rgb(250, 200, 50), format!("{} That's after the target date.", text));
+                            } else {
+                                ui.label(text);
+                            }
+                        }
+                        None => {
+                            ui.colored_label(Color32::from_rgb(220, 50, 50), format!("{}, you won't reach this goal.", pace));
+                        }
+                    }
+                }
+                ui.separator();
+            }
+            if let Some((progress, monthly)) = transfer {
+                self.offer_goal_transfer(&progress, monthly);
+            }
+            if let Some(goal_id) = delete {
+                match db::delete_savings_goal(&mut self.conn, uid, goal_id) {
+                    Ok(()) => self.load_savings_goals(),
+                    Err(e) => self.message = format!("Failed to delete goal: {}", friendly_error(&e)),
+                }
+            }
+
+            ui.heading("Add a Goal");
+            ui.horizontal(|ui| {
+                ui.label("Name:");
+                ui.text_edit_singleline(&mut self.goal_name);
+            });
+            ui.horizontal(|ui| {
+                ui.label("Target:");
+                ui.add(egui::DragValue::new(&mut self.goal_target).speed(10.0).prefix("$").range(0.0..=f32::MAX));
+                ui.label("by");
+                Self::show_date_selector(ui, &mut self.goal_date, "goal_date");
+            });
+            ui.horizontal(|ui| {
+                ui.label("Expected annual return:");
+                ui.add(egui::DragValue::new(&mut self.goal_rate_percent).speed(0.1).range(0.0..=100.0).suffix("%"));
+            });
+            ui.label("Accounts:");
+            for account in &self.accounts_list {
+                let mut linked = self.goal_account_ids.contains(&account.id);
+                if ui.checkbox(&mut linked, format!("{} (${:.2})", account.name, account.balance)).changed() {
+                    if linked {
+                        self.goal_account_ids.push(account.id);
+                    } else {
+                        self.goal_account_ids.retain(|a| *a != account.id);
+                    }
+                }
+            }
+            if ui.button("Create Goal").clicked() {
+                let now = chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+                let created = db::create_savings_goal(
+                    &mut self.conn,
+                    crate::models::NewSavingsGoal {
+                        user_id: uid,
+                        name: &self.goal_name,
+                        target_cents: (self.goal_target * 100.0).round() as i32,
+                        target_date: &self.goal_date,
+                        expected_rate: self.goal_rate_percent / 100.0,
+                        created_at: &now,
+                    },
+                    &self.goal_account_ids,
+                );
+                match created {
+                    Ok(goal) => {
+                        self.message = format!("Added goal {}", goal.name);
+                        self.goal_name.clear();
+                        self.goal_target = 0.0;
+                        self.goal_account_ids.clear();
+                        self.load_savings_goals();
+                    }
+                    Err(e) => self.message = format!("Failed to add goal: {}", friendly_error(&e)),
+                }
+            }
+            ui.separator();
+            ui.label(&self.message);
+        });
+        });
+    }
+
+    fn open_envelopes(&mut self) {
+        self.screen = AppState::Envelopes;
+        self.envelope_month = chrono::Local::now().date_naive().with_day(1).unwrap();
+        self.load_user_categories();
+        self.load_envelopes();
+    }
+
+    fn load_envelopes(&mut self) {
+        let Some(uid) = self.user_id else {
+            self.envelope_statuses.clear();
+            return;
+        };
+        self.envelope_statuses = db::get_envelope_statuses(&mut self.conn, uid, self.envelope_month).unwrap_or_default();
+        self.load_available_to_assign(uid);
+    }
+
+    fn show_envelopes(&mut self, ctx: &egui::Context) {
+        let Some(uid) = self.user_id else { return; };
+        let dollars = |cents: i64| format!("${:.2}", cents as f64 / 100.0);
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            self.maybe_process_due_recurring();
+            ui.heading("Envelopes");
+
+            ui.horizontal(|ui| {
+                if ui.button("Back to Dashboard").clicked() {
+                    self.screen = AppState::Dashboard;
+                }
+                ui.separator();
+                if ui.button("Prev").clicked() {
+                    self.envelope_month = budgeting::period_range(Period::Monthly, self.envelope_month, -1).0.date();
+                    self.load_envelopes();
+                }
+                ui.label(egui::RichText::new(self.envelope_month.format("%B %Y").to_string()).strong());
+                if ui.button("Next").clicked() {
+                    self.envelope_month = budgeting::period_range(Period::Monthly, self.envelope_month, 1).0.date();
+                    self.load_envelopes();
+                }
+            });
+
+            let pool = self.available_to_assign.unwrap_or(0);
+            let pool_color = if pool < 0 { Color32::RED } else { Color32::from_rgb(100, 200, 100) };
+            ui.label(egui::RichText::new(format!("To Be Assigned: {}", dollars(pool))).heading().color(pool_color));
+            if pool < 0 {
+                ui.colored_label(Color32::RED, "More has been assigned than you have. Take money back from an envelope.");
+            }
+            ui.separator();
+
+            let now = chrono::Local::now().naive_local();
+            let mut changed = false;
+            let mut delete_id: Option<i32> = None;
+            egui::Grid::new("envelope_grid").striped(true).num_columns(6).show(ui, |ui| {
+                ui.strong("Envelope");
+                ui.strong("Assigned");
+                ui.strong("Activity");
+                ui.strong("Available");
+                ui.strong("Assign ($, negative to return)");
+                ui.label("");
+                ui.end_row();
+
+                for status in &self.envelope_statuses {
+                    ui.label(&status.category);
+                    ui.label(dollars(status.assigned));
+                    ui.label(dollars(status.activity));
+                    let available_color = if status.available < 0 { Color32::RED } else { Color32::from_rgb(100, 200, 100) };
+                    ui.colored_label(available_color, dollars(status.available));
+                    ui.horizontal(|ui| {
+                        let input = self.envelope_assign_inputs.entry(status.envelope_id).or_insert(0.0);
+                        ui.add(egui::DragValue::new(input).speed(1.0).prefix("$"));
+                        if ui.button("Assign").clicked() {
+                            let cents = (*input * 100.0).round() as i32;
+                            match db::assign_to_envelope(&mut self.conn, uid, status.envelope_id, self.envelope_month, cents, now) {
+                                Ok(()) => {
+                                    *input = 0.0;
+                                    changed = true;
+                                }
+                                Err(e) => self.message = format!("Failed to assign: {}", friendly_error(&e)),
+                            }
+                        }
+                        if status.available < 0 && ui.button("Cover").on_hover_text("Assign enough to bring this envelope back to zero").clicked() {
+                            match db::assign_to_envelope(&mut self.conn, uid, status.envelope_id, self.envelope_month, (-status.available) as i32, now) {
+                                Ok(()) => changed = true,
+                                Err(e) => self.message = format!("Failed to assign: {}", friendly_error(&e)),
+                            }
+                        }
+                    });
+                    if ui.small_button("Remove").on_hover_text("Delete the envelope; what it holds returns to To Be Assigned").clicked() {
+                        delete_id = Some(status.envelope_id);
+                    }
+                    ui.end_row();
+                }
+            });
+            if self.envelope_statuses.is_empty() {
+                ui.label("No envelopes yet. Add one for each category you want to give money to.");
+            }
+
+            if let Some(envelope_id) = delete_id {
+                match db::delete_envelope(&mut self.conn, uid, envelope_id) {
+                    Ok(()) => changed = true,
+                    Err(e) => self.message = format!("Failed to remove envelope: {}", friendly_error(&e)),
+                }
+            }
+
+            ui.separator();
+            if self.envelope_statuses.len() >= 2 {
+                ui.horizontal(|ui| {
+                    ui.label("Move");
+                    ui.add(egui::DragValue::new(&mut self.envelope_move_amount).speed(1.0).prefix("$").range(0.0..=f32::MAX));
+                    let name_of = |id: i32| {
+                        self.envelope_statuses
+                            .iter()
+                            .find(|s| s.envelope_id == id)
+                            .map(|s| s.category.clone())
+                            .unwrap_or_else(|| "Choose".to_string())
+                    };
+                    ui.label("from");
+                    egui::ComboBox::from_id_salt("envelope_move_from")
+                        .selected_text(name_of(self.envelope_move_from))
+                        .show_ui(ui, |ui| {
+                            for status in &self.envelope_statuses {
+                                ui.selectable_value(&mut self.envelope_move_from, status.envelope_id, &status.category);
+                            }
+                        });
+                    ui.label("to");
+                    egui::ComboBox::from_id_salt("envelope_move_to")
+                        .selected_text(name_of(self.envelope_move_to))
+                        .show_ui(ui, |ui| {
+                            for status in &self.envelope_statuses {
+                                ui.selectable_value(&mut self.envelope_move_to, status.envelope_id, &status.category);
+                            }
+                        });
+                    if ui.button("Move").clicked() {
+                        let cents = (self.envelope_move_amount * 100.0).round() as i32;
+                        match db::move_between_envelopes(&mut self.conn, uid, self.envelope_move_from, self.envelope_move_to, self.envelope_month, cents, now) {
+                            Ok(()) => {
+                                self.envelope_move_amount = 0.0;
+                                changed = true;
+                            }
+                            Err(e) => self.message = format!("Failed to move money: {}", friendly_error(&e)),
+                        }
+                    }
+                });
+            }
+
+            ui.horizontal(|ui| {
+                ui.label("New envelope:");
+                let taken: Vec<&str> = self.envelope_statuses.iter().map(|s| s.category.as_str()).collect();
+                let choices: Vec<String> = self
+                    .get_all_categories()
+                    .into_iter()
+                    .filter(|c| c != "Transfer" && !taken.contains(&c.as_str()))
+                    .collect();
+                egui::ComboBox::from_id_salt("envelope_new_category")
+                    .selected_text(&self.envelope_new_category)
+                    .show_ui(ui, |ui| {
+                        for cat in choices {
+                            ui.selectable_value(&mut self.envelope_new_category, cat.clone(), cat);
+                        }
+                    });
+                if ui.button("Add Envelope").clicked() {
+                    match db::create_envelope(&mut self.conn, uid, &self.envelope_new_category, self.envelope_month) {
+                        Ok(_) => {
+                            self.envelope_new_category.clear();
+                            changed = true;
+                        }
+                        Err(e) => self.message = format!("Failed to add envelope: {}", friendly_error(&e)),
+                    }
+                }
+            });
+
+            if changed {
+                self.message.clear();
+                self.load_envelopes();
+            }
+            ui.label(&self.message);
+        });
+    }
+
+    fn load_user_budgets(&mut self) {
+        if let Some(uid) = self.user_id {
+            match db::get_user_budgets(&mut self.conn, uid) {
+                Ok(bs) => self.budgets = bs,
+                Err(_) => self.budgets.clear(),
+            }
+        } else {
+            self.budgets.clear();
+        }
+    }
+
+
+    fn get_period_range(period: Period, offset: i32) -> (NaiveDateTime, NaiveDateTime) {
+        budgeting::period_range(period, chrono::Local::now().date_naive(), offset)
+    }
+
+    fn compute_budget_progress(&mut self, offset: i32) {
+        self.budget_progress.clear();
+        self.budget_rollovers.clear();
+        self.budget_scopes.clear();
+        self.budget_paces.clear();
+        if let Some(uid) = self.user_id {
+            let now = chrono::Local::now().naive_local();
+            let today = now.date();
+            for b in &self.budgets {
+                let (start, end) = budgeting::budget_range(b, today, offset);
+                let rollover = db::get_budget_rollover(&mut self.conn, uid, b, start).unwrap_or(0);
+                self.budget_rollovers.insert(b.id.unwrap_or(0), rollover);
+                let scope = db::get_budget_scope(&mut self.conn, b).unwrap_or_else(|_| BudgetScope::category(&b.category));
+                self.budget_scopes.insert(b.id.unwrap_or(0), scope);
+                match db::get_budget_spend(&mut self.conn, b, start, end) {
+                    Ok(spent_cents) => {
+                        self.budget_progress.insert(b.id.unwrap_or(0), (spent_cents, b.limit_cents));
+                        if TargetType::from_str(&b.target_type) == TargetType::Expense {
+                            let recurring = db::get_budget_recurring_spend(&mut self.conn, b, start, end).unwrap_or(0);
+                            let upcoming: i64 = schedule::expand_occurrences(
+                                &self.recurring_transactions_list,
+                                &[],
+                                &self.holiday_calendars,
+                                now,
+                                end,
+                            )
+                            .iter()
+                            .filter(|o| o.amount < 0.0 && self.budget_scopes[&b.id.unwrap_or(0)].matches(&o.category, o.account_id))
+                            .map(|o| (-o.amount * 100.0).round() as i64)
+                            .sum();
+                            let limit = b.limit_cents as i64 + rollover;
+                            if let Some(pace) = budgeting::project_pace(-spent_cents, -recurring, upcoming, limit, (start, end), now) {
+                                self.budget_paces.insert(b.id.unwrap_or(0), pace);
+                            }
+                        }
+                    }
+                    Err(_) => {
+                        self.budget_progress.insert(b.id.unwrap_or(0), (0, b.limit_cents));
+                    }
+                }
+            }
+        }
+    }
+
+    fn load_budget_history(&mut self) {
+        let today = chrono::Local::now().date_naive();
+        let periods = self.budget_history_periods;
+        let budgets = self.budgets.clone();
+        self.budget_history = budgets
+            .into_iter()
+            .map(|b| {
+                let history = db::get_budget_history(&mut self.conn, &b, today, periods).unwrap_or_default();
+                (b, history)
+            })
+            .collect();
+    }
+
+    fn export_budget_history(&mut self) {
+        let file_path = format!("budget_history_{}.csv", chrono::Local::now().format("%Y%m%d"));
+        match Writer::from_path(&file_path) {
+            Ok(mut writer) => {
+                let _ = writer.write_record([
+                    "budget", "type", "period_start", "period_end", "budgeted", "actual", "variance", "variance_percent", "on_target",
+                ]);
+                let mut rows = 0;
+                for (b, history) in &self.budget_history {
+                    let target = TargetType::from_str(&b.target_type);
+                    for p in history {
+                        let _ = writer.write_record([
+                            b.display_name().to_string(),
+                            b.target_type.clone(),
+                            p.start.format("%Y-%m-%d").to_string(),
+                            (p.end - chrono::Duration::days(1)).format("%Y-%m-%d").to_string(),
+                            format!("{:.2}", p.budgeted as f64 / 100.0),
+                            format!("{:.2}", p.actual as f64 / 100.0),
+                            format!("{:.2}", p.variance(target) as f64 / 100.0),
+                            p.variance_percent(target).map(|v| format!("{:.1}", v)).unwrap_or_default(),
+                            p.on_target(target).to_string(),
+                        ]);
+                        rows += 1;
+                    }
+                }
+                let _ = writer.flush();
+                self.message = format!("Exported {} budget periods to {}", rows, file_path);
+            }
+            Err(e) => {
+                self.message = format!("Failed to export CSV: {}", e);
+            }
+        }
+    }
+
+    fn show_budget_history(&mut self, ctx: &egui::Context) {
+        let dollars = |cents: i64| format!("${:.2}", cents as f64 / 100.0);
+        let good = Color32::from_rgb(100, 200, 100);
+        let bad = Color32::from_rgb(220, 50, 50);
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+        egui::ScrollArea::vertical().auto_shrink([false; 2]).show(ui, |ui| {
+            ui.heading("Budget History");
+            ui.horizontal(|ui| {
+                if ui.button("Back to Budgets").clicked() {
+                    self.screen = AppState::Budgeting;
+                }
+                ui.separator();
+                ui.label("Periods:");
+                if ui.add(egui::DragValue::new(&mut self.budget_history_periods).range(1..=36)).changed() {
+                    self.load_budget_history();
+                }
+                if ui.button("Export CSV").clicked() {
+                    self.export_budget_history();
+                }
+            });
+            ui.separator();
+
+            if self.budget_history.is_empty() {
+                ui.label("No budgets yet.");
+            }
+            let now = chrono::Local::now().naive_local();
+            for (b, history) in &self.budget_history {
+                let target = TargetType::from_str(&b.target_type);
+                let id = b.id.unwrap_or(0);
+                ui.horizontal(|ui| {
+                    ui.strong(b.display_name());
+                    ui.label(egui::RichText::new(format!("{} {}", b.period, b.target_type)).small().weak());
+                    let unit = if budgeting::custom_range(b).is_some() { "periods" } else { Period::from_str(&b.period).plural() };
+                    if let Some((on_target, count)) = budgeting::performance_streak(history, target, now) {
+                        let text = match (target, on_target) {
+                            (TargetType::Expense, true) => format!("Under budget {} {} running", count, unit),
+                            (TargetType::Expense, false) => format!("Over budget {} {} running", count, unit),
+                            (TargetType::Income, true) => format!("Target met {} {} running", count, unit),
+                            (TargetType::Income, false) => format!("Target missed {} {} running", count, unit),
+                        };
+                        ui.colored_label(if on_target { good } else { bad }, text);
+                    }
+                });
+
+                egui::Grid::new(("budget_history_grid", id)).striped(true).num_columns(5).show(ui, |ui| {
+                    ui.strong("Period");
+                    ui.strong("Budgeted");
+                    ui.strong("Actual");
+                    ui.strong("Variance");
+                    ui.strong("Variance %");
+                    ui.end_row();
+                    for p in history {
+                        let variance = p.variance(target);
+                        let color = if variance >= 0 { good } else { bad };
+                        let running = if p.end > now { " (so far)" } else { "" };
+                        ui.label(format!("{}{}", p.start.format("%b %d, %Y"), running));
+                        ui.label(dollars(p.budgeted));
+                        ui.label(dollars(p.actual));
+                        ui.colored_label(color, dollars(variance));
+                        ui.colored_label(color, p.variance_percent(target).map(|v| format!("{:+.1}%", v)).unwrap_or_else(|| "-".to_string()));
+                        ui.end_row();
+                    }
+                });
+
+                egui::CollapsingHeader::new("Chart").id_salt(("budget_history_chart", id)).show(ui, |ui| {
+                    let budgeted: Vec<Bar> = history
+                        .iter()
+                        .enumerate()
+                        .map(|(i, p)| Bar::new(i as f64 - 0.2, p.budgeted as f64 / 100.0).width(0.35))
+                        .collect();
+                    let actual: Vec<Bar> = history
+                        .iter()
+                        .enumerate()
+                        .map(|(i, p)| {
+                            Bar::new(i as f64 + 0.2, p.actual as f64 / 100.0)
+                                .width(0.35)
+                                .fill(if p.on_target(target) { good } else { bad })
+                        })
+                        .collect();
+                    let starts: Vec<String> = history.iter().map(|p| p.start.format("%Y-%m-%d").to_string()).collect();
+                    Plot::new(("budget_history_plot", id))
+                        .height(180.0)
+                        .allow_scroll(false)
+                        .legend(Legend::default())
+                        .x_axis_formatter(move |mark, _range| {
+                            let index = mark.value.round();
+                            if (mark.value - index).abs() > 0.01 || index < 0.0 {
+                                return String::new();
+                            }
+                            starts.get(index as usize).cloned().unwrap_or_default()
+                        })
+                        .show(ui, |plot_ui| {
+                            plot_ui.bar_chart(BarChart::new(budgeted).name("Budgeted").color(Color32::GRAY));
+                            plot_ui.bar_chart(BarChart::new(actual).name("Actual"));
+                        });
+                });
+                ui.separator();
+            }
+            ui.label(&self.message);
+        });
+        });
+    }
+
+    fn load_budget_templates(&mut self) {
+        let Some(uid) = self.user_id else { return; };
+        let today = chrono::Local::now().date_naive();
+
+        let (start, end) = budgeting::recent_months(today, self.template_income_months);
+        let income = db::get_income_for_period(&mut self.conn, uid, start, end).unwrap_or(0);
+        self.template_income = income / self.template_income_months.max(1) as i64;
+
+        let (start, end) = budgeting::recent_months(today, self.template_spend_months);
+        let spend = db::get_spend_by_category_period(&mut self.conn, uid, start, end).unwrap_or_default();
+        self.template_spend_drafts = budgeting::drafts_from_average_spend(&spend, self.template_spend_months)
+            .into_iter()
+            .map(|draft| (draft, true))
+            .collect();
+
+        self.budget_templates = db::get_budget_templates(&mut self.conn, uid).unwrap_or_default();
+    }
+
+    fn create_budgets_from(&mut self, uid: i32, drafts: &[BudgetDraft]) {
+        match db::create_budgets_from_drafts(&mut self.conn, uid, drafts) {
+            Ok(created) => {
+                let skipped = drafts.len() - created;
+                self.message = if skipped > 0 {
+                    format!("Created {} budget(s); {} already had a budget", created, skipped)
+                } else {
+                    format!("Created {} budget(s)", created)
+                };
+                self.load_user_budgets();
+                self.compute_budget_progress(self.period_offset);
+            }
+            Err(e) => self.message = format!("Failed to create budgets: {}", friendly_error(&e)),
+        }
+    }
+
+    fn load_savings_report(&mut self) {
+        let Some(uid) = self.user_id else { return; };
+        let today = chrono::Local::now().date_naive();
+        self.savings_report = db::get_monthly_savings(&mut self.conn, uid, today, self.savings_months).unwrap_or_default();
+    }
+
+    fn show_savings_rate(&mut self, ctx: &egui::Context) {
+        let dollars = |cents: i64| format!("${:.2}", cents as f64 / 100.0);
+        let percent = |rate: Option<f64>| rate.map(|r| format!("{:.1}%", r)).unwrap_or_else(|| "-".to_string());
+        let good = Color32::from_rgb(100, 200, 100);
+        let bad = Color32::from_rgb(220, 50, 50);
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+        egui::ScrollArea::vertical().auto_shrink([false; 2]).show(ui, |ui| {
+            ui.heading("Savings Rate");
             ui.horizontal(|ui| {
-                if ui.button("Savings Calculator").clicked() {
-                    self.screen = AppState::SavingsCalculator;
+                if ui.button("Back to Budgets").clicked() {
+                    self.screen = AppState::Budgeting;
                 }
-                if ui.button("Bond Tools").clicked() {
-                    self.screen = AppState::BondTools;
+                ui.separator();
+                ui.label("Months:");
+                let mut reload = false;
+                for months in [6, 12, 24] {
+                    reload |= ui.radio_value(&mut self.savings_months, months, months.to_string()).changed();
                 }
-                if ui.button("Mortgage Tools").clicked() {
-                    self.screen = AppState::MortgageTools;
+                if reload {
+                    self.load_savings_report();
                 }
-                if ui.button("Cash Flow Tools").clicked() {
-                    self.screen = AppState::CashflowTools;
+            });
+            ui.label(egui::RichText::new("Transfers between your accounts count as neither income nor spending.").small().weak());
+            ui.separator();
+
+            let overall = budgeting::overall_savings_rate(&self.savings_report);
+            ui.label(format!(
+                "Over {} months: income {} | expenses {} | saved {} | savings rate {}",
+                self.savings_report.len(),
+                dollars(self.savings_report.iter().map(|m| m.income).sum()),
+                dollars(self.savings_report.iter().map(|m| m.expenses).sum()),
+                dollars(self.savings_report.iter().map(|m| m.saved()).sum()),
+                percent(overall)
+            ));
+
+            egui::Grid::new("savings_rate_grid").striped(true).num_columns(5).show(ui, |ui| {
+                ui.strong("Month");
+                ui.strong("Income");
+                ui.strong("Expenses");
+                ui.strong("Saved");
+                ui.strong("Savings Rate");
+                ui.end_row();
+                for m in &self.savings_report {
+                    let color = if m.saved() >= 0 { good } else { bad };
+                    ui.label(m.month.format("%b %Y").to_string());
+                    ui.label(dollars(m.income));
+                    ui.label(dollars(m.expenses));
+                    ui.colored_label(color, dollars(m.saved()));
+                    ui.colored_label(color, percent(m.rate()));
+                    ui.end_row();
                 }
             });
 
-            ui.label(&self.message);
+            let bars: Vec<Bar> = self
+                .savings_report
+                .iter()
+                .enumerate()
+                .filter_map(|(i, m)| {
+                    let rate = m.rate()?;
+                    Some(Bar::new(i as f64, rate).width(0.6).fill(if rate >= 0.0 { good } else { bad }))
+                })
+                .collect();
+            let months: Vec<String> = self.savings_report.iter().map(|m| m.month.format("%b %Y").to_string()).collect();
+            Plot::new("savings_rate_plot")
+                .height(200.0)
+                .allow_scroll(false)
+                .y_axis_formatter(|mark, _range| format!("{:.0}%", mark.value))
+                .x_axis_formatter(move |mark, _range| {
+                    let index = mark.value.round();
+                    if (mark.value - index).abs() > 0.01 || index < 0.0 {
+                        return String::new();
+                    }
+                    months.get(index as usize).cloned().unwrap_or_default()
+                })
+                .show(ui, |plot_ui| {
+                    plot_ui.bar_chart(BarChart::new(bars).name("Savings rate"));
+                });
+        });
         });
     }
 
-    fn load_user_budgets(&mut self) {
-        if let Some(uid) = self.user_id {
-            match db::get_user_budgets(&mut self.conn, uid) {
-                Ok(bs) => self.budgets = bs,
-                Err(_) => self.budgets.clear(),
+    fn load_reports(&mut self) {
+        let Some(uid) = self.user_id else { return; };
+        match db::get_income_statement(&mut self.conn, uid, self.report_month) {
+            Ok(statement) => self.income_statement = Some(statement),
+            Err(e) => {
+                self.income_statement = None;
+                self.message = format!("Failed to load the income statement: {}", friendly_error(&e));
             }
-        } else {
-            self.budgets.clear();
+        }
+        self.category_trends = db::get_category_trends(&mut self.conn, uid, self.report_month, REPORT_TREND_MONTHS).unwrap_or_default();
+        if !self.category_trends.iter().any(|t| Some(&t.category) == self.trend_category.as_ref()) {
+            self.trend_category = self.category_trends.first().map(|t| t.category.clone());
         }
     }
 
+    fn show_reports(&mut self, ctx: &egui::Context) {
+        let dollars = |cents: i64| format!("${:.2}", cents as f64 / 100.0);
+        let good = Color32::from_rgb(100, 200, 100);
+        let bad = Color32::from_rgb(220, 50, 50);
+        let this_month = budgeting::period_range(Period::Monthly, chrono::Local::now().date_naive(), 0).0.date();
 
-    fn get_period_range(period: Period, offset: i32) -> (NaiveDateTime, NaiveDateTime) {
-        use chrono::{Datelike, Duration, Local, NaiveDate};
-        let today = Local::now().date_naive();
-        match period {
-            Period::Daily => {
-                let day = today + Duration::days(offset as i64);
-                let start = day.and_hms_opt(0, 0, 0).unwrap();
-                let end = (day + Duration::days(1)).and_hms_opt(0, 0, 0).unwrap();
-                (start, end)
-            }
-            Period::Weekly => {
-                let weekday = today.weekday().num_days_from_monday() as i64;
-                let week_start = today - Duration::days(weekday) + Duration::weeks(offset as i64);
-                let start = week_start.and_hms_opt(0, 0, 0).unwrap();
-                let end = (week_start + Duration::weeks(1)).and_hms_opt(0, 0, 0).unwrap();
-                (start, end)
-            }
-            Period::Monthly => {
-                let mut year = today.year();
-                let mut month = today.month() as i32;
-                let total_month = month - 1 + offset;
-                year += total_month.div_euclid(12);
-                month = (total_month.rem_euclid(12) + 1) as u32 as i32;
-                let start_date = NaiveDate::from_ymd_opt(year, month as u32, 1).unwrap();
-                let next_month = if month == 12 {
-                    NaiveDate::from_ymd_opt(year + 1, 1, 1).unwrap()
-                } else {
-                    NaiveDate::from_ymd_opt(year, (month + 1) as u32, 1).unwrap()
+        egui::CentralPanel::default().show(ctx, |ui| {
+        egui::ScrollArea::vertical().auto_shrink([false; 2]).show(ui, |ui| {
+            ui.heading("Reports");
+            ui.horizontal(|ui| {
+                if ui.button("Back to Dashboard").clicked() {
+                    self.screen = AppState::Dashboard;
+                }
+                ui.separator();
+                let mut step = 0;
+                if ui.button("◀").clicked() {
+                    step = -1;
+                }
+                ui.label(self.report_month.format("%B %Y").to_string());
+                if ui.add_enabled(self.report_month < this_month, egui::Button::new("▶")).clicked() {
+                    step = 1;
+                }
+                if step != 0 {
+                    self.report_month = budgeting::period_range(Period::Monthly, self.report_month, step).0.date();
+                    self.load_reports();
+                }
+            });
+            ui.label(egui::RichText::new("Transfers between your accounts count as neither income nor spending.").small().weak());
+            ui.separator();
+
+            ui.heading("Income Statement");
+            if let Some(statement) = &self.income_statement {
+                let income = statement.total_income();
+                let share = |cents: i64| {
+                    if income > 0 { format!("{:.1}%", cents as f64 / income as f64 * 100.0) } else { "-".to_string() }
                 };
-                (start_date.and_hms_opt(0, 0, 0).unwrap(), next_month.and_hms_opt(0, 0, 0).unwrap())
+                egui::Grid::new("income_statement_grid").striped(true).num_columns(3).show(ui, |ui| {
+                    ui.strong("Category");
+                    ui.strong("Amount");
+                    ui.strong("% of Income");
+                    ui.end_row();
+                    for (title, lines, total_label, total) in [
+                        ("Income", &statement.income, "Total income", income),
+                        ("Expenses", &statement.expenses, "Total expenses", statement.total_expenses()),
+                    ] {
+                        ui.strong(title);
+                        ui.end_row();
+                        if lines.is_empty() {
+                            ui.weak("None");
+                            ui.end_row();
+                        }
+                        for (cat, amount) in lines {
+                            ui.label(format!("    {}", cat));
+                            ui.label(dollars(*amount));
+                            ui.label(share(*amount));
+                            ui.end_row();
+                        }
+                        ui.strong(total_label);
+                        ui.strong(dollars(total));
+                        ui.label(share(total));
+                        ui.end_row();
+                    }
+                    let net = statement.net();
+                    let color = if net >= 0 { good } else { bad };
+                    ui.strong("Net");
+                    ui.colored_label(color, dollars(net));
+                    ui.colored_label(color, share(net));
+                    ui.end_row();
+                });
             }
-            Period::Yearly => {
-                let year = today.year() + offset;
-                let start = NaiveDate::from_ymd_opt(year, 1, 1).unwrap().and_hms_opt(0, 0, 0).unwrap();
-                let end = NaiveDate::from_ymd_opt(year + 1, 1, 1).unwrap().and_hms_opt(0, 0, 0).unwrap();
-                (start, end)
+            ui.separator();
+
+            ui.heading("Category Trends");
+            ui.label(format!(
+                "The {} months to {}. Income categories show money in, the rest money out; changes compare {} with the month before (MoM) and a year before (YoY).",
+                REPORT_TREND_MONTHS,
+                self.report_month.format("%B %Y"),
+                self.report_month.format("%B")
+            ));
+            if self.category_trends.is_empty() {
+                ui.label("No transactions in these months.");
+                return;
             }
-        }
+            let months: Vec<String> = (0..REPORT_TREND_MONTHS as i32)
+                .map(|i| {
+                    budgeting::period_range(Period::Monthly, self.report_month, i + 1 - REPORT_TREND_MONTHS as i32)
+                        .0
+                        .format("%b %y")
+                        .to_string()
+                })
+                .collect();
+            let change_label = |ui: &mut Ui, trend: &CategoryTrend, change: Option<i64>, before: Option<i64>| {
+                let Some(change) = change else {
+                    ui.label("-");
+                    return;
+                };
+                let percent = before
+                    .and_then(|b| reports::percent_change(b, change))
+                    .map(|p| format!(" ({:+.0}%)", p))
+                    .unwrap_or_default();
+                let sign = if change >= 0 { "+" } else { "-" };
+                let text = format!("{}{}{}", sign, dollars(change.abs()), percent);
+                match change.cmp(&0) {
+                    std::cmp::Ordering::Equal => ui.label(text),
+                    // More income or less spending is good.
+                    order => ui.colored_label(if order.is_gt() == trend.is_income { good } else { bad }, text),
+                };
+            };
+            egui::Grid::new("category_trends_grid")
+                .striped(true)
+                .num_columns(REPORT_TREND_MONTHS + 3)
+                .show(ui, |ui| {
+                    ui.strong("Category");
+                    for month in &months {
+                        ui.strong(month);
+                    }
+                    ui.strong("MoM");
+                    ui.strong("YoY");
+                    ui.end_row();
+                    for trend in &self.category_trends {
+                        if trend.is_income {
+                            ui.label(format!("{} (income)", trend.category));
+                        } else {
+                            ui.label(&trend.category);
+                        }
+                        for amount in &trend.amounts {
+                            ui.label(format!("{:.0}", *amount as f64 / 100.0));
+                        }
+                        let previous = trend.amounts.len().checked_sub(2).map(|i| trend.amounts[i]);
+                        change_label(ui, trend, trend.month_over_month(), previous);
+                        change_label(ui, trend, trend.year_over_year(), trend.year_ago.last().copied());
+                        ui.end_row();
+                    }
+                });
+
+            ui.add_space(8.0);
+            ui.horizontal(|ui| {
+                ui.label("Chart:");
+                egui::ComboBox::from_id_salt("trend_category")
+                    .selected_text(self.trend_category.clone().unwrap_or_default())
+                    .show_ui(ui, |ui| {
+                        for trend in &self.category_trends {
+                            ui.selectable_value(&mut self.trend_category, Some(trend.category.clone()), &trend.category);
+                        }
+                    });
+            });
+            let Some(trend) = self.category_trends.iter().find(|t| Some(&t.category) == self.trend_category.as_ref()) else {
+                return;
+            };
+            let line = |amounts: &[i64]| -> Vec<[f64; 2]> {
+                amounts.iter().enumerate().map(|(i, a)| [i as f64, *a as f64 / 100.0]).collect()
+            };
+            let (current, year_ago) = (line(&trend.amounts), line(&trend.year_ago));
+            Plot::new("category_trend_plot")
+                .height(200.0)
+                .allow_scroll(false)
+                .legend(Legend::default())
+                .x_axis_formatter(move |mark, _range| {
+                    let index = mark.value.round();
+                    if (mark.value - index).abs() > 0.01 || index < 0.0 {
+                        return String::new();
+                    }
+                    months.get(index as usize).cloned().unwrap_or_default()
+                })
+                .show(ui, |plot_ui| {
+                    plot_ui.line(Line::new(current).name(&trend.category));
+                    plot_ui.line(Line::new(year_ago).name("A year earlier").style(egui_plot::LineStyle::dashed_loose()));
+                });
+        });
+        });
     }
 
-    fn compute_budget_progress(&mut self, offset: i32) {
-        self.budget_progress.clear();
-        if let Some(uid) = self.user_id {
-            for b in &self.budgets {
-                let budget_period = Period::from_str(&b.period);
-                let (start, end) = Self::get_period_range(budget_period, offset);
-                match db::get_spend_for_category_period(&mut self.conn, uid, &b.category, start, end) {
-                    Ok(spent_cents) => {
-                        self.budget_progress.insert(b.id.unwrap_or(0), (spent_cents, b.limit_cents));
+    fn show_budget_templates(&mut self, ctx: &egui::Context) {
+        let Some(uid) = self.user_id else { return; };
+        let dollars = |cents: i64| format!("${:.2}", cents as f64 / 100.0);
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+        egui::ScrollArea::vertical().auto_shrink([false; 2]).show(ui, |ui| {
+            ui.heading("Budget Templates");
+            if ui.button("Back to Budgets").clicked() {
+                self.screen = AppState::Budgeting;
+            }
+            ui.separator();
+
+            ui.heading("Split Income (50/30/20)");
+            let mut reload = false;
+            ui.horizontal(|ui| {
+                ui.label("Average income over the last");
+                for months in [3, 6, 12] {
+                    reload |= ui.radio_value(&mut self.template_income_months, months, format!("{} months", months)).changed();
+                }
+            });
+            ui.label(format!("Average monthly income: {}", dollars(self.template_income)));
+            let categories = self.get_all_categories();
+            egui::Grid::new("template_income_shares").num_columns(4).show(ui, |ui| {
+                for (index, share) in self.template_shares.iter_mut().enumerate() {
+                    ui.label(&share.name);
+                    ui.add(egui::DragValue::new(&mut share.percent).range(0.0..=100.0).suffix("%"));
+                    ui.label(format!("{}/month", dollars((self.template_income as f64 * share.percent / 100.0).round() as i64)));
+                    let chosen = if share.categories.is_empty() { "Choose categories".to_string() } else { share.categories.join(", ") };
+                    ui.push_id(index, |ui| {
+                        ui.menu_button(chosen, |ui| {
+                            for cat in categories.iter().filter(|c| *c != "Transfer") {
+                                let mut included = share.categories.contains(cat);
+                                if ui.checkbox(&mut included, cat).changed() {
+                                    if included {
+                                        share.categories.push(cat.clone());
+                                    } else {
+                                        share.categories.retain(|c| c != cat);
+                                    }
+                                }
+                            }
+                        });
+                    });
+                    ui.end_row();
+                }
+            });
+            let total: f64 = self.template_shares.iter().map(|s| s.percent).sum();
+            if (total - 100.0).abs() > 0.01 {
+                ui.colored_label(Color32::from_rgb(250, 200, 50), format!("The shares add up to {:.0}% of income.", total));
+            }
+            if ui.button("Create These Budgets").clicked() {
+                let drafts = budgeting::split_income(self.template_income, &self.template_shares);
+                if drafts.is_empty() {
+                    self.message = "Choose categories for at least one share.".to_string();
+                } else {
+                    self.create_budgets_from(uid, &drafts);
+                }
+            }
+            ui.separator();
+
+            ui.heading("From Average Spending");
+            ui.horizontal(|ui| {
+                ui.label("Average spending over the last");
+                for months in [3, 6, 12] {
+                    reload |= ui.radio_value(&mut self.template_spend_months, months, format!("{} months", months)).changed();
+                }
+            });
+            if self.template_spend_drafts.is_empty() {
+                ui.label("No spending in that time.");
+            }
+            egui::Grid::new("template_spend_drafts").striped(true).num_columns(2).show(ui, |ui| {
+                for (draft, included) in &mut self.template_spend_drafts {
+                    ui.checkbox(included, draft.categories.join(", "));
+                    ui.label(format!("{}/month", dollars(draft.limit_cents as i64)));
+                    ui.end_row();
+                }
+            });
+            if !self.template_spend_drafts.is_empty() && ui.button("Create Selected Budgets").clicked() {
+                let drafts: Vec<BudgetDraft> = self.template_spend_drafts.iter().filter(|(_, inc)| *inc).map(|(d, _)| d.clone()).collect();
+                self.create_budgets_from(uid, &drafts);
+            }
+            ui.separator();
+
+            ui.heading("Saved Templates");
+            let mut apply: Option<i32> = None;
+            let mut delete: Option<i32> = None;
+            for template in &self.budget_templates {
+                ui.horizontal(|ui| {
+                    ui.label(&template.name);
+                    if ui.button("Apply").clicked() {
+                        apply = Some(template.id);
                     }
-                    Err(_) => {
-                        self.budget_progress.insert(b.id.unwrap_or(0), (0, b.limit_cents));
+                    if ui.small_button("Delete").clicked() {
+                        delete = Some(template.id);
                     }
+                });
+            }
+            if let Some(template_id) = apply {
+                match db::get_budget_template_drafts(&mut self.conn, uid, template_id) {
+                    Ok(drafts) => self.create_budgets_from(uid, &drafts),
+                    Err(e) => self.message = format!("Failed to apply template: {}", friendly_error(&e)),
                 }
             }
-        }
+            if let Some(template_id) = delete {
+                match db::delete_budget_template(&mut self.conn, uid, template_id) {
+                    Ok(()) => reload = true,
+                    Err(e) => self.message = format!("Failed to delete template: {}", friendly_error(&e)),
+                }
+            }
+            ui.horizontal(|ui| {
+                ui.label("Save current budgets as:");
+                ui.text_edit_singleline(&mut self.template_name);
+                if ui.button("Save Template").clicked() {
+                    let now = chrono::Local::now().naive_local();
+                    let saved = db::get_budget_drafts(&mut self.conn, uid)
+                        .and_then(|drafts| db::save_budget_template(&mut self.conn, uid, &self.template_name, &drafts, now));
+                    match saved {
+                        Ok(template) => {
+                            self.message = format!("Saved template {}", template.name);
+                            self.template_name.clear();
+                            reload = true;
+                        }
+                        Err(e) => self.message = format!("Failed to save template: {}", friendly_error(&e)),
+                    }
+                }
+            });
+
+            if reload {
+                self.load_budget_templates();
+            }
+            ui.separator();
+            ui.label(&self.message);
+        });
+        });
     }
 
     fn show_budgets(&mut self, ctx: &egui::Context) {
@@ -1296,11 +3603,24 @@ impl FinancerApp {
                 self.compute_budget_progress(self.period_offset);
             }
             
-            if self.period_offset != 0 {
-                if ui.button("Reset to Current").clicked() {
-                    self.period_offset = 0;
-                    self.compute_budget_progress(self.period_offset);
-                }
+            if self.period_offset != 0 && ui.button("Reset to Current").clicked() {
+                self.period_offset = 0;
+                self.compute_budget_progress(self.period_offset);
+            }
+
+            ui.separator();
+            if ui.button("History").clicked() {
+                self.screen = AppState::BudgetHistory;
+                self.load_budget_history();
+            }
+            if ui.button("Templates").clicked() {
+                self.screen = AppState::BudgetTemplates;
+                self.load_user_categories();
+                self.load_budget_templates();
+            }
+            if ui.button("Savings Rate").clicked() {
+                self.screen = AppState::SavingsRate;
+                self.load_savings_report();
             }
 
             ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
@@ -1310,6 +3630,14 @@ impl FinancerApp {
                     self.editor_limit_cents = 0;
                     self.editor_period = Period::Monthly;
                     self.editor_target_is_expense = true;
+                    self.editor_rollover_mode = RolloverMode::None;
+                    self.editor_rollover_cap_cents = 0;
+                    self.editor_alert_thresholds = budgeting::DEFAULT_ALERT_THRESHOLDS.to_string();
+                    self.editor_name.clear();
+                    self.editor_extra_categories.clear();
+                    self.editor_include_subcategories = false;
+                    self.editor_account_ids.clear();
+                    self.editor_custom_dates = false;
                     self.editor_open = true;
                 }
             });
@@ -1327,26 +3655,27 @@ impl FinancerApp {
                     .cloned()
                     .unwrap_or((0, b.limit_cents));
 
-                let budget_period = crate::models::Period::from_str(&b.period);
-                let (start_date, end_date) = Self::get_period_range(budget_period, self.period_offset);
+                let (start_date, end_date) = budgeting::budget_range(b, chrono::Local::now().date_naive(), self.period_offset);
                 let date_range_str = format!(
                     "{} - {}",
                     start_date.format("%b %d, %Y"),
                     end_date.format("%b %d, %Y")
                 );
+                let scope = self.budget_scopes.get(&b.id.unwrap_or(0)).cloned().unwrap_or_else(|| BudgetScope::category(&b.category));
 
                 let target_type = crate::models::TargetType::from_str(&b.target_type);
 
                 let spent_for_bar: f32 = match target_type {
                     crate::models::TargetType::Expense => {
-                        (raw_spent.abs()) as f32
+                        (-raw_spent).max(0) as f32
                     }
                     crate::models::TargetType::Income => {
                         raw_spent.max(0) as f32
                     }
                 };
 
-                let limit_f = limit.max(0) as f32;
+                let rollover = self.budget_rollovers.get(&b.id.unwrap_or(0)).copied().unwrap_or(0);
+                let limit_f = (limit as i64 + rollover).max(0) as f32;
                 let ratio = if limit_f > 0.0 {
                     spent_for_bar / limit_f
                 } else {
@@ -1355,16 +3684,22 @@ impl FinancerApp {
 
                 let progress = ratio.clamp(0.0, 5.0);
 
-                let color = if ratio < 0.8 {
-                    Color32::from_rgb(100, 200, 100)
-                } else if ratio <= 1.0 {
-                    Color32::from_rgb(250, 200, 50)
-                } else {
-                    Color32::from_rgb(220, 50, 50)
+                // Spending should stay under its limit; income should reach its target.
+                let color = match target_type {
+                    TargetType::Expense if ratio < 0.8 => Color32::from_rgb(100, 200, 100),
+                    TargetType::Expense if ratio <= 1.0 => Color32::from_rgb(250, 200, 50),
+                    TargetType::Expense => Color32::from_rgb(220, 50, 50),
+                    TargetType::Income if ratio >= 1.0 => Color32::from_rgb(100, 200, 100),
+                    TargetType::Income => Color32::from_rgb(90, 150, 230),
                 };
 
                 ui.horizontal(|ui| {
-                    ui.label(format!("{} ({:?})", b.category, b.period));
+                    let period_label = if budgeting::custom_range(b).is_some() { "Custom dates" } else { b.period.as_str() };
+                    ui.label(format!("{} ({:?})", b.display_name(), period_label));
+                    let mode = RolloverMode::from_str(&b.rollover_mode);
+                    if mode != RolloverMode::None {
+                        ui.label(egui::RichText::new(mode.label()).small().weak());
+                    }
                     
                     if ui.button("Edit").clicked() {
                         self.current_editing = b.id;
@@ -1372,23 +3707,75 @@ impl FinancerApp {
                         self.editor_limit_cents = b.limit_cents;
                         self.editor_period = crate::models::Period::from_str(&b.period);
                         self.editor_target_is_expense = crate::models::TargetType::from_str(&b.target_type) == crate::models::TargetType::Expense;
+                        self.editor_rollover_mode = RolloverMode::from_str(&b.rollover_mode);
+                        self.editor_rollover_cap_cents = b.rollover_cap_cents.unwrap_or(0);
+                        self.editor_alert_thresholds = b.alert_thresholds.clone();
+                        self.editor_name = b.name.clone().unwrap_or_default();
+                        self.editor_extra_categories = scope.categories.iter().skip(1).cloned().collect();
+                        self.editor_include_subcategories = b.include_subcategories;
+                        self.editor_account_ids = scope.account_ids.clone();
+                        self.editor_custom_dates = budgeting::custom_range(b).is_some();
+                        if let (Some(from), Some(to)) = (&b.starts_on, &b.ends_on) {
+                            self.editor_starts_on = from.clone();
+                            self.editor_ends_on = to.clone();
+                        }
                         self.editor_open = true;
                     }
                 });
                 
                 ui.label(egui::RichText::new(&date_range_str).small().italics());
+                if scope.categories.len() > 1 || scope.include_subcategories || !scope.account_ids.is_empty() || b.name.is_some() {
+                    let accounts = if scope.account_ids.is_empty() {
+                        "all accounts".to_string()
+                    } else {
+                        scope.account_ids.iter().map(|id| self.account_name(*id)).collect::<Vec<_>>().join(", ")
+                    };
+                    ui.label(egui::RichText::new(format!("{} on {}", scope.describe_categories(), accounts)).small());
+                }
                 
                 ui.horizontal(|ui| {
                     ui.add_sized(
                         [200.0, 20.0],
                         egui::ProgressBar::new(progress).fill(color).show_percentage()
                     );
-                    ui.label(format!(
-                        "spent ${:.2} / ${:.2}",
-                        spent_for_bar / 100.0,
-                        limit_f / 100.0
-                    ));
+                    match target_type {
+                        TargetType::Expense => {
+                            ui.label(format!("spent ${:.2} / ${:.2}", spent_for_bar / 100.0, limit_f / 100.0));
+                        }
+                        TargetType::Income => {
+                            ui.label(format!("received ${:.2} of ${:.2} target", spent_for_bar / 100.0, limit_f / 100.0));
+                            if spent_for_bar >= limit_f {
+                                ui.colored_label(color, format!("target reached (+${:.2})", (spent_for_bar - limit_f) / 100.0));
+                            } else {
+                                ui.label(format!("${:.2} to go", (limit_f - spent_for_bar) / 100.0));
+                            }
+                        }
+                    }
+                    if rollover != 0 {
+                        ui.label(egui::RichText::new(format!(
+                            "(${:.2} base {} ${:.2} rolled over)",
+                            limit as f32 / 100.0,
+                            if rollover > 0 { "+" } else { "-" },
+                            rollover.abs() as f32 / 100.0
+                        )).small());
+                    }
                 });
+                if let Some(pace) = self.budget_paces.get(&b.id.unwrap_or(0)) {
+                    let pace_color = match pace.status {
+                        PaceStatus::OnTrack => Color32::from_rgb(100, 200, 100),
+                        PaceStatus::AtRisk => Color32::from_rgb(250, 200, 50),
+                        PaceStatus::Over => Color32::from_rgb(220, 50, 50),
+                    };
+                    ui.horizontal(|ui| {
+                        ui.colored_label(pace_color, pace.status.label());
+                        ui.label(format!(
+                            "projected ${:.2} by period end | safe to spend ${:.2}/day for {} more day(s)",
+                            pace.projected as f32 / 100.0,
+                            pace.safe_daily as f32 / 100.0,
+                            pace.days_left
+                        ));
+                    });
+                }
                 ui.separator();
             }
         }
@@ -1417,12 +3804,10 @@ impl FinancerApp {
             ui.horizontal(|ui| {
                 ui.label("New Category:");
                 ui.text_edit_singleline(&mut self.tx_custom_category);
-                if ui.button("Add").clicked() {
-                    if !self.tx_custom_category.is_empty() && self.user_categories.len() < 50 {
-                        self.editor_category = self.tx_custom_category.clone();
-                        self.tx_custom_category.clear();
-                        self.show_category_input = false;
-                    }
+                if ui.button("Add").clicked() && !self.tx_custom_category.is_empty() && self.user_categories.len() < 50 {
+                    self.editor_category = self.tx_custom_category.clone();
+                    self.tx_custom_category.clear();
+                    self.show_category_input = false;
                 }
                 if ui.button("Cancel").clicked() {
                     self.tx_custom_category.clear();
@@ -1432,32 +3817,40 @@ impl FinancerApp {
         }
         
         ui.horizontal(|ui| {
-            ui.label("Limit ($):");
+            ui.label(if self.editor_target_is_expense { "Limit ($):" } else { "Target ($):" });
             let limit_dollars = self.editor_limit_cents as f32 / 100.0;
             let mut temp_limit = limit_dollars;
             if ui.add(egui::DragValue::new(&mut temp_limit).speed(1.0).prefix("$")).changed() {
                 self.editor_limit_cents = (temp_limit * 100.0) as i32;
             }
-            if ui.button("Create").clicked() {
-                if let Some(uid) = self.user_id {
-                    let nb = crate::models::NewBudget {
-                        user_id: uid,
-                        category: self.editor_category.clone(),
-                        limit_cents: self.editor_limit_cents,
-                        period: self.editor_period.to_str().to_string(),
-                        target_type: if self.editor_target_is_expense {
-                            crate::models::TargetType::Expense.to_str().to_string()
-                        } else {
-                            crate::models::TargetType::Income.to_str().to_string()
-                        },
-                    };
-                    if let Ok(_) = db::create_budget(&mut self.conn, nb) {
+            if ui.button("Create").clicked() && let Some(uid) = self.user_id {
+                let nb = crate::models::NewBudget {
+                    user_id: uid,
+                    category: self.editor_category.clone(),
+                    limit_cents: self.editor_limit_cents,
+                    period: self.editor_period.to_str().to_string(),
+                    target_type: if self.editor_target_is_expense {
+                        crate::models::TargetType::Expense.to_str().to_string()
+                    } else {
+                        crate::models::TargetType::Income.to_str().to_string()
+                    },
+                    rollover_mode: RolloverMode::None.to_str().to_string(),
+                    rollover_cap_cents: None,
+                    alert_thresholds: budgeting::DEFAULT_ALERT_THRESHOLDS.to_string(),
+                    name: None,
+                    include_subcategories: false,
+                    starts_on: None,
+                    ends_on: None,
+                };
+                match db::create_budget(&mut self.conn, nb) {
+                    Ok(_) => {
                         self.load_user_budgets();
                         self.compute_budget_progress(self.period_offset);
                         self.editor_category.clear();
                         self.editor_limit_cents = 0;
-                    } else {
-                        self.message = "Failed to create budget.".to_string();
+                    }
+                    Err(e) => {
+                        self.message = format!("Failed to create budget: {}", friendly_error(&e));
                     }
                 }
             }
@@ -1484,17 +3877,15 @@ impl FinancerApp {
 }
 
     fn show_budget_editor(&mut self, ctx: &egui::Context, editing: Option<i32>) {
-        if let Some(id) = editing {
-            if let Some(b) = self.budgets.iter().find(|b| b.id.unwrap_or(-1) == id) {
-                if self.editor_category.is_empty() {
-                    self.editor_category = b.category.clone();
-                }
-                if self.editor_limit_cents == 0 {
-                    self.editor_limit_cents = b.limit_cents;
-                }
-                self.editor_period = crate::models::Period::from_str(&b.period);
-                self.editor_target_is_expense = crate::models::TargetType::from_str(&b.target_type) == crate::models::TargetType::Expense;
+        if let Some(id) = editing && let Some(b) = self.budgets.iter().find(|b| b.id.unwrap_or(-1) == id) {
+            if self.editor_category.is_empty() {
+                self.editor_category = b.category.clone();
             }
+            if self.editor_limit_cents == 0 {
+                self.editor_limit_cents = b.limit_cents;
+            }
+            self.editor_period = crate::models::Period::from_str(&b.period);
+            self.editor_target_is_expense = crate::models::TargetType::from_str(&b.target_type) == crate::models::TargetType::Expense;
         }
 
         egui::Window::new("Budget Editor").resizable(false).show(ctx, |ui| {
@@ -1519,12 +3910,10 @@ impl FinancerApp {
                 ui.horizontal(|ui| {
                     ui.label("New Category:");
                     ui.text_edit_singleline(&mut self.tx_custom_category);
-                    if ui.button("Add").clicked() {
-                        if !self.tx_custom_category.is_empty() && self.user_categories.len() < 50 {
-                            self.editor_category = self.tx_custom_category.clone();
-                            self.tx_custom_category.clear();
-                            self.show_category_input = false;
-                        }
+                    if ui.button("Add").clicked() && !self.tx_custom_category.is_empty() && self.user_categories.len() < 50 {
+                        self.editor_category = self.tx_custom_category.clone();
+                        self.tx_custom_category.clear();
+                        self.show_category_input = false;
                     }
                     if ui.button("Cancel").clicked() {
                         self.tx_custom_category.clear();
@@ -1534,23 +3923,96 @@ impl FinancerApp {
             }
 
             ui.horizontal(|ui| {
-                ui.label("Limit ($):");
+                ui.label("Name (optional):");
+                ui.add(egui::TextEdit::singleline(&mut self.editor_name).desired_width(160.0).hint_text("e.g. Trip"));
+            });
+
+            ui.collapsing("Scope: more categories and accounts", |ui| {
+                ui.label("Also covers:");
+                let extra_choices: Vec<String> = self
+                    .get_all_categories()
+                    .into_iter()
+                    .filter(|c| *c != self.editor_category)
+                    .collect();
+                egui::ScrollArea::vertical().id_salt("budget_editor_extra_categories").max_height(120.0).show(ui, |ui| {
+                    for cat in extra_choices {
+                        let mut included = self.editor_extra_categories.contains(&cat);
+                        if ui.checkbox(&mut included, &cat).changed() {
+                            if included {
+                                self.editor_extra_categories.push(cat);
+                            } else {
+                                self.editor_extra_categories.retain(|c| *c != cat);
+                            }
+                        }
+                    }
+                });
+                ui.checkbox(&mut self.editor_include_subcategories, "Include subcategories (e.g. Food:Groceries under Food)");
+                ui.label("Only these accounts (none checked means all):");
+                for account in &self.accounts_list {
+                    let mut included = self.editor_account_ids.contains(&account.id);
+                    if ui.checkbox(&mut included, &account.name).changed() {
+                        if included {
+                            self.editor_account_ids.push(account.id);
+                        } else {
+                            self.editor_account_ids.retain(|id| *id != account.id);
+                        }
+                    }
+                }
+            });
+
+            ui.horizontal(|ui| {
+                ui.label(if self.editor_target_is_expense { "Limit ($):" } else { "Target ($):" });
                 let mut limit_dollars = self.editor_limit_cents as f32 / 100.0;
                 if ui.add(egui::DragValue::new(&mut limit_dollars).speed(1.0).prefix("$")).changed() {
                     self.editor_limit_cents = (limit_dollars * 100.0) as i32;
                 }
             });
 
-            ui.horizontal(|ui| {
-                ui.label("Period:");
-                ui.radio_value(&mut self.editor_period, Period::Daily, "Daily");
-                ui.radio_value(&mut self.editor_period, Period::Weekly, "Weekly");
-                ui.radio_value(&mut self.editor_period, Period::Monthly, "Monthly");
-                ui.radio_value(&mut self.editor_period, Period::Yearly, "Yearly");
-            });
+            ui.checkbox(&mut self.editor_custom_dates, "Custom dates instead of a repeating period");
+            if self.editor_custom_dates {
+                ui.horizontal(|ui| {
+                    ui.label("From:");
+                    Self::show_date_selector(ui, &mut self.editor_starts_on, "budget_editor_starts_on");
+                    ui.label("To:");
+                    Self::show_date_selector(ui, &mut self.editor_ends_on, "budget_editor_ends_on");
+                });
+            } else {
+                ui.horizontal(|ui| {
+                    ui.label("Period:");
+                    ui.radio_value(&mut self.editor_period, Period::Daily, "Daily");
+                    ui.radio_value(&mut self.editor_period, Period::Weekly, "Weekly");
+                    ui.radio_value(&mut self.editor_period, Period::Monthly, "Monthly");
+                    ui.radio_value(&mut self.editor_period, Period::Yearly, "Yearly");
+                });
+            }
 
             ui.checkbox(&mut self.editor_target_is_expense, "Expense budget");
 
+            if self.editor_target_is_expense && !self.editor_custom_dates {
+                ui.horizontal(|ui| {
+                    ui.label("Rollover:");
+                    egui::ComboBox::from_id_salt("budget_editor_rollover")
+                        .selected_text(self.editor_rollover_mode.label())
+                        .show_ui(ui, |ui| {
+                            for mode in RolloverMode::ALL {
+                                ui.selectable_value(&mut self.editor_rollover_mode, mode, mode.label());
+                            }
+                        });
+                    if self.editor_rollover_mode == RolloverMode::Capped {
+                        let mut cap_dollars = self.editor_rollover_cap_cents as f32 / 100.0;
+                        if ui.add(egui::DragValue::new(&mut cap_dollars).speed(1.0).prefix("cap $").range(0.0..=f32::MAX)).changed() {
+                            self.editor_rollover_cap_cents = (cap_dollars * 100.0) as i32;
+                        }
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Alert at (% of limit):");
+                    ui.add(egui::TextEdit::singleline(&mut self.editor_alert_thresholds).desired_width(120.0).hint_text("e.g. 50,80,100"));
+                })
+                .response
+                .on_hover_text("Comma-separated percentages. Leave empty for no alerts.");
+            }
+
             ui.separator();
 
             ui.horizontal(|ui| {
@@ -1566,39 +4028,55 @@ impl FinancerApp {
                     } else {
                         crate::models::TargetType::Income.to_str().to_string()
                     },
-                };                        let res = match editing {
-                            Some(id) => db::update_budget(&mut self.conn, id, nb).map(|_| ()),
-                            None => db::create_budget(&mut self.conn, nb).map(|_| ()),
-                        };
-
-                        if res.is_ok() {
-                            self.load_user_budgets();
-                            self.compute_budget_progress(0);
-                            self.editor_category.clear();
-                            self.editor_limit_cents = 0;
-                            self.editor_open = false;
-                            self.current_editing = None;
-                        } else {
-                            self.message = format!("Failed to save budget: {:?}", res.err());
-                        }
-                    } else {
-                        self.message = "Not logged in.".to_string();
-                    }
-                }
+                    // Only expense budgets with a repeating period roll over.
+                    rollover_mode: if self.editor_target_is_expense && !self.editor_custom_dates { self.editor_rollover_mode } else { RolloverMode::None }
+                        .to_str()
+                        .to_string(),
+                    rollover_cap_cents: (self.editor_target_is_expense && !self.editor_custom_dates && self.editor_rollover_mode == RolloverMode::Capped)
+                        .then_some(self.editor_rollover_cap_cents),
+                    alert_thresholds: self.editor_alert_thresholds.clone(),
+                    name: Some(self.editor_name.trim().to_string()).filter(|n| !n.is_empty()),
+                    include_subcategories: self.editor_include_subcategories,
+                    starts_on: self.editor_custom_dates.then(|| self.editor_starts_on.clone()),
+                    ends_on: self.editor_custom_dates.then(|| self.editor_ends_on.clone()),
+                };                        let res = match editing {
+                            Some(id) => db::update_budget(&mut self.conn, id, nb),
+                            None => db::create_budget(&mut self.conn, nb),
+                        }
+                        .and_then(|saved| {
+                            db::set_budget_scope(&mut self.conn, uid, saved.id.unwrap_or(0), &self.editor_extra_categories, &self.editor_account_ids)
+                        });
 
-                if editing.is_some() {
-                    if ui.button("Delete").clicked() {
-                        if let Some(id) = editing {
-                            if db::delete_budget(&mut self.conn, id).is_ok() {
+                        match res {
+                            Ok(()) => {
                                 self.load_user_budgets();
                                 self.compute_budget_progress(0);
                                 self.editor_category.clear();
                                 self.editor_limit_cents = 0;
                                 self.editor_open = false;
                                 self.current_editing = None;
-                            } else {
-                                self.message = "Failed to delete budget.".to_string();
                             }
+                            Err(e) => {
+                                self.message = format!("Failed to save budget: {}", friendly_error(&e));
+                            }
+                        }
+                    } else {
+                        self.message = "Not logged in.".to_string();
+                    }
+                }
+
+                if editing.is_some() && ui.button("Delete").clicked() && let Some(id) = editing {
+                    match db::delete_budget(&mut self.conn, id) {
+                        Ok(_) => {
+                            self.load_user_budgets();
+                            self.compute_budget_progress(0);
+                            self.editor_category.clear();
+                            self.editor_limit_cents = 0;
+                            self.editor_open = false;
+                            self.current_editing = None;
+                        }
+                        Err(e) => {
+                            self.message = format!("Failed to delete budget: {}", friendly_error(&e));
                         }
                     }
                 }
@@ -1669,12 +4147,10 @@ impl FinancerApp {
                 ui.horizontal(|ui| {
                     ui.label("New Category:");
                     ui.text_edit_singleline(&mut self.tx_custom_category);
-                    if ui.button("Add").clicked() {
-                        if !self.tx_custom_category.is_empty() && self.user_categories.len() < 50 {
-                            self.tx_category = self.tx_custom_category.clone();
-                            self.tx_custom_category.clear();
-                            self.show_category_input = false;
-                        }
+                    if ui.button("Add").clicked() && !self.tx_custom_category.is_empty() && self.user_categories.len() < 50 {
+                        self.tx_category = self.tx_custom_category.clone();
+                        self.tx_custom_category.clear();
+                        self.show_category_input = false;
                     }
                     if ui.button("Cancel").clicked() {
                         self.tx_custom_category.clear();
@@ -1688,48 +4164,85 @@ impl FinancerApp {
                 Self::show_date_selector(ui, &mut self.tx_date, "tx_date");
             });
 
-            if ui.button("Add Transaction").clicked() {
-                if let Some(_uid) = self.user_id {
-                    if self.tx_account_id > 0 && self.tx_amount > 0.0 {
-                        let account_opt = self.accounts_list.iter().find(|a| a.id == self.tx_account_id);
-                        if let Some(account) = account_opt {
-                            let amount = if self.tx_is_expense { -self.tx_amount } else { self.tx_amount };
-                            if self.tx_is_expense && account.balance < self.tx_amount {
-                                self.message = "Insufficient funds for this expense.".to_string();
-                            } else {
-                                let date_time = format!("{} 00:00:00", self.tx_date);
-
-                                match db::create_transaction(
-                                    &mut self.conn,
-                                    self.tx_account_id,
-                                    0,
-                                    amount,
-                                    self.tx_category.clone(),
-                                    date_time,
-                                ) {
-                                    Ok(_) => {
-                                        self.message = "Transaction added successfully!".to_string();
-                                        self.load_user_transactions();
-                                        self.load_user_categories();
-                                        self.load_user_budgets();
-                                        self.compute_budget_progress(self.period_offset);
-                                        if let Some(uid) = self.user_id {
-                                            self.accounts_list = db::get_user_accounts(&mut self.conn, uid).unwrap_or_default();
-                                        }
-                                        self.tx_amount = 0.0;
-                                        self.tx_is_expense = true;
-                                    }
-                                    Err(e) => {
-                                        self.message = format!("Failed to add transaction: {:?}", e);
+            let mut add_clicked = ui.button("Add Transaction").clicked();
+            let mut over_budget_confirmed = false;
+            if let Some(warning) = &self.tx_budget_warning {
+                ui.colored_label(Color32::from_rgb(250, 200, 50), warning);
+                ui.horizontal(|ui| {
+                    if ui.button("Save Anyway").clicked() {
+                        add_clicked = true;
+                        over_budget_confirmed = true;
+                    }
+                    if ui.button("Cancel").clicked() {
+                        self.tx_budget_warning = None;
+                    }
+                });
+            }
+
+            if add_clicked && let Some(uid) = self.user_id {
+                if self.tx_account_id > 0 && self.tx_amount > 0.0 {
+                    let account_opt = self.accounts_list.iter().find(|a| a.id == self.tx_account_id);
+                    if account_opt.is_some() {
+                        let amount = if self.tx_is_expense { -self.tx_amount } else { self.tx_amount };
+                        let funds_check = if self.tx_is_expense {
+                            db::ensure_sufficient_funds(&mut self.conn, self.tx_account_id, self.tx_amount)
+                        } else {
+                            Ok(())
+                        };
+                        let over_budget = match NaiveDate::parse_from_str(&self.tx_date, "%Y-%m-%d") {
+                            Ok(day) if self.tx_is_expense && !over_budget_confirmed => {
+                                let when = day.and_hms_opt(0, 0, 0).unwrap();
+                                db::budgets_exceeded_by(&mut self.conn, uid, self.tx_account_id, &self.tx_category, amount, when).unwrap_or_default()
+                            }
+                            _ => Vec::new(),
+                        };
+                        if let Err(e) = funds_check {
+                            self.message = friendly_error(&e);
+                        } else if !over_budget.is_empty() {
+                            let details: Vec<String> = over_budget
+                                .iter()
+                                .map(|(b, spent, limit)| {
+                                    format!("{} ({}): ${:.2} of ${:.2}", b.display_name(), b.period, *spent as f64 / 100.0, *limit as f64 / 100.0)
+                                })
+                                .collect();
+                            self.tx_budget_warning = Some(format!("This expense would put you over budget. {}", details.join("; ")));
+                        } else {
+                            self.tx_budget_warning = None;
+                            let date_time = format!("{} 00:00:00", self.tx_date);
+
+                            match db::create_transaction_as(
+                                &mut self.conn,
+                                uid,
+                                self.tx_account_id,
+                                0,
+                                amount,
+                                self.tx_category.clone(),
+                                date_time,
+                                chrono::Local::now().naive_local(),
+                            ) {
+                                Ok(_) => {
+                                    self.message = "Transaction added successfully!".to_string();
+                                    self.load_user_transactions();
+                                    self.load_user_categories();
+                                    self.load_user_budgets();
+                                    self.compute_budget_progress(self.period_offset);
+                                    self.load_notifications();
+                                    if let Some(uid) = self.user_id {
+                                        self.refresh_accounts(uid);
                                     }
+                                    self.tx_amount = 0.0;
+                                    self.tx_is_expense = true;
+                                }
+                                Err(e) => {
+                                    self.message = format!("Failed to add transaction: {}", friendly_error(&e));
                                 }
                             }
-                        } else {
-                            self.message = "Selected account not found.".to_string();
                         }
                     } else {
-                        self.message = "Please select an account and enter a positive amount.".to_string();
+                        self.message = "Selected account not found.".to_string();
                     }
+                } else {
+                    self.message = "Please select an account and enter a positive amount.".to_string();
                 }
             }
 
@@ -1785,12 +4298,10 @@ impl FinancerApp {
                 ui.horizontal(|ui| {
                     ui.label("New Category:");
                     ui.text_edit_singleline(&mut self.recurring_tx_custom_category);
-                    if ui.button("Add").clicked() {
-                        if !self.recurring_tx_custom_category.is_empty() && self.user_categories.len() < 50 {
-                            self.recurring_tx_category = self.recurring_tx_custom_category.clone();
-                            self.recurring_tx_custom_category.clear();
-                            self.recurring_tx_show_category_input = false;
-                        }
+                    if ui.button("Add").clicked() && !self.recurring_tx_custom_category.is_empty() && self.user_categories.len() < 50 {
+                        self.recurring_tx_category = self.recurring_tx_custom_category.clone();
+                        self.recurring_tx_custom_category.clear();
+                        self.recurring_tx_show_category_input = false;
                     }
                     if ui.button("Cancel").clicked() {
                         self.recurring_tx_custom_category.clear();
@@ -1804,17 +4315,22 @@ impl FinancerApp {
                 ui.text_edit_singleline(&mut self.recurring_tx_next_run_at);
             });
 
-            ui.horizontal(|ui| {
-                ui.label("Frequency:");
-                egui::ComboBox::from_id_salt("rec_tx_frequency")
-                    .selected_text(self.recurring_tx_frequency.to_str())
-                    .show_ui(ui, |ui| {
-                        ui.selectable_value(&mut self.recurring_tx_frequency, Period::Daily, "Daily");
-                        ui.selectable_value(&mut self.recurring_tx_frequency, Period::Weekly, "Weekly");
-                        ui.selectable_value(&mut self.recurring_tx_frequency, Period::Monthly, "Monthly");
-                        ui.selectable_value(&mut self.recurring_tx_frequency, Period::Yearly, "Yearly");
-                    });
-            });
+            show_recurrence_picker(ui, &mut self.recurring_tx_rule, &self.recurring_tx_next_run_at, "rec_tx_frequency");
+            Self::show_schedule_limits(
+                ui,
+                &mut self.recurring_tx_has_end,
+                &mut self.recurring_tx_end_date,
+                &mut self.recurring_tx_has_limit,
+                &mut self.recurring_tx_max_occurrences,
+                "rec_tx_limits",
+            );
+            show_roll_picker(ui, &mut self.recurring_tx_roll, "rec_tx_roll");
+            if self.recurring_tx_editing_id.is_some() {
+                ui.checkbox(&mut self.recurring_tx_rewrite_past, "Also rewrite past generated entries")
+                    .on_hover_text("Apply the new amount and accounts to transactions this item already posted. Their dates are kept.");
+            }
+            ui.checkbox(&mut self.recurring_tx_requires_approval, "Hold each occurrence for approval")
+                .on_hover_text("Due occurrences wait in the queue on the Upcoming screen instead of posting automatically.");
 
             let save_label = if self.recurring_tx_editing_id.is_some() {
                 "Update Recurring Transaction"
@@ -1822,48 +4338,65 @@ impl FinancerApp {
                 "Add Recurring Transaction"
             };
 
-            if ui.button(save_label).clicked() {
-                if let Some(uid) = self.user_id {
-                    if self.recurring_tx_account_id <= 0 || self.recurring_tx_amount <= 0.0 {
-                        self.message = "Select an account and enter a positive amount.".to_string();
-                    } else if chrono::NaiveDateTime::parse_from_str(&self.recurring_tx_next_run_at, "%Y-%m-%d %H:%M:%S").is_err() {
-                        self.message = "Next Run must be in format YYYY-MM-DD HH:MM:SS".to_string();
+            if ui.button(save_label).clicked() && let Some(uid) = self.user_id {
+                if self.recurring_tx_account_id <= 0 || self.recurring_tx_amount <= 0.0 {
+                    self.message = "Select an account and enter a positive amount.".to_string();
+                } else if chrono::NaiveDateTime::parse_from_str(&self.recurring_tx_next_run_at, "%Y-%m-%d %H:%M:%S").is_err() {
+                    self.message = "Next Run must be in format YYYY-MM-DD HH:MM:SS".to_string();
+                } else {
+                    let amount = if self.recurring_tx_is_expense {
+                        -self.recurring_tx_amount
                     } else {
-                        let amount = if self.recurring_tx_is_expense {
-                            -self.recurring_tx_amount
-                        } else {
-                            self.recurring_tx_amount
-                        };
-
-                        let changes = crate::models::NewRecurringTransaction {
-                            user_id: uid,
-                            account_id: self.recurring_tx_account_id,
-                            contact_id: 0,
-                            amount,
-                            category: self.recurring_tx_category.clone(),
-                            next_run_at: self.recurring_tx_next_run_at.clone(),
-                            frequency: self.recurring_tx_frequency.to_str().to_string(),
-                        };
+                        self.recurring_tx_amount
+                    };
 
-                        let result = if let Some(item_id) = self.recurring_tx_editing_id {
-                            db::update_recurring_transaction(&mut self.conn, uid, item_id, changes).map(|_| ())
-                        } else {
-                            db::create_recurring_transaction(&mut self.conn, changes).map(|_| ())
-                        };
+                    let changes = crate::models::NewRecurringTransaction {
+                        user_id: uid,
+                        account_id: self.recurring_tx_account_id,
+                        contact_id: 0,
+                        amount,
+                        category: self.recurring_tx_category.clone(),
+                        next_run_at: self.recurring_tx_next_run_at.clone(),
+                        // Filled in from the rule's base period when saved.
+                        frequency: String::new(),
+                        recurrence_rule: Some(self.recurring_tx_rule.clone()),
+                        ends_at: self
+                            .recurring_tx_has_end
+                            .then(|| format!("{} 23:59:59", self.recurring_tx_end_date)),
+                        max_occurrences: self.recurring_tx_has_limit.then_some(self.recurring_tx_max_occurrences),
+                        requires_approval: self.recurring_tx_requires_approval,
+                        roll_convention: self.recurring_tx_roll.to_str().to_string(),
+                    };
 
-                        match result {
-                            Ok(()) => {
-                                self.message = "Recurring transaction saved.".to_string();
-                                self.recurring_tx_editing_id = None;
-                                self.recurring_tx_amount = 0.0;
-                                self.recurring_tx_is_expense = true;
-                                self.recurring_tx_next_run_at = chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
-                                self.load_user_recurring_transactions();
-                                self.load_user_categories();
-                            }
-                            Err(e) => {
-                                self.message = format!("Failed to save recurring transaction: {}", e);
+                    let result = if let Some(item_id) = self.recurring_tx_editing_id {
+                        let rewrite_past = self.recurring_tx_rewrite_past;
+                        db::update_recurring_transaction(&mut self.conn, uid, item_id, changes, rewrite_past).map(|_| {
+                            if rewrite_past {
+                                self.refresh_accounts(uid);
+                                self.load_user_transactions();
                             }
+                        })
+                    } else {
+                        db::create_recurring_transaction(&mut self.conn, changes).map(|_| ())
+                    };
+
+                    match result {
+                        Ok(()) => {
+                            self.message = "Recurring transaction saved.".to_string();
+                            self.recurring_tx_editing_id = None;
+                            self.recurring_tx_has_end = false;
+                            self.recurring_tx_has_limit = false;
+                            self.recurring_tx_requires_approval = false;
+                            self.recurring_tx_rewrite_past = false;
+                            self.recurring_tx_roll = RollConvention::None;
+                            self.recurring_tx_amount = 0.0;
+                            self.recurring_tx_is_expense = true;
+                            self.recurring_tx_next_run_at = chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+                            self.load_user_recurring_transactions();
+                            self.load_user_categories();
+                        }
+                        Err(e) => {
+                            self.message = format!("Failed to save recurring transaction: {}", friendly_error(&e));
                         }
                     }
                 }
@@ -1873,6 +4406,8 @@ impl FinancerApp {
                 ui.label("No recurring transactions.");
             } else {
                 let mut delete_id: Option<i32> = None;
+                let mut toggle: Option<(i32, bool)> = None;
+                let mut skip_next: Option<i32> = None;
                 let mut edit_item: Option<RecurringTransaction> = None;
 
                 for item in &self.recurring_transactions_list {
@@ -1891,8 +4426,23 @@ impl FinancerApp {
                             item.amount.abs(),
                             item.category,
                             item.next_run_at,
-                            item.frequency
+                            schedule_status(
+                                &item.recurrence_rule,
+                                &item.frequency,
+                                &item.next_run_at,
+                                item.ends_at.as_deref(),
+                                item.remaining_occurrences(),
+                                item.active,
+                                item.requires_approval,
+                            )
                         ));
+                        if item.active && ui.button("Skip Next").on_hover_text("Skip only the next occurrence").clicked() {
+                            skip_next = Some(item.id);
+                        }
+                        let toggle_label = if item.active { "Pause" } else { "Resume" };
+                        if ui.button(toggle_label).clicked() {
+                            toggle = Some((item.id, !item.active));
+                        }
                         if ui.button("Edit").clicked() {
                             edit_item = Some(item.clone());
                         }
@@ -1909,23 +4459,54 @@ impl FinancerApp {
                     self.recurring_tx_amount = item.amount.abs();
                     self.recurring_tx_category = item.category;
                     self.recurring_tx_next_run_at = item.next_run_at;
-                    self.recurring_tx_frequency = Period::from_str(&item.frequency);
+                    self.recurring_tx_rule = item.recurrence_rule;
+                    self.recurring_tx_has_end = item.ends_at.is_some();
+                    if let Some(end) = &item.ends_at {
+                        self.recurring_tx_end_date = end.get(..10).unwrap_or(end).to_string();
+                    }
+                    self.recurring_tx_has_limit = item.max_occurrences.is_some();
+                    self.recurring_tx_max_occurrences = item.max_occurrences.unwrap_or(12);
+                    self.recurring_tx_requires_approval = item.requires_approval;
+                    self.recurring_tx_roll = RollConvention::from_str(&item.roll_convention);
                 }
 
-                if let Some(item_id) = delete_id {
-                    if let Some(uid) = self.user_id {
-                        match db::delete_recurring_transaction(&mut self.conn, uid, item_id) {
-                            Ok(_) => {
-                                self.message = "Recurring transaction deleted.".to_string();
-                                self.load_user_recurring_transactions();
-                                if self.recurring_tx_editing_id == Some(item_id) {
-                                    self.recurring_tx_editing_id = None;
-                                }
-                            }
-                            Err(e) => {
-                                self.message = format!("Failed to delete recurring transaction: {}", e);
+                if let Some(item_id) = skip_next && let Some(uid) = self.user_id {
+                    let now = chrono::Local::now().naive_local();
+                    match db::skip_next_recurring_occurrence(&mut self.conn, uid, RecurringKind::Transaction, item_id, now) {
+                        Ok(()) => {
+                            self.message = "Next occurrence skipped.".to_string();
+                            self.load_user_recurring_transactions();
+                        }
+                        Err(e) => {
+                            self.message = format!("Failed to skip occurrence: {}", friendly_error(&e));
+                        }
+                    }
+                }
+
+                if let Some((item_id, resume)) = toggle && let Some(uid) = self.user_id {
+                    match db::set_recurring_transaction_active(&mut self.conn, uid, item_id, resume, chrono::Local::now().naive_local()) {
+                        Ok(()) => {
+                            self.message = if resume { "Recurring transaction resumed." } else { "Recurring transaction paused." }.to_string();
+                            self.load_user_recurring_transactions();
+                        }
+                        Err(e) => {
+                            self.message = format!("Failed to update recurring transaction: {}", friendly_error(&e));
+                        }
+                    }
+                }
+
+                if let Some(item_id) = delete_id && let Some(uid) = self.user_id {
+                    match db::delete_recurring_transaction(&mut self.conn, uid, item_id) {
+                        Ok(_) => {
+                            self.message = "Recurring transaction deleted.".to_string();
+                            self.load_user_recurring_transactions();
+                            if self.recurring_tx_editing_id == Some(item_id) {
+                                self.recurring_tx_editing_id = None;
                             }
                         }
+                        Err(e) => {
+                            self.message = format!("Failed to delete recurring transaction: {}", friendly_error(&e));
+                        }
                     }
                 }
             }
@@ -1966,14 +4547,18 @@ impl FinancerApp {
                                 };
                                 start_match && end_match
                             };
-                            account_match && category_match && date_match
+                            let source_match = self
+                                .tx_filter_recurring
+                                .is_none_or(|recurring| tx.recurring_id.is_some() == recurring);
+
+                            account_match && category_match && date_match && source_match
                         })
                         .collect();
 
                     let wtr = Writer::from_path(&file_path);
                     match wtr {
                         Ok(mut writer) => {
-                            let _ = writer.write_record(&[
+                            let _ = writer.write_record([
                                 "account_name", "amount", "category", "date", "balance_after"
                             ]);
                             for tx in &filtered_transactions {
@@ -1982,7 +4567,7 @@ impl FinancerApp {
                                     .find(|a| a.id == tx.user_account_id)
                                     .map(|a| a.name.clone())
                                     .unwrap_or_else(|| "Unknown".to_string());
-                                let _ = writer.write_record(&[
+                                let _ = writer.write_record([
                                     account_name,
                                     tx.amount.to_string(),
                                     tx.category.clone(),
@@ -2044,6 +4629,20 @@ impl FinancerApp {
                             ui.selectable_value(&mut self.tx_filter_category, Some(cat.clone()), cat);
                         }
                     });
+
+                ui.separator();
+                ui.label("Source:");
+                egui::ComboBox::from_id_salt("tx_filter_recurring")
+                    .selected_text(match self.tx_filter_recurring {
+                        None => "All",
+                        Some(true) => "Recurring",
+                        Some(false) => "One-off",
+                    })
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut self.tx_filter_recurring, None, "All");
+                        ui.selectable_value(&mut self.tx_filter_recurring, Some(true), "Recurring");
+                        ui.selectable_value(&mut self.tx_filter_recurring, Some(false), "One-off");
+                    });
             });
             
             ui.horizontal(|ui| {
@@ -2106,7 +4705,11 @@ impl FinancerApp {
                             start_match && end_match
                         };
                         
-                        account_match && category_match && date_match
+                        let source_match = self
+                            .tx_filter_recurring
+                            .is_none_or(|recurring| tx.recurring_id.is_some() == recurring);
+
+                        account_match && category_match && date_match && source_match
                     })
                     .collect();
 
@@ -2126,16 +4729,29 @@ impl FinancerApp {
                             egui::Color32::from_rgb(200, 50, 50)
                         };
 
+                        let created_by = tx
+                            .created_by
+                            .filter(|creator| Some(*creator) != self.user_id)
+                            .and_then(|creator| self.member_names.get(&creator));
+                        let editable = self.can_edit_account(tx.user_account_id);
+                        let origin = self.recurring_origin(tx);
+
                         ui.horizontal(|ui| {
                             ui.colored_label(color, format!("${:.2}", tx.amount));
                             ui.label(format!("| {} | {} | {}", tx.category, account_name, tx.date));
                             ui.label(format!("| Balance: ${:.2}", tx.balance_after));
+                            if let Some(origin) = &origin {
+                                ui.label(egui::RichText::new("↻ recurring").small().strong()).on_hover_text(origin);
+                            }
+                            if let Some(creator_name) = created_by {
+                                ui.label(egui::RichText::new(format!("| by {}", creator_name)).italics());
+                            }
                             
-                            if ui.button("Edit").clicked() {
+                            if editable && ui.button("Edit").clicked() {
                                 tx_to_edit = Some(tx.clone());
                             }
                             
-                            if ui.button("Delete").clicked() {
+                            if editable && ui.button("Delete").clicked() {
                                 tx_to_delete = Some(tx.id);
                             }
                         });
@@ -2144,135 +4760,471 @@ impl FinancerApp {
                 }
             });
 
-            if let Some(tx) = tx_to_edit {
-                self.tx_editing_id = Some(tx.id);
-                self.tx_editor_open = true;
-                self.tx_editor_account_id = tx.user_account_id;
-                self.tx_editor_amount = tx.amount.abs();
-                self.tx_editor_category = tx.category.clone();
-                self.tx_editor_date = tx.date[..10].to_string();
-                self.tx_editor_is_expense = tx.amount < 0.0;
+            if let Some(tx) = tx_to_edit {
+                self.tx_editing_id = Some(tx.id);
+                self.tx_editor_open = true;
+                self.tx_editor_account_id = tx.user_account_id;
+                self.tx_editor_amount = tx.amount.abs();
+                self.tx_editor_category = tx.category.clone();
+                self.tx_editor_date = tx.date[..10].to_string();
+                self.tx_editor_is_expense = tx.amount < 0.0;
+            }
+
+            if let Some(tx_id) = tx_to_delete {
+                let uid = self.user_id.unwrap_or_default();
+                let result = db::delete_transaction_as(&mut self.conn, uid, tx_id);
+                if let Err(e) = result {
+                    self.message = format!("Error deleting transaction: {}", friendly_error(&e));
+                } else {
+                    self.load_user_transactions();
+                    self.load_user_budgets();
+                    self.compute_budget_progress(self.period_offset);
+
+                    if let Some(uid) = self.user_id {
+                        self.refresh_accounts(uid);
+                    }
+                    self.message = "Transaction deleted successfully".to_string();
+                }
+            }
+        });
+        
+        if self.tx_editor_open {
+            self.show_transaction_editor(ctx);
+        }
+    }
+
+    fn show_transaction_editor(&mut self, ctx: &egui::Context) {
+        let mut should_close = false;
+
+        egui::Window::new("Edit Transaction")
+            .collapsible(false)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Account:");
+                    egui::ComboBox::from_id_salt("tx_editor_account_selector")
+                        .selected_text(
+                            self.accounts_list
+                                .iter()
+                                .find(|a| a.id == self.tx_editor_account_id)
+                                .map(|a| a.name.as_str())
+                                .unwrap_or("Select Account")
+                        )
+                        .show_ui(ui, |ui| {
+                            for acc in &self.accounts_list {
+                                ui.selectable_value(&mut self.tx_editor_account_id, acc.id, &acc.name);
+                            }
+                        });
+                });
+
+                ui.horizontal(|ui| {
+                    ui.label("Category:");
+                    let all_categories = self.get_all_categories();
+                    egui::ComboBox::from_id_salt("tx_editor_category_selector")
+                        .selected_text(&self.tx_editor_category)
+                        .show_ui(ui, |ui| {
+                            for cat in &all_categories {
+                                ui.selectable_value(&mut self.tx_editor_category, cat.clone(), cat);
+                            }
+                        });
+                });
+
+                ui.horizontal(|ui| {
+                    ui.label("Amount:");
+                    ui.add(egui::DragValue::new(&mut self.tx_editor_amount).speed(0.1));
+                    ui.checkbox(&mut self.tx_editor_is_expense, "Expense");
+                });
+
+                ui.horizontal(|ui| {
+                    ui.label("Date:");
+                    Self::show_date_selector(ui, &mut self.tx_editor_date, "tx_editor_date");
+                });
+
+                ui.horizontal(|ui| {
+                    if ui.button("Save").clicked() && let Some(tx_id) = self.tx_editing_id {
+                        let entered_amount = self.tx_editor_amount.abs();
+                        let amount = if self.tx_editor_is_expense {
+                            -entered_amount
+                        } else {
+                            entered_amount
+                        };
+
+                        if entered_amount <= 0.0 {
+                            self.message = "Amount must be positive.".to_string();
+                        } else {
+                            let date_time = format!("{} 00:00:00", self.tx_editor_date);
+                            let uid = self.user_id.unwrap_or_default();
+                            
+                            match db::update_transaction_as(
+                                &mut self.conn,
+                                uid,
+                                tx_id,
+                                self.tx_editor_account_id,
+                                amount,
+                                self.tx_editor_category.clone(),
+                                date_time,
+                                chrono::Local::now().naive_local(),
+                            ) {
+                                Ok(_) => {
+                                    self.message = "Transaction updated successfully".to_string();
+                                    self.load_user_transactions();
+                                    self.load_user_budgets();
+                                    self.compute_budget_progress(self.period_offset);
+                                    self.load_notifications();
+
+                                    if let Some(uid) = self.user_id {
+                                        self.refresh_accounts(uid);
+                                    }
+                                    should_close = true;
+                                }
+                                Err(e) => {
+                                    self.message = format!("Error updating transaction: {}", friendly_error(&e));
+                                }
+                            }
+                        }
+                    }
+
+                    if ui.button("Cancel").clicked() {
+                        should_close = true;
+                    }
+                });
+            });
+
+        if should_close {
+            self.tx_editor_open = false;
+            self.tx_editing_id = None;
+        }
+    }
+
+    fn account_name(&self, account_id: i32) -> String {
+        self.accounts_list
+            .iter()
+            .find(|a| a.id == account_id)
+            .map(|a| a.name.clone())
+            .unwrap_or_else(|| format!("Account {}", account_id))
+    }
+
+    fn describe_occurrence(&self, occ: &Occurrence) -> String {
+        match occ.to_account_id {
+            Some(to) => format!(
+                "Transfer ${:.2}: {} -> {}",
+                occ.amount,
+                self.account_name(occ.account_id),
+                self.account_name(to)
+            ),
+            None => format!(
+                "{} {}${:.2} ({})",
+                occ.category,
+                if occ.amount < 0.0 { "-" } else { "+" },
+                occ.amount.abs(),
+                self.account_name(occ.account_id)
+            ),
+        }
+    }
+
+    /// Hover text for the recurring badge in transaction and transfer histories.
+    fn recurring_origin(&self, tx: &Transaction) -> Option<String> {
+        let (kind, item_id) = tx.recurring_source()?;
+        let template = match kind {
+            RecurringKind::Transaction => self
+                .recurring_transactions_list
+                .iter()
+                .find(|i| i.id == item_id)
+                .map(|i| format!("{} ({})", i.category, RecurrenceRule::parse(&i.recurrence_rule).map(|r| r.describe()).unwrap_or_default())),
+            RecurringKind::Transfer => self
+                .recurring_transfers_list
+                .iter()
+                .find(|i| i.id == item_id)
+                .map(|i| format!(
+                    "{} -> {}",
+                    self.account_name(i.from_account_id),
+                    self.account_name(i.to_account_id)
+                )),
+        };
+        Some(match template {
+            Some(text) => format!("Generated by recurring {}: {}", kind.to_str(), text),
+            None => format!("Generated by recurring {} #{} (no longer scheduled)", kind.to_str(), item_id),
+        })
+    }
+
+    fn describe_run(&self, run: &RecurringRun) -> String {
+        match RecurringKind::from_str(&run.item_kind) {
+            RecurringKind::Transaction => match self.recurring_transactions_list.iter().find(|i| i.id == run.item_id) {
+                Some(item) => format!("{} ({})", item.category, self.account_name(item.account_id)),
+                None => format!("Recurring transaction {}", run.item_id),
+            },
+            RecurringKind::Transfer => match self.recurring_transfers_list.iter().find(|i| i.id == run.item_id) {
+                Some(item) => format!(
+                    "Transfer: {} -> {}",
+                    self.account_name(item.from_account_id),
+                    self.account_name(item.to_account_id)
+                ),
+                None => format!("Recurring transfer {}", run.item_id),
+            },
+        }
+    }
+
+    /// The user's holiday calendar: recurring items set to roll off holidays use it.
+    fn show_holidays(&mut self, ui: &mut egui::Ui) {
+        let Some(uid) = self.user_id else { return; };
+
+        egui::CollapsingHeader::new(format!("Holidays ({})", self.holidays_list.len()))
+            .id_salt("upcoming_holidays")
+            .show(ui, |ui| {
+                let mut changed = false;
+                let mut delete_id: Option<i32> = None;
+                egui::Grid::new("holiday_list").striped(true).show(ui, |ui| {
+                    for holiday in &self.holidays_list {
+                        ui.label(&holiday.holiday_date);
+                        ui.label(&holiday.name);
+                        if ui.small_button("Remove").clicked() {
+                            delete_id = Some(holiday.id);
+                        }
+                        ui.end_row();
+                    }
+                });
+                if let Some(holiday_id) = delete_id {
+                    match db::delete_holiday(&mut self.conn, uid, holiday_id) {
+                        Ok(()) => changed = true,
+                        Err(e) => self.message = format!("Failed to remove holiday: {}", friendly_error(&e)),
+                    }
+                }
+
+                ui.horizontal(|ui| {
+                    Self::show_date_selector(ui, &mut self.new_holiday_date, "new_holiday");
+                    ui.label("Name:");
+                    ui.text_edit_singleline(&mut self.new_holiday_name);
+                    if ui.button("Add Holiday").clicked() {
+                        match chrono::NaiveDate::parse_from_str(&self.new_holiday_date, "%Y-%m-%d") {
+                            Ok(day) => match db::add_holiday(&mut self.conn, uid, day, &self.new_holiday_name) {
+                                Ok(_) => {
+                                    self.new_holiday_name.clear();
+                                    changed = true;
+                                }
+                                Err(e) => self.message = format!("Failed to add holiday: {}", friendly_error(&e)),
+                            },
+                            Err(_) => self.message = "Holiday date must be in format YYYY-MM-DD".to_string(),
+                        }
+                    }
+                });
+
+                ui.horizontal(|ui| {
+                    ui.label("File:");
+                    ui.text_edit_singleline(&mut self.holiday_file);
+                    if ui
+                        .button("Import")
+                        .on_hover_text("One YYYY-MM-DD date per line, optionally followed by a comma and a name, or an .ics calendar")
+                        .clicked()
+                    {
+                        let result = fs::read_to_string(self.holiday_file.trim())
+                            .map_err(|e| format!("Failed to read {}: {}", self.holiday_file.trim(), e))
+                            .and_then(|text| db::import_holidays(&mut self.conn, uid, &text).map_err(|e| friendly_error(&e)));
+                        self.message = match result {
+                            Ok(added) => {
+                                changed = true;
+                                format!("Imported {} holiday(s) from {}", added, self.holiday_file.trim())
+                            }
+                            Err(e) => format!("Failed to import holidays: {}", e),
+                        };
+                    }
+                });
+
+                if changed {
+                    self.load_holidays();
+                }
+            });
+    }
+
+    /// Due occurrences of items held for approval: confirm (optionally with a new amount) or skip.
+    fn show_approval_queue(&mut self, ui: &mut egui::Ui) {
+        if self.pending_runs.is_empty() {
+            return;
+        }
+        let Some(uid) = self.user_id else { return; };
+
+        ui.heading(format!("Awaiting Approval ({})", self.pending_runs.len()));
+        let mut decision: Option<(i32, bool)> = None;
+        egui::Grid::new("approval_queue").striped(true).show(ui, |ui| {
+            for run in &self.pending_runs {
+                ui.label(run.scheduled_for.get(..10).unwrap_or(&run.scheduled_for));
+                ui.label(self.describe_run(run));
+                let edited = self.pending_run_amounts.entry(run.id).or_insert(run.amount.abs());
+                ui.horizontal(|ui| {
+                    ui.label(if run.amount < 0.0 { "-$" } else { "$" });
+                    ui.add(egui::DragValue::new(edited).speed(1.0).range(0.01..=f32::MAX));
+                });
+                if ui.button("Confirm").clicked() {
+                    decision = Some((run.id, true));
+                }
+                if ui.button("Skip").clicked() {
+                    decision = Some((run.id, false));
+                }
+                ui.end_row();
             }
+        });
 
-            if let Some(tx_id) = tx_to_delete {
-                if let Err(e) = db::delete_transaction(&mut self.conn, tx_id) {
-                    self.message = format!("Error deleting transaction: {}", e);
-                } else {
-                    self.load_user_transactions();
-                    self.load_user_budgets();
-                    self.compute_budget_progress(self.period_offset);
-
-                    if let Some(uid) = self.user_id {
-                        self.accounts_list = db::get_user_accounts(&mut self.conn, uid).unwrap_or_default();
+        if let Some((run_id, confirm)) = decision {
+            let now = chrono::Local::now().naive_local();
+            let result = if confirm {
+                let new_amount = self.pending_run_amounts.get(&run_id).copied();
+                db::confirm_recurring_run(&mut self.conn, uid, run_id, new_amount, now)
+            } else {
+                db::skip_recurring_run(&mut self.conn, uid, run_id, now)
+            };
+            match result {
+                Ok(()) => {
+                    self.message = if confirm { "Occurrence posted." } else { "Occurrence skipped." }.to_string();
+                    self.pending_run_amounts.remove(&run_id);
+                    if confirm {
+                        self.refresh_accounts(uid);
+                        self.load_user_transactions();
+                        self.load_user_budgets();
+                        self.compute_budget_progress(self.period_offset);
                     }
-                    self.message = "Transaction deleted successfully".to_string();
                 }
+                Err(e) => self.message = format!("Failed to update occurrence: {}", friendly_error(&e)),
             }
-        });
-        
-        if self.tx_editor_open {
-            self.show_transaction_editor(ctx);
+            self.pending_runs = db::get_pending_recurring_runs(&mut self.conn, uid).unwrap_or_default();
         }
+        ui.separator();
     }
 
-    fn show_transaction_editor(&mut self, ctx: &egui::Context) {
-        let mut should_close = false;
-
-        egui::Window::new("Edit Transaction")
-            .collapsible(false)
-            .show(ctx, |ui| {
-                ui.horizontal(|ui| {
-                    ui.label("Account:");
-                    egui::ComboBox::from_id_salt("tx_editor_account_selector")
-                        .selected_text(
-                            self.accounts_list
-                                .iter()
-                                .find(|a| a.id == self.tx_editor_account_id)
-                                .map(|a| a.name.as_str())
-                                .unwrap_or("Select Account")
-                        )
-                        .show_ui(ui, |ui| {
-                            for acc in &self.accounts_list {
-                                ui.selectable_value(&mut self.tx_editor_account_id, acc.id, &acc.name);
-                            }
-                        });
-                });
+    fn show_upcoming(&mut self, ctx: &egui::Context) {
+        use chrono::Datelike;
 
-                ui.horizontal(|ui| {
-                    ui.label("Category:");
-                    let all_categories = self.get_all_categories();
-                    egui::ComboBox::from_id_salt("tx_editor_category_selector")
-                        .selected_text(&self.tx_editor_category)
-                        .show_ui(ui, |ui| {
-                            for cat in &all_categories {
-                                ui.selectable_value(&mut self.tx_editor_category, cat.clone(), cat);
-                            }
-                        });
-                });
+        egui::CentralPanel::default().show(ctx, |ui| {
+            self.maybe_process_due_recurring();
+            ui.heading("Upcoming Bills, Income and Transfers");
 
-                ui.horizontal(|ui| {
-                    ui.label("Amount:");
-                    ui.add(egui::DragValue::new(&mut self.tx_editor_amount).speed(0.1));
-                    ui.checkbox(&mut self.tx_editor_is_expense, "Expense");
-                });
+            ui.horizontal(|ui| {
+                if ui.button("Back to Dashboard").clicked() {
+                    self.screen = AppState::Dashboard;
+                }
+                ui.separator();
+                ui.label("Window:");
+                for days in [30, 60, 90] {
+                    if ui.radio_value(&mut self.upcoming_days, days, format!("{} days", days)).clicked() {
+                        self.upcoming_selected_day = None;
+                    }
+                }
+            });
 
-                ui.horizontal(|ui| {
-                    ui.label("Date:");
-                    Self::show_date_selector(ui, &mut self.tx_editor_date, "tx_editor_date");
-                });
+            let now = chrono::Local::now().naive_local();
+            let today = now.date();
+            let from = today.and_hms_opt(0, 0, 0).unwrap();
+            let to = from + chrono::Duration::days(self.upcoming_days);
+            let occurrences = schedule::expand_occurrences(
+                &self.recurring_transactions_list,
+                &self.recurring_transfers_list,
+                &self.holiday_calendars,
+                from,
+                to,
+            );
+            let totals = schedule::daily_totals(&occurrences);
+
+            self.show_approval_queue(ui);
+            self.show_holidays(ui);
+
+            let income: f32 = totals.values().map(|t| t.income).sum();
+            let expenses: f32 = totals.values().map(|t| t.expenses).sum();
+            ui.label(format!(
+                "{} scheduled items | income ${:.2} | bills ${:.2} | net ${:.2}",
+                occurrences.len(),
+                income,
+                expenses,
+                income - expenses
+            ));
+
+            if ui.button("Export Calendar (.ics)").clicked() {
+                let file_path = format!(
+                    "upcoming_{}_to_{}.ics",
+                    today.format("%Y%m%d"),
+                    (to.date() - chrono::Duration::days(1)).format("%Y%m%d")
+                );
+                let ics = schedule::to_ics(&occurrences, |occ| self.describe_occurrence(occ), now);
+                self.message = match std::fs::write(&file_path, ics) {
+                    Ok(()) => format!("Exported {} occurrences to {}", occurrences.len(), file_path),
+                    Err(e) => format!("Failed to export calendar: {}", e),
+                };
+            }
+            ui.label(&self.message);
+            ui.separator();
 
-                ui.horizontal(|ui| {
-                    if ui.button("Save").clicked() {
-                        if let Some(tx_id) = self.tx_editing_id {
-                            let entered_amount = self.tx_editor_amount.abs();
-                            let amount = if self.tx_editor_is_expense {
-                                -entered_amount
-                            } else {
-                                entered_amount
-                            };
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                // One grid per calendar month touched by the window.
+                let last_day = to.date() - chrono::Duration::days(1);
+                let mut month_start = today.with_day(1).unwrap();
+                while month_start <= last_day {
+                    ui.strong(month_start.format("%B %Y").to_string());
+                    egui::Grid::new(format!("upcoming_month_{}", month_start))
+                        .num_columns(7)
+                        .min_col_width(90.0)
+                        .show(ui, |ui| {
+                            for name in ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"] {
+                                ui.label(name);
+                            }
+                            ui.end_row();
 
-                            if entered_amount <= 0.0 {
-                                self.message = "Amount must be positive.".to_string();
-                            } else {
-                                let date_time = format!("{} 00:00:00", self.tx_editor_date);
-                                
-                                match db::update_transaction(
-                                    &mut self.conn,
-                                    tx_id,
-                                    self.tx_editor_account_id,
-                                    amount,
-                                    self.tx_editor_category.clone(),
-                                    date_time,
-                                ) {
-                                    Ok(_) => {
-                                        self.message = "Transaction updated successfully".to_string();
-                                        self.load_user_transactions();
-                                        self.load_user_budgets();
-                                        self.compute_budget_progress(self.period_offset);
-
-                                        if let Some(uid) = self.user_id {
-                                            self.accounts_list = db::get_user_accounts(&mut self.conn, uid).unwrap_or_default();
+                            for _ in 0..month_start.weekday().num_days_from_monday() {
+                                ui.label("");
+                            }
+                            let mut day = month_start;
+                            while day.month() == month_start.month() {
+                                let in_window = day >= today && day <= last_day;
+                                let text = match totals.get(&day) {
+                                    Some(t) if in_window => {
+                                        let mut label = format!("{}\n{:+.2}", day.day(), t.net());
+                                        if t.transfers > 0.0 {
+                                            label.push_str(&format!("\n⇄ {:.2}", t.transfers));
                                         }
-                                        should_close = true;
-                                    }
-                                    Err(e) => {
-                                        self.message = format!("Error updating transaction: {}", e);
+                                        let color = if t.net() < 0.0 {
+                                            egui::Color32::from_rgb(200, 80, 80)
+                                        } else {
+                                            egui::Color32::from_rgb(80, 160, 80)
+                                        };
+                                        egui::RichText::new(label).color(color)
                                     }
+                                    _ if in_window => egui::RichText::new(day.day().to_string()),
+                                    _ => egui::RichText::new(day.day().to_string()).weak(),
+                                };
+                                let selected = self.upcoming_selected_day == Some(day);
+                                if ui.selectable_label(selected, text).clicked() && in_window {
+                                    self.upcoming_selected_day = if selected { None } else { Some(day) };
+                                }
+                                if day.weekday() == chrono::Weekday::Sun {
+                                    ui.end_row();
                                 }
+                                day = day.succ_opt().unwrap();
                             }
-                        }
-                    }
+                        });
+                    ui.add_space(8.0);
+                    month_start = recurrence::month_start_after(month_start, 1);
+                }
 
-                    if ui.button("Cancel").clicked() {
-                        should_close = true;
+                ui.separator();
+                match self.upcoming_selected_day {
+                    Some(day) => ui.heading(format!("Scheduled on {}", day.format("%Y-%m-%d"))),
+                    None => ui.heading("All Scheduled Items"),
+                };
+                if occurrences.is_empty() {
+                    ui.label("Nothing scheduled in this window.");
+                }
+                for (day, day_totals) in &totals {
+                    if self.upcoming_selected_day.is_some_and(|d| d != *day) {
+                        continue;
                     }
-                });
+                    ui.label(egui::RichText::new(format!(
+                        "{} | income ${:.2} | bills ${:.2} | transfers ${:.2}",
+                        day.format("%a %Y-%m-%d"),
+                        day_totals.income,
+                        day_totals.expenses,
+                        day_totals.transfers
+                    )).strong());
+                    for occ in occurrences.iter().filter(|o| o.at.date() == *day) {
+                        ui.label(format!("    {}", self.describe_occurrence(occ)));
+                    }
+                }
             });
-
-        if should_close {
-            self.tx_editor_open = false;
-            self.tx_editing_id = None;
-        }
+        });
     }
 
     fn show_transfers(&mut self, ctx: &egui::Context) {
@@ -2352,8 +5304,9 @@ impl FinancerApp {
                     
                     let date_time = format!("{} 00:00:00", self.transfer_date);
                     
-                    match db::create_transfer(
+                    match db::create_transfer_as(
                         &mut self.conn,
+                        self.user_id.unwrap_or_default(),
                         self.transfer_from_account_id,
                         self.transfer_to_account_id,
                         self.transfer_amount,
@@ -2369,13 +5322,13 @@ impl FinancerApp {
                             self.compute_budget_progress(self.period_offset);
                             
                             if let Some(uid) = self.user_id {
-                                self.accounts_list = db::get_user_accounts(&mut self.conn, uid).unwrap_or_default();
+                                self.refresh_accounts(uid);
                             }
                             
                             self.transfer_amount = 0.0;
                         }
                         Err(e) => {
-                            self.message = format!("Transfer failed: {:?}", e);
+                            self.message = format!("Transfer failed: {}", friendly_error(&e));
                         }
                     }
                 } else if self.transfer_from_account_id == self.transfer_to_account_id {
@@ -2445,17 +5398,22 @@ impl FinancerApp {
                 ui.text_edit_singleline(&mut self.recurring_transfer_next_run_at);
             });
 
-            ui.horizontal(|ui| {
-                ui.label("Frequency:");
-                egui::ComboBox::from_id_salt("rec_transfer_frequency")
-                    .selected_text(self.recurring_transfer_frequency.to_str())
-                    .show_ui(ui, |ui| {
-                        ui.selectable_value(&mut self.recurring_transfer_frequency, Period::Daily, "Daily");
-                        ui.selectable_value(&mut self.recurring_transfer_frequency, Period::Weekly, "Weekly");
-                        ui.selectable_value(&mut self.recurring_transfer_frequency, Period::Monthly, "Monthly");
-                        ui.selectable_value(&mut self.recurring_transfer_frequency, Period::Yearly, "Yearly");
-                    });
-            });
+            show_recurrence_picker(ui, &mut self.recurring_transfer_rule, &self.recurring_transfer_next_run_at, "rec_transfer_frequency");
+            Self::show_schedule_limits(
+                ui,
+                &mut self.recurring_transfer_has_end,
+                &mut self.recurring_transfer_end_date,
+                &mut self.recurring_transfer_has_limit,
+                &mut self.recurring_transfer_max_occurrences,
+                "rec_transfer_limits",
+            );
+            show_roll_picker(ui, &mut self.recurring_transfer_roll, "rec_transfer_roll");
+            if self.recurring_transfer_editing_id.is_some() {
+                ui.checkbox(&mut self.recurring_transfer_rewrite_past, "Also rewrite past generated entries")
+                    .on_hover_text("Apply the new amount and accounts to transfers this item already posted. Their dates are kept.");
+            }
+            ui.checkbox(&mut self.recurring_transfer_requires_approval, "Hold each occurrence for approval")
+                .on_hover_text("Due occurrences wait in the queue on the Upcoming screen instead of posting automatically.");
 
             let save_label = if self.recurring_transfer_editing_id.is_some() {
                 "Update Recurring Transfer"
@@ -2463,43 +5421,60 @@ impl FinancerApp {
                 "Add Recurring Transfer"
             };
 
-            if ui.button(save_label).clicked() {
-                if let Some(uid) = self.user_id {
-                    if self.recurring_transfer_from_account_id <= 0
-                        || self.recurring_transfer_to_account_id <= 0
-                        || self.recurring_transfer_from_account_id == self.recurring_transfer_to_account_id
-                        || self.recurring_transfer_amount <= 0.0
-                    {
-                        self.message = "Select two different accounts and enter a positive amount.".to_string();
-                    } else if chrono::NaiveDateTime::parse_from_str(&self.recurring_transfer_next_run_at, "%Y-%m-%d %H:%M:%S").is_err() {
-                        self.message = "Next Run must be in format YYYY-MM-DD HH:MM:SS".to_string();
-                    } else {
-                        let changes = crate::models::NewRecurringTransfer {
-                            user_id: uid,
-                            from_account_id: self.recurring_transfer_from_account_id,
-                            to_account_id: self.recurring_transfer_to_account_id,
-                            amount: self.recurring_transfer_amount,
-                            next_run_at: self.recurring_transfer_next_run_at.clone(),
-                            frequency: self.recurring_transfer_frequency.to_str().to_string(),
-                        };
-
-                        let result = if let Some(item_id) = self.recurring_transfer_editing_id {
-                            db::update_recurring_transfer(&mut self.conn, uid, item_id, changes).map(|_| ())
-                        } else {
-                            db::create_recurring_transfer(&mut self.conn, changes).map(|_| ())
-                        };
+            if ui.button(save_label).clicked() && let Some(uid) = self.user_id {
+                if self.recurring_transfer_from_account_id <= 0
+                    || self.recurring_transfer_to_account_id <= 0
+                    || self.recurring_transfer_from_account_id == self.recurring_transfer_to_account_id
+                    || self.recurring_transfer_amount <= 0.0
+                {
+                    self.message = "Select two different accounts and enter a positive amount.".to_string();
+                } else if chrono::NaiveDateTime::parse_from_str(&self.recurring_transfer_next_run_at, "%Y-%m-%d %H:%M:%S").is_err() {
+                    self.message = "Next Run must be in format YYYY-MM-DD HH:MM:SS".to_string();
+                } else {
+                    let changes = crate::models::NewRecurringTransfer {
+                        user_id: uid,
+                        from_account_id: self.recurring_transfer_from_account_id,
+                        to_account_id: self.recurring_transfer_to_account_id,
+                        amount: self.recurring_transfer_amount,
+                        next_run_at: self.recurring_transfer_next_run_at.clone(),
+                        // Filled in from the rule's base period when saved.
+                        frequency: String::new(),
+                        recurrence_rule: Some(self.recurring_transfer_rule.clone()),
+                        ends_at: self
+                            .recurring_transfer_has_end
+                            .then(|| format!("{} 23:59:59", self.recurring_transfer_end_date)),
+                        max_occurrences: self.recurring_transfer_has_limit.then_some(self.recurring_transfer_max_occurrences),
+                        requires_approval: self.recurring_transfer_requires_approval,
+                        roll_convention: self.recurring_transfer_roll.to_str().to_string(),
+                    };
 
-                        match result {
-                            Ok(()) => {
-                                self.message = "Recurring transfer saved.".to_string();
-                                self.recurring_transfer_editing_id = None;
-                                self.recurring_transfer_amount = 0.0;
-                                self.recurring_transfer_next_run_at = chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
-                                self.load_user_recurring_transfers();
-                            }
-                            Err(e) => {
-                                self.message = format!("Failed to save recurring transfer: {}", e);
+                    let result = if let Some(item_id) = self.recurring_transfer_editing_id {
+                        let rewrite_past = self.recurring_transfer_rewrite_past;
+                        db::update_recurring_transfer(&mut self.conn, uid, item_id, changes, rewrite_past).map(|_| {
+                            if rewrite_past {
+                                self.refresh_accounts(uid);
+                                self.load_user_transactions();
                             }
+                        })
+                    } else {
+                        db::create_recurring_transfer(&mut self.conn, changes).map(|_| ())
+                    };
+
+                    match result {
+                        Ok(()) => {
+                            self.message = "Recurring transfer saved.".to_string();
+                            self.recurring_transfer_editing_id = None;
+                            self.recurring_transfer_has_end = false;
+                            self.recurring_transfer_has_limit = false;
+                            self.recurring_transfer_requires_approval = false;
+                            self.recurring_transfer_rewrite_past = false;
+                            self.recurring_transfer_roll = RollConvention::None;
+                            self.recurring_transfer_amount = 0.0;
+                            self.recurring_transfer_next_run_at = chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+                            self.load_user_recurring_transfers();
+                        }
+                        Err(e) => {
+                            self.message = format!("Failed to save recurring transfer: {}", friendly_error(&e));
                         }
                     }
                 }
@@ -2509,6 +5484,8 @@ impl FinancerApp {
                 ui.label("No recurring transfers.");
             } else {
                 let mut delete_id: Option<i32> = None;
+                let mut toggle: Option<(i32, bool)> = None;
+                let mut skip_next: Option<i32> = None;
                 let mut edit_item: Option<RecurringTransfer> = None;
 
                 for item in &self.recurring_transfers_list {
@@ -2532,8 +5509,23 @@ impl FinancerApp {
                             to_name,
                             item.amount,
                             item.next_run_at,
-                            item.frequency
+                            schedule_status(
+                                &item.recurrence_rule,
+                                &item.frequency,
+                                &item.next_run_at,
+                                item.ends_at.as_deref(),
+                                item.remaining_occurrences(),
+                                item.active,
+                                item.requires_approval,
+                            )
                         ));
+                        if item.active && ui.button("Skip Next").on_hover_text("Skip only the next occurrence").clicked() {
+                            skip_next = Some(item.id);
+                        }
+                        let toggle_label = if item.active { "Pause" } else { "Resume" };
+                        if ui.button(toggle_label).clicked() {
+                            toggle = Some((item.id, !item.active));
+                        }
                         if ui.button("Edit").clicked() {
                             edit_item = Some(item.clone());
                         }
@@ -2549,23 +5541,54 @@ impl FinancerApp {
                     self.recurring_transfer_to_account_id = item.to_account_id;
                     self.recurring_transfer_amount = item.amount;
                     self.recurring_transfer_next_run_at = item.next_run_at;
-                    self.recurring_transfer_frequency = Period::from_str(&item.frequency);
+                    self.recurring_transfer_rule = item.recurrence_rule;
+                    self.recurring_transfer_has_end = item.ends_at.is_some();
+                    if let Some(end) = &item.ends_at {
+                        self.recurring_transfer_end_date = end.get(..10).unwrap_or(end).to_string();
+                    }
+                    self.recurring_transfer_has_limit = item.max_occurrences.is_some();
+                    self.recurring_transfer_max_occurrences = item.max_occurrences.unwrap_or(12);
+                    self.recurring_transfer_requires_approval = item.requires_approval;
+                    self.recurring_transfer_roll = RollConvention::from_str(&item.roll_convention);
                 }
 
-                if let Some(item_id) = delete_id {
-                    if let Some(uid) = self.user_id {
-                        match db::delete_recurring_transfer(&mut self.conn, uid, item_id) {
-                            Ok(_) => {
-                                self.message = "Recurring transfer deleted.".to_string();
-                                self.load_user_recurring_transfers();
-                                if self.recurring_transfer_editing_id == Some(item_id) {
-                                    self.recurring_transfer_editing_id = None;
-                                }
-                            }
-                            Err(e) => {
-                                self.message = format!("Failed to delete recurring transfer: {}", e);
+                if let Some(item_id) = skip_next && let Some(uid) = self.user_id {
+                    let now = chrono::Local::now().naive_local();
+                    match db::skip_next_recurring_occurrence(&mut self.conn, uid, RecurringKind::Transfer, item_id, now) {
+                        Ok(()) => {
+                            self.message = "Next occurrence skipped.".to_string();
+                            self.load_user_recurring_transfers();
+                        }
+                        Err(e) => {
+                            self.message = format!("Failed to skip occurrence: {}", friendly_error(&e));
+                        }
+                    }
+                }
+
+                if let Some((item_id, resume)) = toggle && let Some(uid) = self.user_id {
+                    match db::set_recurring_transfer_active(&mut self.conn, uid, item_id, resume, chrono::Local::now().naive_local()) {
+                        Ok(()) => {
+                            self.message = if resume { "Recurring transfer resumed." } else { "Recurring transfer paused." }.to_string();
+                            self.load_user_recurring_transfers();
+                        }
+                        Err(e) => {
+                            self.message = format!("Failed to update recurring transfer: {}", friendly_error(&e));
+                        }
+                    }
+                }
+
+                if let Some(item_id) = delete_id && let Some(uid) = self.user_id {
+                    match db::delete_recurring_transfer(&mut self.conn, uid, item_id) {
+                        Ok(_) => {
+                            self.message = "Recurring transfer deleted.".to_string();
+                            self.load_user_recurring_transfers();
+                            if self.recurring_transfer_editing_id == Some(item_id) {
+                                self.recurring_transfer_editing_id = None;
                             }
                         }
+                        Err(e) => {
+                            self.message = format!("Failed to delete recurring transfer: {}", friendly_error(&e));
+                        }
                     }
                 }
             }
@@ -2601,7 +5624,7 @@ impl FinancerApp {
                     let wtr = csv::Writer::from_path(&file_path);
                     match wtr {
                         Ok(mut writer) => {
-                            let _ = writer.write_record(&[
+                            let _ = writer.write_record([
                                 "account_name", "amount", "category", "date", "balance_after"
                             ]);
                             for tx in &filtered_transfers {
@@ -2610,7 +5633,7 @@ impl FinancerApp {
                                     .find(|a| a.id == tx.user_account_id)
                                     .map(|a| a.name.clone())
                                     .unwrap_or_else(|| "Unknown".to_string());
-                                let _ = writer.write_record(&[
+                                let _ = writer.write_record([
                                     account_name,
                                     tx.amount.to_string(),
                                     tx.category.clone(),
@@ -2702,6 +5725,9 @@ impl FinancerApp {
                             ui.colored_label(color, format!("${:.2}", tx.amount.abs()));
                             ui.label(format!("| {} {} | {} | Balance: ${:.2}", 
                                 transfer_type, account_name, tx.date, tx.balance_after));
+                            if let Some(origin) = self.recurring_origin(tx) {
+                                ui.label(egui::RichText::new("↻ recurring").small().strong()).on_hover_text(origin);
+                            }
                         });
                         ui.separator();
                     }
@@ -2835,21 +5861,17 @@ impl FinancerApp {
                     ui.label("No scenarios available.");
                 }
 
-                if ui.button("Add series to selected").clicked() {
-                    if let Ok(start) = NaiveDate::parse_from_str(&self.cf_gen_start_date, "%Y-%m-%d") {
-                        if !self.cf_scenarios.is_empty() {
-                            if self.cf_selected_scenario_for_gen >= self.cf_scenarios.len() {
-                                self.cf_selected_scenario_for_gen = self.cf_scenarios.len() - 1;
-                            }
-                            let scen_index = self.cf_selected_scenario_for_gen;
-                            let scen = &mut self.cf_scenarios[scen_index];
-                            for i in 0..self.cf_gen_months.max(0) {
-                                let date = start
-                                    .checked_add_months(chrono::Months::new(i as u32))
-                                    .unwrap_or(start);
-                                scen.lines.push_str(&format!("{} {:.2}\n", date.format("%Y-%m-%d"), self.cf_gen_amount));
-                            }
-                        }
+                if ui.button("Add series to selected").clicked() && let Ok(start) = NaiveDate::parse_from_str(&self.cf_gen_start_date, "%Y-%m-%d") && !self.cf_scenarios.is_empty() {
+                    if self.cf_selected_scenario_for_gen >= self.cf_scenarios.len() {
+                        self.cf_selected_scenario_for_gen = self.cf_scenarios.len() - 1;
+                    }
+                    let scen_index = self.cf_selected_scenario_for_gen;
+                    let scen = &mut self.cf_scenarios[scen_index];
+                    for i in 0..self.cf_gen_months.max(0) {
+                        let date = start
+                            .checked_add_months(chrono::Months::new(i as u32))
+                            .unwrap_or(start);
+                        scen.lines.push_str(&format!("{} {:.2}\n", date.format("%Y-%m-%d"), self.cf_gen_amount));
                     }
                 }
             });
@@ -2890,19 +5912,14 @@ impl FinancerApp {
                     }
                 });
 
-                if let Some(idx) = to_delete {
-                    if self.cf_scenarios.len() > 1 {
-                        self.cf_scenarios.remove(idx);
-                    }
+                if let Some(idx) = to_delete && self.cf_scenarios.len() > 1 {
+                    self.cf_scenarios.remove(idx);
                 }
             }
 
             if ui.button("Compute PV & FV for all scenarios").clicked() {
                 let parsed_date = NaiveDate::parse_from_str(&self.cf_valuation_date, "%Y-%m-%d");
-                if let Err(_) = parsed_date {
-                    self.cf_error = Some("Invalid valuation date format.".to_string());
-                } else {
-                    let valuation_date = parsed_date.unwrap();
+                if let Ok(valuation_date) = parsed_date {
                     let horizon_date = if self.cf_horizon_date.trim().is_empty() {
                         None
                     } else {
@@ -2979,6 +5996,8 @@ impl FinancerApp {
                     } else {
                         self.cf_error = Some("Please enter at least one cash flow in a scenario.".to_string());
                     }
+                } else {
+                    self.cf_error = Some("Invalid valuation date format.".to_string());
                 }
             }
 
@@ -3142,10 +6161,8 @@ impl FinancerApp {
                     }
                 });
 
-                if let Some(idx) = to_delete {
-                    if self.bond_scenarios.len() > 1 {
-                        self.bond_scenarios.remove(idx);
-                    }
+                if let Some(idx) = to_delete && self.bond_scenarios.len() > 1 {
+                    self.bond_scenarios.remove(idx);
                 }
             }
 
@@ -3191,6 +6208,223 @@ impl FinancerApp {
         });
     }
 
+    /// Starts the planner from the credit card and loan accounts. Rates and minimums aren't
+    /// stored with accounts, so the minimum is a guess of 2% of the balance, at least $25.
+    fn load_debts_from_accounts(&mut self) {
+        self.debts = self
+            .accounts_list
+            .iter()
+            .filter(|a| {
+                let kind = a.account_type.to_lowercase();
+                kind.contains("credit") || kind.contains("loan") || a.balance < 0.0
+            })
+            .filter(|a| a.balance != 0.0)
+            .map(|a| {
+                let balance = a.balance.abs() as f64;
+                Debt {
+                    name: a.name.clone(),
+                    balance,
+                    apr: 0.0,
+                    minimum_payment: (balance * 0.02).max(25.0).min(balance).round(),
+                }
+            })
+            .collect();
+        self.debt_custom_order = (0..self.debts.len()).collect();
+        self.message = if self.debts.is_empty() {
+            "No credit card or loan accounts found. Add debts below.".to_string()
+        } else {
+            format!("Loaded {} debt(s). Check each rate and minimum payment.", self.debts.len())
+        };
+    }
+
+    /// The first day of the month `month` months after this one; month 1 is next month.
+    fn payoff_month_start(month: u32) -> chrono::NaiveDate {
+        budgeting::period_range(Period::Monthly, chrono::Local::now().date_naive(), month as i32).0.date()
+    }
+
+    fn export_debt_plan(&mut self, plan: &PayoffPlan) {
+        let file_path = format!("debt_payoff_{}.csv", chrono::Local::now().format("%Y%m%d"));
+        match Writer::from_path(&file_path) {
+            Ok(mut writer) => {
+                let _ = writer.write_record(["month", "date", "debt", "payment", "interest", "principal", "remaining_balance"]);
+                let mut rows = 0;
+                for month in &plan.schedule {
+                    for p in &month.payments {
+                        let _ = writer.write_record([
+                            month.month.to_string(),
+                            Self::payoff_month_start(month.month).format("%Y-%m").to_string(),
+                            self.debts[p.debt].name.clone(),
+                            format!("{:.2}", p.payment),
+                            format!("{:.2}", p.interest),
+                            format!("{:.2}", p.principal),
+                            format!("{:.2}", p.remaining_balance),
+                        ]);
+                        rows += 1;
+                    }
+                }
+                let _ = writer.flush();
+                self.message = format!("Exported {} payments to {}", rows, file_path);
+            }
+            Err(e) => {
+                self.message = format!("Failed to export CSV: {}", e);
+            }
+        }
+    }
+
+    fn show_debt_planner(&mut self, ctx: &egui::Context) {
+        const MAX_MONTHS: u32 = 600;
+        let month_label = |month: Option<u32>| match month {
+            Some(m) => format!("{} ({} months)", Self::payoff_month_start(m).format("%b %Y"), m),
+            None => "Not within 50 years".to_string(),
+        };
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+        egui::ScrollArea::vertical().auto_shrink([false; 2]).show(ui, |ui| {
+            ui.heading("Debt Payoff Planner");
+            ui.horizontal(|ui| {
+                if ui.button("Back to Dashboard").clicked() {
+                    self.screen = AppState::Dashboard;
+                }
+                if ui.button("Reload from Accounts").clicked() {
+                    self.load_debts_from_accounts();
+                }
+            });
+            ui.separator();
+
+            let mut remove: Option<usize> = None;
+            egui::Grid::new("debt_planner_debts").striped(true).num_columns(5).show(ui, |ui| {
+                ui.strong("Debt");
+                ui.strong("Balance");
+                ui.strong("APR");
+                ui.strong("Minimum");
+                ui.end_row();
+                for (i, debt) in self.debts.iter_mut().enumerate() {
+                    ui.push_id(i, |ui| {
+                        ui.text_edit_singleline(&mut debt.name);
+                    });
+                    ui.add(egui::DragValue::new(&mut debt.balance).speed(10.0).prefix("$").range(0.0..=f64::MAX));
+                    let mut apr_percent = debt.apr * 100.0;
+                    if ui.add(egui::DragValue::new(&mut apr_percent).speed(0.1).suffix("%").range(0.0..=100.0)).changed() {
+                        debt.apr = apr_percent / 100.0;
+                    }
+                    ui.add(egui::DragValue::new(&mut debt.minimum_payment).speed(1.0).prefix("$").range(0.0..=f64::MAX));
+                    if ui.small_button("Remove").clicked() {
+                        remove = Some(i);
+                    }
+                    ui.end_row();
+                }
+            });
+            if let Some(i) = remove {
+                self.debts.remove(i);
+                self.debt_custom_order = (0..self.debts.len()).collect();
+            }
+            if ui.button("Add Debt").clicked() {
+                self.debts.push(Debt { name: format!("Debt {}", self.debts.len() + 1), balance: 1000.0, apr: 0.2, minimum_payment: 25.0 });
+                self.debt_custom_order = (0..self.debts.len()).collect();
+            }
+
+            ui.horizontal(|ui| {
+                ui.label("Extra each month, on top of the minimums:");
+                ui.add(egui::DragValue::new(&mut self.debt_extra_payment).speed(10.0).prefix("$").range(0.0..=f64::MAX));
+            });
+            ui.horizontal(|ui| {
+                ui.label("Strategy:");
+                for strategy in PayoffStrategy::ALL {
+                    ui.radio_value(&mut self.debt_strategy, strategy, strategy.label());
+                }
+            });
+            if self.debt_strategy == PayoffStrategy::Custom {
+                let mut swap: Option<(usize, usize)> = None;
+                for (position, &i) in self.debt_custom_order.iter().enumerate() {
+                    ui.horizontal(|ui| {
+                        ui.label(format!("{}. {}", position + 1, self.debts[i].name));
+                        if ui.add_enabled(position > 0, egui::Button::new("Up").small()).clicked() {
+                            swap = Some((position - 1, position));
+                        }
+                        if ui.add_enabled(position + 1 < self.debt_custom_order.len(), egui::Button::new("Down").small()).clicked() {
+                            swap = Some((position, position + 1));
+                        }
+                    });
+                }
+                if let Some((a, b)) = swap {
+                    self.debt_custom_order.swap(a, b);
+                }
+            }
+            ui.separator();
+
+            if self.debts.is_empty() {
+                ui.label("Add a debt to see a plan.");
+                ui.label(&self.message);
+                return;
+            }
+
+            ui.heading("Compare Strategies");
+            egui::Grid::new("debt_planner_compare").striped(true).num_columns(3).show(ui, |ui| {
+                ui.strong("Strategy");
+                ui.strong("Debt-free");
+                ui.strong("Total interest");
+                ui.end_row();
+                for strategy in PayoffStrategy::ALL {
+                    let plan = simulate_debt_payoff(&self.debts, self.debt_extra_payment, strategy, &self.debt_custom_order, MAX_MONTHS);
+                    let done = plan.is_complete().then(|| plan.payoff_month.iter().flatten().copied().max().unwrap_or(0));
+                    ui.label(strategy.label());
+                    ui.label(month_label(done));
+                    ui.label(format!("${:.2}", plan.total_interest()));
+                    ui.end_row();
+                }
+            });
+            ui.separator();
+
+            let plan = simulate_debt_payoff(&self.debts, self.debt_extra_payment, self.debt_strategy, &self.debt_custom_order, MAX_MONTHS);
+            ui.heading(format!("{} Plan", self.debt_strategy.label()));
+            if !plan.is_complete() {
+                ui.colored_label(Color32::from_rgb(220, 50, 50), "The payments don't keep up with the interest on every debt. Raise the minimums or the extra amount.");
+            }
+            egui::Grid::new("debt_planner_payoff").striped(true).num_columns(3).show(ui, |ui| {
+                ui.strong("Debt");
+                ui.strong("Paid off");
+                ui.strong("Interest");
+                ui.end_row();
+                for (i, debt) in self.debts.iter().enumerate() {
+                    ui.label(&debt.name);
+                    ui.label(month_label(plan.payoff_month[i]));
+                    ui.label(format!("${:.2}", plan.interest_by_debt[i]));
+                    ui.end_row();
+                }
+            });
+            ui.label(format!("Total paid ${:.2}, of which interest ${:.2}", plan.total_paid(), plan.total_interest()));
+            if ui.button("Export Schedule CSV").clicked() {
+                self.export_debt_plan(&plan);
+            }
+
+            egui::CollapsingHeader::new("Monthly Schedule").show(ui, |ui| {
+                egui::Grid::new("debt_planner_schedule").striped(true).num_columns(6).show(ui, |ui| {
+                    ui.strong("Month");
+                    ui.strong("Debt");
+                    ui.strong("Payment");
+                    ui.strong("Interest");
+                    ui.strong("Principal");
+                    ui.strong("Remaining");
+                    ui.end_row();
+                    for month in &plan.schedule {
+                        for p in &month.payments {
+                            ui.label(Self::payoff_month_start(month.month).format("%b %Y").to_string());
+                            ui.label(&self.debts[p.debt].name);
+                            ui.label(format!("${:.2}", p.payment));
+                            ui.label(format!("${:.2}", p.interest));
+                            ui.label(format!("${:.2}", p.principal));
+                            ui.label(format!("${:.2}", p.remaining_balance));
+                            ui.end_row();
+                        }
+                    }
+                });
+            });
+            ui.separator();
+            ui.label(&self.message);
+        });
+        });
+    }
+
     fn show_mortgage_tools(&mut self, ctx: &egui::Context) {
         egui::CentralPanel::default().show(ctx, |ui| {
             egui::ScrollArea::vertical().auto_shrink([false; 2]).show(ui, |ui| {
@@ -3312,10 +6546,8 @@ impl FinancerApp {
                     }
                 });
 
-                if let Some(idx) = to_delete {
-                    if self.mortgage_scenarios.len() > 1 {
-                        self.mortgage_scenarios.remove(idx);
-                    }
+                if let Some(idx) = to_delete && self.mortgage_scenarios.len() > 1 {
+                    self.mortgage_scenarios.remove(idx);
                 }
             }
 
@@ -3612,13 +6844,24 @@ impl FinancerApp {
 
 impl eframe::App for FinancerApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        self.maybe_auto_lock(ctx);
         match self.screen {
             AppState::Login => self.show_login(ctx),
             AppState::Register => self.show_register(ctx),
             AppState::Dashboard => self.show_dashboard(ctx),
+            AppState::SecuritySettings => self.show_security_settings(ctx),
+            AppState::Locked => self.show_lock_screen(ctx),
             AppState::Budgeting => self.show_budgets(ctx),
+            AppState::BudgetHistory => self.show_budget_history(ctx),
+            AppState::BudgetTemplates => self.show_budget_templates(ctx),
+            AppState::SavingsRate => self.show_savings_rate(ctx),
+            AppState::SavingsGoals => self.show_savings_goals(ctx),
+            AppState::Reports => self.show_reports(ctx),
+            AppState::DebtPlanner => self.show_debt_planner(ctx),
             AppState::Transactions => self.show_transactions(ctx),
             AppState::Transfers => self.show_transfers(ctx),
+            AppState::Upcoming => self.show_upcoming(ctx),
+            AppState::Envelopes => self.show_envelopes(ctx),
             AppState::CashflowTools => self.show_cashflow_tools(ctx),
             AppState::BondTools => self.show_bond_tools(ctx),
             AppState::MortgageTools => self.show_mortgage_tools(ctx),
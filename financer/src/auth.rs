@@ -0,0 +1,236 @@
+//! Password hashing and password-strength policy.
+
+use argon2::{Algorithm, Argon2, Params, PasswordHasher, PasswordVerifier, Version};
+use ::password_hash::{PasswordHash, SaltString};
+use crate::error::FinancerError;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PasswordPolicy {
+    pub min_length: usize,
+    pub require_mixed_case: bool,
+    pub require_digit: bool,
+    pub require_symbol: bool,
+}
+
+impl Default for PasswordPolicy {
+    fn default() -> Self {
+        Self {
+            min_length: 8,
+            require_mixed_case: false,
+            require_digit: true,
+            require_symbol: false,
+        }
+    }
+}
+
+impl PasswordPolicy {
+    /// Reads `PASSWORD_MIN_LENGTH`, `PASSWORD_REQUIRE_MIXED_CASE`, `PASSWORD_REQUIRE_DIGIT`
+    /// and `PASSWORD_REQUIRE_SYMBOL`, falling back to the defaults for anything unset.
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        let flag = |key: &str, default: bool| match std::env::var(key) {
+            Ok(v) => matches!(v.trim().to_ascii_lowercase().as_str(), "1" | "true" | "yes"),
+            Err(_) => default,
+        };
+        Self {
+            min_length: std::env::var("PASSWORD_MIN_LENGTH")
+                .ok()
+                .and_then(|v| v.trim().parse().ok())
+                .unwrap_or(defaults.min_length),
+            require_mixed_case: flag("PASSWORD_REQUIRE_MIXED_CASE", defaults.require_mixed_case),
+            require_digit: flag("PASSWORD_REQUIRE_DIGIT", defaults.require_digit),
+            require_symbol: flag("PASSWORD_REQUIRE_SYMBOL", defaults.require_symbol),
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        let mut parts = vec![format!("at least {} characters", self.min_length)];
+        if self.require_mixed_case {
+            parts.push("upper and lower case letters".to_string());
+        }
+        if self.require_digit {
+            parts.push("a digit".to_string());
+        }
+        if self.require_symbol {
+            parts.push("a symbol".to_string());
+        }
+        format!("Password must contain {}", parts.join(", "))
+    }
+
+    pub fn validate(&self, password: &str) -> Result<(), FinancerError> {
+        let long_enough = password.chars().count() >= self.min_length;
+        let mixed_ok = !self.require_mixed_case
+            || (password.chars().any(|c| c.is_uppercase()) && password.chars().any(|c| c.is_lowercase()));
+        let digit_ok = !self.require_digit || password.chars().any(|c| c.is_ascii_digit());
+        let symbol_ok = !self.require_symbol || password.chars().any(|c| !c.is_alphanumeric() && !c.is_whitespace());
+
+        if long_enough && mixed_ok && digit_ok && symbol_ok {
+            Ok(())
+        } else {
+            Err(FinancerError::Validation(self.describe()))
+        }
+    }
+}
+
+pub fn hash_password(password: &str) -> Result<String, FinancerError> {
+    let salt = SaltString::generate(&mut rand::thread_rng());
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|h| h.to_string())
+        .map_err(|e| FinancerError::Internal(format!("Password could not be hashed: {}", e)))
+}
+
+/// Checks `password` against a stored PHC string. Hashes produced with other Argon2
+/// variants or cost parameters still verify, since the parameters live in the string.
+pub fn verify_password(password: &str, stored_hash: &str) -> Result<bool, FinancerError> {
+    let parsed_hash = PasswordHash::new(stored_hash)
+        .map_err(|e| FinancerError::CorruptData(format!("Password hash is unreadable: {}", e)))?;
+    Ok(Argon2::default().verify_password(password.as_bytes(), &parsed_hash).is_ok())
+}
+
+/// True when a stored hash was not produced with the current `Argon2::default()` settings.
+pub fn needs_rehash(stored_hash: &str) -> bool {
+    let Ok(parsed_hash) = PasswordHash::new(stored_hash) else {
+        return true;
+    };
+    let Ok(params) = Params::try_from(&parsed_hash) else {
+        return true;
+    };
+    let current = Params::default();
+
+    parsed_hash.algorithm != Algorithm::default().ident()
+        || parsed_hash.version != Some(Version::default().into())
+        || params.m_cost() != current.m_cost()
+        || params.t_cost() != current.t_cost()
+        || params.p_cost() != current.p_cost()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoginThrottlePolicy {
+    /// Failures allowed back to back before any delay kicks in.
+    pub free_attempts: i32,
+    pub base_delay_secs: i64,
+    pub max_delay_secs: i64,
+    /// Failures (including the free ones) that trigger a full lockout.
+    pub lockout_threshold: i32,
+    pub lockout_secs: i64,
+}
+
+impl Default for LoginThrottlePolicy {
+    fn default() -> Self {
+        Self {
+            free_attempts: 3,
+            base_delay_secs: 2,
+            max_delay_secs: 300,
+            lockout_threshold: 10,
+            lockout_secs: 15 * 60,
+        }
+    }
+}
+
+impl LoginThrottlePolicy {
+    /// Reads `LOGIN_FREE_ATTEMPTS`, `LOGIN_LOCKOUT_THRESHOLD` and `LOGIN_LOCKOUT_MINUTES`,
+    /// falling back to the defaults for anything unset.
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        let int = |key: &str| std::env::var(key).ok().and_then(|v| v.trim().parse::<i64>().ok());
+        Self {
+            free_attempts: int("LOGIN_FREE_ATTEMPTS").map(|v| v as i32).unwrap_or(defaults.free_attempts),
+            lockout_threshold: int("LOGIN_LOCKOUT_THRESHOLD").map(|v| v as i32).unwrap_or(defaults.lockout_threshold),
+            lockout_secs: int("LOGIN_LOCKOUT_MINUTES").map(|v| v * 60).unwrap_or(defaults.lockout_secs),
+            ..defaults
+        }
+    }
+
+    /// Seconds to wait after the `failed_count`-th consecutive failure. Doubles with
+    /// every failure past the free ones, and jumps to the lockout at the threshold.
+    pub fn delay_after(&self, failed_count: i32) -> i64 {
+        if failed_count >= self.lockout_threshold {
+            return self.lockout_secs;
+        }
+        if failed_count < self.free_attempts {
+            return 0;
+        }
+        let doublings = (failed_count - self.free_attempts).min(30) as u32;
+        self.base_delay_secs
+            .saturating_mul(1i64 << doublings)
+            .min(self.max_delay_secs)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AutoLockPolicy {
+    /// `None` disables the idle timer.
+    pub idle_timeout: Option<std::time::Duration>,
+    pub lock_on_minimize: bool,
+}
+
+impl Default for AutoLockPolicy {
+    fn default() -> Self {
+        Self {
+            idle_timeout: Some(std::time::Duration::from_secs(5 * 60)),
+            lock_on_minimize: true,
+        }
+    }
+}
+
+impl AutoLockPolicy {
+    /// Reads `IDLE_LOCK_MINUTES` (0 turns the timer off) and `LOCK_ON_MINIMIZE`,
+    /// falling back to the defaults for anything unset.
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        let idle_timeout = match std::env::var("IDLE_LOCK_MINUTES").ok().and_then(|v| v.trim().parse::<u64>().ok()) {
+            Some(0) => None,
+            Some(minutes) => Some(std::time::Duration::from_secs(minutes * 60)),
+            None => defaults.idle_timeout,
+        };
+        let lock_on_minimize = match std::env::var("LOCK_ON_MINIMIZE") {
+            Ok(v) => matches!(v.trim().to_ascii_lowercase().as_str(), "1" | "true" | "yes"),
+            Err(_) => defaults.lock_on_minimize,
+        };
+        Self { idle_timeout, lock_on_minimize }
+    }
+
+    pub fn should_lock(&self, idle_for: std::time::Duration, minimized: bool) -> bool {
+        (self.lock_on_minimize && minimized) || self.idle_timeout.is_some_and(|limit| idle_for >= limit)
+    }
+}
+
+/// What the 2FA section of Security Settings holds between frames.
+#[derive(Debug, Default)]
+pub struct TotpSetupForm {
+    pub password: String,
+    /// Set while an enrollment is waiting for its first code.
+    pub pending_secret: Option<String>,
+    pub confirm_code: String,
+    /// Shown once after enrollment; they can't be read back from the database.
+    pub new_recovery_codes: Vec<String>,
+}
+
+impl TotpSetupForm {
+    /// Wipes what the user typed but keeps an unfinished enrollment and freshly issued
+    /// recovery codes, so an auto-lock doesn't throw them away.
+    pub fn wipe_typed_secrets(&mut self) {
+        wipe_secret(&mut self.password);
+        wipe_secret(&mut self.confirm_code);
+    }
+
+    pub fn clear(&mut self) {
+        self.wipe_typed_secrets();
+        if let Some(mut secret) = self.pending_secret.take() {
+            wipe_secret(&mut secret);
+        }
+        for code in &mut self.new_recovery_codes {
+            wipe_secret(code);
+        }
+        self.new_recovery_codes.clear();
+    }
+}
+
+/// Overwrites a secret in place before clearing it, so the old bytes don't linger in
+/// the buffer's allocation.
+pub fn wipe_secret(secret: &mut String) {
+    let len = secret.len();
+    secret.replace_range(.., &"\0".repeat(len));
+    secret.clear();
+}
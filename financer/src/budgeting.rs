@@ -0,0 +1,394 @@
+//! Budget periods and rollover: the date arithmetic and carrying rules behind budget
+//! progress, kept apart from the database and the clock.
+
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime};
+
+use crate::error::FinancerError;
+use crate::models::{Budget, Period, RolloverMode, TargetType};
+
+/// Start (inclusive) and end (exclusive) of the budget period `offset` periods away from the
+/// one containing `today`. Weeks start on Monday.
+pub fn period_range(period: Period, today: NaiveDate, offset: i32) -> (NaiveDateTime, NaiveDateTime) {
+    let midnight = |d: NaiveDate| d.and_hms_opt(0, 0, 0).unwrap();
+    match period {
+        Period::Daily => {
+            let day = today + Duration::days(offset as i64);
+            (midnight(day), midnight(day + Duration::days(1)))
+        }
+        Period::Weekly => {
+            let weekday = today.weekday().num_days_from_monday() as i64;
+            let week_start = today - Duration::days(weekday) + Duration::weeks(offset as i64);
+            (midnight(week_start), midnight(week_start + Duration::weeks(1)))
+        }
+        Period::Monthly => {
+            let total_month = today.month0() as i32 + offset;
+            let year = today.year() + total_month.div_euclid(12);
+            let month = total_month.rem_euclid(12) as u32 + 1;
+            let start = NaiveDate::from_ymd_opt(year, month, 1).unwrap();
+            let next = if month == 12 {
+                NaiveDate::from_ymd_opt(year + 1, 1, 1).unwrap()
+            } else {
+                NaiveDate::from_ymd_opt(year, month + 1, 1).unwrap()
+            };
+            (midnight(start), midnight(next))
+        }
+        Period::Yearly => {
+            let year = today.year() + offset;
+            (
+                midnight(NaiveDate::from_ymd_opt(year, 1, 1).unwrap()),
+                midnight(NaiveDate::from_ymd_opt(year + 1, 1, 1).unwrap()),
+            )
+        }
+    }
+}
+
+/// A budget's own date range as start (inclusive) and end (exclusive), if it has one.
+pub fn custom_range(budget: &Budget) -> Option<(NaiveDateTime, NaiveDateTime)> {
+    let day = |text: &Option<String>| text.as_deref().and_then(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok());
+    let (start, end) = (day(&budget.starts_on)?, day(&budget.ends_on)?);
+    Some((start.and_hms_opt(0, 0, 0).unwrap(), (end + Duration::days(1)).and_hms_opt(0, 0, 0).unwrap()))
+}
+
+/// The span a budget covers `offset` periods from the one containing `today`. A budget with
+/// its own dates always covers just those.
+pub fn budget_range(budget: &Budget, today: NaiveDate, offset: i32) -> (NaiveDateTime, NaiveDateTime) {
+    custom_range(budget).unwrap_or_else(|| period_range(Period::from_str(&budget.period), today, offset))
+}
+
+/// Consecutive periods from the one containing `first` up to, but not including, the one
+/// starting at or after `before`.
+pub fn periods_between(period: Period, first: NaiveDate, before: NaiveDateTime) -> Vec<(NaiveDateTime, NaiveDateTime)> {
+    let mut periods = Vec::new();
+    let mut offset = 0;
+    loop {
+        let range = period_range(period, first, offset);
+        if range.0 >= before {
+            return periods;
+        }
+        periods.push(range);
+        offset += 1;
+    }
+}
+
+/// What carries into the next period after spending `spent_per_period` (cents, oldest
+/// first) against a limit of `limit_cents` each period, starting from `carried_in` for the
+/// first of them. Each period's own limit already includes what the one before it carried.
+pub fn rollover_after(mode: RolloverMode, cap_cents: Option<i32>, limit_cents: i32, carried_in: i64, spent_per_period: &[i64]) -> i64 {
+    spent_per_period.iter().fold(carried_in, |carried, spent| {
+        let left = limit_cents as i64 + carried - spent;
+        match mode {
+            RolloverMode::None => 0,
+            RolloverMode::Surplus => left.max(0),
+            RolloverMode::SurplusAndDeficit => left,
+            RolloverMode::Capped => left.clamp(0, cap_cents.unwrap_or(0).max(0) as i64),
+        }
+    })
+}
+
+/// Budgeted against actual for one budget period, in cents. `actual` is what was spent for an
+/// expense budget and what came in for an income target.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PeriodPerformance {
+    pub start: NaiveDateTime,
+    /// Exclusive.
+    pub end: NaiveDateTime,
+    /// The limit or target, including any rollover.
+    pub budgeted: i64,
+    pub actual: i64,
+}
+
+impl PeriodPerformance {
+    /// How far the period came out on the good side of the budget: under an expense limit or
+    /// over an income target. Negative when it missed.
+    pub fn variance(&self, target: TargetType) -> i64 {
+        match target {
+            TargetType::Expense => self.budgeted - self.actual,
+            TargetType::Income => self.actual - self.budgeted,
+        }
+    }
+
+    /// `variance` as a percentage of the budgeted amount; `None` when nothing was budgeted.
+    pub fn variance_percent(&self, target: TargetType) -> Option<f64> {
+        (self.budgeted != 0).then(|| self.variance(target) as f64 * 100.0 / self.budgeted as f64)
+    }
+
+    pub fn on_target(&self, target: TargetType) -> bool {
+        self.variance(target) >= 0
+    }
+}
+
+/// How many finished periods in a row, counting back from the latest, were on target (`true`)
+/// or missed it (`false`). Periods still running at `now` don't count either way.
+pub fn performance_streak(history: &[PeriodPerformance], target: TargetType, now: NaiveDateTime) -> Option<(bool, usize)> {
+    let mut finished = history.iter().rev().filter(|p| p.end <= now);
+    let on_target = finished.next()?.on_target(target);
+    Some((on_target, 1 + finished.take_while(|p| p.on_target(target) == on_target).count()))
+}
+
+/// Where an expense budget is heading by the end of its period.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PaceStatus {
+    OnTrack,
+    /// Spending at this pace, plus the known bills, would pass the limit.
+    AtRisk,
+    /// Already past the limit.
+    Over,
+}
+
+impl PaceStatus {
+    pub fn label(self) -> &'static str {
+        match self {
+            PaceStatus::OnTrack => "On track",
+            PaceStatus::AtRisk => "At risk",
+            PaceStatus::Over => "Over",
+        }
+    }
+}
+
+/// An expense budget's spend projected to the end of the running period. All amounts are in
+/// cents and positive when money goes out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PaceProjection {
+    pub projected: i64,
+    /// Days left in the period, counting today.
+    pub days_left: i64,
+    /// What can go out each remaining day and still leave room for the upcoming bills.
+    pub safe_daily: i64,
+    pub status: PaceStatus,
+}
+
+/// Projects an expense budget to the end of `start..end`. Everyday spending carries on at its
+/// pace so far; recurring charges don't, since `upcoming` already counts the ones still to come.
+/// `spent` includes `recurring_spent`, the part recurring items posted. `None` once the period
+/// is over or before it starts.
+pub fn project_pace(
+    spent: i64,
+    recurring_spent: i64,
+    upcoming: i64,
+    limit: i64,
+    (start, end): (NaiveDateTime, NaiveDateTime),
+    now: NaiveDateTime,
+) -> Option<PaceProjection> {
+    if now < start || now >= end {
+        return None;
+    }
+    let elapsed = (now - start).num_seconds();
+    let remaining = (end - now).num_seconds();
+    let everyday = (spent - recurring_spent).max(0);
+    let ahead = if elapsed > 0 { (everyday as f64 * remaining as f64 / elapsed as f64).round() as i64 } else { 0 };
+    let projected = spent + ahead + upcoming;
+
+    let days_left = (end.date() - now.date()).num_days().max(1);
+    let safe_daily = (limit - spent - upcoming).max(0) / days_left;
+    let status = if spent > limit {
+        PaceStatus::Over
+    } else if projected > limit {
+        PaceStatus::AtRisk
+    } else {
+        PaceStatus::OnTrack
+    };
+    Some(PaceProjection { projected, days_left, safe_daily, status })
+}
+
+/// Money in and out during one calendar month, in cents, leaving out transfers between
+/// accounts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MonthlySavings {
+    /// The first day of the month.
+    pub month: NaiveDate,
+    pub income: i64,
+    /// Money out, as a positive amount.
+    pub expenses: i64,
+}
+
+impl MonthlySavings {
+    pub fn saved(&self) -> i64 {
+        self.income - self.expenses
+    }
+
+    /// The share of income kept, as a percentage; `None` in a month without income.
+    pub fn rate(&self) -> Option<f64> {
+        (self.income > 0).then(|| self.saved() as f64 * 100.0 / self.income as f64)
+    }
+}
+
+/// The savings rate across several months together, weighting each by its income.
+pub fn overall_savings_rate(months: &[MonthlySavings]) -> Option<f64> {
+    let total = MonthlySavings {
+        month: months.first()?.month,
+        income: months.iter().map(|m| m.income).sum(),
+        expenses: months.iter().map(|m| m.expenses).sum(),
+    };
+    total.rate()
+}
+
+/// One envelope's month in zero-based budgeting. All amounts are in cents.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnvelopeStatus {
+    pub envelope_id: i32,
+    pub category: String,
+    /// Assigned during the month, net of money moved out or returned.
+    pub assigned: i64,
+    /// The category's transactions during the month; negative when money was spent.
+    pub activity: i64,
+    /// Left at the end of the month, counting every month before it.
+    pub available: i64,
+}
+
+/// The `%Y-%m` key envelope assignments are stored under.
+pub fn month_key(day: NaiveDate) -> String {
+    day.format("%Y-%m").to_string()
+}
+
+/// Alert thresholds a new budget starts with.
+pub const DEFAULT_ALERT_THRESHOLDS: &str = "80,100";
+
+/// Percentages in a budget's `alert_thresholds`, smallest first. An empty list turns alerts off.
+pub fn parse_alert_thresholds(text: &str) -> Result<Vec<i32>, FinancerError> {
+    let mut thresholds = Vec::new();
+    for part in text.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+        let percent: i32 = part.trim_end_matches('%').trim().parse().map_err(|_| {
+            FinancerError::Validation(format!("Alert threshold '{}' is not a whole percentage", part))
+        })?;
+        if !(1..=1000).contains(&percent) {
+            return Err(FinancerError::Validation(format!("Alert threshold {}% must be between 1% and 1000%", percent)));
+        }
+        thresholds.push(percent);
+    }
+    thresholds.sort_unstable();
+    thresholds.dedup();
+    Ok(thresholds)
+}
+
+/// The thresholds that spending `spent_cents` against `limit_cents` has reached.
+pub fn crossed_thresholds(thresholds: &[i32], spent_cents: i64, limit_cents: i64) -> Vec<i32> {
+    if limit_cents <= 0 {
+        return Vec::new();
+    }
+    thresholds
+        .iter()
+        .copied()
+        .filter(|t| spent_cents * 100 >= *t as i64 * limit_cents)
+        .collect()
+}
+
+/// Separates a parent category from its subcategory, as in `Food:Groceries`.
+pub const SUBCATEGORY_SEPARATOR: char = ':';
+
+/// Whether `category` is `parent` or one of its subcategories, at any depth.
+pub fn is_within(category: &str, parent: &str) -> bool {
+    category == parent
+        || category
+            .strip_prefix(parent)
+            .is_some_and(|rest| rest.starts_with(SUBCATEGORY_SEPARATOR))
+}
+
+/// What a budget counts: transactions in its categories on the accounts it is limited to.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BudgetScope {
+    /// The budget's own category first, then any others it covers.
+    pub categories: Vec<String>,
+    /// Also count subcategories of each of `categories`.
+    pub include_subcategories: bool,
+    /// Empty means every account the owner can see.
+    pub account_ids: Vec<i32>,
+}
+
+impl BudgetScope {
+    /// A single category on every account, as budgets and envelopes have by default.
+    pub fn category(category: &str) -> Self {
+        BudgetScope {
+            categories: vec![category.to_string()],
+            ..Default::default()
+        }
+    }
+
+    pub fn matches(&self, category: &str, account_id: i32) -> bool {
+        let in_category = self
+            .categories
+            .iter()
+            .any(|c| c == category || (self.include_subcategories && is_within(category, c)));
+        in_category && (self.account_ids.is_empty() || self.account_ids.contains(&account_id))
+    }
+
+    /// The categories, with a marker when subcategories count too.
+    pub fn describe_categories(&self) -> String {
+        let suffix = if self.include_subcategories { " (and subcategories)" } else { "" };
+        format!("{}{}", self.categories.join(", "), suffix)
+    }
+}
+
+/// A budget waiting to be created, from a template or one of the planners.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BudgetDraft {
+    pub name: Option<String>,
+    /// The budget's own category first, then any others it covers.
+    pub categories: Vec<String>,
+    pub limit_cents: i32,
+    pub period: Period,
+    pub target_type: TargetType,
+}
+
+/// One slice of monthly income in a percentage plan such as 50/30/20.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IncomeShare {
+    pub name: String,
+    pub percent: f64,
+    /// The categories the share's budget covers; a share with none gets no budget.
+    pub categories: Vec<String>,
+}
+
+/// The classic plan: half of income for needs, 30% for wants and 20% for savings.
+pub fn fifty_thirty_twenty() -> Vec<IncomeShare> {
+    [("Needs", 50.0), ("Wants", 30.0), ("Savings", 20.0)]
+        .into_iter()
+        .map(|(name, percent)| IncomeShare {
+            name: name.to_string(),
+            percent,
+            categories: Vec::new(),
+        })
+        .collect()
+}
+
+/// Monthly expense budgets that divide `monthly_income_cents` between the shares.
+pub fn split_income(monthly_income_cents: i64, shares: &[IncomeShare]) -> Vec<BudgetDraft> {
+    shares
+        .iter()
+        .filter(|share| !share.categories.is_empty())
+        .map(|share| BudgetDraft {
+            name: Some(share.name.clone()),
+            categories: share.categories.clone(),
+            limit_cents: (monthly_income_cents.max(0) as f64 * share.percent / 100.0).round() as i32,
+            period: Period::Monthly,
+            target_type: TargetType::Expense,
+        })
+        .collect()
+}
+
+/// The `months` whole months before the one containing `today`, as start and end (exclusive).
+pub fn recent_months(today: NaiveDate, months: u32) -> (NaiveDateTime, NaiveDateTime) {
+    (period_range(Period::Monthly, today, -(months as i32)).0, period_range(Period::Monthly, today, 0).0)
+}
+
+/// Monthly expense budgets at each category's average monthly spending, rounded up to a whole
+/// dollar. `net_by_category` is the net amount per category over `months` months; categories
+/// that came out positive and transfers are left out.
+pub fn drafts_from_average_spend(net_by_category: &[(String, i64)], months: u32) -> Vec<BudgetDraft> {
+    let months = months.max(1) as i64;
+    let mut drafts: Vec<BudgetDraft> = net_by_category
+        .iter()
+        .filter(|(category, net)| *net < 0 && category != "Transfer")
+        .map(|(category, net)| {
+            let dollars = (-net + months * 100 - 1) / (months * 100);
+            BudgetDraft {
+                name: None,
+                categories: vec![category.clone()],
+                limit_cents: (dollars * 100) as i32,
+                period: Period::Monthly,
+                target_type: TargetType::Expense,
+            }
+        })
+        .collect();
+    drafts.sort_by(|a, b| a.categories.cmp(&b.categories));
+    drafts
+}
@@ -0,0 +1,137 @@
+//! Business-day calendars: weekends plus a user's holidays, used to move recurring
+//! occurrences that land on a day when nothing would really be paid.
+//!
+//! Holiday files are either plain text, one `YYYY-MM-DD[,name]` per line with `#` comments,
+//! or an iCalendar file whose all-day events are the holidays.
+
+use std::collections::BTreeSet;
+
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, Weekday};
+
+use crate::error::FinancerError;
+use crate::models::RollConvention;
+
+/// How far an occurrence may move looking for a business day. Past this the calendar is
+/// assumed to be wrong and the scheduled date is kept.
+pub const MAX_ROLL_DAYS: i64 = 10;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HolidayCalendar {
+    holidays: BTreeSet<NaiveDate>,
+}
+
+impl HolidayCalendar {
+    pub fn new(holidays: impl IntoIterator<Item = NaiveDate>) -> Self {
+        HolidayCalendar {
+            holidays: holidays.into_iter().collect(),
+        }
+    }
+
+    pub fn is_holiday(&self, day: NaiveDate) -> bool {
+        self.holidays.contains(&day)
+    }
+
+    pub fn is_business_day(&self, day: NaiveDate) -> bool {
+        !matches!(day.weekday(), Weekday::Sat | Weekday::Sun) && !self.is_holiday(day)
+    }
+
+    /// The day an occurrence scheduled for `day` actually happens on.
+    pub fn roll(&self, day: NaiveDate, convention: RollConvention) -> NaiveDate {
+        if convention == RollConvention::None || self.is_business_day(day) {
+            return day;
+        }
+        match convention {
+            RollConvention::None => day,
+            RollConvention::Following => self.nearest_business_day(day, 1).unwrap_or(day),
+            RollConvention::Preceding => self.nearest_business_day(day, -1).unwrap_or(day),
+            RollConvention::ModifiedFollowing => match self.nearest_business_day(day, 1) {
+                Some(next) if next.month() == day.month() => next,
+                _ => self.nearest_business_day(day, -1).unwrap_or(day),
+            },
+        }
+    }
+
+    /// `roll` for a scheduled moment; the time of day is kept.
+    pub fn roll_datetime(&self, at: NaiveDateTime, convention: RollConvention) -> NaiveDateTime {
+        self.roll(at.date(), convention).and_time(at.time())
+    }
+
+    fn nearest_business_day(&self, day: NaiveDate, direction: i64) -> Option<NaiveDate> {
+        (1..=MAX_ROLL_DAYS)
+            .map(|n| day + Duration::days(n * direction))
+            .find(|d| self.is_business_day(*d))
+    }
+}
+
+/// Holidays listed in a calendar file, in file order. Entries without a name are called
+/// "Holiday".
+pub fn parse_holiday_file(text: &str) -> Result<Vec<(NaiveDate, String)>, FinancerError> {
+    if text.trim_start().to_ascii_uppercase().starts_with("BEGIN:VCALENDAR") {
+        return Ok(parse_ics_holidays(text));
+    }
+
+    let mut holidays = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+        let (date_text, name) = match line.split_once([',', '\t', ' ']) {
+            Some((date, name)) => (date, name.trim()),
+            None => (line, ""),
+        };
+        let day = NaiveDate::parse_from_str(date_text.trim(), "%Y-%m-%d").map_err(|_| {
+            FinancerError::Validation(format!(
+                "Line {}: expected a YYYY-MM-DD date, got '{}'",
+                number + 1,
+                date_text.trim()
+            ))
+        })?;
+        holidays.push((day, holiday_name(name)));
+    }
+    Ok(holidays)
+}
+
+/// The start date and summary of every event; unfolds continuation lines first.
+fn parse_ics_holidays(text: &str) -> Vec<(NaiveDate, String)> {
+    let mut unfolded: Vec<String> = Vec::new();
+    for line in text.lines() {
+        match (line.strip_prefix(' ').or_else(|| line.strip_prefix('\t')), unfolded.last_mut()) {
+            (Some(rest), Some(last)) => last.push_str(rest),
+            _ => unfolded.push(line.to_string()),
+        }
+    }
+
+    let mut holidays = Vec::new();
+    let mut day = None;
+    let mut name = String::new();
+    for line in &unfolded {
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let key = key.split(';').next().unwrap_or("").to_ascii_uppercase();
+        match key.as_str() {
+            "BEGIN" if value.eq_ignore_ascii_case("VEVENT") => {
+                day = None;
+                name.clear();
+            }
+            "DTSTART" => day = value.get(..8).and_then(|d| NaiveDate::parse_from_str(d, "%Y%m%d").ok()),
+            "SUMMARY" => name = value.replace("\\,", ",").replace("\\;", ";").replace("\\\\", "\\"),
+            "END" if value.eq_ignore_ascii_case("VEVENT") => {
+                if let Some(d) = day.take() {
+                    holidays.push((d, holiday_name(&name)));
+                }
+            }
+            _ => {}
+        }
+    }
+    holidays
+}
+
+fn holiday_name(name: &str) -> String {
+    if name.trim().is_empty() {
+        "Holiday".to_string()
+    } else {
+        name.trim().to_string()
+    }
+}
@@ -1,6 +1,6 @@
 use diesel::prelude::*;
 use diesel::sqlite::SqliteConnection;
-use diesel::result::Error;
+use crate::error::FinancerError;
 use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
 use crate::models::{
     User,
@@ -14,17 +14,51 @@ use crate::models::{
     NewRecurringTransaction,
     RecurringTransfer,
     NewRecurringTransfer,
+    LoginAttempt,
+    LoginEvent,
+    NewLoginEvent,
+    UserTotp,
+    NewRecoveryCode,
+    AccountMember,
+    AccountRole,
+    RecurringKind,
+    RecurringRun,
+    NewRecurringRun,
+    RunStatus,
+    Holiday,
+    NewHoliday,
+    RollConvention,
+    Envelope,
+    NewEnvelope,
+    NewEnvelopeAssignment,
+    Notification,
+    NewNotification,
+    NotificationKind,
+    BudgetTemplate,
+    NewBudgetTemplate,
+    BudgetTemplateItem,
+    NewBudgetTemplateItem,
+    SavingsGoal,
+    NewSavingsGoal,
+    BalanceSnapshot,
 };
 use crate::schema::users::dsl::*;
 use crate::schema::accounts::dsl::*;
 use crate::schema::contacts::dsl::*;
 use crate::schema::transactions::dsl::*;
-use argon2::{Argon2, PasswordHasher, PasswordVerifier};
-use ::password_hash::{SaltString, PasswordHash};
+use crate::auth::{self, LoginThrottlePolicy, PasswordPolicy};
+use crate::totp;
+use crate::goals::GoalProgress;
+use crate::net_worth::reconstruct_daily_balances;
+use crate::reports::{CategoryTrend, IncomeStatement};
 use email_address::EmailAddress;
 
-use crate::models::{Budget, NewBudget};
-use crate::models::Period;
+use crate::models::{Budget, NewBudget, Period, RolloverMode, TargetType};
+use crate::budgeting::{budget_range, crossed_thresholds, custom_range, month_key, parse_alert_thresholds, period_range, periods_between, rollover_after, BudgetDraft, BudgetScope, EnvelopeStatus, MonthlySavings, PeriodPerformance};
+use crate::calendar::{parse_holiday_file, HolidayCalendar, MAX_ROLL_DAYS};
+use crate::forecast::SpendEstimate;
+use crate::recurrence::{schedule_finished, RecurrenceRule};
+use std::collections::HashMap;
 use chrono::NaiveDateTime;
 use diesel::dsl::sum;
 
@@ -33,26 +67,23 @@ pub const MIGRATIONS: EmbeddedMigrations = embed_migrations!("./migrations");
 pub fn establish_connection() -> SqliteConnection {
     let db_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set in .env");
     let mut conn = SqliteConnection::establish(&db_url)
-        .expect(&format!("Error connecting to {}", db_url));
+        .unwrap_or_else(|_| panic!("Error connecting to {}", db_url));
     conn.run_pending_migrations(MIGRATIONS)
         .expect("Failed to run database migrations");
     conn
 }
 
-pub fn create_user(conn: &mut SqliteConnection, new_username: &str, new_password: &str, new_email: Option<&str>) -> Result<usize, Error> {
+pub fn create_user(conn: &mut SqliteConnection, new_username: &str, new_password: &str, new_email: Option<&str>) -> Result<usize, FinancerError> {
+    if new_username.trim().is_empty() || new_password.is_empty() {
+        return Err(FinancerError::Validation("Username and password cannot be empty".to_string()));
+    }
     if users.filter(username.eq(new_username)).first::<User>(conn).optional()?.is_some() {
-        println!("User already exists: {}", new_username);
-        return Err(Error::DatabaseError(diesel::result::DatabaseErrorKind::UniqueViolation, Box::new("Username already exists".to_string())));
+        return Err(FinancerError::Conflict(format!("Username {} already exists", new_username)));
     }
-    if let Some(email_str) = new_email {
-        if !EmailAddress::is_valid(email_str) {
-            println!("Invalid email format: {}", email_str);
-            return Err(Error::DatabaseError(diesel::result::DatabaseErrorKind::Unknown, Box::new("Invalid email format".to_string())));
-        }
+    if let Some(email_str) = new_email && !EmailAddress::is_valid(email_str) {
+        return Err(FinancerError::Validation(format!("Invalid email format: {}", email_str)));
     }
-    let salt = SaltString::generate(&mut rand::thread_rng());
-    let argon2 = Argon2::default();
-    let hashed_password = argon2.hash_password(new_password.as_bytes(), &salt).expect("Error hashing password").to_string();
+    let hashed_password = auth::hash_password(new_password)?;
 
     let new_user = NewUser {
         username: new_username,
@@ -60,10 +91,76 @@ pub fn create_user(conn: &mut SqliteConnection, new_username: &str, new_password
         email: new_email,
     };
 
-    diesel::insert_into(users).values(&new_user).execute(conn)
+    Ok(diesel::insert_into(users).values(&new_user).execute(conn)?)
+}
+
+pub fn register_user(conn: &mut SqliteConnection, new_username: &str, new_password: &str, new_email: Option<&str>, policy: &PasswordPolicy) -> Result<usize, FinancerError> {
+    policy.validate(new_password)?;
+    create_user(conn, new_username, new_password, new_email)
+}
+
+fn find_user(conn: &mut SqliteConnection, owner_id: i32) -> Result<User, FinancerError> {
+    users
+        .filter(crate::schema::users::dsl::id.eq(owner_id))
+        .first::<User>(conn)
+        .optional()?
+        .ok_or_else(|| FinancerError::NotFound(format!("User {}", owner_id)))
+}
+
+pub fn change_password(conn: &mut SqliteConnection, owner_id: i32, old_password: &str, new_password: &str, policy: &PasswordPolicy) -> Result<(), FinancerError> {
+    require_password(conn, owner_id, old_password)?;
+    policy.validate(new_password)?;
+    let hashed_password = auth::hash_password(new_password)?;
+
+    conn.transaction::<_, FinancerError, _>(|conn| {
+        diesel::update(users.filter(crate::schema::users::dsl::id.eq(owner_id)))
+            .set(password_hash.eq(hashed_password))
+            .execute(conn)?;
+
+        // The TOTP secret is encrypted under the password, so it follows the password.
+        if let Some(enrollment) = find_totp(conn, owner_id)? {
+            let secret = totp::decrypt_secret(&stored_secret(&enrollment), old_password)?;
+            save_totp_secret(conn, owner_id, &totp::encrypt_secret(&secret, new_password)?)?;
+        }
+        Ok(())
+    })
+}
+
+pub fn change_email(conn: &mut SqliteConnection, owner_id: i32, new_email: Option<&str>) -> Result<(), FinancerError> {
+    find_user(conn, owner_id)?;
+    if let Some(email_str) = new_email && !EmailAddress::is_valid(email_str) {
+        return Err(FinancerError::Validation(format!("Invalid email format: {}", email_str)));
+    }
+
+    diesel::update(users.filter(crate::schema::users::dsl::id.eq(owner_id)))
+        .set(email.eq(new_email))
+        .execute(conn)?;
+    Ok(())
+}
+
+pub fn change_username(conn: &mut SqliteConnection, owner_id: i32, new_username: &str) -> Result<(), FinancerError> {
+    let new_username = new_username.trim();
+    if new_username.is_empty() {
+        return Err(FinancerError::Validation("Username cannot be empty".to_string()));
+    }
+    let current = find_user(conn, owner_id)?;
+    if current.username == new_username {
+        return Ok(());
+    }
+    if users.filter(username.eq(new_username)).first::<User>(conn).optional()?.is_some() {
+        return Err(FinancerError::Conflict(format!("Username {} already exists", new_username)));
+    }
+
+    diesel::update(users.filter(crate::schema::users::dsl::id.eq(owner_id)))
+        .set(username.eq(new_username))
+        .execute(conn)?;
+    Ok(())
 }
 
-pub fn create_account(conn: &mut SqliteConnection, new_name: &str, new_account_type: &str, new_balance: f32, account_owner_id: i32) -> Result<usize, Error> {
+pub fn create_account(conn: &mut SqliteConnection, new_name: &str, new_account_type: &str, new_balance: f32, account_owner_id: i32) -> Result<usize, FinancerError> {
+    if new_name.trim().is_empty() {
+        return Err(FinancerError::Validation("Account name cannot be empty".to_string()));
+    }
     let new_account = NewAccount {
         name: new_name,
         account_type: new_account_type,
@@ -71,463 +168,3104 @@ pub fn create_account(conn: &mut SqliteConnection, new_name: &str, new_account_t
         user_id: account_owner_id,
     };
 
-    diesel::insert_into(accounts).values(&new_account).execute(conn)
+    conn.transaction::<_, FinancerError, _>(|conn| {
+        use crate::schema::account_members;
+
+        let inserted = diesel::insert_into(accounts).values(&new_account).execute(conn)?;
+        let new_id: i32 = accounts
+            .select(crate::schema::accounts::dsl::id)
+            .order(crate::schema::accounts::dsl::id.desc())
+            .first(conn)?;
+        diesel::insert_into(account_members::table)
+            .values(AccountMember {
+                account_id: new_id,
+                user_id: account_owner_id,
+                role: AccountRole::Owner.to_str().to_string(),
+            })
+            .execute(conn)?;
+        Ok(inserted)
+    })
 }
 
-pub fn get_user_accounts(conn: &mut SqliteConnection, owner_id: i32) -> Result<Vec<Account>, Error> {
-    accounts
-        .filter(user_id.eq(owner_id))
+/// Active accounts `owner_id` is a member of, whether they own them or they were shared.
+pub fn get_user_accounts(conn: &mut SqliteConnection, owner_id: i32) -> Result<Vec<Account>, FinancerError> {
+    Ok(accounts
+        .filter(crate::schema::accounts::dsl::id.eq_any(member_account_ids(owner_id)))
         .filter(active.eq(true))
-        .load::<Account>(conn)
+        .load::<Account>(conn)?)
+}
+
+/// Subquery selecting the ids of every account `member_id` belongs to, in any role.
+#[diesel::dsl::auto_type]
+fn member_account_ids(member_id: i32) -> _ {
+    crate::schema::account_members::table
+        .filter(crate::schema::account_members::user_id.eq(member_id))
+        .select(crate::schema::account_members::account_id)
+}
+
+pub fn get_account_role(conn: &mut SqliteConnection, member_id: i32, account_id: i32) -> Result<Option<AccountRole>, FinancerError> {
+    use crate::schema::account_members;
+
+    let role: Option<String> = account_members::table
+        .filter(account_members::account_id.eq(account_id))
+        .filter(account_members::user_id.eq(member_id))
+        .select(account_members::role)
+        .first(conn)
+        .optional()?;
+    Ok(role.map(|r| AccountRole::from_str(&r)))
+}
+
+/// Role of `member_id` on each account they belong to, for labelling shared accounts.
+pub fn get_user_account_roles(conn: &mut SqliteConnection, member_id: i32) -> Result<Vec<(i32, AccountRole)>, FinancerError> {
+    use crate::schema::account_members;
+
+    let rows: Vec<(i32, String)> = account_members::table
+        .filter(account_members::user_id.eq(member_id))
+        .select((account_members::account_id, account_members::role))
+        .load(conn)?;
+    Ok(rows.into_iter().map(|(acc, role)| (acc, AccountRole::from_str(&role))).collect())
+}
+
+fn find_account(conn: &mut SqliteConnection, account_id: i32) -> Result<Account, FinancerError> {
+    use crate::schema::accounts::dsl::*;
+
+    accounts
+        .filter(id.eq(account_id))
+        .first::<Account>(conn)
+        .optional()?
+        .ok_or_else(|| FinancerError::NotFound(format!("Account {}", account_id)))
+}
+
+/// Loads an account after checking `member_id` holds at least `needed` on it.
+fn find_account_with_role(conn: &mut SqliteConnection, member_id: i32, account_id: i32, needed: AccountRole) -> Result<Account, FinancerError> {
+    let account = find_account(conn, account_id)?;
+    match get_account_role(conn, member_id, account_id)? {
+        Some(role) if role <= needed => Ok(account),
+        Some(role) => Err(FinancerError::PermissionDenied(format!(
+            "Account {} is shared with you as {}",
+            account.name,
+            role.to_str()
+        ))),
+        None => Err(FinancerError::PermissionDenied(format!("Account {} belongs to another user", account_id))),
+    }
+}
+
+fn find_owned_account(conn: &mut SqliteConnection, owner_id: i32, account_id: i32) -> Result<Account, FinancerError> {
+    find_account_with_role(conn, owner_id, account_id, AccountRole::Owner)
+}
+
+fn find_editable_account(conn: &mut SqliteConnection, member_id: i32, account_id: i32) -> Result<Account, FinancerError> {
+    find_account_with_role(conn, member_id, account_id, AccountRole::Editor)
+}
+
+/// Members of an account with their usernames, owners first. Any member may look.
+pub fn get_account_members(conn: &mut SqliteConnection, member_id: i32, account_id: i32) -> Result<Vec<(AccountMember, String)>, FinancerError> {
+    use crate::schema::{account_members, users};
+
+    find_account_with_role(conn, member_id, account_id, AccountRole::Viewer)?;
+    let mut members: Vec<(AccountMember, String)> = account_members::table
+        .inner_join(users::table)
+        .filter(account_members::account_id.eq(account_id))
+        .select((account_members::all_columns, users::username))
+        .load(conn)?;
+    members.sort_by_key(|(m, member_name)| (AccountRole::from_str(&m.role), member_name.clone()));
+    Ok(members)
+}
+
+pub fn add_account_member(
+    conn: &mut SqliteConnection,
+    owner_id: i32,
+    account_id: i32,
+    member_username: &str,
+    role: AccountRole,
+) -> Result<(), FinancerError> {
+    use crate::schema::account_members;
+
+    find_owned_account(conn, owner_id, account_id)?;
+    let member = get_userid_by_username(conn, member_username.trim())?;
+    if get_account_role(conn, member.id, account_id)?.is_some() {
+        return Err(FinancerError::Conflict(format!("{} already has access to this account", member.username)));
+    }
+
+    diesel::insert_into(account_members::table)
+        .values(AccountMember {
+            account_id,
+            user_id: member.id,
+            role: role.to_str().to_string(),
+        })
+        .execute(conn)?;
+    forget_user_budget_carries(conn, member.id)?;
+    Ok(())
+}
+
+fn count_account_owners(conn: &mut SqliteConnection, account_id: i32) -> Result<i64, FinancerError> {
+    use crate::schema::account_members;
+
+    Ok(account_members::table
+        .filter(account_members::account_id.eq(account_id))
+        .filter(account_members::role.eq(AccountRole::Owner.to_str()))
+        .count()
+        .get_result(conn)?)
+}
+
+pub fn set_account_member_role(
+    conn: &mut SqliteConnection,
+    owner_id: i32,
+    account_id: i32,
+    member_id: i32,
+    role: AccountRole,
+) -> Result<(), FinancerError> {
+    use crate::schema::account_members;
+
+    find_owned_account(conn, owner_id, account_id)?;
+    let current = get_account_role(conn, member_id, account_id)?
+        .ok_or_else(|| FinancerError::NotFound(format!("Member {}", member_id)))?;
+    if current == AccountRole::Owner && role != AccountRole::Owner && count_account_owners(conn, account_id)? <= 1 {
+        return Err(FinancerError::Validation("An account needs at least one owner".to_string()));
+    }
+
+    diesel::update(
+        account_members::table
+            .filter(account_members::account_id.eq(account_id))
+            .filter(account_members::user_id.eq(member_id)),
+    )
+    .set(account_members::role.eq(role.to_str()))
+    .execute(conn)?;
+    Ok(())
+}
+
+/// Owners can remove anyone; other members can only remove themselves.
+pub fn remove_account_member(conn: &mut SqliteConnection, acting_id: i32, account_id: i32, member_id: i32) -> Result<(), FinancerError> {
+    use crate::schema::account_members;
+
+    if acting_id != member_id {
+        find_owned_account(conn, acting_id, account_id)?;
+    }
+    let current = get_account_role(conn, member_id, account_id)?
+        .ok_or_else(|| FinancerError::NotFound(format!("Member {}", member_id)))?;
+    if current == AccountRole::Owner && count_account_owners(conn, account_id)? <= 1 {
+        return Err(FinancerError::Validation("An account needs at least one owner".to_string()));
+    }
+
+    diesel::delete(
+        account_members::table
+            .filter(account_members::account_id.eq(account_id))
+            .filter(account_members::user_id.eq(member_id)),
+    )
+    .execute(conn)?;
+    forget_user_budget_carries(conn, member_id)?;
+    Ok(())
+}
+
+/// Usernames for a set of user ids, e.g. the `created_by` of transactions on a shared account.
+pub fn get_usernames(conn: &mut SqliteConnection, user_ids: &[i32]) -> Result<Vec<(i32, String)>, FinancerError> {
+    use crate::schema::users;
+
+    Ok(users::table
+        .filter(users::id.eq_any(user_ids))
+        .select((users::id, users::username))
+        .load(conn)?)
+}
+
+pub fn ensure_sufficient_funds(conn: &mut SqliteConnection, account_id: i32, required: f32) -> Result<(), FinancerError> {
+    let account = find_account(conn, account_id)?;
+    if account.balance < required {
+        return Err(FinancerError::InsufficientFunds {
+            account: account.name,
+            available: account.balance,
+            required,
+        });
+    }
+    Ok(())
 }
 
-pub fn delete_user_and_all_data(conn: &mut SqliteConnection, owner_id: i32) -> Result<(), Error> {
-    conn.transaction::<_, Error, _>(|conn| {
-        use crate::schema::{accounts, budgets, contacts, recurring_transactions, recurring_transfers, transactions, users};
+pub fn delete_user_and_all_data(conn: &mut SqliteConnection, owner_id: i32) -> Result<(), FinancerError> {
+    conn.transaction::<_, FinancerError, _>(|conn| {
+        use crate::schema::{account_members, accounts, balance_snapshots, budget_accounts, budget_categories, budget_template_items, budget_templates, budgets, contacts, envelope_assignments, envelopes, holidays, login_attempts, login_events, notifications, recovery_codes, recurring_runs, recurring_transactions, recurring_transfers, savings_goal_accounts, savings_goals, transactions, user_totp, users};
 
-        let account_ids: Vec<i32> = accounts::table
-            .filter(accounts::user_id.eq(owner_id))
-            .select(accounts::id)
+        let memberships: Vec<AccountMember> = account_members::table
+            .filter(account_members::user_id.eq(owner_id))
             .load(conn)?;
 
+        // Accounts only this user can see go with them. Shared accounts stay with the
+        // remaining members, and if nobody else owned one, the strongest remaining role takes over.
+        let mut account_ids: Vec<i32> = Vec::new();
+        for membership in memberships {
+            let others: Vec<AccountMember> = account_members::table
+                .filter(account_members::account_id.eq(membership.account_id))
+                .filter(account_members::user_id.ne(owner_id))
+                .load(conn)?;
+            diesel::delete(
+                account_members::table
+                    .filter(account_members::account_id.eq(membership.account_id))
+                    .filter(account_members::user_id.eq(owner_id)),
+            )
+            .execute(conn)?;
+
+            let Some(successor) = others
+                .iter()
+                .min_by_key(|m| (AccountRole::from_str(&m.role), m.user_id))
+            else {
+                account_ids.push(membership.account_id);
+                continue;
+            };
+            if AccountRole::from_str(&successor.role) != AccountRole::Owner {
+                diesel::update(
+                    account_members::table
+                        .filter(account_members::account_id.eq(successor.account_id))
+                        .filter(account_members::user_id.eq(successor.user_id)),
+                )
+                .set(account_members::role.eq(AccountRole::Owner.to_str()))
+                .execute(conn)?;
+            }
+            diesel::update(
+                accounts::table
+                    .filter(accounts::id.eq(membership.account_id))
+                    .filter(accounts::user_id.eq(owner_id)),
+            )
+            .set(accounts::user_id.eq(successor.user_id))
+            .execute(conn)?;
+            // Schedules on a surviving account keep running for the remaining members.
+            diesel::update(
+                recurring_transactions::table
+                    .filter(recurring_transactions::account_id.eq(membership.account_id))
+                    .filter(recurring_transactions::user_id.eq(owner_id)),
+            )
+            .set(recurring_transactions::user_id.eq(successor.user_id))
+            .execute(conn)?;
+            diesel::update(
+                recurring_transfers::table
+                    .filter(
+                        recurring_transfers::from_account_id
+                            .eq(membership.account_id)
+                            .or(recurring_transfers::to_account_id.eq(membership.account_id)),
+                    )
+                    .filter(recurring_transfers::user_id.eq(owner_id)),
+            )
+            .set(recurring_transfers::user_id.eq(successor.user_id))
+            .execute(conn)?;
+        }
+
         if !account_ids.is_empty() {
             diesel::delete(
                 transactions::table.filter(transactions::user_account_id.eq_any(&account_ids)),
             )
             .execute(conn)?;
+            diesel::delete(recurring_transactions::table.filter(recurring_transactions::account_id.eq_any(&account_ids)))
+                .execute(conn)?;
+            diesel::delete(
+                recurring_transfers::table.filter(
+                    recurring_transfers::from_account_id
+                        .eq_any(&account_ids)
+                        .or(recurring_transfers::to_account_id.eq_any(&account_ids)),
+                ),
+            )
+            .execute(conn)?;
         }
+        diesel::update(transactions::table.filter(transactions::created_by.eq(owner_id)))
+            .set(transactions::created_by.eq(None::<i32>))
+            .execute(conn)?;
 
         diesel::delete(recurring_transactions::table.filter(recurring_transactions::user_id.eq(owner_id)))
             .execute(conn)?;
         diesel::delete(recurring_transfers::table.filter(recurring_transfers::user_id.eq(owner_id)))
             .execute(conn)?;
+        delete_orphaned_recurring_runs(conn)?;
+        diesel::update(recurring_runs::table.filter(recurring_runs::decided_by.eq(owner_id)))
+            .set(recurring_runs::decided_by.eq(None::<i32>))
+            .execute(conn)?;
+        diesel::delete(notifications::table.filter(notifications::user_id.eq(owner_id))).execute(conn)?;
+        forget_user_budget_carries(conn, owner_id)?;
+        let owned_budgets = budgets::table.filter(budgets::user_id.eq(owner_id)).select(budgets::id.assume_not_null());
+        diesel::delete(budget_categories::table.filter(budget_categories::budget_id.eq_any(owned_budgets))).execute(conn)?;
+        diesel::delete(budget_accounts::table.filter(budget_accounts::budget_id.eq_any(owned_budgets))).execute(conn)?;
         diesel::delete(budgets::table.filter(budgets::user_id.eq(owner_id))).execute(conn)?;
+        let owned_templates = budget_templates::table.filter(budget_templates::user_id.eq(owner_id)).select(budget_templates::id);
+        diesel::delete(budget_template_items::table.filter(budget_template_items::template_id.eq_any(owned_templates))).execute(conn)?;
+        diesel::delete(budget_templates::table.filter(budget_templates::user_id.eq(owner_id))).execute(conn)?;
+        let owned_goals = savings_goals::table.filter(savings_goals::user_id.eq(owner_id)).select(savings_goals::id);
+        diesel::delete(
+            savings_goal_accounts::table.filter(
+                savings_goal_accounts::goal_id
+                    .eq_any(owned_goals)
+                    .or(savings_goal_accounts::account_id.eq_any(&account_ids)),
+            ),
+        )
+        .execute(conn)?;
+        diesel::delete(savings_goals::table.filter(savings_goals::user_id.eq(owner_id))).execute(conn)?;
+        diesel::delete(holidays::table.filter(holidays::user_id.eq(owner_id))).execute(conn)?;
+        diesel::delete(
+            envelope_assignments::table.filter(
+                envelope_assignments::envelope_id.eq_any(envelopes::table.filter(envelopes::user_id.eq(owner_id)).select(envelopes::id)),
+            ),
+        )
+        .execute(conn)?;
+        diesel::delete(envelopes::table.filter(envelopes::user_id.eq(owner_id))).execute(conn)?;
         diesel::delete(contacts::table.filter(contacts::user.eq(owner_id))).execute(conn)?;
-        diesel::delete(accounts::table.filter(accounts::user_id.eq(owner_id))).execute(conn)?;
+        diesel::delete(balance_snapshots::table.filter(balance_snapshots::account_id.eq_any(&account_ids))).execute(conn)?;
+        diesel::delete(accounts::table.filter(accounts::id.eq_any(&account_ids))).execute(conn)?;
+        let owner_name: String = users::table
+            .filter(users::id.eq(owner_id))
+            .select(users::username)
+            .first(conn)?;
+        diesel::delete(login_events::table.filter(login_events::user_id.eq(owner_id))).execute(conn)?;
+        diesel::delete(recovery_codes::table.filter(recovery_codes::user_id.eq(owner_id))).execute(conn)?;
+        diesel::delete(user_totp::table.filter(user_totp::user_id.eq(owner_id))).execute(conn)?;
+        diesel::delete(login_attempts::table.filter(login_attempts::username.eq(&owner_name))).execute(conn)?;
         diesel::delete(users::table.filter(users::id.eq(owner_id))).execute(conn)?;
 
         Ok(())
     })
 }
 
-pub fn delete_account(conn: &mut SqliteConnection, owner_id: i32, account_id: i32) -> Result<usize, Error> {
-    diesel::update(
-        accounts
-            .filter(crate::schema::accounts::dsl::id.eq(account_id))
-            .filter(crate::schema::accounts::dsl::user_id.eq(owner_id)),
-    )
+pub fn delete_account(conn: &mut SqliteConnection, owner_id: i32, account_id: i32) -> Result<usize, FinancerError> {
+    find_owned_account(conn, owner_id, account_id)?;
+
+    let updated = diesel::update(accounts.filter(crate::schema::accounts::dsl::id.eq(account_id)))
         .set(crate::schema::accounts::dsl::active.eq(false))
-        .execute(conn)
+        .execute(conn)?;
+    forget_account_budget_carries(conn, account_id)?;
+    Ok(updated)
+}
+
+/// Validates an item's schedule, returning the frequency label and canonical rule to store.
+/// The label always matches the rule's base period so older readers of `frequency` stay right.
+fn resolve_recurrence(frequency_label: &str, rule_text: Option<&str>) -> Result<(String, String), FinancerError> {
+    let rule = RecurrenceRule::for_item(frequency_label, rule_text)?;
+    Ok((rule.period.to_str().to_string(), rule.to_rule_string()))
+}
+
+/// Parses an item's first run and end date, and checks its occurrence limit.
+fn validate_schedule_limits(
+    first_run: &str,
+    ends: Option<&str>,
+    max: Option<i32>,
+) -> Result<(chrono::NaiveDateTime, Option<chrono::NaiveDateTime>), FinancerError> {
+    let parse = |value: &str, field: &str| {
+        chrono::NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S")
+            .map_err(|_| FinancerError::Validation(format!("{} must be in format YYYY-MM-DD HH:MM:SS", field)))
+    };
+    let first = parse(first_run, "Next run")?;
+    let end = ends.map(|e| parse(e, "End date")).transpose()?;
+    if max.is_some_and(|m| m < 1) {
+        return Err(FinancerError::Validation("Occurrence limit must be at least 1".to_string()));
+    }
+    Ok((first, end))
+}
+
+/// Where a paused schedule picks up again: the first occurrence at or after `now`, so the
+/// ones missed while paused are skipped. `None` if the schedule finishes before then.
+fn resume_point(
+    rule: &RecurrenceRule,
+    next_run: chrono::NaiveDateTime,
+    now: chrono::NaiveDateTime,
+    ends: Option<chrono::NaiveDateTime>,
+    max: Option<i32>,
+    run: i32,
+) -> Option<chrono::NaiveDateTime> {
+    let mut dt = next_run;
+    let mut iterations = 0;
+    while dt < now && iterations < 10_000 {
+        dt = rule.next_after(dt)?;
+        iterations += 1;
+    }
+    (!schedule_finished(dt, ends, max, run)).then_some(dt)
+}
+
+fn validate_roll_convention(text: &str) -> Result<(), FinancerError> {
+    if RollConvention::ALL.iter().any(|c| c.to_str() == text) {
+        Ok(())
+    } else {
+        Err(FinancerError::Validation(format!("Unknown weekend/holiday adjustment '{}'", text)))
+    }
+}
+
+fn stored_schedule_finished(next_run: &str, ends: Option<&str>, max: Option<i32>, run: i32) -> Result<bool, FinancerError> {
+    let ends = ends.map(parse_db_datetime).transpose()?;
+    Ok(schedule_finished(parse_db_datetime(next_run)?, ends, max, run))
+}
+
+fn stored_rule(kind: &str, item_id: i32, rule_text: &str) -> Result<RecurrenceRule, FinancerError> {
+    RecurrenceRule::parse(rule_text)
+        .map_err(|e| FinancerError::CorruptData(format!("{} {} has a bad schedule: {}", kind, item_id, e)))
+}
+
+/// Includes paused and finished items so they can be resumed or reviewed.
+pub fn get_user_recurring_transactions(conn: &mut SqliteConnection, owner_id: i32) -> Result<Vec<RecurringTransaction>, FinancerError> {
+    use crate::schema::recurring_transactions::dsl::*;
+
+    Ok(recurring_transactions
+        .filter(account_id.eq_any(member_account_ids(owner_id)))
+        .order((active.desc(), next_run_at.asc()))
+        .load::<RecurringTransaction>(conn)?)
+}
+
+pub fn create_recurring_transaction(conn: &mut SqliteConnection, mut new_item: NewRecurringTransaction) -> Result<RecurringTransaction, FinancerError> {
+    use crate::schema::recurring_transactions::dsl::*;
+
+    find_editable_account(conn, new_item.user_id, new_item.account_id)?;
+    let (label, rule) = resolve_recurrence(&new_item.frequency, new_item.recurrence_rule.as_deref())?;
+    new_item.frequency = label;
+    new_item.recurrence_rule = Some(rule);
+    let (first_run, ends) = validate_schedule_limits(&new_item.next_run_at, new_item.ends_at.as_deref(), new_item.max_occurrences)?;
+    validate_roll_convention(&new_item.roll_convention)?;
+    if ends.is_some_and(|e| e < first_run) {
+        return Err(FinancerError::Validation("End date is before the first run".to_string()));
+    }
+
+    diesel::insert_into(recurring_transactions)
+        .values(&new_item)
+        .execute(conn)?;
+    Ok(recurring_transactions.order(id.desc()).first(conn)?)
+}
+
+/// Saves the edited template. With `rewrite_past`, the transactions it generated earlier are
+/// rewritten to match in the same database transaction.
+pub fn update_recurring_transaction(
+    conn: &mut SqliteConnection,
+    owner_id: i32,
+    item_id: i32,
+    changes: NewRecurringTransaction,
+    rewrite_past: bool,
+) -> Result<usize, FinancerError> {
+    conn.transaction::<_, FinancerError, _>(|conn| {
+        use crate::schema::recurring_transactions::dsl::*;
+
+        let existing: RecurringTransaction = recurring_transactions
+            .filter(id.eq(item_id))
+            .first(conn)
+            .optional()?
+            .ok_or_else(|| FinancerError::NotFound(format!("Recurring transaction {}", item_id)))?;
+        find_editable_account(conn, owner_id, existing.account_id)?;
+        find_editable_account(conn, owner_id, changes.account_id)?;
+        let (label, rule) = resolve_recurrence(&changes.frequency, changes.recurrence_rule.as_deref())?;
+        let (first_run, ends) = validate_schedule_limits(&changes.next_run_at, changes.ends_at.as_deref(), changes.max_occurrences)?;
+        validate_roll_convention(&changes.roll_convention)?;
+        if ends.is_some_and(|e| e < first_run) {
+            return Err(FinancerError::Validation("End date is before the first run".to_string()));
+        }
+        // A paused item stays paused; a finished one comes back if the new limits allow it.
+        let was_paused = !existing.active && !stored_schedule_finished(&existing.next_run_at, existing.ends_at.as_deref(), existing.max_occurrences, existing.occurrences_run)?;
+        let still_active = !was_paused && !schedule_finished(first_run, ends, changes.max_occurrences, existing.occurrences_run);
+
+        let updated = diesel::update(recurring_transactions.filter(id.eq(item_id)))
+            .set((
+                account_id.eq(changes.account_id),
+                contact_id.eq(changes.contact_id),
+                amount.eq(changes.amount),
+                category.eq(changes.category),
+                next_run_at.eq(changes.next_run_at),
+                frequency.eq(label),
+                recurrence_rule.eq(rule),
+                ends_at.eq(changes.ends_at),
+                max_occurrences.eq(changes.max_occurrences),
+                requires_approval.eq(changes.requires_approval),
+                roll_convention.eq(changes.roll_convention),
+                active.eq(still_active),
+            ))
+            .execute(conn)?;
+        if updated == 0 {
+            return Err(FinancerError::NotFound(format!("Recurring transaction {}", item_id)));
+        }
+        if rewrite_past {
+            rewrite_past_recurring_occurrences(conn, owner_id, RecurringKind::Transaction, item_id)?;
+        }
+        Ok(updated)
+    })
+}
+
+pub fn delete_recurring_transaction(conn: &mut SqliteConnection, owner_id: i32, item_id: i32) -> Result<usize, FinancerError> {
+    use crate::schema::recurring_transactions::dsl::*;
+
+    let existing: RecurringTransaction = recurring_transactions
+        .filter(id.eq(item_id))
+        .first(conn)
+        .optional()?
+        .ok_or_else(|| FinancerError::NotFound(format!("Recurring transaction {}", item_id)))?;
+    find_editable_account(conn, owner_id, existing.account_id)?;
+
+    let deleted = diesel::delete(recurring_transactions.filter(id.eq(item_id))).execute(conn)?;
+    delete_orphaned_recurring_runs(conn)?;
+    Ok(deleted)
+}
+
+/// Pauses or resumes an item. Resuming skips the occurrences missed while paused and fails
+/// if the schedule has nothing left to run.
+pub fn set_recurring_transaction_active(conn: &mut SqliteConnection, owner_id: i32, item_id: i32, resume: bool, now: chrono::NaiveDateTime) -> Result<(), FinancerError> {
+    use crate::schema::recurring_transactions::dsl::*;
+
+    let existing: RecurringTransaction = recurring_transactions
+        .filter(id.eq(item_id))
+        .first(conn)
+        .optional()?
+        .ok_or_else(|| FinancerError::NotFound(format!("Recurring transaction {}", item_id)))?;
+    find_editable_account(conn, owner_id, existing.account_id)?;
+
+    if !resume {
+        diesel::update(recurring_transactions.filter(id.eq(item_id)))
+            .set(active.eq(false))
+            .execute(conn)?;
+        return Ok(());
+    }
+
+    let rule = stored_rule("Recurring transaction", existing.id, &existing.recurrence_rule)?;
+    let ends = existing.ends_at.as_deref().map(parse_db_datetime).transpose()?;
+    let next = resume_point(&rule, parse_db_datetime(&existing.next_run_at)?, now, ends, existing.max_occurrences, existing.occurrences_run)
+        .ok_or_else(|| FinancerError::Validation("This schedule has finished; extend its end date or occurrence limit first".to_string()))?;
+    diesel::update(recurring_transactions.filter(id.eq(item_id)))
+        .set((active.eq(true), next_run_at.eq(format_db_datetime(next))))
+        .execute(conn)?;
+    Ok(())
+}
+
+/// Includes paused and finished items so they can be resumed or reviewed.
+pub fn get_user_recurring_transfers(conn: &mut SqliteConnection, owner_id: i32) -> Result<Vec<RecurringTransfer>, FinancerError> {
+    use crate::schema::recurring_transfers::dsl::*;
+
+    Ok(recurring_transfers
+        .filter(
+            from_account_id
+                .eq_any(member_account_ids(owner_id))
+                .or(to_account_id.eq_any(member_account_ids(owner_id))),
+        )
+        .order((active.desc(), next_run_at.asc()))
+        .load::<RecurringTransfer>(conn)?)
+}
+
+pub fn create_recurring_transfer(conn: &mut SqliteConnection, mut new_item: NewRecurringTransfer) -> Result<RecurringTransfer, FinancerError> {
+    use crate::schema::recurring_transfers::dsl::*;
+
+    find_editable_account(conn, new_item.user_id, new_item.from_account_id)?;
+    find_editable_account(conn, new_item.user_id, new_item.to_account_id)?;
+    let (label, rule) = resolve_recurrence(&new_item.frequency, new_item.recurrence_rule.as_deref())?;
+    new_item.frequency = label;
+    new_item.recurrence_rule = Some(rule);
+    let (first_run, ends) = validate_schedule_limits(&new_item.next_run_at, new_item.ends_at.as_deref(), new_item.max_occurrences)?;
+    validate_roll_convention(&new_item.roll_convention)?;
+    if ends.is_some_and(|e| e < first_run) {
+        return Err(FinancerError::Validation("End date is before the first run".to_string()));
+    }
+
+    diesel::insert_into(recurring_transfers)
+        .values(&new_item)
+        .execute(conn)?;
+    Ok(recurring_transfers.order(id.desc()).first(conn)?)
+}
+
+/// Saves the edited template. With `rewrite_past`, the transactions it generated earlier are
+/// rewritten to match in the same database transaction.
+pub fn update_recurring_transfer(
+    conn: &mut SqliteConnection,
+    owner_id: i32,
+    item_id: i32,
+    changes: NewRecurringTransfer,
+    rewrite_past: bool,
+) -> Result<usize, FinancerError> {
+    conn.transaction::<_, FinancerError, _>(|conn| {
+        use crate::schema::recurring_transfers::dsl::*;
+
+        let existing: RecurringTransfer = recurring_transfers
+            .filter(id.eq(item_id))
+            .first(conn)
+            .optional()?
+            .ok_or_else(|| FinancerError::NotFound(format!("Recurring transfer {}", item_id)))?;
+        find_editable_account(conn, owner_id, existing.from_account_id)?;
+        find_editable_account(conn, owner_id, existing.to_account_id)?;
+        find_editable_account(conn, owner_id, changes.from_account_id)?;
+        find_editable_account(conn, owner_id, changes.to_account_id)?;
+        let (label, rule) = resolve_recurrence(&changes.frequency, changes.recurrence_rule.as_deref())?;
+        let (first_run, ends) = validate_schedule_limits(&changes.next_run_at, changes.ends_at.as_deref(), changes.max_occurrences)?;
+        validate_roll_convention(&changes.roll_convention)?;
+        if ends.is_some_and(|e| e < first_run) {
+            return Err(FinancerError::Validation("End date is before the first run".to_string()));
+        }
+        // A paused item stays paused; a finished one comes back if the new limits allow it.
+        let was_paused = !existing.active && !stored_schedule_finished(&existing.next_run_at, existing.ends_at.as_deref(), existing.max_occurrences, existing.occurrences_run)?;
+        let still_active = !was_paused && !schedule_finished(first_run, ends, changes.max_occurrences, existing.occurrences_run);
+
+        let updated = diesel::update(recurring_transfers.filter(id.eq(item_id)))
+            .set((
+                from_account_id.eq(changes.from_account_id),
+                to_account_id.eq(changes.to_account_id),
+                amount.eq(changes.amount),
+                next_run_at.eq(changes.next_run_at),
+                frequency.eq(label),
+                recurrence_rule.eq(rule),
+                ends_at.eq(changes.ends_at),
+                max_occurrences.eq(changes.max_occurrences),
+                requires_approval.eq(changes.requires_approval),
+                roll_convention.eq(changes.roll_convention),
+                active.eq(still_active),
+            ))
+            .execute(conn)?;
+        if updated == 0 {
+            return Err(FinancerError::NotFound(format!("Recurring transfer {}", item_id)));
+        }
+        if rewrite_past {
+            rewrite_past_recurring_occurrences(conn, owner_id, RecurringKind::Transfer, item_id)?;
+        }
+        Ok(updated)
+    })
+}
+
+pub fn delete_recurring_transfer(conn: &mut SqliteConnection, owner_id: i32, item_id: i32) -> Result<usize, FinancerError> {
+    use crate::schema::recurring_transfers::dsl::*;
+
+    let existing: RecurringTransfer = recurring_transfers
+        .filter(id.eq(item_id))
+        .first(conn)
+        .optional()?
+        .ok_or_else(|| FinancerError::NotFound(format!("Recurring transfer {}", item_id)))?;
+    find_editable_account(conn, owner_id, existing.from_account_id)?;
+    find_editable_account(conn, owner_id, existing.to_account_id)?;
+
+    let deleted = diesel::delete(recurring_transfers.filter(id.eq(item_id))).execute(conn)?;
+    delete_orphaned_recurring_runs(conn)?;
+    Ok(deleted)
+}
+
+/// Pauses or resumes an item. Resuming skips the occurrences missed while paused and fails
+/// if the schedule has nothing left to run.
+pub fn set_recurring_transfer_active(conn: &mut SqliteConnection, owner_id: i32, item_id: i32, resume: bool, now: chrono::NaiveDateTime) -> Result<(), FinancerError> {
+    use crate::schema::recurring_transfers::dsl::*;
+
+    let existing: RecurringTransfer = recurring_transfers
+        .filter(id.eq(item_id))
+        .first(conn)
+        .optional()?
+        .ok_or_else(|| FinancerError::NotFound(format!("Recurring transfer {}", item_id)))?;
+    find_editable_account(conn, owner_id, existing.from_account_id)?;
+    find_editable_account(conn, owner_id, existing.to_account_id)?;
+
+    if !resume {
+        diesel::update(recurring_transfers.filter(id.eq(item_id)))
+            .set(active.eq(false))
+            .execute(conn)?;
+        return Ok(());
+    }
+
+    let rule = stored_rule("Recurring transfer", existing.id, &existing.recurrence_rule)?;
+    let ends = existing.ends_at.as_deref().map(parse_db_datetime).transpose()?;
+    let next = resume_point(&rule, parse_db_datetime(&existing.next_run_at)?, now, ends, existing.max_occurrences, existing.occurrences_run)
+        .ok_or_else(|| FinancerError::Validation("This schedule has finished; extend its end date or occurrence limit first".to_string()))?;
+    diesel::update(recurring_transfers.filter(id.eq(item_id)))
+        .set((active.eq(true), next_run_at.eq(format_db_datetime(next))))
+        .execute(conn)?;
+    Ok(())
+}
+
+pub fn get_user_holidays(conn: &mut SqliteConnection, owner_id: i32) -> Result<Vec<Holiday>, FinancerError> {
+    use crate::schema::holidays;
+
+    Ok(holidays::table
+        .filter(holidays::user_id.eq(owner_id))
+        .order(holidays::holiday_date.asc())
+        .load(conn)?)
 }
 
-fn add_months_clamped(dt: chrono::NaiveDateTime, months: i32) -> chrono::NaiveDateTime {
-    use chrono::{Datelike, NaiveDate, Timelike};
+pub fn add_holiday(conn: &mut SqliteConnection, owner_id: i32, day: chrono::NaiveDate, holiday_name: &str) -> Result<Holiday, FinancerError> {
+    use crate::schema::holidays;
+
+    let day_text = day.format("%Y-%m-%d").to_string();
+    let existing: Option<Holiday> = holidays::table
+        .filter(holidays::user_id.eq(owner_id))
+        .filter(holidays::holiday_date.eq(&day_text))
+        .first(conn)
+        .optional()?;
+    if let Some(existing) = existing {
+        return Err(FinancerError::Conflict(format!("{} is already a holiday ({})", day_text, existing.name)));
+    }
+    let holiday_name = if holiday_name.trim().is_empty() { "Holiday" } else { holiday_name.trim() };
+
+    diesel::insert_into(holidays::table)
+        .values(&NewHoliday {
+            user_id: owner_id,
+            holiday_date: &day_text,
+            name: holiday_name,
+        })
+        .execute(conn)?;
+    Ok(holidays::table.order(holidays::id.desc()).first(conn)?)
+}
 
-    let start_year = dt.date().year();
-    let start_month0 = dt.date().month0() as i32;
-    let total_month0 = start_month0 + months;
+pub fn delete_holiday(conn: &mut SqliteConnection, owner_id: i32, holiday_id: i32) -> Result<(), FinancerError> {
+    use crate::schema::holidays;
 
-    let mut year = start_year + total_month0.div_euclid(12);
-    let mut month0 = total_month0.rem_euclid(12);
-    if month0 < 0 {
-        month0 += 12;
-        year -= 1;
+    let deleted = diesel::delete(
+        holidays::table
+            .filter(holidays::id.eq(holiday_id))
+            .filter(holidays::user_id.eq(owner_id)),
+    )
+    .execute(conn)?;
+    if deleted == 0 {
+        return Err(FinancerError::NotFound(format!("Holiday {}", holiday_id)));
     }
-    let month = (month0 + 1) as u32;
+    Ok(())
+}
 
-    let day = dt.date().day();
-    let mut candidate = NaiveDate::from_ymd_opt(year, month, day);
-    if candidate.is_none() {
-        let mut d = day;
-        while d > 28 {
-            d -= 1;
-            candidate = NaiveDate::from_ymd_opt(year, month, d);
-            if candidate.is_some() {
-                break;
+/// Adds the holidays from a calendar file (see `calendar::parse_holiday_file`). Dates already
+/// on the user's calendar are left as they are. Returns how many were added.
+pub fn import_holidays(conn: &mut SqliteConnection, owner_id: i32, file_contents: &str) -> Result<usize, FinancerError> {
+    let parsed = parse_holiday_file(file_contents)?;
+    conn.transaction::<_, FinancerError, _>(|conn| {
+        let mut added = 0;
+        for (day, holiday_name) in parsed {
+            match add_holiday(conn, owner_id, day, &holiday_name) {
+                Ok(_) => added += 1,
+                Err(FinancerError::Conflict(_)) => {}
+                Err(e) => return Err(e),
             }
         }
+        Ok(added)
+    })
+}
+
+/// Each user's business-day calendar, keyed by user id. Users without holidays still get
+/// one, which only skips weekends.
+pub fn get_holiday_calendars(conn: &mut SqliteConnection, user_ids: &[i32]) -> Result<HashMap<i32, HolidayCalendar>, FinancerError> {
+    use crate::schema::holidays;
+
+    let rows: Vec<Holiday> = holidays::table
+        .filter(holidays::user_id.eq_any(user_ids))
+        .load(conn)?;
+    let mut days: HashMap<i32, Vec<chrono::NaiveDate>> = user_ids.iter().map(|u| (*u, Vec::new())).collect();
+    for row in rows {
+        let day = chrono::NaiveDate::parse_from_str(&row.holiday_date, "%Y-%m-%d")
+            .map_err(|_| FinancerError::CorruptData(format!("Holiday {} has a bad date '{}'", row.id, row.holiday_date)))?;
+        days.entry(row.user_id).or_default().push(day);
     }
+    Ok(days.into_iter().map(|(u, d)| (u, HolidayCalendar::new(d))).collect())
+}
 
-    let new_date = candidate.unwrap();
-    new_date
-        .and_hms_opt(dt.time().hour(), dt.time().minute(), dt.time().second())
-        .unwrap()
+/// When an occurrence scheduled for `scheduled` is actually posted, under its owner's calendar.
+fn rolled_date(calendars: &HashMap<i32, HolidayCalendar>, owner: i32, convention: &str, scheduled: NaiveDateTime) -> NaiveDateTime {
+    calendars
+        .get(&owner)
+        .cloned()
+        .unwrap_or_default()
+        .roll_datetime(scheduled, RollConvention::from_str(convention))
 }
 
-fn add_period(dt: chrono::NaiveDateTime, period: Period) -> chrono::NaiveDateTime {
-    use chrono::Duration;
+/// Posts every due occurrence of the member's active recurring items, or queues it for
+/// approval when the item asks for that, and moves each schedule forward. Occurrences
+/// already in the run log are left alone, so running this twice never posts twice.
+/// Occurrences on weekends or holidays are posted on the day the item's roll convention
+/// picks, which may be before the scheduled date. Returns how many were posted or queued.
+pub fn process_due_recurring(conn: &mut SqliteConnection, owner_id: i32, now: chrono::NaiveDateTime) -> Result<usize, FinancerError> {
+    conn.transaction::<_, FinancerError, _>(|conn| {
+        use crate::schema::{recurring_transactions, recurring_transfers};
+        use diesel::ExpressionMethods;
+        use diesel::QueryDsl;
+
+        // Items rolled back to an earlier business day are due before their scheduled date.
+        let now_str = format_db_datetime(now + chrono::Duration::days(MAX_ROLL_DAYS));
+
+        let due_txs: Vec<RecurringTransaction> = recurring_transactions::table
+            .filter(recurring_transactions::account_id.eq_any(member_account_ids(owner_id)))
+            .filter(recurring_transactions::active.eq(true))
+            .filter(recurring_transactions::next_run_at.le(&now_str))
+            .order(recurring_transactions::next_run_at.asc())
+            .load(conn)?;
+
+        let due_transfers: Vec<RecurringTransfer> = recurring_transfers::table
+            .filter(
+                recurring_transfers::from_account_id
+                    .eq_any(member_account_ids(owner_id))
+                    .or(recurring_transfers::to_account_id.eq_any(member_account_ids(owner_id))),
+            )
+            .filter(recurring_transfers::active.eq(true))
+            .filter(recurring_transfers::next_run_at.le(&now_str))
+            .order(recurring_transfers::next_run_at.asc())
+            .load(conn)?;
+
+        let owners: Vec<i32> = due_txs.iter().map(|i| i.user_id).chain(due_transfers.iter().map(|i| i.user_id)).collect();
+        let calendars = get_holiday_calendars(conn, &owners)?;
+        let mut processed = 0usize;
+
+        for item in due_txs {
+            let mut dt = chrono::NaiveDateTime::parse_from_str(&item.next_run_at, "%Y-%m-%d %H:%M:%S")
+                .unwrap_or(now);
+            let rule = stored_rule("Recurring transaction", item.id, &item.recurrence_rule)?;
+            let ends = item.ends_at.as_deref().map(parse_db_datetime).transpose()?;
+            let mut run = item.occurrences_run;
+            let mut finished = schedule_finished(dt, ends, item.max_occurrences, run);
+            let mut iterations = 0;
 
-    match period {
-        Period::Daily => dt + Duration::days(1),
-        Period::Weekly => dt + Duration::days(7),
-        Period::Monthly => add_months_clamped(dt, 1),
-        Period::Yearly => add_months_clamped(dt, 12),
+            while !finished && rolled_date(&calendars, item.user_id, &item.roll_convention, dt) <= now && iterations < 100 {
+                let scheduled_for = format_db_datetime(dt);
+                let posted_on = format_db_datetime(rolled_date(&calendars, item.user_id, &item.roll_convention, dt));
+                if find_recurring_run(conn, RecurringKind::Transaction, item.id, &scheduled_for)?.is_none() {
+                    if item.requires_approval {
+                        log_recurring_run(conn, RecurringKind::Transaction, item.id, &scheduled_for, RunStatus::Pending, item.amount, None, now)?;
+                    } else {
+                        insert_transaction(
+                            conn,
+                            Some(item.user_id),
+                            Some((RecurringKind::Transaction, item.id)),
+                            item.account_id,
+                            item.contact_id,
+                            item.amount,
+                            item.category.clone(),
+                            posted_on.clone(),
+                            false,
+                        )?;
+                        check_budget_alerts(conn, item.account_id, &item.category, &posted_on, now)?;
+                        log_recurring_run(conn, RecurringKind::Transaction, item.id, &scheduled_for, RunStatus::Posted, item.amount, None, now)?;
+                    }
+                    processed += 1;
+                }
+                run += 1;
+                iterations += 1;
+                match rule.next_after(dt) {
+                    Some(next) => {
+                        dt = next;
+                        finished = schedule_finished(dt, ends, item.max_occurrences, run);
+                    }
+                    None => finished = true,
+                }
+            }
+
+            diesel::update(recurring_transactions::table.filter(recurring_transactions::id.eq(item.id)))
+                .set((
+                    recurring_transactions::next_run_at.eq(dt.format("%Y-%m-%d %H:%M:%S").to_string()),
+                    recurring_transactions::occurrences_run.eq(run),
+                    recurring_transactions::active.eq(!finished),
+                ))
+                .execute(conn)?;
+        }
+
+        for item in due_transfers {
+            let mut dt = chrono::NaiveDateTime::parse_from_str(&item.next_run_at, "%Y-%m-%d %H:%M:%S")
+                .unwrap_or(now);
+            let rule = stored_rule("Recurring transfer", item.id, &item.recurrence_rule)?;
+            let ends = item.ends_at.as_deref().map(parse_db_datetime).transpose()?;
+            let mut run = item.occurrences_run;
+            let mut finished = schedule_finished(dt, ends, item.max_occurrences, run);
+            let mut iterations = 0;
+
+            while !finished && rolled_date(&calendars, item.user_id, &item.roll_convention, dt) <= now && iterations < 100 {
+                let scheduled_for = format_db_datetime(dt);
+                let posted_on = format_db_datetime(rolled_date(&calendars, item.user_id, &item.roll_convention, dt));
+                if find_recurring_run(conn, RecurringKind::Transfer, item.id, &scheduled_for)?.is_none() {
+                    if item.requires_approval {
+                        log_recurring_run(conn, RecurringKind::Transfer, item.id, &scheduled_for, RunStatus::Pending, item.amount, None, now)?;
+                    } else {
+                        insert_transfer(
+                            conn,
+                            Some(item.user_id),
+                            Some((RecurringKind::Transfer, item.id)),
+                            item.from_account_id,
+                            item.to_account_id,
+                            item.amount,
+                            posted_on,
+                        )?;
+                        log_recurring_run(conn, RecurringKind::Transfer, item.id, &scheduled_for, RunStatus::Posted, item.amount, None, now)?;
+                    }
+                    processed += 1;
+                }
+                run += 1;
+                iterations += 1;
+                match rule.next_after(dt) {
+                    Some(next) => {
+                        dt = next;
+                        finished = schedule_finished(dt, ends, item.max_occurrences, run);
+                    }
+                    None => finished = true,
+                }
+            }
+
+            diesel::update(recurring_transfers::table.filter(recurring_transfers::id.eq(item.id)))
+                .set((
+                    recurring_transfers::next_run_at.eq(dt.format("%Y-%m-%d %H:%M:%S").to_string()),
+                    recurring_transfers::occurrences_run.eq(run),
+                    recurring_transfers::active.eq(!finished),
+                ))
+                .execute(conn)?;
+        }
+
+        Ok(processed)
+    })
+}
+
+fn find_recurring_run(conn: &mut SqliteConnection, kind: RecurringKind, item: i32, scheduled: &str) -> Result<Option<RecurringRun>, FinancerError> {
+    use crate::schema::recurring_runs;
+
+    Ok(recurring_runs::table
+        .filter(recurring_runs::item_kind.eq(kind.to_str()))
+        .filter(recurring_runs::item_id.eq(item))
+        .filter(recurring_runs::scheduled_for.eq(scheduled))
+        .first(conn)
+        .optional()?)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn log_recurring_run(
+    conn: &mut SqliteConnection,
+    kind: RecurringKind,
+    item: i32,
+    scheduled: &str,
+    run_status: RunStatus,
+    run_amount: f32,
+    decided: Option<i32>,
+    now: NaiveDateTime,
+) -> Result<(), FinancerError> {
+    use crate::schema::recurring_runs;
+
+    let decided_time = decided.map(|_| format_db_datetime(now));
+    diesel::insert_into(recurring_runs::table)
+        .values(&NewRecurringRun {
+            item_kind: kind.to_str(),
+            item_id: item,
+            scheduled_for: scheduled,
+            status: run_status.to_str(),
+            amount: run_amount,
+            decided_by: decided,
+            decided_at: decided_time.as_deref(),
+        })
+        .execute(conn)?;
+    Ok(())
+}
+
+/// Runs whose recurring item no longer exists.
+fn delete_orphaned_recurring_runs(conn: &mut SqliteConnection) -> Result<(), FinancerError> {
+    use crate::schema::{recurring_runs, recurring_transactions, recurring_transfers};
+
+    diesel::delete(
+        recurring_runs::table
+            .filter(recurring_runs::item_kind.eq(RecurringKind::Transaction.to_str()))
+            .filter(recurring_runs::item_id.ne_all(recurring_transactions::table.select(recurring_transactions::id))),
+    )
+    .execute(conn)?;
+    diesel::delete(
+        recurring_runs::table
+            .filter(recurring_runs::item_kind.eq(RecurringKind::Transfer.to_str()))
+            .filter(recurring_runs::item_id.ne_all(recurring_transfers::table.select(recurring_transfers::id))),
+    )
+    .execute(conn)?;
+    Ok(())
+}
+
+/// Checks that `member` may act on the item's accounts.
+fn ensure_can_edit_recurring_item(conn: &mut SqliteConnection, member: i32, kind: RecurringKind, item: i32) -> Result<(), FinancerError> {
+    use crate::schema::{recurring_transactions, recurring_transfers};
+
+    match kind {
+        RecurringKind::Transaction => {
+            let account: i32 = recurring_transactions::table
+                .filter(recurring_transactions::id.eq(item))
+                .select(recurring_transactions::account_id)
+                .first(conn)
+                .optional()?
+                .ok_or_else(|| FinancerError::NotFound(format!("Recurring transaction {}", item)))?;
+            find_editable_account(conn, member, account)?;
+        }
+        RecurringKind::Transfer => {
+            let (from, to): (i32, i32) = recurring_transfers::table
+                .filter(recurring_transfers::id.eq(item))
+                .select((recurring_transfers::from_account_id, recurring_transfers::to_account_id))
+                .first(conn)
+                .optional()?
+                .ok_or_else(|| FinancerError::NotFound(format!("Recurring transfer {}", item)))?;
+            find_editable_account(conn, member, from)?;
+            find_editable_account(conn, member, to)?;
+        }
+    }
+    Ok(())
+}
+
+/// Occurrences waiting for approval on recurring items the member can see, oldest first.
+pub fn get_pending_recurring_runs(conn: &mut SqliteConnection, member: i32) -> Result<Vec<RecurringRun>, FinancerError> {
+    use crate::schema::recurring_runs;
+
+    let tx_ids: Vec<i32> = get_user_recurring_transactions(conn, member)?.iter().map(|i| i.id).collect();
+    let transfer_ids: Vec<i32> = get_user_recurring_transfers(conn, member)?.iter().map(|i| i.id).collect();
+
+    Ok(recurring_runs::table
+        .filter(recurring_runs::status.eq(RunStatus::Pending.to_str()))
+        .filter(
+            recurring_runs::item_kind
+                .eq(RecurringKind::Transaction.to_str())
+                .and(recurring_runs::item_id.eq_any(tx_ids))
+                .or(recurring_runs::item_kind
+                    .eq(RecurringKind::Transfer.to_str())
+                    .and(recurring_runs::item_id.eq_any(transfer_ids))),
+        )
+        .order((recurring_runs::scheduled_for.asc(), recurring_runs::id.asc()))
+        .load(conn)?)
+}
+
+fn find_pending_run(conn: &mut SqliteConnection, member: i32, run_id: i32) -> Result<RecurringRun, FinancerError> {
+    use crate::schema::recurring_runs;
+
+    let run: RecurringRun = recurring_runs::table
+        .filter(recurring_runs::id.eq(run_id))
+        .first(conn)
+        .optional()?
+        .ok_or_else(|| FinancerError::NotFound(format!("Scheduled occurrence {}", run_id)))?;
+    ensure_can_edit_recurring_item(conn, member, RecurringKind::from_str(&run.item_kind), run.item_id)?;
+    if RunStatus::from_str(&run.status) != RunStatus::Pending {
+        return Err(FinancerError::Conflict(format!("Occurrence on {} was already {}", run.scheduled_for, run.status)));
+    }
+    Ok(run)
+}
+
+/// Posts a queued occurrence on its scheduled date, rolled off weekends and holidays like
+/// automatic posts, optionally with a different amount.
+/// A transaction keeps the item's sign; a transfer always moves a positive amount.
+pub fn confirm_recurring_run(
+    conn: &mut SqliteConnection,
+    member: i32,
+    run_id: i32,
+    new_amount: Option<f32>,
+    now: NaiveDateTime,
+) -> Result<(), FinancerError> {
+    conn.transaction::<_, FinancerError, _>(|conn| {
+        use crate::schema::{recurring_runs, recurring_transactions, recurring_transfers};
+
+        let run = find_pending_run(conn, member, run_id)?;
+        let posted_amount = match new_amount {
+            Some(a) if a == 0.0 || !a.is_finite() => {
+                return Err(FinancerError::Validation("Amount must be non-zero".to_string()));
+            }
+            Some(a) => a.abs().copysign(run.amount),
+            None => run.amount,
+        };
+
+        match RecurringKind::from_str(&run.item_kind) {
+            RecurringKind::Transaction => {
+                let item: RecurringTransaction = recurring_transactions::table
+                    .filter(recurring_transactions::id.eq(run.item_id))
+                    .first(conn)?;
+                let posted_on = rolled_date(&get_holiday_calendars(conn, &[item.user_id])?, item.user_id, &item.roll_convention, parse_db_datetime(&run.scheduled_for)?);
+                let posted_on = format_db_datetime(posted_on);
+                insert_transaction(conn, Some(member), Some((RecurringKind::Transaction, item.id)), item.account_id, item.contact_id, posted_amount, item.category.clone(), posted_on.clone(), false)?;
+                check_budget_alerts(conn, item.account_id, &item.category, &posted_on, now)?;
+            }
+            RecurringKind::Transfer => {
+                let item: RecurringTransfer = recurring_transfers::table
+                    .filter(recurring_transfers::id.eq(run.item_id))
+                    .first(conn)?;
+                let posted_on = rolled_date(&get_holiday_calendars(conn, &[item.user_id])?, item.user_id, &item.roll_convention, parse_db_datetime(&run.scheduled_for)?);
+                insert_transfer(conn, Some(member), Some((RecurringKind::Transfer, item.id)), item.from_account_id, item.to_account_id, posted_amount, format_db_datetime(posted_on))?;
+            }
+        }
+
+        diesel::update(recurring_runs::table.filter(recurring_runs::id.eq(run_id)))
+            .set((
+                recurring_runs::status.eq(RunStatus::Posted.to_str()),
+                recurring_runs::amount.eq(posted_amount),
+                recurring_runs::decided_by.eq(Some(member)),
+                recurring_runs::decided_at.eq(Some(format_db_datetime(now))),
+            ))
+            .execute(conn)?;
+        Ok(())
+    })
+}
+
+/// Drops a queued occurrence without posting anything.
+pub fn skip_recurring_run(conn: &mut SqliteConnection, member: i32, run_id: i32, now: NaiveDateTime) -> Result<(), FinancerError> {
+    use crate::schema::recurring_runs;
+
+    find_pending_run(conn, member, run_id)?;
+    diesel::update(recurring_runs::table.filter(recurring_runs::id.eq(run_id)))
+        .set((
+            recurring_runs::status.eq(RunStatus::Skipped.to_str()),
+            recurring_runs::decided_by.eq(Some(member)),
+            recurring_runs::decided_at.eq(Some(format_db_datetime(now))),
+        ))
+        .execute(conn)?;
+    Ok(())
+}
+
+/// Skips an item's next scheduled occurrence before it happens: the skip is logged and the
+/// schedule moves on. It still counts towards the item's occurrence limit.
+pub fn skip_next_recurring_occurrence(
+    conn: &mut SqliteConnection,
+    member: i32,
+    kind: RecurringKind,
+    item: i32,
+    now: NaiveDateTime,
+) -> Result<(), FinancerError> {
+    conn.transaction::<_, FinancerError, _>(|conn| {
+        use crate::schema::{recurring_transactions, recurring_transfers};
+
+        ensure_can_edit_recurring_item(conn, member, kind, item)?;
+        let (next_run, rule_text, ends, max, run, item_amount) = match kind {
+            RecurringKind::Transaction => {
+                let i: RecurringTransaction = recurring_transactions::table.filter(recurring_transactions::id.eq(item)).first(conn)?;
+                (i.next_run_at, i.recurrence_rule, i.ends_at, i.max_occurrences, i.occurrences_run, i.amount)
+            }
+            RecurringKind::Transfer => {
+                let i: RecurringTransfer = recurring_transfers::table.filter(recurring_transfers::id.eq(item)).first(conn)?;
+                (i.next_run_at, i.recurrence_rule, i.ends_at, i.max_occurrences, i.occurrences_run, i.amount)
+            }
+        };
+
+        let current = parse_db_datetime(&next_run)?;
+        let ends = ends.as_deref().map(parse_db_datetime).transpose()?;
+        if schedule_finished(current, ends, max, run) {
+            return Err(FinancerError::Validation("This schedule has no occurrences left to skip".to_string()));
+        }
+        if find_recurring_run(conn, kind, item, &next_run)?.is_none() {
+            log_recurring_run(conn, kind, item, &next_run, RunStatus::Skipped, item_amount, Some(member), now)?;
+        }
+
+        let rule = stored_rule("Recurring item", item, &rule_text)?;
+        let following = rule.next_after(current);
+        let finished = following.is_none_or(|next| schedule_finished(next, ends, max, run + 1));
+        let next_text = format_db_datetime(following.unwrap_or(current));
+        match kind {
+            RecurringKind::Transaction => {
+                diesel::update(recurring_transactions::table.filter(recurring_transactions::id.eq(item)))
+                    .set((
+                        recurring_transactions::next_run_at.eq(next_text),
+                        recurring_transactions::occurrences_run.eq(run + 1),
+                        recurring_transactions::active.eq(!finished),
+                    ))
+                    .execute(conn)?;
+            }
+            RecurringKind::Transfer => {
+                diesel::update(recurring_transfers::table.filter(recurring_transfers::id.eq(item)))
+                    .set((
+                        recurring_transfers::next_run_at.eq(next_text),
+                        recurring_transfers::occurrences_run.eq(run + 1),
+                        recurring_transfers::active.eq(!finished),
+                    ))
+                    .execute(conn)?;
+            }
+        }
+        Ok(())
+    })
+}
+
+/// Applies a recurring item's current amount, category and accounts to the transactions it
+/// generated earlier, keeping their dates. Returns how many transactions were rewritten.
+///
+/// Account balances move by the difference, and the running `balance_after` of every
+/// affected account is rebuilt in date order.
+fn rewrite_past_recurring_occurrences(
+    conn: &mut SqliteConnection,
+    member: i32,
+    kind: RecurringKind,
+    item: i32,
+) -> Result<usize, FinancerError> {
+    conn.transaction::<_, FinancerError, _>(|conn| {
+        use crate::schema::{recurring_transactions, recurring_transfers};
+
+        ensure_can_edit_recurring_item(conn, member, kind, item)?;
+        let generated: Vec<Transaction> = transactions
+            .filter(recurring_kind.eq(kind.to_str()))
+            .filter(recurring_id.eq(item))
+            .load(conn)?;
+
+        // (account, amount, category) for the generated rows. A transfer's rows are money out
+        // of `from` (negative) and into `to` (positive), and keep their "Transfer" category.
+        let (out_account, in_account, template_amount, template_category) = match kind {
+            RecurringKind::Transaction => {
+                let template: RecurringTransaction = recurring_transactions::table
+                    .filter(recurring_transactions::id.eq(item))
+                    .first(conn)?;
+                (template.account_id, template.account_id, template.amount, Some(template.category))
+            }
+            RecurringKind::Transfer => {
+                let template: RecurringTransfer = recurring_transfers::table
+                    .filter(recurring_transfers::id.eq(item))
+                    .first(conn)?;
+                (template.from_account_id, template.to_account_id, template.amount.abs(), None)
+            }
+        };
+
+        let mut touched_accounts: Vec<i32> = Vec::new();
+        for tx in &generated {
+            find_editable_account(conn, member, tx.user_account_id)?;
+            let (target_account, target_amount) = match kind {
+                RecurringKind::Transaction => (out_account, template_amount),
+                RecurringKind::Transfer if tx.amount < 0.0 => (out_account, -template_amount),
+                RecurringKind::Transfer => (in_account, template_amount),
+            };
+            let target_category = template_category.clone().unwrap_or_else(|| tx.category.clone());
+            update_account_balance(conn, tx.user_account_id, -tx.amount)?;
+            update_account_balance(conn, target_account, target_amount)?;
+            diesel::update(transactions.filter(crate::schema::transactions::id.eq(tx.id)))
+                .set((
+                    user_account_id.eq(target_account),
+                    amount.eq(target_amount),
+                    category.eq(target_category),
+                    amount_cents.eq((target_amount * 100.0) as i32),
+                ))
+                .execute(conn)?;
+            forget_budget_carries_after(conn, &tx.date)?;
+            touched_accounts.extend([tx.user_account_id, target_account]);
+        }
+        touched_accounts.sort_unstable();
+        touched_accounts.dedup();
+        for account in touched_accounts {
+            recompute_balance_after(conn, account)?;
+        }
+        Ok(generated.len())
+    })
+}
+
+/// Rewrites `balance_after` on every transaction of an account as a running total in date
+/// order, ending at the account's current balance.
+fn recompute_balance_after(conn: &mut SqliteConnection, account_id: i32) -> Result<(), FinancerError> {
+    use crate::schema::transactions::dsl::*;
+
+    let current_balance = find_account(conn, account_id)?.balance;
+    let history: Vec<(i32, f32)> = transactions
+        .filter(user_account_id.eq(account_id))
+        .order((date.asc(), id.asc()))
+        .select((id, amount))
+        .load(conn)?;
+
+    let mut running = current_balance - history.iter().map(|(_, a)| a).sum::<f32>();
+    for (tx_id, tx_amount) in history {
+        running += tx_amount;
+        diesel::update(transactions.filter(id.eq(tx_id)))
+            .set(balance_after.eq(running))
+            .execute(conn)?;
+    }
+    Ok(())
+}
+
+pub fn get_userid_by_username(conn: &mut SqliteConnection, search_username: &str) -> Result<User, FinancerError> {
+    users
+        .filter(username.eq(search_username))
+        .first::<User>(conn)
+        .optional()?
+        .ok_or_else(|| FinancerError::NotFound(format!("User {}", search_username)))
+}
+
+#[allow(dead_code)]
+pub fn create_contact(conn: &mut SqliteConnection, new_name: &str, new_user: i32) -> Result<usize, FinancerError> {
+    let new_contact = NewContact {
+        name: new_name,
+        user: new_user,
+    };
+
+    Ok(diesel::insert_into(contacts).values(&new_contact).execute(conn)?)
+}
+
+/// Adds a transaction without a permission check, crediting it to the account's creator.
+/// The UI goes through `create_transaction_as`, which records the acting member.
+#[allow(dead_code)]
+pub fn create_transaction(
+    conn: &mut SqliteConnection,
+    new_user_account: i32,
+    new_contact_id: i32,
+    new_amount: f32,
+    new_category: String,
+    new_date: String,
+) -> Result<usize, FinancerError> {
+    let result = insert_transaction(conn, None, None, new_user_account, new_contact_id, new_amount, new_category.clone(), new_date.clone(), false)?;
+    check_budget_alerts(conn, new_user_account, &new_category, &new_date, chrono::Local::now().naive_local())?;
+    Ok(result)
+}
+
+/// Adds a transaction on behalf of `member_id`, who must be an owner or editor of the account.
+/// Budget alerts it sets off are stamped with `now`.
+#[allow(clippy::too_many_arguments)]
+pub fn create_transaction_as(
+    conn: &mut SqliteConnection,
+    member_id: i32,
+    new_user_account: i32,
+    new_contact_id: i32,
+    new_amount: f32,
+    new_category: String,
+    new_date: String,
+    now: NaiveDateTime,
+) -> Result<usize, FinancerError> {
+    find_editable_account(conn, member_id, new_user_account)?;
+    let result = insert_transaction(conn, Some(member_id), None, new_user_account, new_contact_id, new_amount, new_category.clone(), new_date.clone(), false)?;
+    check_budget_alerts(conn, new_user_account, &new_category, &new_date, now)?;
+    Ok(result)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn insert_transaction(
+    conn: &mut SqliteConnection,
+    creator_id: Option<i32>,
+    source: Option<(RecurringKind, i32)>,
+    new_user_account: i32,
+    new_contact_id: i32,
+    new_amount: f32,
+    new_category: String,
+    new_date: String,
+    transfer: bool,
+) -> Result<usize, FinancerError> {
+    let cents = (new_amount * 100.0) as i32;
+    
+    let current_account = find_account(conn, new_user_account)?;
+    let new_balance = current_account.balance + new_amount;
+    
+    let new_transaction = NewTransaction {
+        user_account_id: new_user_account,
+        contact_id: new_contact_id,
+        amount: new_amount,
+        category: new_category.clone(),
+        date: new_date.clone(),
+        amount_cents: cents,
+        balance_after: new_balance,
+        created_by: Some(creator_id.unwrap_or(current_account.user_id)),
+        recurring_id: source.map(|(_, item)| item),
+        recurring_kind: source.map(|(kind, _)| kind.to_str().to_string()),
+        is_transfer: transfer,
+    };
+    
+    let result = diesel::insert_into(transactions).values(&new_transaction).execute(conn)?;
+    
+    update_account_balance(conn, new_user_account, new_amount)?;
+    forget_budget_carries_after(conn, &new_date)?;
+    
+    Ok(result)
+}
+
+#[allow(dead_code)]
+pub fn create_transfer(
+    conn: &mut SqliteConnection,
+    from_account_id: i32,
+    to_account_id: i32,
+    transfer_amount: f32,
+    transfer_date: String,
+) -> Result<(), FinancerError> {
+    insert_transfer(conn, None, None, from_account_id, to_account_id, transfer_amount, transfer_date)
+}
+
+/// Moves money between two accounts on behalf of `member_id`, who must be able to edit both.
+pub fn create_transfer_as(
+    conn: &mut SqliteConnection,
+    member_id: i32,
+    from_account_id: i32,
+    to_account_id: i32,
+    transfer_amount: f32,
+    transfer_date: String,
+) -> Result<(), FinancerError> {
+    find_editable_account(conn, member_id, from_account_id)?;
+    find_editable_account(conn, member_id, to_account_id)?;
+    insert_transfer(conn, Some(member_id), None, from_account_id, to_account_id, transfer_amount, transfer_date)
+}
+
+fn insert_transfer(
+    conn: &mut SqliteConnection,
+    creator_id: Option<i32>,
+    source: Option<(RecurringKind, i32)>,
+    from_account_id: i32,
+    to_account_id: i32,
+    transfer_amount: f32,
+    transfer_date: String,
+) -> Result<(), FinancerError> {
+    if from_account_id == to_account_id {
+        return Err(FinancerError::Validation("Cannot transfer to the same account".to_string()));
+    }
+    conn.transaction::<_, FinancerError, _>(|conn| {
+        insert_transaction(
+            conn,
+            creator_id,
+            source,
+            from_account_id,
+            0,
+            -transfer_amount.abs(),
+            "Transfer".to_string(),
+            transfer_date.clone(),
+            true,
+        )?;
+        
+        insert_transaction(
+            conn,
+            creator_id,
+            source,
+            to_account_id,
+            0,
+            transfer_amount.abs(),
+            "Transfer".to_string(),
+            transfer_date,
+            true,
+        )?;
+        
+        Ok(())
+    })
+}
+
+#[allow(dead_code)]
+pub fn verify_user(conn: &mut SqliteConnection, login_username: &str, login_password: &str) -> Result<bool, FinancerError> {
+    verify_user_at(
+        conn,
+        login_username,
+        login_password,
+        None,
+        chrono::Local::now().naive_local(),
+        &LoginThrottlePolicy::default(),
+    )
+}
+
+/// Checks a login attempt, applying the throttle policy as of `now`. While a username is
+/// delayed or locked out, attempts are refused with `LoginThrottled` without checking the
+/// password. Every attempt is written to `login_events`.
+///
+/// When the user has 2FA enabled, a correct password without `totp_code` returns
+/// `TwoFactorRequired`. The code may be a 6-digit TOTP or an unused recovery code; a wrong
+/// one counts as a failed attempt.
+pub fn verify_user_at(
+    conn: &mut SqliteConnection,
+    login_username: &str,
+    login_password: &str,
+    totp_code: Option<&str>,
+    now: chrono::NaiveDateTime,
+    policy: &LoginThrottlePolicy,
+) -> Result<bool, FinancerError> {
+    use crate::schema::login_attempts;
+
+    let attempt: Option<LoginAttempt> = login_attempts::table
+        .find(login_username)
+        .first(conn)
+        .optional()?;
+
+    let matched_user = users
+        .filter(username.eq(login_username))
+        .first::<User>(conn)
+        .optional()?;
+    let matched_id = matched_user.as_ref().map(|u| u.id);
+
+    if let Some(locked_until) = attempt.as_ref().and_then(|a| a.locked_until.as_deref()) {
+        let locked_until = parse_db_datetime(locked_until)?;
+        if locked_until > now {
+            record_login_event(conn, login_username, matched_id, false, "throttled", now)?;
+            return Err(FinancerError::LoginThrottled {
+                retry_after_secs: (locked_until - now).num_seconds().max(1),
+            });
+        }
+    }
+
+    let verified = match &matched_user {
+        Some(u) => auth::verify_password(login_password, &u.password_hash)?,
+        None => false,
+    };
+
+    let mut failure_reason = "bad_credentials";
+    if let (true, Some(u)) = (verified, &matched_user) {
+        let uid = u.id;
+        let second_factor = match (find_totp(conn, uid)?, totp_code) {
+            (Some(enrollment), Some(code)) if enrollment.enabled => {
+                check_second_factor(conn, &enrollment, login_password, code, now)?
+            }
+            (Some(enrollment), None) if enrollment.enabled => return Err(FinancerError::TwoFactorRequired),
+            _ => Some("ok"),
+        };
+        match second_factor {
+            Some(reason) => {
+                // Only a completed login upgrades the stored hash.
+                if auth::needs_rehash(&u.password_hash) {
+                    let upgraded = auth::hash_password(login_password)?;
+                    diesel::update(users.filter(crate::schema::users::dsl::id.eq(uid)))
+                        .set(password_hash.eq(upgraded))
+                        .execute(conn)?;
+                }
+                diesel::delete(login_attempts::table.find(login_username)).execute(conn)?;
+                record_login_event(conn, login_username, matched_id, true, reason, now)?;
+                return Ok(true);
+            }
+            None => failure_reason = "bad_2fa_code",
+        }
+    }
+
+    let failed_count = attempt.map(|a| a.failed_count).unwrap_or(0) + 1;
+    let delay = policy.delay_after(failed_count);
+    let reason = if failed_count >= policy.lockout_threshold { "locked_out" } else { failure_reason };
+    let row = LoginAttempt {
+        username: login_username.to_string(),
+        // A lockout starts a fresh count once it expires.
+        failed_count: if failed_count >= policy.lockout_threshold { 0 } else { failed_count },
+        last_failed_at: Some(format_db_datetime(now)),
+        locked_until: (delay > 0).then(|| format_db_datetime(now + chrono::Duration::seconds(delay))),
+    };
+    diesel::replace_into(login_attempts::table).values(&row).execute(conn)?;
+    record_login_event(conn, login_username, matched_id, false, reason, now)?;
+    Ok(false)
+}
+
+/// Returns the login event reason when `code` passes, `None` when it doesn't.
+fn check_second_factor(
+    conn: &mut SqliteConnection,
+    enrollment: &UserTotp,
+    login_password: &str,
+    code: &str,
+    now: chrono::NaiveDateTime,
+) -> Result<Option<&'static str>, FinancerError> {
+    use crate::schema::{recovery_codes, user_totp};
+
+    if totp::looks_like_totp_code(code) {
+        let secret = totp::decrypt_secret(&stored_secret(enrollment), login_password)?;
+        let matched_step = totp::verify_code(&secret, code, unix_time(now))?
+            .filter(|step| enrollment.last_used_step.is_none_or(|last| *step > last));
+        let Some(step) = matched_step else {
+            return Ok(None);
+        };
+        diesel::update(user_totp::table.find(enrollment.user_id))
+            .set(user_totp::last_used_step.eq(Some(step)))
+            .execute(conn)?;
+        return Ok(Some("ok"));
+    }
+
+    let used = diesel::update(
+        recovery_codes::table
+            .filter(recovery_codes::user_id.eq(enrollment.user_id))
+            .filter(recovery_codes::code_hash.eq(totp::hash_recovery_code(code)))
+            .filter(recovery_codes::used_at.is_null()),
+    )
+    .set(recovery_codes::used_at.eq(Some(format_db_datetime(now))))
+    .execute(conn)?;
+    Ok((used > 0).then_some("recovery_code"))
+}
+
+/// Seconds since the Unix epoch for a local wall-clock time, as TOTP codes are based on UTC.
+pub fn unix_time(now: chrono::NaiveDateTime) -> i64 {
+    use chrono::TimeZone;
+
+    chrono::Local
+        .from_local_datetime(&now)
+        .earliest()
+        .map(|dt| dt.timestamp())
+        .unwrap_or_else(|| now.and_utc().timestamp())
+}
+
+fn find_totp(conn: &mut SqliteConnection, owner_id: i32) -> Result<Option<UserTotp>, FinancerError> {
+    use crate::schema::user_totp;
+
+    Ok(user_totp::table.find(owner_id).first::<UserTotp>(conn).optional()?)
+}
+
+fn stored_secret(enrollment: &UserTotp) -> totp::EncryptedSecret {
+    totp::EncryptedSecret {
+        key_salt: enrollment.key_salt.clone(),
+        nonce: enrollment.nonce.clone(),
+        ciphertext: enrollment.ciphertext.clone(),
+    }
+}
+
+fn save_totp_secret(conn: &mut SqliteConnection, owner_id: i32, encrypted: &totp::EncryptedSecret) -> Result<(), FinancerError> {
+    use crate::schema::user_totp;
+
+    diesel::update(user_totp::table.find(owner_id))
+        .set((
+            user_totp::key_salt.eq(&encrypted.key_salt),
+            user_totp::nonce.eq(&encrypted.nonce),
+            user_totp::ciphertext.eq(&encrypted.ciphertext),
+        ))
+        .execute(conn)?;
+    Ok(())
+}
+
+fn require_password(conn: &mut SqliteConnection, owner_id: i32, password: &str) -> Result<User, FinancerError> {
+    let current = find_user(conn, owner_id)?;
+    if !auth::verify_password(password, &current.password_hash)? {
+        return Err(FinancerError::Validation("Current password is incorrect".to_string()));
+    }
+    Ok(current)
+}
+
+pub fn is_totp_enabled(conn: &mut SqliteConnection, owner_id: i32) -> Result<bool, FinancerError> {
+    Ok(find_totp(conn, owner_id)?.is_some_and(|t| t.enabled))
+}
+
+/// Starts 2FA enrollment with a fresh secret, replacing any unfinished one. Returns the
+/// base32 secret for manual entry; 2FA stays off until `confirm_totp_enrollment`.
+pub fn begin_totp_enrollment(conn: &mut SqliteConnection, owner_id: i32, password: &str, now: chrono::NaiveDateTime) -> Result<String, FinancerError> {
+    use crate::schema::user_totp;
+
+    require_password(conn, owner_id, password)?;
+    if is_totp_enabled(conn, owner_id)? {
+        return Err(FinancerError::Conflict("Two-factor authentication is already enabled".to_string()));
+    }
+
+    let secret = totp::generate_secret();
+    let encrypted = totp::encrypt_secret(&secret, password)?;
+    diesel::replace_into(user_totp::table)
+        .values(UserTotp {
+            user_id: owner_id,
+            key_salt: encrypted.key_salt,
+            nonce: encrypted.nonce,
+            ciphertext: encrypted.ciphertext,
+            enabled: false,
+            last_used_step: None,
+            created_at: format_db_datetime(now),
+        })
+        .execute(conn)?;
+    Ok(secret)
+}
+
+/// Turns 2FA on once the user proves their app produces the right code, and returns the
+/// recovery codes. Only their hashes are stored, so this is the one time they can be shown.
+pub fn confirm_totp_enrollment(
+    conn: &mut SqliteConnection,
+    owner_id: i32,
+    password: &str,
+    code: &str,
+    now: chrono::NaiveDateTime,
+) -> Result<Vec<String>, FinancerError> {
+    use crate::schema::{recovery_codes, user_totp};
+
+    let enrollment = find_totp(conn, owner_id)?
+        .filter(|t| !t.enabled)
+        .ok_or_else(|| FinancerError::NotFound("Pending two-factor enrollment".to_string()))?;
+    let secret = totp::decrypt_secret(&stored_secret(&enrollment), password)?;
+    let Some(step) = totp::verify_code(&secret, code, unix_time(now))? else {
+        return Err(FinancerError::Validation("The code from your authenticator app is incorrect".to_string()));
+    };
+
+    let codes = totp::generate_recovery_codes();
+    conn.transaction::<_, FinancerError, _>(|conn| {
+        diesel::update(user_totp::table.find(owner_id))
+            .set((user_totp::enabled.eq(true), user_totp::last_used_step.eq(Some(step))))
+            .execute(conn)?;
+        diesel::delete(recovery_codes::table.filter(recovery_codes::user_id.eq(owner_id))).execute(conn)?;
+        let rows: Vec<NewRecoveryCode> = codes
+            .iter()
+            .map(|c| NewRecoveryCode { user_id: owner_id, code_hash: totp::hash_recovery_code(c) })
+            .collect();
+        diesel::insert_into(recovery_codes::table).values(&rows).execute(conn)?;
+        Ok(())
+    })?;
+    Ok(codes)
+}
+
+pub fn disable_totp(conn: &mut SqliteConnection, owner_id: i32, password: &str) -> Result<(), FinancerError> {
+    use crate::schema::{recovery_codes, user_totp};
+
+    require_password(conn, owner_id, password)?;
+    conn.transaction::<_, FinancerError, _>(|conn| {
+        diesel::delete(recovery_codes::table.filter(recovery_codes::user_id.eq(owner_id))).execute(conn)?;
+        diesel::delete(user_totp::table.find(owner_id)).execute(conn)?;
+        Ok(())
+    })
+}
+
+pub fn count_unused_recovery_codes(conn: &mut SqliteConnection, owner_id: i32) -> Result<i64, FinancerError> {
+    use crate::schema::recovery_codes;
+
+    Ok(recovery_codes::table
+        .filter(recovery_codes::user_id.eq(owner_id))
+        .filter(recovery_codes::used_at.is_null())
+        .count()
+        .get_result(conn)?)
+}
+
+fn record_login_event(
+    conn: &mut SqliteConnection,
+    login_username: &str,
+    event_user: Option<i32>,
+    succeeded: bool,
+    reason: &str,
+    now: chrono::NaiveDateTime,
+) -> Result<(), FinancerError> {
+    use crate::schema::login_events;
+
+    diesel::insert_into(login_events::table)
+        .values(NewLoginEvent {
+            username: login_username,
+            user_id: event_user,
+            succeeded,
+            reason,
+            occurred_at: format_db_datetime(now),
+        })
+        .execute(conn)?;
+    Ok(())
+}
+
+/// Most recent sign-in attempts for a user, newest first.
+pub fn get_login_events(conn: &mut SqliteConnection, owner_id: i32, limit: i64) -> Result<Vec<LoginEvent>, FinancerError> {
+    use crate::schema::login_events;
+
+    Ok(login_events::table
+        .filter(login_events::user_id.eq(owner_id))
+        .order((login_events::occurred_at.desc(), login_events::id.desc()))
+        .limit(limit)
+        .load::<LoginEvent>(conn)?)
+}
+
+fn format_db_datetime(dt: chrono::NaiveDateTime) -> String {
+    dt.format("%Y-%m-%d %H:%M:%S").to_string()
+}
+
+fn parse_db_datetime(value: &str) -> Result<chrono::NaiveDateTime, FinancerError> {
+    chrono::NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S")
+        .map_err(|e| FinancerError::CorruptData(format!("Bad timestamp '{}': {}", value, e)))
+}
+
+fn validate_rollover(mode: &str, cap: Option<i32>) -> Result<(), FinancerError> {
+    if !RolloverMode::ALL.iter().any(|m| m.to_str() == mode) {
+        return Err(FinancerError::Validation(format!("Unknown rollover mode '{}'", mode)));
+    }
+    if cap.is_some_and(|c| c < 0) {
+        return Err(FinancerError::Validation("Rollover cap can't be negative".to_string()));
+    }
+    if RolloverMode::from_str(mode) == RolloverMode::Capped && cap.is_none() {
+        return Err(FinancerError::Validation("Capped rollover needs a cap".to_string()));
+    }
+    Ok(())
+}
+
+/// A budget's own dates must come as a pair, in order. Such a budget is a single span, so it
+/// can't roll over.
+fn validate_budget_range(changes: &NewBudget) -> Result<(), FinancerError> {
+    let day = |text: &Option<String>| {
+        text.as_deref()
+            .map(|d| {
+                chrono::NaiveDate::parse_from_str(d, "%Y-%m-%d")
+                    .map_err(|_| FinancerError::Validation(format!("'{}' is not a YYYY-MM-DD date", d)))
+            })
+            .transpose()
+    };
+    match (day(&changes.starts_on)?, day(&changes.ends_on)?) {
+        (None, None) => Ok(()),
+        (Some(start), Some(end)) if start > end => Err(FinancerError::Validation("The budget ends before it starts".to_string())),
+        (Some(_), Some(_)) if RolloverMode::from_str(&changes.rollover_mode) != RolloverMode::None => {
+            Err(FinancerError::Validation("A budget with its own dates can't roll over".to_string()))
+        }
+        (Some(_), Some(_)) => Ok(()),
+        _ => Err(FinancerError::Validation("Give the budget both a start and an end date".to_string())),
+    }
+}
+
+/// Checks a budget's alert thresholds and stores them tidied up, e.g. `100, 50%` as `50,100`.
+fn normalize_alert_thresholds(text: &mut String) -> Result<(), FinancerError> {
+    *text = parse_alert_thresholds(text)?
+        .iter()
+        .map(|t| t.to_string())
+        .collect::<Vec<_>>()
+        .join(",");
+    Ok(())
+}
+
+pub fn create_budget(conn: &mut SqliteConnection, new_budget: NewBudget) -> Result<Budget, FinancerError> {
+    create_budget_at(conn, new_budget, chrono::Local::now().naive_local())
+}
+
+/// Creates a budget whose rollover history starts from the period containing `now`.
+pub fn create_budget_at(conn: &mut SqliteConnection, mut new_budget: NewBudget, now: NaiveDateTime) -> Result<Budget, FinancerError> {
+    use crate::schema::budgets::dsl::*;
+    
+    validate_rollover(&new_budget.rollover_mode, new_budget.rollover_cap_cents)?;
+    validate_budget_range(&new_budget)?;
+    normalize_alert_thresholds(&mut new_budget.alert_thresholds)?;
+    diesel::insert_into(budgets)
+        .values((&new_budget, created_at.eq(format_db_datetime(now))))
+        .execute(conn)?;
+    
+    Ok(budgets.order(id.desc()).first(conn)?)
+}
+
+pub fn get_user_budgets(conn: &mut SqliteConnection, owner_id: i32) -> Result<Vec<Budget>, FinancerError> {
+    use crate::schema::budgets::dsl::*;
+    
+    Ok(budgets
+        .filter(user_id.eq(owner_id))
+        .filter(active.eq(true))
+        .load::<Budget>(conn)?)
+}
+
+pub fn update_budget(conn: &mut SqliteConnection, budget_id: i32, mut changes: NewBudget) -> Result<Budget, FinancerError> {
+    use crate::schema::budgets::dsl::*;
+    
+    validate_rollover(&changes.rollover_mode, changes.rollover_cap_cents)?;
+    validate_budget_range(&changes)?;
+    normalize_alert_thresholds(&mut changes.alert_thresholds)?;
+    let updated = diesel::update(budgets.filter(id.eq(budget_id)))
+        .set((
+            category.eq(&changes.category),
+            limit_cents.eq(&changes.limit_cents),
+            period.eq(&changes.period),
+            target_type.eq(&changes.target_type),
+            rollover_mode.eq(&changes.rollover_mode),
+            rollover_cap_cents.eq(changes.rollover_cap_cents),
+            alert_thresholds.eq(&changes.alert_thresholds),
+            name.eq(&changes.name),
+            include_subcategories.eq(changes.include_subcategories),
+            starts_on.eq(&changes.starts_on),
+            ends_on.eq(&changes.ends_on),
+        ))
+        .execute(conn)?;
+    if updated == 0 {
+        return Err(FinancerError::NotFound(format!("Budget {}", budget_id)));
+    }
+    forget_budget_carries(conn, budget_id)?;
+    
+    Ok(budgets.filter(id.eq(budget_id)).first(conn)?)
+}
+
+pub fn delete_budget(conn: &mut SqliteConnection, budget_id: i32) -> Result<usize, FinancerError> {
+    use crate::schema::budgets::dsl::*;
+    use crate::schema::{budget_accounts, budget_categories, notifications};
+    
+    diesel::delete(notifications::table.filter(notifications::budget_id.eq(budget_id))).execute(conn)?;
+    diesel::delete(budget_categories::table.filter(budget_categories::budget_id.eq(budget_id))).execute(conn)?;
+    diesel::delete(budget_accounts::table.filter(budget_accounts::budget_id.eq(budget_id))).execute(conn)?;
+    forget_budget_carries(conn, budget_id)?;
+    let deleted = diesel::delete(budgets.filter(id.eq(budget_id)))
+        .execute(conn)?;
+    if deleted == 0 {
+        return Err(FinancerError::NotFound(format!("Budget {}", budget_id)));
+    }
+    Ok(deleted)
+}
+
+/// What an expense budget carries into the period starting at `period_start`, in cents, from
+/// every earlier period since the one the budget was created in. Income targets, budgets
+/// with their own dates and budgets without a rollover mode never carry anything.
+///
+/// Each result is saved in `budget_carries`, and the next call continues from the latest saved
+/// period instead of walking the whole history again.
+pub fn get_budget_rollover(
+    conn: &mut SqliteConnection,
+    owner_id: i32,
+    budget: &Budget,
+    period_start: NaiveDateTime,
+) -> Result<i64, FinancerError> {
+    use crate::schema::{budget_carries, transactions};
+
+    let mode = RolloverMode::from_str(&budget.rollover_mode);
+    if mode == RolloverMode::None || TargetType::from_str(&budget.target_type) == TargetType::Income || custom_range(budget).is_some() {
+        return Ok(0);
+    }
+    let budget_id = budget.id.unwrap_or(0);
+    let saved: Option<(String, i64)> = budget_carries::table
+        .filter(budget_carries::budget_id.eq(budget_id))
+        .filter(budget_carries::period_start.le(format_db_datetime(period_start)))
+        .order(budget_carries::period_start.desc())
+        .select((budget_carries::period_start, budget_carries::carried_cents))
+        .first(conn)
+        .optional()?;
+    let (from, carried_in) = match saved {
+        Some((start, carried)) => (parse_db_datetime(&start)?, carried),
+        None => (parse_db_datetime(&budget.created_at)?, 0),
+    };
+    let periods = periods_between(Period::from_str(&budget.period), from.date(), period_start);
+    let Some(first) = periods.first() else {
+        return Ok(carried_in);
+    };
+
+    let scope = get_budget_scope(conn, budget)?;
+    let history: Vec<(String, i32)> = transactions::table
+        .filter(scope_filter(owner_id, &scope))
+        .filter(transactions::date.ge(format_db_datetime(first.0)))
+        .filter(transactions::date.lt(format_db_datetime(period_start)))
+        .select((transactions::date, transactions::amount_cents))
+        .load(conn)?;
+
+    let mut net = vec![0i64; periods.len()];
+    for (when, cents) in history {
+        let when = parse_db_datetime(&when)?;
+        let index = periods.partition_point(|(start, _)| *start <= when);
+        if index > 0 {
+            net[index - 1] += cents as i64;
+        }
+    }
+    // Expenses are negative; refunds can't make a period's spending go below zero.
+    let spent: Vec<i64> = net.into_iter().map(|n| (-n).max(0)).collect();
+    let carried = rollover_after(mode, budget.rollover_cap_cents, budget.limit_cents, carried_in, &spent);
+    diesel::replace_into(budget_carries::table)
+        .values((
+            budget_carries::budget_id.eq(budget_id),
+            budget_carries::period_start.eq(format_db_datetime(period_start)),
+            budget_carries::carried_cents.eq(carried),
+        ))
+        .execute(conn)?;
+    Ok(carried)
+}
+
+/// Drops the saved carries of one budget, after its settings or scope change.
+fn forget_budget_carries(conn: &mut SqliteConnection, budget_id: i32) -> Result<(), FinancerError> {
+    use crate::schema::budget_carries;
+
+    diesel::delete(budget_carries::table.filter(budget_carries::budget_id.eq(budget_id))).execute(conn)?;
+    Ok(())
+}
+
+/// Drops every saved carry into a period starting after `when`, the date of a transaction
+/// that was just added, changed or removed.
+fn forget_budget_carries_after(conn: &mut SqliteConnection, when: &str) -> Result<(), FinancerError> {
+    use crate::schema::budget_carries;
+
+    diesel::delete(budget_carries::table.filter(budget_carries::period_start.gt(when))).execute(conn)?;
+    Ok(())
+}
+
+/// Drops the saved carries of a user's budgets, after the accounts they can see change.
+fn forget_user_budget_carries(conn: &mut SqliteConnection, member_id: i32) -> Result<(), FinancerError> {
+    use crate::schema::{budget_carries, budgets};
+
+    let owned = budgets::table.filter(budgets::user_id.eq(member_id)).select(budgets::id.assume_not_null());
+    diesel::delete(budget_carries::table.filter(budget_carries::budget_id.eq_any(owned))).execute(conn)?;
+    Ok(())
+}
+
+/// Drops the saved carries of every budget owned by a member of the account.
+fn forget_account_budget_carries(conn: &mut SqliteConnection, account_id: i32) -> Result<(), FinancerError> {
+    use crate::schema::{account_members, budget_carries, budgets};
+
+    let members = account_members::table.filter(account_members::account_id.eq(account_id)).select(account_members::user_id);
+    let owned = budgets::table.filter(budgets::user_id.eq_any(members)).select(budgets::id.assume_not_null());
+    diesel::delete(budget_carries::table.filter(budget_carries::budget_id.eq_any(owned))).execute(conn)?;
+    Ok(())
+}
+
+/// Budgeted against actual for the last `periods` periods of a budget, oldest first, ending
+/// with the one containing `today`. A budget with its own dates has just the one.
+pub fn get_budget_history(
+    conn: &mut SqliteConnection,
+    budget: &Budget,
+    today: chrono::NaiveDate,
+    periods: usize,
+) -> Result<Vec<PeriodPerformance>, FinancerError> {
+    let target = TargetType::from_str(&budget.target_type);
+    let offsets: Vec<i32> = if custom_range(budget).is_some() {
+        vec![0]
+    } else {
+        (0..periods as i32).rev().map(|back| -back).collect()
+    };
+    let mut history = Vec::with_capacity(offsets.len());
+    for offset in offsets {
+        let (start, end) = budget_range(budget, today, offset);
+        let net = get_budget_spend(conn, budget, start, end)?;
+        let actual = match target {
+            TargetType::Expense => (-net).max(0),
+            TargetType::Income => net.max(0),
+        };
+        let budgeted = budget.limit_cents as i64 + get_budget_rollover(conn, budget.user_id, budget, start)?;
+        history.push(PeriodPerformance { start, end, budgeted, actual });
+    }
+    Ok(history)
+}
+
+/// The period of an expense budget containing `when`: its start, what was spent in it and the
+/// limit including rollover, both in cents. `None` if `when` is outside a budget's own dates.
+fn expense_budget_period(conn: &mut SqliteConnection, budget: &Budget, when: NaiveDateTime) -> Result<Option<(NaiveDateTime, i64, i64)>, FinancerError> {
+    let (start, end) = budget_range(budget, when.date(), 0);
+    if when < start || when >= end {
+        return Ok(None);
+    }
+    let spent = (-get_budget_spend(conn, budget, start, end)?).max(0);
+    let limit = budget.limit_cents as i64 + get_budget_rollover(conn, budget.user_id, budget, start)?;
+    Ok(Some((start, spent, limit)))
+}
+
+/// Raises a notification for each alert threshold that expense budgets covering `cat` on the
+/// account have reached in the period containing `when`, for every member of the account. A threshold alerts once
+/// per budget period. Returns how many notifications were raised.
+fn check_budget_alerts(conn: &mut SqliteConnection, account_id: i32, cat: &str, when: &str, now: NaiveDateTime) -> Result<usize, FinancerError> {
+    use crate::schema::{account_members, budgets, notifications};
+
+    // Dates the app didn't write can't be placed in a period; the transaction is still saved.
+    let Ok(when) = parse_db_datetime(when) else {
+        return Ok(0);
+    };
+    let watching: Vec<Budget> = budgets::table
+        .filter(
+            budgets::user_id.eq_any(
+                account_members::table
+                    .filter(account_members::account_id.eq(account_id))
+                    .select(account_members::user_id),
+            ),
+        )
+        .filter(budgets::active.eq(true))
+        .filter(budgets::target_type.eq(TargetType::Expense.to_str()))
+        .load(conn)?;
+
+    let mut raised = 0;
+    for budget in watching {
+        let thresholds = parse_alert_thresholds(&budget.alert_thresholds)?;
+        if thresholds.is_empty() || !get_budget_scope(conn, &budget)?.matches(cat, account_id) {
+            continue;
+        }
+        let Some((start, spent, limit)) = expense_budget_period(conn, &budget, when)? else {
+            continue;
+        };
+        let period_start = format_db_datetime(start);
+        let created_at = format_db_datetime(now);
+        for threshold in crossed_thresholds(&thresholds, spent, limit) {
+            let message = format!(
+                "{} budget reached {}%: ${:.2} of ${:.2} spent in the period starting {}",
+                budget.display_name(),
+                threshold,
+                spent as f64 / 100.0,
+                limit as f64 / 100.0,
+                start.format("%b %d, %Y")
+            );
+            raised += diesel::insert_or_ignore_into(notifications::table)
+                .values(&NewNotification {
+                    user_id: budget.user_id,
+                    kind: NotificationKind::BudgetThreshold.to_str(),
+                    budget_id: budget.id,
+                    period_start: Some(&period_start),
+                    threshold: Some(threshold),
+                    message: &message,
+                    created_at: &created_at,
+                })
+                .execute(conn)?;
+        }
+    }
+    Ok(raised)
+}
+
+/// `owner_id`'s expense budgets that an expense of `new_amount` in `cat` from the account on
+/// `when` would take over their limit, with the spending it would bring them to and the
+/// limit, in cents.
+pub fn budgets_exceeded_by(
+    conn: &mut SqliteConnection,
+    owner_id: i32,
+    account_id: i32,
+    cat: &str,
+    new_amount: f32,
+    when: NaiveDateTime,
+) -> Result<Vec<(Budget, i64, i64)>, FinancerError> {
+    if new_amount >= 0.0 {
+        return Ok(Vec::new());
+    }
+    let extra = (-new_amount as f64 * 100.0).round() as i64;
+    let mut exceeded = Vec::new();
+    for budget in get_user_budgets(conn, owner_id)? {
+        if TargetType::from_str(&budget.target_type) != TargetType::Expense || !get_budget_scope(conn, &budget)?.matches(cat, account_id) {
+            continue;
+        }
+        let Some((_, spent, limit)) = expense_budget_period(conn, &budget, when)? else {
+            continue;
+        };
+        if spent + extra > limit {
+            exceeded.push((budget, spent + extra, limit));
+        }
+    }
+    Ok(exceeded)
+}
+
+/// Notifications `owner_id` hasn't dismissed, newest first.
+pub fn get_notifications(conn: &mut SqliteConnection, owner_id: i32) -> Result<Vec<Notification>, FinancerError> {
+    use crate::schema::notifications;
+
+    Ok(notifications::table
+        .filter(notifications::user_id.eq(owner_id))
+        .filter(notifications::dismissed.eq(false))
+        .order((notifications::created_at.desc(), notifications::id.desc()))
+        .load(conn)?)
+}
+
+pub fn count_unread_notifications(conn: &mut SqliteConnection, owner_id: i32) -> Result<i64, FinancerError> {
+    use crate::schema::notifications;
+
+    Ok(notifications::table
+        .filter(notifications::user_id.eq(owner_id))
+        .filter(notifications::dismissed.eq(false))
+        .filter(notifications::is_read.eq(false))
+        .count()
+        .get_result(conn)?)
+}
+
+pub fn mark_notification_read(conn: &mut SqliteConnection, owner_id: i32, notification_id: i32) -> Result<(), FinancerError> {
+    use crate::schema::notifications;
+
+    let updated = diesel::update(
+        notifications::table
+            .filter(notifications::id.eq(notification_id))
+            .filter(notifications::user_id.eq(owner_id)),
+    )
+    .set(notifications::is_read.eq(true))
+    .execute(conn)?;
+    if updated == 0 {
+        return Err(FinancerError::NotFound(format!("Notification {}", notification_id)));
+    }
+    Ok(())
+}
+
+pub fn mark_all_notifications_read(conn: &mut SqliteConnection, owner_id: i32) -> Result<usize, FinancerError> {
+    use crate::schema::notifications;
+
+    Ok(diesel::update(notifications::table.filter(notifications::user_id.eq(owner_id)))
+        .set(notifications::is_read.eq(true))
+        .execute(conn)?)
+}
+
+/// Hides a notification for good. It stays in the table so the same alert isn't raised again.
+pub fn dismiss_notification(conn: &mut SqliteConnection, owner_id: i32, notification_id: i32) -> Result<(), FinancerError> {
+    use crate::schema::notifications;
+
+    let updated = diesel::update(
+        notifications::table
+            .filter(notifications::id.eq(notification_id))
+            .filter(notifications::user_id.eq(owner_id)),
+    )
+    .set((notifications::is_read.eq(true), notifications::dismissed.eq(true)))
+    .execute(conn)?;
+    if updated == 0 {
+        return Err(FinancerError::NotFound(format!("Notification {}", notification_id)));
+    }
+    Ok(())
+}
+
+/// Income in cents between `start` and `end` on accounts `owner_id` can see. Transfers between
+/// accounts aren't income.
+pub fn get_income_for_period(conn: &mut SqliteConnection, owner_id: i32, start: NaiveDateTime, end: NaiveDateTime) -> Result<i64, FinancerError> {
+    use crate::schema::transactions;
+
+    let result: Option<i64> = transactions::table
+        .filter(transactions::user_account_id.eq_any(member_account_ids(owner_id)))
+        .filter(transactions::amount_cents.gt(0))
+        .filter(not_transfer())
+        .filter(transactions::date.ge(format_db_datetime(start)))
+        .filter(transactions::date.lt(format_db_datetime(end)))
+        .select(sum(transactions::amount_cents))
+        .first(conn)?;
+    Ok(result.unwrap_or(0))
+}
+
+/// Spending in cents, as a positive amount, between `start` and `end` on accounts `owner_id`
+/// can see. Transfers between accounts aren't spending.
+pub fn get_expenses_for_period(conn: &mut SqliteConnection, owner_id: i32, start: NaiveDateTime, end: NaiveDateTime) -> Result<i64, FinancerError> {
+    use crate::schema::transactions;
+
+    let result: Option<i64> = transactions::table
+        .filter(transactions::user_account_id.eq_any(member_account_ids(owner_id)))
+        .filter(transactions::amount_cents.lt(0))
+        .filter(not_transfer())
+        .filter(transactions::date.ge(format_db_datetime(start)))
+        .filter(transactions::date.lt(format_db_datetime(end)))
+        .select(sum(transactions::amount_cents))
+        .first(conn)?;
+    Ok(-result.unwrap_or(0))
+}
+
+/// Income and expenses for each of the last `months` calendar months, oldest first, ending with
+/// the one containing `today`. Transfers between accounts count as neither.
+pub fn get_monthly_savings(conn: &mut SqliteConnection, owner_id: i32, today: chrono::NaiveDate, months: usize) -> Result<Vec<MonthlySavings>, FinancerError> {
+    let mut report = Vec::with_capacity(months);
+    for back in (0..months as i32).rev() {
+        let (start, end) = period_range(Period::Monthly, today, -back);
+        report.push(MonthlySavings {
+            month: start.date(),
+            income: get_income_for_period(conn, owner_id, start, end)?,
+            expenses: get_expenses_for_period(conn, owner_id, start, end)?,
+        });
+    }
+    Ok(report)
+}
+
+/// Sum per category of the transactions `direction` matches between `start` and `end`, on
+/// accounts `owner_id` can see. Transfers between accounts are left out.
+fn category_sums(
+    conn: &mut SqliteConnection,
+    owner_id: i32,
+    start: NaiveDateTime,
+    end: NaiveDateTime,
+    direction: TransactionFilter,
+) -> Result<Vec<(String, i64)>, FinancerError> {
+    use crate::schema::transactions;
+
+    let rows: Vec<(String, Option<i64>)> = transactions::table
+        .filter(transactions::user_account_id.eq_any(member_account_ids(owner_id)))
+        .filter(direction)
+        .filter(not_transfer())
+        .filter(transactions::date.ge(format_db_datetime(start)))
+        .filter(transactions::date.lt(format_db_datetime(end)))
+        .group_by(transactions::category)
+        .select((transactions::category, sum(transactions::amount_cents)))
+        .load(conn)?;
+    Ok(rows.into_iter().map(|(cat, total)| (cat, total.unwrap_or(0))).collect())
+}
+
+/// Income and spending by category in the calendar month containing `month`, largest first.
+/// Transfers between accounts count as neither.
+pub fn get_income_statement(conn: &mut SqliteConnection, owner_id: i32, month: chrono::NaiveDate) -> Result<IncomeStatement, FinancerError> {
+    use crate::schema::transactions;
+
+    let (start, end) = period_range(Period::Monthly, month, 0);
+    let mut income = category_sums(conn, owner_id, start, end, Box::new(transactions::amount_cents.gt(0)))?;
+    let mut expenses: Vec<(String, i64)> = category_sums(conn, owner_id, start, end, Box::new(transactions::amount_cents.lt(0)))?
+        .into_iter()
+        .map(|(cat, total)| (cat, -total))
+        .collect();
+    for lines in [&mut income, &mut expenses] {
+        lines.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    }
+    Ok(IncomeStatement { month: start.date(), income, expenses })
+}
+
+/// Each category's net amount in the `months` calendar months ending with the one containing
+/// `today`, and in the same months a year earlier. Income categories come first, then the
+/// rest, each with the largest over the run first. Transfers are left out.
+pub fn get_category_trends(conn: &mut SqliteConnection, owner_id: i32, today: chrono::NaiveDate, months: usize) -> Result<Vec<CategoryTrend>, FinancerError> {
+    use crate::schema::transactions;
+    use diesel::dsl::sql;
+    use diesel::sql_types::Text;
+
+    let span = months + 12;
+    let month_keys: Vec<String> = (0..span as i32)
+        .rev()
+        .map(|back| month_key(period_range(Period::Monthly, today, -back).0.date()))
+        .collect();
+    let (start, _) = period_range(Period::Monthly, today, 1 - span as i32);
+    let (_, end) = period_range(Period::Monthly, today, 0);
+
+    // One pass over the window, summed per calendar month and category.
+    let rows: Vec<(String, String, Option<i64>)> = transactions::table
+        .filter(transactions::user_account_id.eq_any(member_account_ids(owner_id)))
+        .filter(transactions::amount_cents.ne(0))
+        .filter(not_transfer())
+        .filter(transactions::date.ge(format_db_datetime(start)))
+        .filter(transactions::date.lt(format_db_datetime(end)))
+        .group_by(sql::<Text>("substr(date, 1, 7), category"))
+        .select((sql::<Text>("substr(date, 1, 7)"), sql::<Text>("category"), sum(transactions::amount_cents)))
+        .load(conn)?;
+
+    let mut net: std::collections::BTreeMap<String, Vec<i64>> = std::collections::BTreeMap::new();
+    for (month, cat, total) in rows {
+        if let Some(slot) = month_keys.iter().position(|key| *key == month) {
+            net.entry(cat).or_insert_with(|| vec![0; span])[slot] = total.unwrap_or(0);
+        }
+    }
+    let mut trends: Vec<CategoryTrend> = net
+        .into_iter()
+        .map(|(cat, amounts)| CategoryTrend::from_net(cat, &amounts))
+        .filter(|trend| trend.amounts.iter().any(|a| *a != 0))
+        .collect();
+    trends.sort_by_key(|trend| (!trend.is_income, -trend.amounts.iter().sum::<i64>()));
+    Ok(trends)
+}
+
+/// Creates a budget for each draft in one go, skipping any whose category already has an
+/// active budget for the same period and type. Returns how many were created.
+pub fn create_budgets_from_drafts(conn: &mut SqliteConnection, owner_id: i32, drafts: &[BudgetDraft]) -> Result<usize, FinancerError> {
+    conn.transaction::<_, FinancerError, _>(|conn| {
+        let existing = get_user_budgets(conn, owner_id)?;
+        let mut created = 0;
+        for draft in drafts {
+            let Some((primary, extra)) = draft.categories.split_first() else {
+                continue;
+            };
+            let taken = existing.iter().any(|b| {
+                b.category == *primary
+                    && custom_range(b).is_none()
+                    && Period::from_str(&b.period) == draft.period
+                    && TargetType::from_str(&b.target_type) == draft.target_type
+            });
+            if taken {
+                continue;
+            }
+            let budget = create_budget(
+                conn,
+                NewBudget {
+                    user_id: owner_id,
+                    category: primary.clone(),
+                    limit_cents: draft.limit_cents,
+                    period: draft.period.to_str().to_string(),
+                    target_type: draft.target_type.to_str().to_string(),
+                    rollover_mode: RolloverMode::None.to_str().to_string(),
+                    rollover_cap_cents: None,
+                    alert_thresholds: crate::budgeting::DEFAULT_ALERT_THRESHOLDS.to_string(),
+                    name: draft.name.clone(),
+                    include_subcategories: false,
+                    starts_on: None,
+                    ends_on: None,
+                },
+            )?;
+            if !extra.is_empty() {
+                set_budget_scope(conn, owner_id, budget.id.unwrap_or(0), extra, &[])?;
+            }
+            created += 1;
+        }
+        Ok(created)
+    })
+}
+
+/// The user's repeating budgets as drafts, ready to save as a template. Account limits and
+/// budgets with their own dates are left out.
+pub fn get_budget_drafts(conn: &mut SqliteConnection, owner_id: i32) -> Result<Vec<BudgetDraft>, FinancerError> {
+    let mut drafts = Vec::new();
+    for budget in get_user_budgets(conn, owner_id)? {
+        if custom_range(&budget).is_some() {
+            continue;
+        }
+        let scope = get_budget_scope(conn, &budget)?;
+        drafts.push(BudgetDraft {
+            name: budget.name.clone(),
+            categories: scope.categories,
+            limit_cents: budget.limit_cents,
+            period: Period::from_str(&budget.period),
+            target_type: TargetType::from_str(&budget.target_type),
+        });
+    }
+    Ok(drafts)
+}
+
+pub fn get_budget_templates(conn: &mut SqliteConnection, owner_id: i32) -> Result<Vec<BudgetTemplate>, FinancerError> {
+    use crate::schema::budget_templates;
+
+    Ok(budget_templates::table
+        .filter(budget_templates::user_id.eq(owner_id))
+        .order(budget_templates::name.asc())
+        .load(conn)?)
+}
+
+fn find_budget_template(conn: &mut SqliteConnection, owner_id: i32, template_id: i32) -> Result<BudgetTemplate, FinancerError> {
+    use crate::schema::budget_templates;
+
+    budget_templates::table
+        .filter(budget_templates::id.eq(template_id))
+        .filter(budget_templates::user_id.eq(owner_id))
+        .first(conn)
+        .optional()?
+        .ok_or_else(|| FinancerError::NotFound(format!("Budget template {}", template_id)))
+}
+
+pub fn save_budget_template(
+    conn: &mut SqliteConnection,
+    owner_id: i32,
+    template_name: &str,
+    drafts: &[BudgetDraft],
+    now: NaiveDateTime,
+) -> Result<BudgetTemplate, FinancerError> {
+    use crate::schema::{budget_template_items, budget_templates};
+
+    let template_name = template_name.trim();
+    if template_name.is_empty() {
+        return Err(FinancerError::Validation("Give the template a name".to_string()));
+    }
+    if drafts.iter().all(|d| d.categories.is_empty()) {
+        return Err(FinancerError::Validation("A template needs at least one budget".to_string()));
+    }
+    let exists = budget_templates::table
+        .filter(budget_templates::user_id.eq(owner_id))
+        .filter(budget_templates::name.eq(template_name))
+        .count()
+        .get_result::<i64>(conn)?
+        > 0;
+    if exists {
+        return Err(FinancerError::Conflict(format!("There is already a template called {}", template_name)));
+    }
+
+    conn.transaction::<_, FinancerError, _>(|conn| {
+        diesel::insert_into(budget_templates::table)
+            .values(&NewBudgetTemplate {
+                user_id: owner_id,
+                name: template_name,
+                created_at: &format_db_datetime(now),
+            })
+            .execute(conn)?;
+        let template: BudgetTemplate = budget_templates::table.order(budget_templates::id.desc()).first(conn)?;
+        for draft in drafts {
+            let Some((primary, extra)) = draft.categories.split_first() else {
+                continue;
+            };
+            let extra = serde_json::to_string(extra).map_err(|e| FinancerError::CorruptData(e.to_string()))?;
+            diesel::insert_into(budget_template_items::table)
+                .values(&NewBudgetTemplateItem {
+                    template_id: template.id,
+                    name: draft.name.as_deref(),
+                    category: primary,
+                    extra_categories: &extra,
+                    limit_cents: draft.limit_cents,
+                    period: draft.period.to_str(),
+                    target_type: draft.target_type.to_str(),
+                })
+                .execute(conn)?;
+        }
+        Ok(template)
+    })
+}
+
+/// The budgets a saved template creates.
+pub fn get_budget_template_drafts(conn: &mut SqliteConnection, owner_id: i32, template_id: i32) -> Result<Vec<BudgetDraft>, FinancerError> {
+    use crate::schema::budget_template_items;
+
+    find_budget_template(conn, owner_id, template_id)?;
+    let items: Vec<BudgetTemplateItem> = budget_template_items::table
+        .filter(budget_template_items::template_id.eq(template_id))
+        .order(budget_template_items::id.asc())
+        .load(conn)?;
+    items
+        .into_iter()
+        .map(|item| {
+            let extra: Vec<String> = serde_json::from_str(&item.extra_categories)
+                .map_err(|e| FinancerError::CorruptData(format!("Template item {}: {}", item.id, e)))?;
+            Ok(BudgetDraft {
+                name: item.name,
+                categories: std::iter::once(item.category).chain(extra).collect(),
+                limit_cents: item.limit_cents,
+                period: Period::from_str(&item.period),
+                target_type: TargetType::from_str(&item.target_type),
+            })
+        })
+        .collect()
+}
+
+pub fn delete_budget_template(conn: &mut SqliteConnection, owner_id: i32, template_id: i32) -> Result<(), FinancerError> {
+    use crate::schema::{budget_template_items, budget_templates};
+
+    find_budget_template(conn, owner_id, template_id)?;
+    conn.transaction::<_, FinancerError, _>(|conn| {
+        diesel::delete(budget_template_items::table.filter(budget_template_items::template_id.eq(template_id))).execute(conn)?;
+        diesel::delete(budget_templates::table.filter(budget_templates::id.eq(template_id))).execute(conn)?;
+        Ok(())
+    })
+}
+
+/// Adds a savings goal tracked from `account_ids`, which `new_goal.user_id` must be able to see.
+pub fn create_savings_goal(conn: &mut SqliteConnection, new_goal: NewSavingsGoal, account_ids: &[i32]) -> Result<SavingsGoal, FinancerError> {
+    use crate::schema::{savings_goal_accounts, savings_goals};
+
+    let goal_name = new_goal.name.trim();
+    if goal_name.is_empty() {
+        return Err(FinancerError::Validation("Give the goal a name".to_string()));
+    }
+    if new_goal.target_cents <= 0 {
+        return Err(FinancerError::Validation("The target must be more than zero".to_string()));
+    }
+    if chrono::NaiveDate::parse_from_str(new_goal.target_date, "%Y-%m-%d").is_err() {
+        return Err(FinancerError::Validation("The target date must be in format YYYY-MM-DD".to_string()));
+    }
+    if !(0.0..=1.0).contains(&new_goal.expected_rate) {
+        return Err(FinancerError::Validation("The expected return must be between 0% and 100%".to_string()));
+    }
+    if account_ids.is_empty() {
+        return Err(FinancerError::Validation("Link the goal to at least one account".to_string()));
+    }
+    for account in account_ids {
+        find_account_with_role(conn, new_goal.user_id, *account, AccountRole::Viewer)?;
+    }
+    let exists = savings_goals::table
+        .filter(savings_goals::user_id.eq(new_goal.user_id))
+        .filter(savings_goals::name.eq(goal_name))
+        .count()
+        .get_result::<i64>(conn)?
+        > 0;
+    if exists {
+        return Err(FinancerError::Conflict(format!("There is already a goal called {}", goal_name)));
+    }
+
+    conn.transaction::<_, FinancerError, _>(|conn| {
+        diesel::insert_into(savings_goals::table)
+            .values(&NewSavingsGoal { name: goal_name, ..new_goal })
+            .execute(conn)?;
+        let goal: SavingsGoal = savings_goals::table.order(savings_goals::id.desc()).first(conn)?;
+        for account in account_ids {
+            diesel::insert_or_ignore_into(savings_goal_accounts::table)
+                .values((savings_goal_accounts::goal_id.eq(goal.id), savings_goal_accounts::account_id.eq(*account)))
+                .execute(conn)?;
+        }
+        Ok(goal)
+    })
+}
+
+/// Every goal of `owner_id` with the balance of its linked accounts and the average monthly
+/// amount paid into them over the last three whole months. Accounts the owner can no longer
+/// see are left out.
+pub fn get_savings_goals(conn: &mut SqliteConnection, owner_id: i32, today: chrono::NaiveDate) -> Result<Vec<GoalProgress>, FinancerError> {
+    use crate::schema::{savings_goal_accounts, savings_goals, transactions};
+
+    const PACE_MONTHS: u32 = 3;
+    let (start, end) = crate::budgeting::recent_months(today, PACE_MONTHS);
+    let goals: Vec<SavingsGoal> = savings_goals::table
+        .filter(savings_goals::user_id.eq(owner_id))
+        .order(savings_goals::target_date.asc())
+        .load(conn)?;
+
+    let mut progress = Vec::with_capacity(goals.len());
+    for goal in goals {
+        let account_ids: Vec<i32> = savings_goal_accounts::table
+            .filter(savings_goal_accounts::goal_id.eq(goal.id))
+            .filter(savings_goal_accounts::account_id.eq_any(member_account_ids(owner_id)))
+            .select(savings_goal_accounts::account_id)
+            .order(savings_goal_accounts::account_id.asc())
+            .load(conn)?;
+        let balances: Vec<f32> = crate::schema::accounts::table
+            .filter(crate::schema::accounts::id.eq_any(&account_ids))
+            .select(crate::schema::accounts::balance)
+            .load(conn)?;
+        let paid_in: Option<i64> = transactions::table
+            .filter(transactions::user_account_id.eq_any(&account_ids))
+            .filter(transactions::date.ge(format_db_datetime(start)))
+            .filter(transactions::date.lt(format_db_datetime(end)))
+            .select(sum(transactions::amount_cents))
+            .first(conn)?;
+        progress.push(GoalProgress {
+            goal,
+            account_ids,
+            saved: balances.iter().map(|b| (*b as f64 * 100.0).round() as i64).sum(),
+            monthly_pace: paid_in.unwrap_or(0) / PACE_MONTHS as i64,
+        });
+    }
+    Ok(progress)
+}
+
+pub fn delete_savings_goal(conn: &mut SqliteConnection, owner_id: i32, goal_id: i32) -> Result<(), FinancerError> {
+    use crate::schema::{savings_goal_accounts, savings_goals};
+
+    let found = savings_goals::table
+        .filter(savings_goals::id.eq(goal_id))
+        .filter(savings_goals::user_id.eq(owner_id))
+        .count()
+        .get_result::<i64>(conn)?;
+    if found == 0 {
+        return Err(FinancerError::NotFound(format!("Savings goal {}", goal_id)));
+    }
+    conn.transaction::<_, FinancerError, _>(|conn| {
+        diesel::delete(savings_goal_accounts::table.filter(savings_goal_accounts::goal_id.eq(goal_id))).execute(conn)?;
+        diesel::delete(savings_goals::table.filter(savings_goals::id.eq(goal_id))).execute(conn)?;
+        Ok(())
+    })
+}
+
+/// Largest number of snapshot rows written per statement, well under SQLite's bind limit.
+const SNAPSHOT_BATCH: usize = 500;
+
+fn first_transaction_day(conn: &mut SqliteConnection, account_id: i32) -> Result<Option<chrono::NaiveDate>, FinancerError> {
+    use crate::schema::transactions;
+
+    let first: Option<String> = transactions::table
+        .filter(transactions::user_account_id.eq(account_id))
+        .select(diesel::dsl::min(transactions::date))
+        .first(conn)?;
+    Ok(first.map(|when| parse_db_datetime(&when)).transpose()?.map(|when| when.date()))
+}
+
+/// Rewrites the snapshots of `account` from `from` through `to`, working back from its current
+/// balance through the transactions dated after each day. Returns the number of days written.
+fn write_balance_snapshots(conn: &mut SqliteConnection, account: &Account, from: chrono::NaiveDate, to: chrono::NaiveDate) -> Result<usize, FinancerError> {
+    use crate::schema::{balance_snapshots, transactions};
+
+    let rows: Vec<(String, i32)> = transactions::table
+        .filter(transactions::user_account_id.eq(account.id))
+        .filter(transactions::date.ge(format_db_datetime(from.and_hms_opt(0, 0, 0).unwrap())))
+        .select((transactions::date, transactions::amount_cents))
+        .load(conn)?;
+    let mut dated = Vec::with_capacity(rows.len());
+    for (when, cents) in rows {
+        dated.push((parse_db_datetime(&when)?.date(), cents as i64));
+    }
+
+    let current = (account.balance as f64 * 100.0).round() as i64;
+    let snapshots: Vec<BalanceSnapshot> = reconstruct_daily_balances(current, &dated, from, to)
+        .into_iter()
+        .map(|(on, cents)| BalanceSnapshot {
+            account_id: account.id,
+            day: on.format("%Y-%m-%d").to_string(),
+            balance_cents: cents as i32,
+        })
+        .collect();
+    for batch in snapshots.chunks(SNAPSHOT_BATCH) {
+        diesel::replace_into(balance_snapshots::table).values(batch).execute(conn)?;
     }
+    Ok(snapshots.len())
 }
 
-pub fn get_user_recurring_transactions(conn: &mut SqliteConnection, owner_id: i32) -> Result<Vec<RecurringTransaction>, Error> {
-    use crate::schema::recurring_transactions::dsl::*;
-
-    recurring_transactions
-        .filter(user_id.eq(owner_id))
-        .filter(active.eq(true))
-        .order(next_run_at.asc())
-        .load::<RecurringTransaction>(conn)
+/// Records today's balance of every active account, filling in the days since its last
+/// snapshot, which is rewritten too. An account without snapshots starts from its first
+/// transaction. Run at startup; returns the number of days written.
+pub fn update_balance_snapshots(conn: &mut SqliteConnection, today: chrono::NaiveDate) -> Result<usize, FinancerError> {
+    use crate::schema::balance_snapshots;
+
+    let active_accounts: Vec<Account> = crate::schema::accounts::table
+        .filter(crate::schema::accounts::active.eq(true))
+        .load(conn)?;
+    conn.transaction::<_, FinancerError, _>(|conn| {
+        let mut written = 0;
+        for account in &active_accounts {
+            let last: Option<String> = balance_snapshots::table
+                .filter(balance_snapshots::account_id.eq(account.id))
+                .select(diesel::dsl::max(balance_snapshots::day))
+                .first(conn)?;
+            let from = match last {
+                Some(last) => chrono::NaiveDate::parse_from_str(&last, "%Y-%m-%d")
+                    .map_err(|_| FinancerError::CorruptData(format!("Balance snapshot of account {} has a bad day '{}'", account.id, last)))?,
+                None => first_transaction_day(conn, account.id)?.unwrap_or(today),
+            };
+            written += write_balance_snapshots(conn, account, from.min(today), today)?;
+        }
+        Ok(written)
+    })
 }
 
-pub fn create_recurring_transaction(conn: &mut SqliteConnection, new_item: NewRecurringTransaction) -> Result<RecurringTransaction, Error> {
-    use crate::schema::recurring_transactions::dsl::*;
-
-    diesel::insert_into(recurring_transactions)
-        .values(&new_item)
-        .execute(conn)?;
-    recurring_transactions.order(id.desc()).first(conn)
+/// Throws away the snapshots of every active account `member_id` can see and rebuilds them
+/// from each account's first transaction through `today`. Returns the number of days written.
+pub fn backfill_balance_snapshots(conn: &mut SqliteConnection, member_id: i32, today: chrono::NaiveDate) -> Result<usize, FinancerError> {
+    use crate::schema::balance_snapshots;
+
+    let visible: Vec<Account> = crate::schema::accounts::table
+        .filter(crate::schema::accounts::id.eq_any(member_account_ids(member_id)))
+        .filter(crate::schema::accounts::active.eq(true))
+        .load(conn)?;
+    conn.transaction::<_, FinancerError, _>(|conn| {
+        let mut written = 0;
+        for account in &visible {
+            diesel::delete(balance_snapshots::table.filter(balance_snapshots::account_id.eq(account.id))).execute(conn)?;
+            let from = first_transaction_day(conn, account.id)?.unwrap_or(today).min(today);
+            written += write_balance_snapshots(conn, account, from, today)?;
+        }
+        Ok(written)
+    })
 }
 
-pub fn update_recurring_transaction(conn: &mut SqliteConnection, owner_id: i32, item_id: i32, changes: NewRecurringTransaction) -> Result<usize, Error> {
-    use crate::schema::recurring_transactions::dsl::*;
+/// Snapshots of the accounts `member_id` can see, oldest day first, from `from` if given.
+pub fn get_balance_snapshots(conn: &mut SqliteConnection, member_id: i32, from: Option<chrono::NaiveDate>) -> Result<Vec<BalanceSnapshot>, FinancerError> {
+    use crate::schema::balance_snapshots;
 
-    diesel::update(recurring_transactions.filter(id.eq(item_id)).filter(user_id.eq(owner_id)))
-        .set((
-            account_id.eq(changes.account_id),
-            contact_id.eq(changes.contact_id),
-            amount.eq(changes.amount),
-            category.eq(changes.category),
-            next_run_at.eq(changes.next_run_at),
-            frequency.eq(changes.frequency),
-        ))
-        .execute(conn)
+    let mut query = balance_snapshots::table
+        .filter(balance_snapshots::account_id.eq_any(member_account_ids(member_id)))
+        .into_boxed();
+    if let Some(from) = from {
+        query = query.filter(balance_snapshots::day.ge(from.format("%Y-%m-%d").to_string()));
+    }
+    Ok(query
+        .order((balance_snapshots::day.asc(), balance_snapshots::account_id.asc()))
+        .load(conn)?)
 }
 
-pub fn delete_recurring_transaction(conn: &mut SqliteConnection, owner_id: i32, item_id: i32) -> Result<usize, Error> {
-    use crate::schema::recurring_transactions::dsl::*;
+pub fn get_user_envelopes(conn: &mut SqliteConnection, owner_id: i32) -> Result<Vec<Envelope>, FinancerError> {
+    use crate::schema::envelopes;
 
-    diesel::delete(recurring_transactions.filter(id.eq(item_id)).filter(user_id.eq(owner_id)))
-        .execute(conn)
+    Ok(envelopes::table
+        .filter(envelopes::user_id.eq(owner_id))
+        .order(envelopes::category.asc())
+        .load(conn)?)
 }
 
-pub fn get_user_recurring_transfers(conn: &mut SqliteConnection, owner_id: i32) -> Result<Vec<RecurringTransfer>, Error> {
-    use crate::schema::recurring_transfers::dsl::*;
+fn find_envelope(conn: &mut SqliteConnection, owner_id: i32, envelope_id: i32) -> Result<Envelope, FinancerError> {
+    use crate::schema::envelopes;
 
-    recurring_transfers
-        .filter(user_id.eq(owner_id))
-        .filter(active.eq(true))
-        .order(next_run_at.asc())
-        .load::<RecurringTransfer>(conn)
+    envelopes::table
+        .filter(envelopes::id.eq(envelope_id))
+        .filter(envelopes::user_id.eq(owner_id))
+        .first(conn)
+        .optional()?
+        .ok_or_else(|| FinancerError::NotFound(format!("Envelope {}", envelope_id)))
 }
 
-pub fn create_recurring_transfer(conn: &mut SqliteConnection, new_item: NewRecurringTransfer) -> Result<RecurringTransfer, Error> {
-    use crate::schema::recurring_transfers::dsl::*;
+/// Starts an envelope for a category. Spending in the category draws it down from the start
+/// of `month` onwards; earlier spending is left alone.
+pub fn create_envelope(conn: &mut SqliteConnection, owner_id: i32, cat: &str, month: chrono::NaiveDate) -> Result<Envelope, FinancerError> {
+    use crate::schema::envelopes;
 
-    diesel::insert_into(recurring_transfers)
-        .values(&new_item)
+    let cat = cat.trim();
+    if cat.is_empty() {
+        return Err(FinancerError::Validation("Choose a category for the envelope".to_string()));
+    }
+    if cat == "Transfer" {
+        return Err(FinancerError::Validation("Transfers move money between accounts and can't have an envelope".to_string()));
+    }
+    let exists = envelopes::table
+        .filter(envelopes::user_id.eq(owner_id))
+        .filter(envelopes::category.eq(cat))
+        .count()
+        .get_result::<i64>(conn)?
+        > 0;
+    if exists {
+        return Err(FinancerError::Conflict(format!("There is already an envelope for {}", cat)));
+    }
+    let starts_on = period_range(Period::Monthly, month, 0).0.format("%Y-%m-%d").to_string();
+    diesel::insert_into(envelopes::table)
+        .values(&NewEnvelope {
+            user_id: owner_id,
+            category: cat,
+            starts_on: &starts_on,
+        })
         .execute(conn)?;
-    recurring_transfers.order(id.desc()).first(conn)
+    Ok(envelopes::table.order(envelopes::id.desc()).first(conn)?)
 }
 
-pub fn update_recurring_transfer(conn: &mut SqliteConnection, owner_id: i32, item_id: i32, changes: NewRecurringTransfer) -> Result<usize, Error> {
-    use crate::schema::recurring_transfers::dsl::*;
+/// Removes an envelope and its assignments; whatever it held goes back to To Be Assigned.
+pub fn delete_envelope(conn: &mut SqliteConnection, owner_id: i32, envelope_id: i32) -> Result<(), FinancerError> {
+    use crate::schema::{envelope_assignments, envelopes};
 
-    diesel::update(recurring_transfers.filter(id.eq(item_id)).filter(user_id.eq(owner_id)))
-        .set((
-            from_account_id.eq(changes.from_account_id),
-            to_account_id.eq(changes.to_account_id),
-            amount.eq(changes.amount),
-            next_run_at.eq(changes.next_run_at),
-            frequency.eq(changes.frequency),
-        ))
-        .execute(conn)
+    find_envelope(conn, owner_id, envelope_id)?;
+    conn.transaction::<_, FinancerError, _>(|conn| {
+        diesel::delete(envelope_assignments::table.filter(envelope_assignments::envelope_id.eq(envelope_id))).execute(conn)?;
+        diesel::delete(envelopes::table.filter(envelopes::id.eq(envelope_id))).execute(conn)?;
+        Ok(())
+    })
 }
 
-pub fn delete_recurring_transfer(conn: &mut SqliteConnection, owner_id: i32, item_id: i32) -> Result<usize, Error> {
-    use crate::schema::recurring_transfers::dsl::*;
-
-    diesel::delete(recurring_transfers.filter(id.eq(item_id)).filter(user_id.eq(owner_id)))
-        .execute(conn)
+/// Assigns money from To Be Assigned to an envelope for `month`; a negative amount returns it.
+/// Assigning more than is available is allowed and leaves To Be Assigned negative.
+pub fn assign_to_envelope(
+    conn: &mut SqliteConnection,
+    owner_id: i32,
+    envelope_id: i32,
+    month: chrono::NaiveDate,
+    cents: i32,
+    now: NaiveDateTime,
+) -> Result<(), FinancerError> {
+    use crate::schema::envelope_assignments;
+
+    if cents == 0 {
+        return Err(FinancerError::Validation("Amount must be non-zero".to_string()));
+    }
+    find_envelope(conn, owner_id, envelope_id)?;
+    diesel::insert_into(envelope_assignments::table)
+        .values(&NewEnvelopeAssignment {
+            envelope_id,
+            month: &month_key(month),
+            amount_cents: cents,
+            created_at: &format_db_datetime(now),
+        })
+        .execute(conn)?;
+    Ok(())
 }
 
-pub fn process_due_recurring(conn: &mut SqliteConnection, owner_id: i32, now: chrono::NaiveDateTime) -> Result<usize, Error> {
-    conn.transaction::<_, Error, _>(|conn| {
-        use crate::schema::{recurring_transactions, recurring_transfers};
-        use diesel::ExpressionMethods;
-        use diesel::QueryDsl;
-
-        let now_str = now.format("%Y-%m-%d %H:%M:%S").to_string();
-
-        let due_txs: Vec<RecurringTransaction> = recurring_transactions::table
-            .filter(recurring_transactions::user_id.eq(owner_id))
-            .filter(recurring_transactions::active.eq(true))
-            .filter(recurring_transactions::next_run_at.le(&now_str))
-            .order(recurring_transactions::next_run_at.asc())
-            .load(conn)?;
+pub fn move_between_envelopes(
+    conn: &mut SqliteConnection,
+    owner_id: i32,
+    from_envelope: i32,
+    to_envelope: i32,
+    month: chrono::NaiveDate,
+    cents: i32,
+    now: NaiveDateTime,
+) -> Result<(), FinancerError> {
+    if cents <= 0 {
+        return Err(FinancerError::Validation("Amount must be positive".to_string()));
+    }
+    if from_envelope == to_envelope {
+        return Err(FinancerError::Validation("Choose two different envelopes".to_string()));
+    }
+    conn.transaction::<_, FinancerError, _>(|conn| {
+        assign_to_envelope(conn, owner_id, from_envelope, month, -cents, now)?;
+        assign_to_envelope(conn, owner_id, to_envelope, month, cents, now)
+    })
+}
 
-        let due_transfers: Vec<RecurringTransfer> = recurring_transfers::table
-            .filter(recurring_transfers::user_id.eq(owner_id))
-            .filter(recurring_transfers::active.eq(true))
-            .filter(recurring_transfers::next_run_at.le(&now_str))
-            .order(recurring_transfers::next_run_at.asc())
+/// Every envelope's assigned, activity and available amounts for the month containing `month`.
+pub fn get_envelope_statuses(conn: &mut SqliteConnection, owner_id: i32, month: chrono::NaiveDate) -> Result<Vec<EnvelopeStatus>, FinancerError> {
+    use crate::schema::envelope_assignments;
+
+    let (month_start, month_end) = period_range(Period::Monthly, month, 0);
+    let key = month_key(month);
+    let mut statuses = Vec::new();
+    for envelope in get_user_envelopes(conn, owner_id)? {
+        let rows: Vec<(String, i32)> = envelope_assignments::table
+            .filter(envelope_assignments::envelope_id.eq(envelope.id))
+            .filter(envelope_assignments::month.le(&key))
+            .select((envelope_assignments::month, envelope_assignments::amount_cents))
             .load(conn)?;
+        let assigned: i64 = rows.iter().filter(|(m, _)| *m == key).map(|(_, c)| *c as i64).sum();
+        let assigned_to_date: i64 = rows.iter().map(|(_, c)| *c as i64).sum();
+
+        let starts = parse_db_datetime(&format!("{} 00:00:00", envelope.starts_on))?;
+        let (activity, activity_to_date) = if month_end <= starts {
+            (0, 0)
+        } else {
+            (
+                get_spend_for_category_period(conn, owner_id, &envelope.category, month_start.max(starts), month_end)?,
+                get_spend_for_category_period(conn, owner_id, &envelope.category, starts, month_end)?,
+            )
+        };
+        statuses.push(EnvelopeStatus {
+            envelope_id: envelope.id,
+            category: envelope.category,
+            assigned,
+            activity,
+            available: assigned_to_date + activity_to_date,
+        });
+    }
+    Ok(statuses)
+}
 
-        let mut processed = 0usize;
-
-        for item in due_txs {
-            let mut dt = chrono::NaiveDateTime::parse_from_str(&item.next_run_at, "%Y-%m-%d %H:%M:%S")
-                .unwrap_or(now);
-            let period = Period::from_str(&item.frequency);
-            let mut iterations = 0;
-
-            while dt <= now && iterations < 100 {
-                create_transaction(
-                    conn,
-                    item.account_id,
-                    item.contact_id,
-                    item.amount,
-                    item.category.clone(),
-                    dt.format("%Y-%m-%d %H:%M:%S").to_string(),
-                )?;
-                processed += 1;
-                dt = add_period(dt, period);
-                iterations += 1;
-            }
+/// Money not yet given a job, in cents: income received from the month the first envelope
+/// starts through the end of the month containing `month`, less everything assigned to
+/// envelopes. Assignments to later months are already taken out.
+pub fn get_available_to_assign(conn: &mut SqliteConnection, owner_id: i32, month: chrono::NaiveDate) -> Result<i64, FinancerError> {
+    use crate::schema::envelope_assignments;
 
-            diesel::update(recurring_transactions::table.filter(recurring_transactions::id.eq(item.id)))
-                .set(recurring_transactions::next_run_at.eq(dt.format("%Y-%m-%d %H:%M:%S").to_string()))
-                .execute(conn)?;
-        }
+    let envelopes = get_user_envelopes(conn, owner_id)?;
+    let Some(first_start) = envelopes.iter().map(|e| e.starts_on.as_str()).min() else {
+        return Ok(0);
+    };
+    let starts = parse_db_datetime(&format!("{} 00:00:00", first_start))?;
+    let (_, month_end) = period_range(Period::Monthly, month, 0);
+    let income = if month_end <= starts { 0 } else { get_income_for_period(conn, owner_id, starts, month_end)? };
+
+    let assigned: Option<i64> = envelope_assignments::table
+        .filter(envelope_assignments::envelope_id.eq_any(envelopes.iter().map(|e| e.id).collect::<Vec<_>>()))
+        .select(sum(envelope_assignments::amount_cents))
+        .first(conn)?;
+    Ok(income - assigned.unwrap_or(0))
+}
 
-        for item in due_transfers {
-            let mut dt = chrono::NaiveDateTime::parse_from_str(&item.next_run_at, "%Y-%m-%d %H:%M:%S")
-                .unwrap_or(now);
-            let period = Period::from_str(&item.frequency);
-            let mut iterations = 0;
+pub fn get_spend_for_category_period(
+    conn: &mut SqliteConnection,
+    owner_id: i32,
+    cat: &str,
+    start: NaiveDateTime,
+    end: NaiveDateTime,
+) -> Result<i64, FinancerError> {
+    get_spend_for_scope_period(conn, owner_id, &BudgetScope::category(cat), start, end)
+}
 
-            while dt <= now && iterations < 100 {
-                create_transfer(
-                    conn,
-                    item.from_account_id,
-                    item.to_account_id,
-                    item.amount,
-                    dt.format("%Y-%m-%d %H:%M:%S").to_string(),
-                )?;
-                processed += 1;
-                dt = add_period(dt, period);
-                iterations += 1;
-            }
+/// Net amount in cents of the transactions in `scope` on accounts `owner_id` can see, from
+/// `start` up to but not including `end`. Expenses are negative.
+pub fn get_spend_for_scope_period(
+    conn: &mut SqliteConnection,
+    owner_id: i32,
+    scope: &BudgetScope,
+    start: NaiveDateTime,
+    end: NaiveDateTime,
+) -> Result<i64, FinancerError> {
+    use crate::schema::transactions;
 
-            diesel::update(recurring_transfers::table.filter(recurring_transfers::id.eq(item.id)))
-                .set(recurring_transfers::next_run_at.eq(dt.format("%Y-%m-%d %H:%M:%S").to_string()))
-                .execute(conn)?;
-        }
+    let result: Option<i64> = transactions::table
+        .filter(scope_filter(owner_id, scope))
+        .filter(transactions::date.ge(format_db_datetime(start)))
+        .filter(transactions::date.lt(format_db_datetime(end)))
+        .select(sum(transactions::amount_cents))
+        .first(conn)?;
 
-        Ok(processed)
-    })
+    Ok(result.unwrap_or(0))
 }
 
-pub fn get_userid_by_username(conn: &mut SqliteConnection, search_username: &str) -> Result<User, Error> {
-    users.filter(username.eq(search_username)).first::<User>(conn)
-}
+type TransactionFilter = Box<dyn BoxableExpression<crate::schema::transactions::table, diesel::sqlite::Sqlite, SqlType = diesel::sql_types::Bool>>;
 
-#[allow(dead_code)]
-pub fn create_contact(conn: &mut SqliteConnection, new_name: &str, new_user: i32) -> Result<usize, Error> {
-    let new_contact = NewContact {
-        name: new_name,
-        user: new_user,
-    };
+/// Leaves out both legs of transfers between accounts, whether posted as a transfer or entered
+/// by hand under the "Transfer" category.
+fn not_transfer() -> TransactionFilter {
+    use crate::schema::transactions;
 
-    diesel::insert_into(contacts).values(&new_contact).execute(conn)
+    Box::new(transactions::is_transfer.eq(false).and(transactions::category.ne("Transfer")))
 }
 
-pub fn create_transaction(
-    conn: &mut SqliteConnection,
-    new_user_account: i32,
-    new_contact_id: i32,
-    new_amount: f32,
-    new_category: String,
-    new_date: String,
-) -> Result<usize, Error> {
-    use crate::schema::accounts::dsl::*;
-    
-    let cents = (new_amount * 100.0) as i32;
-    
-    let current_account: Account = accounts.filter(id.eq(new_user_account)).first(conn)?;
-    let new_balance = current_account.balance + new_amount;
-    
-    let new_transaction = NewTransaction {
-        user_account_id: new_user_account,
-        contact_id: new_contact_id,
-        amount: new_amount,
-        category: new_category,
-        date: new_date,
-        amount_cents: cents,
-        balance_after: new_balance,
-    };
-    
-    let result = diesel::insert_into(transactions).values(&new_transaction).execute(conn)?;
-    
-    update_account_balance(conn, new_user_account, new_amount)?;
-    
-    Ok(result)
+/// Matches the transactions a budget scope counts, on accounts `owner_id` can see.
+fn scope_filter(owner_id: i32, scope: &BudgetScope) -> TransactionFilter {
+    use crate::schema::transactions;
+
+    let mut in_categories: TransactionFilter = Box::new(transactions::category.eq_any(scope.categories.clone()));
+    if scope.include_subcategories {
+        for parent in &scope.categories {
+            let escaped = parent.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+            let pattern = format!("{}{}%", escaped, crate::budgeting::SUBCATEGORY_SEPARATOR);
+            in_categories = Box::new(in_categories.or(transactions::category.like(pattern).escape('\\')));
+        }
+    }
+    let mut filter: TransactionFilter = Box::new(in_categories.and(transactions::user_account_id.eq_any(member_account_ids(owner_id))));
+    if !scope.account_ids.is_empty() {
+        filter = Box::new(filter.and(transactions::user_account_id.eq_any(scope.account_ids.clone())));
+    }
+    filter
 }
 
-pub fn create_transfer(
-    conn: &mut SqliteConnection,
-    from_account_id: i32,
-    to_account_id: i32,
-    transfer_amount: f32,
-    transfer_date: String,
-) -> Result<(), Error> {
-    conn.transaction::<_, Error, _>(|conn| {
-        create_transaction(
-            conn,
-            from_account_id,
-            0,
-            -transfer_amount.abs(),
-            "Transfer".to_string(),
-            transfer_date.clone(),
-        )?;
-        
-        create_transaction(
-            conn,
-            to_account_id,
-            0,
-            transfer_amount.abs(),
-            "Transfer".to_string(),
-            transfer_date,
-        )?;
-        
-        Ok(())
+/// The categories and accounts a budget counts.
+pub fn get_budget_scope(conn: &mut SqliteConnection, budget: &Budget) -> Result<BudgetScope, FinancerError> {
+    use crate::schema::{budget_accounts, budget_categories};
+
+    let budget_id = budget.id.unwrap_or(0);
+    let extra: Vec<String> = budget_categories::table
+        .filter(budget_categories::budget_id.eq(budget_id))
+        .filter(budget_categories::category.ne(&budget.category))
+        .select(budget_categories::category)
+        .order(budget_categories::category.asc())
+        .load(conn)?;
+    let account_ids: Vec<i32> = budget_accounts::table
+        .filter(budget_accounts::budget_id.eq(budget_id))
+        .select(budget_accounts::account_id)
+        .order(budget_accounts::account_id.asc())
+        .load(conn)?;
+    Ok(BudgetScope {
+        categories: std::iter::once(budget.category.clone()).chain(extra).collect(),
+        include_subcategories: budget.include_subcategories,
+        account_ids,
     })
 }
 
-pub fn verify_user(conn: &mut SqliteConnection, login_username: &str, login_password: &str) -> Result<bool, Error> {
-    match users.filter(username.eq(login_username)).first::<User>(conn){
-        Ok(u) => {
-            let parsed_hash = PasswordHash::new(&u.password_hash).expect("Error parsing password hash");
-            let argon2 = Argon2::default();
-            Ok(argon2.verify_password(login_password.as_bytes(), &parsed_hash).is_ok())
+/// Sets the categories a budget covers besides its own and the accounts it is limited to;
+/// no accounts means all of them. Each account must be one the owner can see.
+pub fn set_budget_scope(
+    conn: &mut SqliteConnection,
+    owner_id: i32,
+    budget_id: i32,
+    extra_categories: &[String],
+    account_ids: &[i32],
+) -> Result<(), FinancerError> {
+    use crate::schema::{account_members, budget_accounts, budget_categories, budgets};
+
+    let budget: Budget = budgets::table
+        .filter(budgets::id.eq(budget_id))
+        .filter(budgets::user_id.eq(owner_id))
+        .first(conn)
+        .optional()?
+        .ok_or_else(|| FinancerError::NotFound(format!("Budget {}", budget_id)))?;
+    for account_id in account_ids {
+        let member = account_members::table
+            .filter(account_members::account_id.eq(account_id))
+            .filter(account_members::user_id.eq(owner_id))
+            .count()
+            .get_result::<i64>(conn)?
+            > 0;
+        if !member {
+            return Err(FinancerError::PermissionDenied(format!("Account {} isn't one of yours", account_id)));
         }
-        Err(diesel::result::Error::NotFound) => Ok(false),
-        Err(e) => Err(e),
     }
+    let mut categories: Vec<&str> = extra_categories
+        .iter()
+        .map(|c| c.trim())
+        .filter(|c| !c.is_empty() && *c != budget.category)
+        .collect();
+    categories.sort_unstable();
+    categories.dedup();
+    let mut accounts_in_scope = account_ids.to_vec();
+    accounts_in_scope.sort_unstable();
+    accounts_in_scope.dedup();
+
+    conn.transaction::<_, FinancerError, _>(|conn| {
+        diesel::delete(budget_categories::table.filter(budget_categories::budget_id.eq(budget_id))).execute(conn)?;
+        diesel::delete(budget_accounts::table.filter(budget_accounts::budget_id.eq(budget_id))).execute(conn)?;
+        for cat in categories {
+            diesel::insert_into(budget_categories::table)
+                .values((budget_categories::budget_id.eq(budget_id), budget_categories::category.eq(cat)))
+                .execute(conn)?;
+        }
+        for account_id in accounts_in_scope {
+            diesel::insert_into(budget_accounts::table)
+                .values((budget_accounts::budget_id.eq(budget_id), budget_accounts::account_id.eq(account_id)))
+                .execute(conn)?;
+        }
+        forget_budget_carries(conn, budget_id)
+    })
 }
 
-pub fn create_budget(conn: &mut SqliteConnection, new_budget: NewBudget) -> Result<Budget, Error> {
-    use crate::schema::budgets::dsl::*;
-    
-    diesel::insert_into(budgets)
-        .values(&new_budget)
-        .execute(conn)?;
-    
-    budgets.order(id.desc()).first(conn)
+/// Net amount in cents of the transactions a budget counts between `start` and `end`,
+/// on the accounts its owner can see.
+pub fn get_budget_spend(conn: &mut SqliteConnection, budget: &Budget, start: NaiveDateTime, end: NaiveDateTime) -> Result<i64, FinancerError> {
+    let scope = get_budget_scope(conn, budget)?;
+    get_spend_for_scope_period(conn, budget.user_id, &scope, start, end)
 }
 
-pub fn get_user_budgets(conn: &mut SqliteConnection, owner_id: i32) -> Result<Vec<Budget>, Error> {
-    use crate::schema::budgets::dsl::*;
-    
-    budgets
-        .filter(user_id.eq(owner_id))
-        .filter(active.eq(true))
-        .load::<Budget>(conn)
-}
+/// The part of `get_budget_spend` that recurring items posted.
+pub fn get_budget_recurring_spend(conn: &mut SqliteConnection, budget: &Budget, start: NaiveDateTime, end: NaiveDateTime) -> Result<i64, FinancerError> {
+    use crate::schema::transactions;
 
-pub fn update_budget(conn: &mut SqliteConnection, budget_id: i32, changes: NewBudget) -> Result<Budget, Error> {
-    use crate::schema::budgets::dsl::*;
-    
-    diesel::update(budgets.filter(id.eq(budget_id)))
-        .set((
-            category.eq(&changes.category),
-            limit_cents.eq(&changes.limit_cents),
-            period.eq(&changes.period),
-            target_type.eq(&changes.target_type),
-        ))
-        .execute(conn)?;
-    
-    budgets.filter(id.eq(budget_id)).first(conn)
-}
+    let scope = get_budget_scope(conn, budget)?;
+    let result: Option<i64> = transactions::table
+        .filter(scope_filter(budget.user_id, &scope))
+        .filter(transactions::recurring_id.is_not_null())
+        .filter(transactions::date.ge(format_db_datetime(start)))
+        .filter(transactions::date.lt(format_db_datetime(end)))
+        .select(sum(transactions::amount_cents))
+        .first(conn)?;
 
-pub fn delete_budget(conn: &mut SqliteConnection, budget_id: i32) -> Result<usize, Error> {
-    use crate::schema::budgets::dsl::*;
-    
-    diesel::delete(budgets.filter(id.eq(budget_id)))
-        .execute(conn)
+    Ok(result.unwrap_or(0))
 }
 
-pub fn get_spend_for_category_period(
+pub fn get_spend_by_category_period(
     conn: &mut SqliteConnection,
     owner_id: i32,
-    cat: &str,
     start: NaiveDateTime,
     end: NaiveDateTime,
-) -> Result<i64, Error> {
+) -> Result<Vec<(String, i64)>, FinancerError> {
     use crate::schema::transactions::dsl::*;
     use crate::schema::accounts;
+    use diesel::dsl::sum;
     
     let start_str = start.format("%Y-%m-%d %H:%M:%S").to_string();
     let end_str = end.format("%Y-%m-%d %H:%M:%S").to_string();
     
-    let result: Option<Option<i64>> = transactions
+    let results = transactions
         .inner_join(accounts::table.on(user_account_id.eq(accounts::id)))
-        .filter(accounts::user_id.eq(owner_id))
-        .filter(category.eq(cat))
+        .filter(accounts::id.eq_any(member_account_ids(owner_id)))
         .filter(date.ge(start_str))
         .filter(date.lt(end_str))
-        .select(sum(amount_cents))
-        .first(conn)
-        .optional()?;
-    
-    Ok(result.flatten().unwrap_or(0))
+        .group_by(category)
+        .select((category, sum(amount_cents)))
+        .load::<(String, Option<i64>)>(conn)?;
+
+    Ok(results
+        .into_iter()
+        .map(|(cat, amt)| (cat, amt.unwrap_or(0)))
+        .collect())
 }
 
-#[allow(dead_code)]
-pub fn get_spend_by_category_period(
+/// Transactions dated on or after `from`, oldest first. Their amounts are already in the
+/// account balances even though they haven't happened yet.
+pub fn get_scheduled_transactions(conn: &mut SqliteConnection, owner_id: i32, from: NaiveDateTime) -> Result<Vec<Transaction>, FinancerError> {
+    use crate::schema::transactions::dsl::*;
+    use crate::schema::accounts;
+
+    Ok(transactions
+        .inner_join(accounts::table.on(user_account_id.eq(accounts::id)))
+        .filter(accounts::id.eq_any(member_account_ids(owner_id)))
+        .filter(date.ge(format_db_datetime(from)))
+        .order(date.asc())
+        .select((id, user_account_id, contact_id, amount, category, date, amount_cents, balance_after, created_by, recurring_id, recurring_kind, is_transfer))
+        .load::<Transaction>(conn)?)
+}
+
+/// Average daily spending per account and category between `start` and `end`. Entries posted
+/// by recurring items and transfers are left out, since a forecast schedules those itself.
+pub fn get_spend_estimates(
     conn: &mut SqliteConnection,
     owner_id: i32,
     start: NaiveDateTime,
     end: NaiveDateTime,
-) -> Result<Vec<(String, i64)>, Error> {
+) -> Result<Vec<SpendEstimate>, FinancerError> {
     use crate::schema::transactions::dsl::*;
     use crate::schema::accounts;
-    use diesel::dsl::sum;
-    
-    let start_str = start.format("%Y-%m-%d %H:%M:%S").to_string();
-    let end_str = end.format("%Y-%m-%d %H:%M:%S").to_string();
-    
-    transactions
+
+    let days = (end - start).num_days();
+    if days <= 0 {
+        return Err(FinancerError::Validation("Spending history must cover at least one day".to_string()));
+    }
+    let totals = transactions
         .inner_join(accounts::table.on(user_account_id.eq(accounts::id)))
-        .filter(accounts::user_id.eq(owner_id))
-        .filter(date.ge(start_str))
-        .filter(date.lt(end_str))
-        .group_by(category)
-        .select((category, sum(amount_cents)))
-        .load::<(String, Option<i64>)>(conn)
-        .map(|results| {
-            results
-                .into_iter()
-                .map(|(cat, amt)| (cat, amt.unwrap_or(0)))
-                .collect()
+        .filter(accounts::id.eq_any(member_account_ids(owner_id)))
+        .filter(accounts::active.eq(true))
+        .filter(amount_cents.lt(0))
+        .filter(recurring_id.is_null())
+        .filter(is_transfer.eq(false))
+        .filter(category.ne("Transfer"))
+        .filter(date.ge(format_db_datetime(start)))
+        .filter(date.lt(format_db_datetime(end)))
+        .group_by((user_account_id, category))
+        .select((user_account_id, category, sum(amount_cents)))
+        .load::<(i32, String, Option<i64>)>(conn)?;
+
+    Ok(totals
+        .into_iter()
+        .filter_map(|(account, cat, cents)| {
+            let spent = -cents? as f32 / 100.0;
+            Some(SpendEstimate {
+                account_id: account,
+                category: cat,
+                daily_amount: spent / days as f32,
+            })
         })
+        .collect())
+}
+
+pub fn get_low_balance_threshold(conn: &mut SqliteConnection, owner_id: i32) -> Result<f32, FinancerError> {
+    Ok(find_user(conn, owner_id)?.low_balance_threshold)
+}
+
+pub fn set_low_balance_threshold(conn: &mut SqliteConnection, owner_id: i32, threshold: f32) -> Result<(), FinancerError> {
+    if !threshold.is_finite() {
+        return Err(FinancerError::Validation("Threshold must be a number".to_string()));
+    }
+    find_user(conn, owner_id)?;
+    diesel::update(users.filter(crate::schema::users::dsl::id.eq(owner_id)))
+        .set(crate::schema::users::dsl::low_balance_threshold.eq(threshold))
+        .execute(conn)?;
+    Ok(())
 }
 
-pub fn get_user_transactions(conn: &mut SqliteConnection, owner_id: i32) -> Result<Vec<Transaction>, Error> {
+pub fn get_user_transactions(conn: &mut SqliteConnection, owner_id: i32) -> Result<Vec<Transaction>, FinancerError> {
     use crate::schema::transactions::dsl::*;
     use crate::schema::accounts;
     
-    transactions
+    Ok(transactions
         .inner_join(accounts::table.on(user_account_id.eq(accounts::id)))
-        .filter(accounts::user_id.eq(owner_id))
+        .filter(accounts::id.eq_any(member_account_ids(owner_id)))
         .order(date.desc())
-        .select((id, user_account_id, contact_id, amount, category, date, amount_cents, balance_after))
-        .load::<Transaction>(conn)
+        .select((id, user_account_id, contact_id, amount, category, date, amount_cents, balance_after, created_by, recurring_id, recurring_kind, is_transfer))
+        .load::<Transaction>(conn)?)
 }
 
-pub fn get_user_categories(conn: &mut SqliteConnection, owner_id: i32) -> Result<Vec<String>, Error> {
+pub fn get_user_categories(conn: &mut SqliteConnection, owner_id: i32) -> Result<Vec<String>, FinancerError> {
     use crate::schema::transactions::dsl::*;
     use crate::schema::accounts;
     use crate::schema::budgets;
     
     let tx_categories: Vec<String> = transactions
         .inner_join(accounts::table.on(user_account_id.eq(accounts::id)))
-        .filter(accounts::user_id.eq(owner_id))
+        .filter(accounts::id.eq_any(member_account_ids(owner_id)))
         .select(category)
         .distinct()
         .load::<String>(conn)?;
@@ -540,7 +3278,7 @@ pub fn get_user_categories(conn: &mut SqliteConnection, owner_id: i32) -> Result
         .load::<String>(conn)?;
     
     let mut all_categories: Vec<String> = tx_categories.into_iter()
-        .chain(budget_categories.into_iter())
+        .chain(budget_categories)
         .collect();
     all_categories.sort();
     all_categories.dedup();
@@ -548,6 +3286,8 @@ pub fn get_user_categories(conn: &mut SqliteConnection, owner_id: i32) -> Result
     Ok(all_categories)
 }
 
+/// Edits a transaction without a permission check. The UI goes through `update_transaction_as`.
+#[allow(dead_code)]
 pub fn update_transaction(
     conn: &mut SqliteConnection,
     transaction_id: i32,
@@ -555,11 +3295,22 @@ pub fn update_transaction(
     new_amount: f32,
     new_category: String,
     new_date: String,
-) -> Result<usize, Error> {
+) -> Result<usize, FinancerError> {
+    update_transaction_at(conn, transaction_id, new_user_account, new_amount, new_category, new_date, chrono::Local::now().naive_local())
+}
+
+fn update_transaction_at(
+    conn: &mut SqliteConnection,
+    transaction_id: i32,
+    new_user_account: i32,
+    new_amount: f32,
+    new_category: String,
+    new_date: String,
+    now: NaiveDateTime,
+) -> Result<usize, FinancerError> {
     use crate::schema::transactions::dsl::*;
-    use crate::schema::accounts;
     
-    let old_tx: Transaction = transactions.filter(id.eq(transaction_id)).first(conn)?;
+    let old_tx = find_transaction(conn, transaction_id)?;
     
     let cents = (new_amount * 100.0) as i32;
     
@@ -567,7 +3318,7 @@ pub fn update_transaction(
 
     update_account_balance(conn, new_user_account, new_amount)?;
     
-    let current_account: Account = accounts::table.filter(accounts::id.eq(new_user_account)).first(conn)?;
+    let current_account = find_account(conn, new_user_account)?;
     let new_balance_after = current_account.balance;
     
     let result = diesel::update(transactions.filter(id.eq(transaction_id)))
@@ -580,34 +3331,77 @@ pub fn update_transaction(
             balance_after.eq(new_balance_after),
         ))
         .execute(conn)?;
+    forget_budget_carries_after(conn, old_tx.date.as_str().min(new_date.as_str()))?;
+    // Transfer legs only move money between accounts, so they never count against a budget.
+    if !old_tx.is_transfer {
+        check_budget_alerts(conn, new_user_account, &new_category, &new_date, now)?;
+    }
     
     Ok(result)
 }
 
-pub fn delete_transaction(conn: &mut SqliteConnection, transaction_id: i32) -> Result<usize, Error> {
+/// Edits a transaction on behalf of `member_id`, who must be an owner or editor of both
+/// the account it is on and the account it moves to. Budget alerts are stamped with `now`.
+#[allow(clippy::too_many_arguments)]
+pub fn update_transaction_as(
+    conn: &mut SqliteConnection,
+    member_id: i32,
+    transaction_id: i32,
+    new_user_account: i32,
+    new_amount: f32,
+    new_category: String,
+    new_date: String,
+    now: NaiveDateTime,
+) -> Result<usize, FinancerError> {
+    let old_tx = find_transaction(conn, transaction_id)?;
+    find_editable_account(conn, member_id, old_tx.user_account_id)?;
+    find_editable_account(conn, member_id, new_user_account)?;
+    update_transaction_at(conn, transaction_id, new_user_account, new_amount, new_category, new_date, now)
+}
+
+fn find_transaction(conn: &mut SqliteConnection, transaction_id: i32) -> Result<Transaction, FinancerError> {
+    use crate::schema::transactions::dsl::*;
+
+    transactions
+        .filter(id.eq(transaction_id))
+        .first::<Transaction>(conn)
+        .optional()?
+        .ok_or_else(|| FinancerError::NotFound(format!("Transaction {}", transaction_id)))
+}
+
+/// Deletes a transaction without a permission check. The UI goes through `delete_transaction_as`.
+pub fn delete_transaction(conn: &mut SqliteConnection, transaction_id: i32) -> Result<usize, FinancerError> {
     use crate::schema::transactions::dsl::*;
     
-    let old_tx: Transaction = transactions.filter(id.eq(transaction_id)).first(conn)?;
+    let old_tx = find_transaction(conn, transaction_id)?;
     
     let result = diesel::delete(transactions.filter(id.eq(transaction_id)))
         .execute(conn)?;
     
     update_account_balance(conn, old_tx.user_account_id, -old_tx.amount)?;
+    forget_budget_carries_after(conn, &old_tx.date)?;
     
     Ok(result)
 }
 
+/// Deletes a transaction on behalf of `member_id`, who must be an owner or editor of its account.
+pub fn delete_transaction_as(conn: &mut SqliteConnection, member_id: i32, transaction_id: i32) -> Result<usize, FinancerError> {
+    let old_tx = find_transaction(conn, transaction_id)?;
+    find_editable_account(conn, member_id, old_tx.user_account_id)?;
+    delete_transaction(conn, transaction_id)
+}
+
 fn update_account_balance(
     conn: &mut SqliteConnection,
     account_id: i32,
     amount_change: f32,
-) -> Result<usize, Error> {
+) -> Result<usize, FinancerError> {
     use crate::schema::accounts::dsl::*;
     
-    let current_account: Account = accounts.filter(id.eq(account_id)).first(conn)?;
+    let current_account = find_account(conn, account_id)?;
     let new_balance = current_account.balance + amount_change;
     
-    diesel::update(accounts.filter(id.eq(account_id)))
+    Ok(diesel::update(accounts.filter(id.eq(account_id)))
         .set(balance.eq(new_balance))
-        .execute(conn)
+        .execute(conn)?)
 }
\ No newline at end of file
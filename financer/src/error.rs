@@ -0,0 +1,75 @@
+//! Domain errors returned by the database layer.
+
+use diesel::result::{DatabaseErrorKind, Error as DieselError};
+use std::fmt;
+
+#[derive(Debug)]
+pub enum FinancerError {
+    /// Input was rejected before reaching the database (bad email, empty name, ...).
+    Validation(String),
+    /// The requested record does not exist.
+    NotFound(String),
+    /// The record exists but belongs to another user.
+    PermissionDenied(String),
+    /// The change would collide with an existing record (duplicate username, ...).
+    Conflict(String),
+    InsufficientFunds {
+        account: String,
+        available: f32,
+        required: f32,
+    },
+    /// Any other failure from the underlying SQLite connection.
+    Storage(DieselError),
+    /// A stored value could not be parsed back (password hash, date, ...).
+    CorruptData(String),
+    /// Too many failed logins for this username; try again after the delay.
+    LoginThrottled { retry_after_secs: i64 },
+    /// The password was right but the account has 2FA on and no code was given.
+    TwoFactorRequired,
+    /// Hashing or encryption failed for reasons unrelated to the user's input.
+    Internal(String),
+}
+
+impl fmt::Display for FinancerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FinancerError::Validation(msg) => write!(f, "validation failed: {}", msg),
+            FinancerError::NotFound(what) => write!(f, "{} not found", what),
+            FinancerError::PermissionDenied(what) => write!(f, "permission denied: {}", what),
+            FinancerError::Conflict(msg) => write!(f, "conflict: {}", msg),
+            FinancerError::InsufficientFunds { account, available, required } => write!(
+                f,
+                "insufficient funds in {}: available {:.2}, required {:.2}",
+                account, available, required
+            ),
+            FinancerError::Storage(e) => write!(f, "storage error: {}", e),
+            FinancerError::CorruptData(msg) => write!(f, "corrupt data: {}", msg),
+            FinancerError::LoginThrottled { retry_after_secs } => {
+                write!(f, "login throttled for {} more seconds", retry_after_secs)
+            }
+            FinancerError::TwoFactorRequired => write!(f, "two-factor code required"),
+            FinancerError::Internal(msg) => write!(f, "internal error: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for FinancerError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            FinancerError::Storage(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<DieselError> for FinancerError {
+    fn from(e: DieselError) -> Self {
+        match e {
+            DieselError::NotFound => FinancerError::NotFound("Record".to_string()),
+            DieselError::DatabaseError(DatabaseErrorKind::UniqueViolation, info) => {
+                FinancerError::Conflict(info.message().to_string())
+            }
+            other => FinancerError::Storage(other),
+        }
+    }
+}
@@ -1,4 +1,4 @@
-/// Financial calculation utilities: present/future value, cash flows, bonds, and mortgages.
+/// Financial calculation utilities: present/future value, cash flows, bonds, mortgages and debt payoff.
 use chrono::NaiveDate;
 use serde::{Serialize, Deserialize};
 
@@ -181,6 +181,176 @@ pub fn mortgage_monthly_payment(principal: f64, annual_rate: f64, years: u32) ->
     mortgage_payment_with_frequency(principal, annual_rate, years, PaymentFrequency::Monthly)
 }
 
+/// A credit card or loan to pay down.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Debt {
+    pub name: String,
+    pub balance: f64,
+    /// Annual rate as a fraction, charged monthly.
+    pub apr: f64,
+    pub minimum_payment: f64,
+}
+
+/// Which debt gets the money left after every minimum is paid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PayoffStrategy {
+    /// Smallest balance first.
+    Snowball,
+    /// Highest rate first.
+    Avalanche,
+    /// An order the user chose.
+    Custom,
+}
+
+impl PayoffStrategy {
+    pub const ALL: [PayoffStrategy; 3] = [PayoffStrategy::Snowball, PayoffStrategy::Avalanche, PayoffStrategy::Custom];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            PayoffStrategy::Snowball => "Snowball",
+            PayoffStrategy::Avalanche => "Avalanche",
+            PayoffStrategy::Custom => "Custom order",
+        }
+    }
+}
+
+/// What one debt got in one month of a payoff plan.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DebtPayment {
+    /// Index into the debts the plan was made from.
+    pub debt: usize,
+    pub payment: f64,
+    pub interest: f64,
+    pub principal: f64,
+    pub remaining_balance: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PayoffMonth {
+    /// Starting at 1.
+    pub month: u32,
+    /// Only the debts still open at the start of the month.
+    pub payments: Vec<DebtPayment>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PayoffPlan {
+    pub schedule: Vec<PayoffMonth>,
+    /// The month each debt is cleared, by index; `None` if it isn't within the simulation.
+    pub payoff_month: Vec<Option<u32>>,
+    pub interest_by_debt: Vec<f64>,
+}
+
+impl PayoffPlan {
+    pub fn total_interest(&self) -> f64 {
+        self.interest_by_debt.iter().sum()
+    }
+
+    pub fn total_paid(&self) -> f64 {
+        self.schedule.iter().flat_map(|m| &m.payments).map(|p| p.payment).sum()
+    }
+
+    /// Whether every debt gets paid off.
+    pub fn is_complete(&self) -> bool {
+        self.payoff_month.iter().all(Option::is_some)
+    }
+}
+
+/// The order debts receive extra payments in. `custom_order` lists indexes for
+/// `PayoffStrategy::Custom`; debts it leaves out follow in their original order.
+pub fn debt_payoff_order(debts: &[Debt], strategy: PayoffStrategy, custom_order: &[usize]) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..debts.len()).collect();
+    match strategy {
+        PayoffStrategy::Snowball => order.sort_by(|&a, &b| {
+            debts[a].balance.total_cmp(&debts[b].balance).then(debts[b].apr.total_cmp(&debts[a].apr))
+        }),
+        PayoffStrategy::Avalanche => order.sort_by(|&a, &b| {
+            debts[b].apr.total_cmp(&debts[a].apr).then(debts[a].balance.total_cmp(&debts[b].balance))
+        }),
+        PayoffStrategy::Custom => {
+            let mut chosen: Vec<usize> = Vec::with_capacity(debts.len());
+            for &index in custom_order.iter().chain(order.iter()) {
+                if index < debts.len() && !chosen.contains(&index) {
+                    chosen.push(index);
+                }
+            }
+            order = chosen;
+        }
+    }
+    order
+}
+
+/// Pays down `debts` month by month. Each month interest is added, every open debt gets its
+/// minimum, and the rest of the budget goes to the first open debt in the strategy's order.
+/// The budget is the sum of all the minimums plus `extra_payment` and stays the same as
+/// debts are cleared, so freed-up minimums roll on to the next debt. Stops once everything is
+/// paid or after `max_months`.
+pub fn simulate_debt_payoff(
+    debts: &[Debt],
+    extra_payment: f64,
+    strategy: PayoffStrategy,
+    custom_order: &[usize],
+    max_months: u32,
+) -> PayoffPlan {
+    const PAID_OFF: f64 = 0.005;
+
+    let order = debt_payoff_order(debts, strategy, custom_order);
+    let budget = debts.iter().map(|d| d.minimum_payment.max(0.0)).sum::<f64>() + extra_payment.max(0.0);
+    let mut balances: Vec<f64> = debts.iter().map(|d| d.balance.max(0.0)).collect();
+    let mut payoff_month: Vec<Option<u32>> = balances.iter().map(|b| (*b < PAID_OFF).then_some(0)).collect();
+    let mut interest_by_debt = vec![0.0; debts.len()];
+    let mut schedule = Vec::new();
+
+    for month in 1..=max_months {
+        if payoff_month.iter().all(Option::is_some) {
+            break;
+        }
+        let open: Vec<usize> = (0..debts.len()).filter(|&i| payoff_month[i].is_none()).collect();
+        let mut paid = vec![0.0; debts.len()];
+        let mut interest = vec![0.0; debts.len()];
+        for &i in &open {
+            interest[i] = balances[i] * debts[i].apr / 12.0;
+            balances[i] += interest[i];
+            interest_by_debt[i] += interest[i];
+        }
+
+        let mut left = budget;
+        for &i in &open {
+            let minimum = debts[i].minimum_payment.max(0.0).min(balances[i]).min(left);
+            paid[i] += minimum;
+            balances[i] -= minimum;
+            left -= minimum;
+        }
+        for &i in order.iter().filter(|i| open.contains(i)) {
+            if left <= 0.0 {
+                break;
+            }
+            let more = left.min(balances[i]);
+            paid[i] += more;
+            balances[i] -= more;
+            left -= more;
+        }
+
+        let mut payments = Vec::with_capacity(open.len());
+        for &i in &open {
+            if balances[i] < PAID_OFF {
+                balances[i] = 0.0;
+                payoff_month[i] = Some(month);
+            }
+            payments.push(DebtPayment {
+                debt: i,
+                payment: paid[i],
+                interest: interest[i],
+                principal: paid[i] - interest[i],
+                remaining_balance: balances[i],
+            });
+        }
+        schedule.push(PayoffMonth { month, payments });
+    }
+
+    PayoffPlan { schedule, payoff_month, interest_by_debt }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ContributionFrequency {
     Monthly,
@@ -295,3 +465,42 @@ pub fn compound_interest_future_value_with_contributions(
 
     balance
 }
+
+/// The monthly contribution that grows `current` to `target` in `months` months at
+/// `annual_rate`, compounding monthly. Zero when growth alone gets there.
+pub fn required_monthly_contribution(current: f64, target: f64, annual_rate: f64, months: u32) -> f64 {
+    if months == 0 {
+        return (target - current).max(0.0);
+    }
+    let years = months as f64 / 12.0;
+    let grow = |addition: f64| {
+        compound_interest_future_value_with_contributions(
+            current,
+            addition,
+            ContributionFrequency::Monthly,
+            annual_rate,
+            CompoundingFrequency::Monthly,
+            years,
+        )
+    };
+    let without = grow(0.0);
+    if without >= target {
+        return 0.0;
+    }
+    // The future value grows linearly with the contribution.
+    (target - without) / (grow(1.0) - without)
+}
+
+/// Whole months until `current`, plus `monthly_contribution` each month, reaches `target` at
+/// `annual_rate`, compounding monthly. `None` if it doesn't within `max_months`.
+pub fn months_to_reach_target(current: f64, target: f64, monthly_contribution: f64, annual_rate: f64, max_months: u32) -> Option<u32> {
+    let rate_per_month = annual_rate / 12.0;
+    let mut balance = current;
+    for month in 0..=max_months {
+        if balance >= target {
+            return Some(month);
+        }
+        balance = balance * (1.0 + rate_per_month) + monthly_contribution;
+    }
+    None
+}
@@ -0,0 +1,118 @@
+//! Day-by-day balance projection for each account, built from what is already known about
+//! the future: future-dated transactions, scheduled recurring items and, optionally, the
+//! typical day-to-day spending seen in the past. Nothing here touches the database.
+
+use std::collections::HashMap;
+
+use chrono::{Duration, NaiveDate};
+
+use crate::models::{Account, Transaction};
+use crate::schedule::Occurrence;
+
+/// Typical daily spending in one category from one account, from its recent history.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpendEstimate {
+    pub account_id: i32,
+    pub category: String,
+    /// Positive; taken off the balance every day after today.
+    pub daily_amount: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ForecastPoint {
+    pub date: NaiveDate,
+    /// Balance at the end of the day.
+    pub balance: f32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccountForecast {
+    pub account_id: i32,
+    /// One point per day, starting with today.
+    pub points: Vec<ForecastPoint>,
+}
+
+impl AccountForecast {
+    /// The lowest projected balance; the earliest day if it occurs more than once.
+    pub fn lowest(&self) -> Option<ForecastPoint> {
+        self.points
+            .iter()
+            .copied()
+            .reduce(|low, p| if p.balance < low.balance { p } else { low })
+    }
+
+    /// The first day the balance is projected to be below `threshold`.
+    pub fn first_below(&self, threshold: f32) -> Option<ForecastPoint> {
+        self.points.iter().copied().find(|p| p.balance < threshold)
+    }
+}
+
+/// Projects each account's end-of-day balance from `today` through `today + days`.
+///
+/// `scheduled` are transactions dated after today. Account balances already include them,
+/// so they are backed out of today's figure and applied again on their own dates.
+/// `occurrences` are recurring items still to run, including any later today; transfers to
+/// or from accounts not in `accounts` only affect the side that is. Spending estimates start
+/// tomorrow, since today's spending is already in the balance.
+pub fn forecast_balances(
+    accounts: &[Account],
+    scheduled: &[Transaction],
+    occurrences: &[Occurrence],
+    estimates: &[SpendEstimate],
+    today: NaiveDate,
+    days: i64,
+) -> Vec<AccountForecast> {
+    let mut changes: HashMap<(i32, NaiveDate), f32> = HashMap::new();
+    let mut balances: HashMap<i32, f32> = accounts.iter().map(|a| (a.id, a.balance)).collect();
+
+    for tx in scheduled {
+        let Some(day) = tx.date.get(..10).and_then(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok()) else {
+            continue;
+        };
+        if day <= today {
+            continue;
+        }
+        if let Some(balance) = balances.get_mut(&tx.user_account_id) {
+            *balance -= tx.amount;
+            *changes.entry((tx.user_account_id, day)).or_default() += tx.amount;
+        }
+    }
+
+    for occ in occurrences {
+        let day = occ.at.date().max(today);
+        match occ.to_account_id {
+            Some(to) => {
+                *changes.entry((occ.account_id, day)).or_default() -= occ.amount;
+                *changes.entry((to, day)).or_default() += occ.amount;
+            }
+            None => *changes.entry((occ.account_id, day)).or_default() += occ.amount,
+        }
+    }
+
+    let mut daily_spend: HashMap<i32, f32> = HashMap::new();
+    for estimate in estimates {
+        *daily_spend.entry(estimate.account_id).or_default() += estimate.daily_amount;
+    }
+
+    accounts
+        .iter()
+        .map(|account| {
+            let mut balance = balances[&account.id];
+            let spend = daily_spend.get(&account.id).copied().unwrap_or(0.0);
+            let points = (0..=days.max(0))
+                .map(|offset| {
+                    let date = today + Duration::days(offset);
+                    balance += changes.get(&(account.id, date)).copied().unwrap_or(0.0);
+                    if offset > 0 {
+                        balance -= spend;
+                    }
+                    ForecastPoint { date, balance }
+                })
+                .collect();
+            AccountForecast {
+                account_id: account.id,
+                points,
+            }
+        })
+        .collect()
+}
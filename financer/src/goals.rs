@@ -0,0 +1,71 @@
+//! Savings goals: progress from the linked accounts' balances, the contribution needed to
+//! finish on time and when the current pace gets there. Nothing here touches the database.
+
+use chrono::{Datelike, Months, NaiveDate};
+
+use crate::finance_calculations::{months_to_reach_target, required_monthly_contribution};
+use crate::models::SavingsGoal;
+
+/// How far ahead a projected completion date is looked for.
+const MAX_PROJECTION_MONTHS: u32 = 1200;
+
+/// A goal with what its linked accounts hold now. Amounts are in cents.
+#[derive(Debug, Clone)]
+pub struct GoalProgress {
+    pub goal: SavingsGoal,
+    pub account_ids: Vec<i32>,
+    /// Combined balance of the linked accounts.
+    pub saved: i64,
+    /// Average net amount paid into the linked accounts per month recently.
+    pub monthly_pace: i64,
+}
+
+impl GoalProgress {
+    pub fn target_date(&self) -> Option<NaiveDate> {
+        NaiveDate::parse_from_str(&self.goal.target_date, "%Y-%m-%d").ok()
+    }
+
+    /// Saved as a fraction of the target, capped at 1.
+    pub fn fraction(&self) -> f32 {
+        (self.saved as f32 / self.goal.target_cents as f32).clamp(0.0, 1.0)
+    }
+
+    pub fn is_reached(&self) -> bool {
+        self.saved >= self.goal.target_cents as i64
+    }
+
+    /// Monthly contribution in cents that reaches the target by its date, counting the
+    /// expected return. `None` without a valid target date.
+    pub fn required_monthly(&self, today: NaiveDate) -> Option<i64> {
+        let months = months_until(today, self.target_date()?);
+        let dollars = required_monthly_contribution(
+            self.saved as f64 / 100.0,
+            self.goal.target_cents as f64 / 100.0,
+            self.goal.expected_rate,
+            months,
+        );
+        Some((dollars * 100.0).ceil() as i64)
+    }
+
+    /// When the goal is reached if `monthly_pace` keeps up. `None` if it never gets there.
+    pub fn projected_completion(&self, today: NaiveDate) -> Option<NaiveDate> {
+        let months = months_to_reach_target(
+            self.saved as f64 / 100.0,
+            self.goal.target_cents as f64 / 100.0,
+            self.monthly_pace as f64 / 100.0,
+            self.goal.expected_rate,
+            MAX_PROJECTION_MONTHS,
+        )?;
+        today.checked_add_months(Months::new(months))
+    }
+}
+
+/// Whole months from `today` to `target`, not counting a final partial month. Zero once the
+/// date has passed.
+pub fn months_until(today: NaiveDate, target: NaiveDate) -> u32 {
+    let mut months = (target.year() - today.year()) * 12 + target.month() as i32 - today.month() as i32;
+    if target.day() < today.day() {
+        months -= 1;
+    }
+    months.max(0) as u32
+}
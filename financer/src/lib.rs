@@ -1,4 +1,15 @@
+pub mod auth;
+pub mod budgeting;
 pub mod db;
+pub mod error;
 pub mod models;
 pub mod schema;
-pub mod finance_calculations;
\ No newline at end of file
+pub mod finance_calculations;
+pub mod forecast;
+pub mod goals;
+pub mod net_worth;
+pub mod reports;
+pub mod calendar;
+pub mod recurrence;
+pub mod schedule;
+pub mod totp;
\ No newline at end of file
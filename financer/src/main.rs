@@ -1,8 +1,19 @@
 mod app;
+mod auth;
+mod budgeting;
 mod db;
+mod error;
 mod models;
 pub mod schema;
+mod calendar;
 mod finance_calculations;
+mod forecast;
+mod goals;
+mod net_worth;
+mod reports;
+mod recurrence;
+mod schedule;
+mod totp;
 
 use eframe::NativeOptions;
 use app::FinancerApp;
@@ -3,8 +3,22 @@ use super::schema::accounts;
 use super::schema::contacts;
 use super::schema::transactions;
 use super::schema::budgets;
+use super::schema::budget_templates;
+use super::schema::budget_template_items;
 use super::schema::recurring_transactions;
 use super::schema::recurring_transfers;
+use super::schema::recurring_runs;
+use super::schema::holidays;
+use super::schema::savings_goals;
+use super::schema::balance_snapshots;
+use super::schema::envelopes;
+use super::schema::envelope_assignments;
+use super::schema::notifications;
+use super::schema::login_attempts;
+use super::schema::login_events;
+use super::schema::user_totp;
+use super::schema::recovery_codes;
+use super::schema::account_members;
 use diesel::{Insertable, Queryable};
 
 #[derive(Debug)]
@@ -25,6 +39,20 @@ pub struct Transaction {
     pub date: String, 
     pub amount_cents: i32, 
     pub balance_after: f32, 
+    /// Member who entered the transaction; `None` if that user has since been deleted.
+    pub created_by: Option<i32>,
+    /// The recurring item that generated this transaction, if any.
+    pub recurring_id: Option<i32>,
+    /// `RecurringKind` of `recurring_id`.
+    pub recurring_kind: Option<String>,
+    /// One leg of a transfer between accounts rather than income or spending.
+    pub is_transfer: bool,
+}
+
+impl Transaction {
+    pub fn recurring_source(&self) -> Option<(RecurringKind, i32)> {
+        Some((RecurringKind::from_str(self.recurring_kind.as_deref()?), self.recurring_id?))
+    }
 }
 
 #[derive(Debug, Insertable)]
@@ -37,6 +65,10 @@ pub struct NewTransaction {
     pub date: String,
     pub amount_cents: i32, 
     pub balance_after: f32, 
+    pub created_by: Option<i32>,
+    pub recurring_id: Option<i32>,
+    pub recurring_kind: Option<String>,
+    pub is_transfer: bool,
 }
 
 #[derive(Debug, Queryable)]
@@ -80,6 +112,8 @@ pub struct User {
     pub username: String,
     pub password_hash: String,
     pub email: Option<String>,
+    /// Forecast balances below this are flagged on the dashboard.
+    pub low_balance_threshold: f32,
 }
 
 #[derive(Debug, Insertable)]
@@ -90,6 +124,56 @@ pub struct NewUser<'a> {
     pub email: Option<&'a str>,
 }
 
+#[derive(Debug, Clone, Queryable, Insertable)]
+#[diesel(table_name = login_attempts)]
+pub struct LoginAttempt {
+    pub username: String,
+    pub failed_count: i32,
+    pub last_failed_at: Option<String>,
+    pub locked_until: Option<String>,
+}
+
+#[derive(Debug, Clone, Queryable)]
+#[allow(dead_code)]
+pub struct LoginEvent {
+    pub id: i32,
+    pub username: String,
+    pub user_id: Option<i32>,
+    pub succeeded: bool,
+    pub reason: String,
+    pub occurred_at: String,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = login_events)]
+pub struct NewLoginEvent<'a> {
+    pub username: &'a str,
+    pub user_id: Option<i32>,
+    pub succeeded: bool,
+    pub reason: &'a str,
+    pub occurred_at: String,
+}
+
+#[derive(Debug, Clone, Queryable, Insertable)]
+#[diesel(table_name = user_totp)]
+pub struct UserTotp {
+    pub user_id: i32,
+    pub key_salt: String,
+    pub nonce: String,
+    pub ciphertext: String,
+    pub enabled: bool,
+    /// Time step of the last accepted code, so a code can't be replayed.
+    pub last_used_step: Option<i64>,
+    pub created_at: String,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = recovery_codes)]
+pub struct NewRecoveryCode {
+    pub user_id: i32,
+    pub code_hash: String,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Period {
     Daily,
@@ -99,7 +183,7 @@ pub enum Period {
 }
 
 impl Period {
-    pub fn to_str(&self) -> &'static str {
+    pub fn to_str(self) -> &'static str {
         match self {
             Period::Daily => "Daily",
             Period::Weekly => "Weekly",
@@ -108,6 +192,7 @@ impl Period {
         }
     }
 
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> Self {
         match s {
             "Daily" => Period::Daily,
@@ -117,23 +202,84 @@ impl Period {
             _ => Period::Monthly,
         }
     }
+
+    /// The period as a plural noun, as in "3 months".
+    pub fn plural(self) -> &'static str {
+        match self {
+            Period::Daily => "days",
+            Period::Weekly => "weeks",
+            Period::Monthly => "months",
+            Period::Yearly => "years",
+        }
+    }
 }
 
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+/// What an expense budget does with money left over, or overspent, at the end of a period.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RolloverMode {
+    /// Every period starts from the base limit.
+    None,
+    /// Unspent money is added to the next period.
+    Surplus,
+    /// Unspent money is added to the next period and overspending taken from it.
+    SurplusAndDeficit,
+    /// Like `Surplus`, but never carrying more than the budget's cap.
+    Capped,
+}
+
+impl RolloverMode {
+    pub const ALL: [RolloverMode; 4] = [
+        RolloverMode::None,
+        RolloverMode::Surplus,
+        RolloverMode::SurplusAndDeficit,
+        RolloverMode::Capped,
+    ];
+
+    pub fn to_str(self) -> &'static str {
+        match self {
+            RolloverMode::None => "none",
+            RolloverMode::Surplus => "surplus",
+            RolloverMode::SurplusAndDeficit => "surplus_and_deficit",
+            RolloverMode::Capped => "capped",
+        }
+    }
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Self {
+        match s {
+            "surplus" => RolloverMode::Surplus,
+            "surplus_and_deficit" => RolloverMode::SurplusAndDeficit,
+            "capped" => RolloverMode::Capped,
+            _ => RolloverMode::None,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            RolloverMode::None => "No rollover",
+            RolloverMode::Surplus => "Carry surplus",
+            RolloverMode::SurplusAndDeficit => "Carry surplus and deficit",
+            RolloverMode::Capped => "Carry surplus up to a cap",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum TargetType {
     Expense, // spending limit
     Income,  // income target
 }
 
 impl TargetType {
-    pub fn to_str(&self) -> &'static str {
+    pub fn to_str(self) -> &'static str {
         match self {
             TargetType::Expense => "Expense",
             TargetType::Income => "Income",
         }
     }
 
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> Self {
         match s {
             "Expense" => TargetType::Expense,
@@ -144,6 +290,46 @@ impl TargetType {
 }
 
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum AccountRole {
+    Owner,  // can share, unshare and delete the account
+    Editor, // can add and change transactions
+    Viewer, // read only
+}
+
+impl AccountRole {
+    pub const ALL: [AccountRole; 3] = [AccountRole::Owner, AccountRole::Editor, AccountRole::Viewer];
+
+    pub fn to_str(self) -> &'static str {
+        match self {
+            AccountRole::Owner => "owner",
+            AccountRole::Editor => "editor",
+            AccountRole::Viewer => "viewer",
+        }
+    }
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Self {
+        match s {
+            "owner" => AccountRole::Owner,
+            "editor" => AccountRole::Editor,
+            _ => AccountRole::Viewer,
+        }
+    }
+
+    pub fn can_edit(self) -> bool {
+        matches!(self, AccountRole::Owner | AccountRole::Editor)
+    }
+}
+
+#[derive(Debug, Clone, Queryable, Insertable)]
+#[diesel(table_name = account_members)]
+pub struct AccountMember {
+    pub account_id: i32,
+    pub user_id: i32,
+    pub role: String,
+}
+
 #[derive(Debug, Queryable, Clone)]
 #[allow(dead_code)]
 pub struct Budget {
@@ -155,6 +341,26 @@ pub struct Budget {
     pub target_type: String,
     pub active: bool, 
     pub updated_at: String, 
+    pub rollover_mode: String,
+    /// Most that `RolloverMode::Capped` carries forward.
+    pub rollover_cap_cents: Option<i32>,
+    /// Comma-separated percentages of the limit that raise a notification, e.g. `50,80,100`.
+    pub alert_thresholds: String,
+    /// Shown instead of the category when set, e.g. "Trip".
+    pub name: Option<String>,
+    /// Also count `Parent:Child` categories under the budget's categories.
+    pub include_subcategories: bool,
+    /// `%Y-%m-%d`, inclusive. A budget with dates covers just that range instead of a period.
+    pub starts_on: Option<String>,
+    pub ends_on: Option<String>,
+    /// Local time; rollover counts periods from the one containing it.
+    pub created_at: String,
+}
+
+impl Budget {
+    pub fn display_name(&self) -> &str {
+        self.name.as_deref().filter(|n| !n.trim().is_empty()).unwrap_or(&self.category)
+    }
 }
 
 #[derive(Debug, Insertable)]
@@ -165,6 +371,172 @@ pub struct NewBudget {
     pub limit_cents: i32,
     pub period: String,
     pub target_type: String,
+    pub rollover_mode: String,
+    pub rollover_cap_cents: Option<i32>,
+    pub alert_thresholds: String,
+    pub name: Option<String>,
+    pub include_subcategories: bool,
+    pub starts_on: Option<String>,
+    pub ends_on: Option<String>,
+}
+
+#[derive(Debug, Clone, Queryable)]
+#[allow(dead_code)]
+pub struct BudgetTemplate {
+    pub id: i32,
+    pub user_id: i32,
+    pub name: String,
+    pub created_at: String,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = budget_templates)]
+pub struct NewBudgetTemplate<'a> {
+    pub user_id: i32,
+    pub name: &'a str,
+    pub created_at: &'a str,
+}
+
+#[derive(Debug, Clone, Queryable)]
+#[allow(dead_code)]
+pub struct BudgetTemplateItem {
+    pub id: i32,
+    pub template_id: i32,
+    pub name: Option<String>,
+    pub category: String,
+    /// JSON array of the other categories the budget covers.
+    pub extra_categories: String,
+    pub limit_cents: i32,
+    pub period: String,
+    pub target_type: String,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = budget_template_items)]
+pub struct NewBudgetTemplateItem<'a> {
+    pub template_id: i32,
+    pub name: Option<&'a str>,
+    pub category: &'a str,
+    pub extra_categories: &'a str,
+    pub limit_cents: i32,
+    pub period: &'a str,
+    pub target_type: &'a str,
+}
+
+#[derive(Debug, Clone, Queryable)]
+#[allow(dead_code)]
+pub struct SavingsGoal {
+    pub id: i32,
+    pub user_id: i32,
+    pub name: String,
+    pub target_cents: i32,
+    /// `%Y-%m-%d`.
+    pub target_date: String,
+    /// Expected annual return on the linked accounts, as a fraction.
+    pub expected_rate: f64,
+    pub created_at: String,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = savings_goals)]
+pub struct NewSavingsGoal<'a> {
+    pub user_id: i32,
+    pub name: &'a str,
+    pub target_cents: i32,
+    pub target_date: &'a str,
+    pub expected_rate: f64,
+    pub created_at: &'a str,
+}
+
+/// An account's balance at the end of a day.
+#[derive(Debug, Clone, Queryable, Insertable)]
+#[diesel(table_name = balance_snapshots)]
+pub struct BalanceSnapshot {
+    pub account_id: i32,
+    /// `%Y-%m-%d`.
+    pub day: String,
+    pub balance_cents: i32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotificationKind {
+    BudgetThreshold,
+}
+
+impl NotificationKind {
+    pub fn to_str(self) -> &'static str {
+        match self {
+            NotificationKind::BudgetThreshold => "budget_threshold",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Queryable)]
+#[allow(dead_code)]
+pub struct Notification {
+    pub id: i32,
+    pub user_id: i32,
+    pub kind: String,
+    pub budget_id: Option<i32>,
+    /// Start of the budget period the alert is for.
+    pub period_start: Option<String>,
+    /// The percentage of the limit that was reached.
+    pub threshold: Option<i32>,
+    pub message: String,
+    pub created_at: String,
+    pub is_read: bool,
+    pub dismissed: bool,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = notifications)]
+pub struct NewNotification<'a> {
+    pub user_id: i32,
+    pub kind: &'a str,
+    pub budget_id: Option<i32>,
+    pub period_start: Option<&'a str>,
+    pub threshold: Option<i32>,
+    pub message: &'a str,
+    pub created_at: &'a str,
+}
+
+/// A category envelope in zero-based budgeting.
+#[derive(Debug, Clone, Queryable)]
+#[allow(dead_code)]
+pub struct Envelope {
+    pub id: i32,
+    pub user_id: i32,
+    pub category: String,
+    /// `%Y-%m-%d`, always the first of a month.
+    pub starts_on: String,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = envelopes)]
+pub struct NewEnvelope<'a> {
+    pub user_id: i32,
+    pub category: &'a str,
+    pub starts_on: &'a str,
+}
+
+#[derive(Debug, Clone, Queryable)]
+#[allow(dead_code)]
+pub struct EnvelopeAssignment {
+    pub id: i32,
+    pub envelope_id: i32,
+    /// `%Y-%m`.
+    pub month: String,
+    pub amount_cents: i32,
+    pub created_at: String,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = envelope_assignments)]
+pub struct NewEnvelopeAssignment<'a> {
+    pub envelope_id: i32,
+    pub month: &'a str,
+    pub amount_cents: i32,
+    pub created_at: &'a str,
 }
 
 #[derive(Debug, Clone, Queryable)]
@@ -179,6 +551,19 @@ pub struct RecurringTransaction {
     pub next_run_at: String,
     pub frequency: String,
     pub active: bool,
+    pub recurrence_rule: String,
+    pub ends_at: Option<String>,
+    pub max_occurrences: Option<i32>,
+    pub occurrences_run: i32,
+    pub requires_approval: bool,
+    pub roll_convention: String,
+}
+
+impl RecurringTransaction {
+    /// Occurrences left before `max_occurrences` is reached, if a limit is set.
+    pub fn remaining_occurrences(&self) -> Option<i32> {
+        self.max_occurrences.map(|max| (max - self.occurrences_run).max(0))
+    }
 }
 
 #[derive(Debug, Insertable)]
@@ -191,6 +576,14 @@ pub struct NewRecurringTransaction {
     pub category: String,
     pub next_run_at: String,
     pub frequency: String,
+    /// RRULE subset (see `recurrence`); `None` repeats plainly at `frequency`.
+    pub recurrence_rule: Option<String>,
+    /// Last moment an occurrence may fall on; later ones are never posted.
+    pub ends_at: Option<String>,
+    pub max_occurrences: Option<i32>,
+    /// Queue each occurrence for confirmation instead of posting it.
+    pub requires_approval: bool,
+    pub roll_convention: String,
 }
 
 #[derive(Debug, Clone, Queryable)]
@@ -204,6 +597,20 @@ pub struct RecurringTransfer {
     pub next_run_at: String,
     pub frequency: String,
     pub active: bool,
+    pub recurrence_rule: String,
+    pub ends_at: Option<String>,
+    pub max_occurrences: Option<i32>,
+    pub occurrences_run: i32,
+    pub requires_approval: bool,
+    /// How occurrences on weekends and holidays move; see `RollConvention`.
+    pub roll_convention: String,
+}
+
+impl RecurringTransfer {
+    /// Occurrences left before `max_occurrences` is reached, if a limit is set.
+    pub fn remaining_occurrences(&self) -> Option<i32> {
+        self.max_occurrences.map(|max| (max - self.occurrences_run).max(0))
+    }
 }
 
 #[derive(Debug, Insertable)]
@@ -215,4 +622,157 @@ pub struct NewRecurringTransfer {
     pub amount: f32,
     pub next_run_at: String,
     pub frequency: String,
+    /// RRULE subset (see `recurrence`); `None` repeats plainly at `frequency`.
+    pub recurrence_rule: Option<String>,
+    /// Last moment an occurrence may fall on; later ones are never posted.
+    pub ends_at: Option<String>,
+    pub max_occurrences: Option<i32>,
+    /// Queue each occurrence for confirmation instead of posting it.
+    pub requires_approval: bool,
+    /// How occurrences on weekends and holidays move; see `RollConvention`.
+    pub roll_convention: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecurringKind {
+    Transaction,
+    Transfer,
+}
+
+impl RecurringKind {
+    pub fn to_str(self) -> &'static str {
+        match self {
+            RecurringKind::Transaction => "transaction",
+            RecurringKind::Transfer => "transfer",
+        }
+    }
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Self {
+        match s {
+            "transfer" => RecurringKind::Transfer,
+            _ => RecurringKind::Transaction,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunStatus {
+    /// Waiting in the approval queue.
+    Pending,
+    Posted,
+    Skipped,
+}
+
+impl RunStatus {
+    pub fn to_str(self) -> &'static str {
+        match self {
+            RunStatus::Pending => "pending",
+            RunStatus::Posted => "posted",
+            RunStatus::Skipped => "skipped",
+        }
+    }
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Self {
+        match s {
+            "posted" => RunStatus::Posted,
+            "skipped" => RunStatus::Skipped,
+            _ => RunStatus::Pending,
+        }
+    }
+}
+
+/// Where a recurring occurrence lands when its date is a weekend or holiday.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RollConvention {
+    /// Post on the scheduled date regardless.
+    None,
+    /// Next business day.
+    Following,
+    /// Previous business day.
+    Preceding,
+    /// Next business day, unless that is in the next month; then the previous one.
+    ModifiedFollowing,
+}
+
+impl RollConvention {
+    pub const ALL: [RollConvention; 4] = [
+        RollConvention::None,
+        RollConvention::Following,
+        RollConvention::Preceding,
+        RollConvention::ModifiedFollowing,
+    ];
+
+    pub fn to_str(self) -> &'static str {
+        match self {
+            RollConvention::None => "none",
+            RollConvention::Following => "following",
+            RollConvention::Preceding => "preceding",
+            RollConvention::ModifiedFollowing => "modified_following",
+        }
+    }
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Self {
+        match s {
+            "following" => RollConvention::Following,
+            "preceding" => RollConvention::Preceding,
+            "modified_following" => RollConvention::ModifiedFollowing,
+            _ => RollConvention::None,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            RollConvention::None => "Keep date",
+            RollConvention::Following => "Next business day",
+            RollConvention::Preceding => "Previous business day",
+            RollConvention::ModifiedFollowing => "Next business day (same month)",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Queryable)]
+pub struct Holiday {
+    pub id: i32,
+    pub user_id: i32,
+    /// `%Y-%m-%d`.
+    pub holiday_date: String,
+    pub name: String,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = holidays)]
+pub struct NewHoliday<'a> {
+    pub user_id: i32,
+    pub holiday_date: &'a str,
+    pub name: &'a str,
+}
+
+/// One occurrence produced by a recurring transaction or transfer.
+#[derive(Debug, Clone, Queryable)]
+#[allow(dead_code)]
+pub struct RecurringRun {
+    pub id: i32,
+    pub item_kind: String,
+    pub item_id: i32,
+    pub scheduled_for: String,
+    pub status: String,
+    /// Amount posted, or proposed while pending; signed like the item's amount.
+    pub amount: f32,
+    pub decided_by: Option<i32>,
+    pub decided_at: Option<String>,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = recurring_runs)]
+pub struct NewRecurringRun<'a> {
+    pub item_kind: &'a str,
+    pub item_id: i32,
+    pub scheduled_for: &'a str,
+    pub status: &'a str,
+    pub amount: f32,
+    pub decided_by: Option<i32>,
+    pub decided_at: Option<&'a str>,
 }
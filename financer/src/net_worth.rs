@@ -0,0 +1,113 @@
+//! Net worth history: past daily balances rebuilt from transactions, and account snapshots
+//! rolled up into totals and month- and year-end figures. Nothing here touches the database.
+
+use std::collections::{BTreeMap, HashMap};
+
+use chrono::{Datelike, NaiveDate};
+
+use crate::models::BalanceSnapshot;
+
+/// End-of-day balances from `from` to `to`, oldest first, for an account whose balance is
+/// `current` now. `transactions` are (day, amount) pairs in cents, which must include every
+/// transaction dated after `from`; the balance at the end of a day is the current one less
+/// everything dated later.
+pub fn reconstruct_daily_balances(current: i64, transactions: &[(NaiveDate, i64)], from: NaiveDate, to: NaiveDate) -> Vec<(NaiveDate, i64)> {
+    if to < from {
+        return Vec::new();
+    }
+    let mut by_day: HashMap<NaiveDate, i64> = HashMap::new();
+    let mut after_to = 0;
+    for (day, amount) in transactions {
+        if *day > to {
+            after_to += amount;
+        } else {
+            *by_day.entry(*day).or_insert(0) += amount;
+        }
+    }
+
+    let mut balance = current - after_to;
+    let mut balances = Vec::with_capacity((to - from).num_days() as usize + 1);
+    let mut day = to;
+    loop {
+        balances.push((day, balance));
+        if day <= from {
+            break;
+        }
+        balance -= by_day.get(&day).copied().unwrap_or(0);
+        day = day.pred_opt().expect("dates in range");
+    }
+    balances.reverse();
+    balances
+}
+
+/// Combined balances on one day. Amounts are in cents, `liabilities` as a positive amount owed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NetWorthPoint {
+    pub day: NaiveDate,
+    /// Sum of the accounts with a positive balance.
+    pub assets: i64,
+    /// Sum owed on the accounts with a negative balance.
+    pub liabilities: i64,
+}
+
+impl NetWorthPoint {
+    pub fn net(&self) -> i64 {
+        self.assets - self.liabilities
+    }
+}
+
+fn snapshot_day(snapshot: &BalanceSnapshot) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(&snapshot.day, "%Y-%m-%d").ok()
+}
+
+/// Net worth on every day that has a snapshot, oldest first. Snapshots with an unreadable
+/// day are skipped.
+pub fn net_worth_by_day(snapshots: &[BalanceSnapshot]) -> Vec<NetWorthPoint> {
+    let mut days: BTreeMap<NaiveDate, NetWorthPoint> = BTreeMap::new();
+    for snapshot in snapshots {
+        let Some(day) = snapshot_day(snapshot) else { continue; };
+        let point = days.entry(day).or_insert(NetWorthPoint { day, assets: 0, liabilities: 0 });
+        let balance = snapshot.balance_cents as i64;
+        if balance >= 0 {
+            point.assets += balance;
+        } else {
+            point.liabilities -= balance;
+        }
+    }
+    days.into_values().collect()
+}
+
+/// Each account's daily balances, oldest first, by account id.
+pub fn balances_by_account(snapshots: &[BalanceSnapshot]) -> BTreeMap<i32, Vec<(NaiveDate, i64)>> {
+    let mut accounts: BTreeMap<i32, Vec<(NaiveDate, i64)>> = BTreeMap::new();
+    for snapshot in snapshots {
+        let Some(day) = snapshot_day(snapshot) else { continue; };
+        accounts.entry(snapshot.account_id).or_default().push((day, snapshot.balance_cents as i64));
+    }
+    for balances in accounts.values_mut() {
+        balances.sort_by_key(|(day, _)| *day);
+    }
+    accounts
+}
+
+/// The last point of each month in `points`, which must be oldest first. The current month
+/// shows its latest day.
+pub fn month_ends(points: &[NetWorthPoint]) -> Vec<NetWorthPoint> {
+    last_per(points, |day| (day.year(), day.month()))
+}
+
+/// The last point of each year in `points`, which must be oldest first.
+pub fn year_ends(points: &[NetWorthPoint]) -> Vec<NetWorthPoint> {
+    last_per(points, |day| (day.year(), 0))
+}
+
+fn last_per(points: &[NetWorthPoint], key: impl Fn(NaiveDate) -> (i32, u32)) -> Vec<NetWorthPoint> {
+    let mut ends: Vec<NetWorthPoint> = Vec::new();
+    for point in points {
+        match ends.last_mut() {
+            Some(last) if key(last.day) == key(point.day) => *last = *point,
+            _ => ends.push(*point),
+        }
+    }
+    ends
+}
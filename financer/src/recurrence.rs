@@ -0,0 +1,357 @@
+//! Recurrence rules for scheduled items: a small subset of iCalendar RRULE.
+//!
+//! Supported parts are `FREQ`, `INTERVAL`, `BYMONTHDAY`, `BYDAY` (plain weekdays, no
+//! ordinals) and `BYSETPOS`, e.g. `FREQ=MONTHLY;BYDAY=TU;BYSETPOS=2` for the second
+//! Tuesday of every month. Occurrences are computed from the previous one, so an item
+//! only needs its `next_run_at` and its rule.
+
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, Weekday};
+
+use crate::error::FinancerError;
+use crate::models::Period;
+
+/// How many periods to look ahead before concluding a rule never fires again.
+const MAX_PERIODS_SEARCHED: i64 = 500;
+
+const WEEKDAYS: [Weekday; 7] = [
+    Weekday::Mon,
+    Weekday::Tue,
+    Weekday::Wed,
+    Weekday::Thu,
+    Weekday::Fri,
+    Weekday::Sat,
+    Weekday::Sun,
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecurrenceRule {
+    pub period: Period,
+    /// Repeat every `interval` periods; always at least 1.
+    pub interval: u32,
+    /// Days of the month, 1..=31, or -1..=-31 counting back from the last day.
+    pub by_month_day: Vec<i32>,
+    pub by_weekday: Vec<Weekday>,
+    /// Picks from the matching days of each period: 1 is the first, -1 the last.
+    pub by_set_pos: Vec<i32>,
+}
+
+impl RecurrenceRule {
+    /// The plain "every day/week/month/year" rule that a bare frequency has always meant.
+    pub fn from_period(period: Period) -> Self {
+        RecurrenceRule {
+            period,
+            interval: 1,
+            by_month_day: Vec::new(),
+            by_weekday: Vec::new(),
+            by_set_pos: Vec::new(),
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<Self, FinancerError> {
+        let text = text.trim();
+        let text = text
+            .strip_prefix("RRULE:")
+            .or_else(|| text.strip_prefix("rrule:"))
+            .unwrap_or(text);
+        let invalid = |msg: String| FinancerError::Validation(format!("Invalid recurrence rule: {}", msg));
+
+        let mut period = None;
+        let mut rule = RecurrenceRule::from_period(Period::Monthly);
+        for part in text.split(';').map(str::trim).filter(|p| !p.is_empty()) {
+            let (key, value) = part
+                .split_once('=')
+                .ok_or_else(|| invalid(format!("'{}' is not KEY=VALUE", part)))?;
+            let value = value.trim().to_ascii_uppercase();
+            match key.trim().to_ascii_uppercase().as_str() {
+                "FREQ" => {
+                    period = Some(match value.as_str() {
+                        "DAILY" => Period::Daily,
+                        "WEEKLY" => Period::Weekly,
+                        "MONTHLY" => Period::Monthly,
+                        "YEARLY" => Period::Yearly,
+                        other => return Err(invalid(format!("unsupported FREQ '{}'", other))),
+                    })
+                }
+                "INTERVAL" => {
+                    rule.interval = value
+                        .parse::<u32>()
+                        .ok()
+                        .filter(|n| (1..=999).contains(n))
+                        .ok_or_else(|| invalid(format!("INTERVAL must be 1-999, got '{}'", value)))?;
+                }
+                "BYMONTHDAY" => {
+                    rule.by_month_day = parse_numbers(&value, 31).map_err(|v| invalid(format!("bad BYMONTHDAY value '{}'", v)))?;
+                }
+                "BYDAY" => {
+                    rule.by_weekday = value
+                        .split(',')
+                        .map(|d| parse_weekday(d.trim()).ok_or_else(|| invalid(format!("bad BYDAY value '{}'", d))))
+                        .collect::<Result<_, _>>()?;
+                }
+                "BYSETPOS" => {
+                    rule.by_set_pos = parse_numbers(&value, 366).map_err(|v| invalid(format!("bad BYSETPOS value '{}'", v)))?;
+                }
+                other => return Err(invalid(format!("unsupported part '{}'", other))),
+            }
+        }
+
+        rule.period = period.ok_or_else(|| invalid("FREQ is required".to_string()))?;
+        Ok(rule)
+    }
+
+    /// The rule stored for an item: its `recurrence_rule` if set, else its plain frequency.
+    pub fn for_item(frequency: &str, recurrence_rule: Option<&str>) -> Result<Self, FinancerError> {
+        match recurrence_rule.map(str::trim).filter(|r| !r.is_empty()) {
+            Some(text) => RecurrenceRule::parse(text),
+            None => Ok(RecurrenceRule::from_period(Period::from_str(frequency))),
+        }
+    }
+
+    /// Canonical RRULE text, the form stored in the database.
+    pub fn to_rule_string(&self) -> String {
+        let freq = match self.period {
+            Period::Daily => "DAILY",
+            Period::Weekly => "WEEKLY",
+            Period::Monthly => "MONTHLY",
+            Period::Yearly => "YEARLY",
+        };
+        let mut parts = vec![format!("FREQ={}", freq)];
+        if self.interval > 1 {
+            parts.push(format!("INTERVAL={}", self.interval));
+        }
+        if !self.by_month_day.is_empty() {
+            parts.push(format!("BYMONTHDAY={}", join_numbers(&self.by_month_day)));
+        }
+        if !self.by_weekday.is_empty() {
+            let days: Vec<&str> = self.by_weekday.iter().map(|d| weekday_code(*d)).collect();
+            parts.push(format!("BYDAY={}", days.join(",")));
+        }
+        if !self.by_set_pos.is_empty() {
+            parts.push(format!("BYSETPOS={}", join_numbers(&self.by_set_pos)));
+        }
+        parts.join(";")
+    }
+
+    /// The first occurrence strictly after `current`, keeping its time of day.
+    ///
+    /// Later days in the same period as `current` come first; after that the search jumps
+    /// `interval` periods at a time. Without any BY parts, monthly and yearly rules keep the
+    /// day of month of `current`, clamped to shorter months. `None` means the rule has no
+    /// further occurrences (e.g. `BYMONTHDAY=30` every 12 months from a date in February).
+    pub fn next_after(&self, current: NaiveDateTime) -> Option<NaiveDateTime> {
+        let anchor = current.date();
+        let step = self.interval.max(1) as i64;
+        (0..MAX_PERIODS_SEARCHED)
+            .flat_map(|n| self.days_in_period(anchor, n * step))
+            .find(|day| *day > anchor)
+            .map(|day| day.and_time(current.time()))
+    }
+
+    /// Up to `count` occurrences following `current`.
+    pub fn occurrences_after(&self, current: NaiveDateTime, count: usize) -> Vec<NaiveDateTime> {
+        let mut out = Vec::with_capacity(count);
+        let mut at = current;
+        while out.len() < count {
+            match self.next_after(at) {
+                Some(next) => {
+                    out.push(next);
+                    at = next;
+                }
+                None => break,
+            }
+        }
+        out
+    }
+
+    /// The matching days, in order, of the period `offset` periods after the one holding `anchor`.
+    fn days_in_period(&self, anchor: NaiveDate, offset: i64) -> Vec<NaiveDate> {
+        let mut days: Vec<NaiveDate> = match self.period {
+            Period::Daily => vec![anchor + Duration::days(offset)],
+            Period::Weekly => {
+                let monday = anchor - Duration::days(anchor.weekday().num_days_from_monday() as i64)
+                    + Duration::weeks(offset);
+                (0..7)
+                    .map(|i| monday + Duration::days(i))
+                    .filter(|d| !self.by_weekday.is_empty() || d.weekday() == anchor.weekday())
+                    .collect()
+            }
+            Period::Monthly | Period::Yearly => {
+                let months = if self.period == Period::Yearly { offset * 12 } else { offset };
+                let first = shift_months(first_of_month(anchor), months);
+                let length = days_in_month(first);
+                if self.by_month_day.is_empty() && self.by_weekday.is_empty() {
+                    let day = anchor.day().min(length);
+                    vec![first.with_day(day).expect("clamped day is valid")]
+                } else if self.period == Period::Yearly {
+                    // Like RRULE without BYMONTH, the BY parts pick from the whole year.
+                    let january = first.with_month(1).expect("January is valid");
+                    let next_january = shift_months(january, 12);
+                    january.iter_days().take_while(|d| *d < next_january).collect()
+                } else {
+                    (1..=length).map(|d| first.with_day(d).expect("day within month")).collect()
+                }
+            }
+        };
+
+        days.retain(|d| self.matches_by_parts(*d));
+        if self.by_set_pos.is_empty() {
+            return days;
+        }
+
+        let mut picked: Vec<NaiveDate> = self
+            .by_set_pos
+            .iter()
+            .filter_map(|pos| {
+                let index = if *pos > 0 { *pos as i64 - 1 } else { days.len() as i64 + *pos as i64 };
+                usize::try_from(index).ok().and_then(|i| days.get(i).copied())
+            })
+            .collect();
+        picked.sort();
+        picked.dedup();
+        picked
+    }
+
+    fn matches_by_parts(&self, day: NaiveDate) -> bool {
+        let weekday_ok = self.by_weekday.is_empty() || self.by_weekday.contains(&day.weekday());
+        let length = days_in_month(day) as i32;
+        let month_day_ok = self.by_month_day.is_empty()
+            || self
+                .by_month_day
+                .iter()
+                .any(|d| if *d > 0 { *d == day.day() as i32 } else { length + d + 1 == day.day() as i32 });
+        weekday_ok && month_day_ok
+    }
+
+    /// Short human-readable summary for lists, e.g. "Every 2 weeks" or "Monthly on day 1, 15".
+    pub fn describe(&self) -> String {
+        if let Some((label, _)) = presets().into_iter().find(|(_, rule)| rule == self) {
+            return label.to_string();
+        }
+
+        let unit = match self.period {
+            Period::Daily => "day",
+            Period::Weekly => "week",
+            Period::Monthly => "month",
+            Period::Yearly => "year",
+        };
+        let mut text = if self.interval > 1 {
+            format!("Every {} {}s", self.interval, unit)
+        } else {
+            self.period.to_str().to_string()
+        };
+        let mut filters = Vec::new();
+        if !self.by_weekday.is_empty() {
+            let days: Vec<String> = self.by_weekday.iter().map(|d| format!("{:?}", d)).collect();
+            filters.push(days.join(", "));
+        }
+        if !self.by_month_day.is_empty() {
+            let days: Vec<String> = self
+                .by_month_day
+                .iter()
+                .map(|d| if *d == -1 { "last".to_string() } else if *d < 0 { format!("{} from end", -d) } else { d.to_string() })
+                .collect();
+            filters.push(format!("day {}", days.join(", ")));
+        }
+        if !self.by_set_pos.is_empty() {
+            let positions: Vec<String> = self.by_set_pos.iter().map(|p| ordinal(*p)).collect();
+            let matching = if filters.is_empty() { "matching days".to_string() } else { filters.join(" / ") };
+            text.push_str(&format!(" on the {} of {}", positions.join(" and "), matching));
+        } else if !filters.is_empty() {
+            text.push_str(&format!(" on {}", filters.join(" / ")));
+        }
+        text
+    }
+}
+
+/// Whether a schedule whose next occurrence is `next_run_at` has nothing left to post:
+/// its occurrence limit is used up, or that occurrence falls after its end date.
+pub fn schedule_finished(
+    next_run_at: NaiveDateTime,
+    ends_at: Option<NaiveDateTime>,
+    max_occurrences: Option<i32>,
+    occurrences_run: i32,
+) -> bool {
+    max_occurrences.is_some_and(|max| occurrences_run >= max) || ends_at.is_some_and(|end| next_run_at > end)
+}
+
+/// Common schedules offered in the recurring item forms.
+pub fn presets() -> Vec<(&'static str, RecurrenceRule)> {
+    let rule = |text: &str| RecurrenceRule::parse(text).expect("preset rules are valid");
+    vec![
+        ("Daily", rule("FREQ=DAILY")),
+        ("Weekly", rule("FREQ=WEEKLY")),
+        ("Bi-weekly", rule("FREQ=WEEKLY;INTERVAL=2")),
+        ("Semi-monthly (1st and 15th)", rule("FREQ=MONTHLY;BYMONTHDAY=1,15")),
+        ("Monthly", rule("FREQ=MONTHLY")),
+        ("Quarterly", rule("FREQ=MONTHLY;INTERVAL=3")),
+        ("Yearly", rule("FREQ=YEARLY")),
+        ("Last business day of the month", rule("FREQ=MONTHLY;BYDAY=MO,TU,WE,TH,FR;BYSETPOS=-1")),
+        ("Second Tuesday of the month", rule("FREQ=MONTHLY;BYDAY=TU;BYSETPOS=2")),
+    ]
+}
+
+fn parse_numbers(value: &str, max_abs: i32) -> Result<Vec<i32>, String> {
+    value
+        .split(',')
+        .map(|v| {
+            v.trim()
+                .parse::<i32>()
+                .ok()
+                .filter(|n| *n != 0 && n.abs() <= max_abs)
+                .ok_or_else(|| v.trim().to_string())
+        })
+        .collect()
+}
+
+fn join_numbers(values: &[i32]) -> String {
+    values.iter().map(|v| v.to_string()).collect::<Vec<_>>().join(",")
+}
+
+fn parse_weekday(code: &str) -> Option<Weekday> {
+    WEEKDAYS.into_iter().find(|d| weekday_code(*d) == code)
+}
+
+fn weekday_code(day: Weekday) -> &'static str {
+    match day {
+        Weekday::Mon => "MO",
+        Weekday::Tue => "TU",
+        Weekday::Wed => "WE",
+        Weekday::Thu => "TH",
+        Weekday::Fri => "FR",
+        Weekday::Sat => "SA",
+        Weekday::Sun => "SU",
+    }
+}
+
+fn ordinal(pos: i32) -> String {
+    match pos {
+        -1 => "last".to_string(),
+        -2 => "second to last".to_string(),
+        p if p < 0 => format!("{} from last", -p),
+        1 => "first".to_string(),
+        2 => "second".to_string(),
+        3 => "third".to_string(),
+        4 => "fourth".to_string(),
+        p => format!("{}th", p),
+    }
+}
+
+fn first_of_month(date: NaiveDate) -> NaiveDate {
+    date.with_day(1).expect("every month has a first day")
+}
+
+/// The first day of the month `months` after the one holding `date`.
+pub fn month_start_after(date: NaiveDate, months: i64) -> NaiveDate {
+    shift_months(first_of_month(date), months)
+}
+
+fn shift_months(first: NaiveDate, months: i64) -> NaiveDate {
+    let total = first.year() as i64 * 12 + first.month0() as i64 + months;
+    NaiveDate::from_ymd_opt(total.div_euclid(12) as i32, total.rem_euclid(12) as u32 + 1, 1)
+        .expect("first of month is valid")
+}
+
+fn days_in_month(date: NaiveDate) -> u32 {
+    let first = first_of_month(date);
+    (shift_months(first, 1) - first).num_days() as u32
+}
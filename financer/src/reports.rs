@@ -0,0 +1,76 @@
+//! Monthly income statements and per-category trends, built from the category totals the
+//! database adds up. Nothing here touches the database.
+
+use chrono::NaiveDate;
+
+/// Income and expenses by category for one month, largest first. Amounts are positive cents.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IncomeStatement {
+    /// The first day of the month.
+    pub month: NaiveDate,
+    pub income: Vec<(String, i64)>,
+    pub expenses: Vec<(String, i64)>,
+}
+
+impl IncomeStatement {
+    pub fn total_income(&self) -> i64 {
+        self.income.iter().map(|(_, amount)| amount).sum()
+    }
+
+    pub fn total_expenses(&self) -> i64 {
+        self.expenses.iter().map(|(_, amount)| amount).sum()
+    }
+
+    pub fn net(&self) -> i64 {
+        self.total_income() - self.total_expenses()
+    }
+}
+
+/// One category's monthly amounts over a run of months, oldest first. Income categories
+/// count money in and expense categories money out, so a rise means more of either.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CategoryTrend {
+    pub category: String,
+    /// Whether the category brought in more than it paid out over the whole run.
+    pub is_income: bool,
+    /// Cents per month, in the direction above.
+    pub amounts: Vec<i64>,
+    /// The same months a year earlier.
+    pub year_ago: Vec<i64>,
+}
+
+impl CategoryTrend {
+    /// Builds a trend from each month's signed net amount, oldest first: the twelve months
+    /// before the run, then the run itself.
+    pub fn from_net(category: String, net: &[i64]) -> Self {
+        let run = net.get(12..).unwrap_or_default();
+        let is_income = run.iter().sum::<i64>() > 0;
+        let direction = if is_income { 1 } else { -1 };
+        CategoryTrend {
+            category,
+            is_income,
+            amounts: run.iter().map(|n| n * direction).collect(),
+            year_ago: net[..run.len()].iter().map(|n| n * direction).collect(),
+        }
+    }
+
+    pub fn latest(&self) -> i64 {
+        self.amounts.last().copied().unwrap_or(0)
+    }
+
+    /// Change in the latest month from the one before.
+    pub fn month_over_month(&self) -> Option<i64> {
+        let before = self.amounts.len().checked_sub(2).map(|i| self.amounts[i])?;
+        Some(self.latest() - before)
+    }
+
+    /// Change in the latest month from the same month a year earlier.
+    pub fn year_over_year(&self) -> Option<i64> {
+        Some(self.latest() - self.year_ago.last()?)
+    }
+}
+
+/// `change` as a percentage of `before`. `None` when there was nothing before to compare with.
+pub fn percent_change(before: i64, change: i64) -> Option<f64> {
+    (before != 0).then(|| change as f64 / before.abs() as f64 * 100.0)
+}
@@ -0,0 +1,228 @@
+//! Projection of recurring items into concrete dated occurrences, for the Upcoming screen
+//! and its iCalendar export. Nothing here touches the database.
+
+use std::collections::{BTreeMap, HashMap};
+
+use chrono::{NaiveDate, NaiveDateTime};
+
+use crate::calendar::HolidayCalendar;
+use crate::models::{RecurringTransaction, RecurringTransfer, RollConvention};
+use crate::recurrence::{schedule_finished, RecurrenceRule};
+
+/// Safety valve for rules that fire very often relative to the window (e.g. daily for years).
+const MAX_OCCURRENCES_PER_ITEM: usize = 5_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OccurrenceSource {
+    Transaction(i32),
+    Transfer(i32),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Occurrence {
+    pub at: NaiveDateTime,
+    pub source: OccurrenceSource,
+    /// The charged account; for transfers, the account the money leaves.
+    pub account_id: i32,
+    pub to_account_id: Option<i32>,
+    /// Signed like transaction amounts; transfers are always positive.
+    pub amount: f32,
+    pub category: String,
+}
+
+impl Occurrence {
+    pub fn is_transfer(&self) -> bool {
+        matches!(self.source, OccurrenceSource::Transfer(_))
+    }
+}
+
+/// Income, bills and transfers scheduled on one day.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct DayTotals {
+    pub income: f32,
+    pub expenses: f32,
+    pub transfers: f32,
+}
+
+impl DayTotals {
+    /// Net change across all accounts; transfers cancel out.
+    pub fn net(&self) -> f32 {
+        self.income - self.expenses
+    }
+}
+
+/// Dates at or after `from` and before `to` on which an item will run, honouring its rule,
+/// end date and remaining occurrence count, rolled off weekends and holidays. Paused and
+/// finished items yield nothing.
+#[allow(clippy::too_many_arguments)]
+fn item_dates(
+    next_run_at: &str,
+    rule_text: &str,
+    ends_at: Option<&str>,
+    max_occurrences: Option<i32>,
+    occurrences_run: i32,
+    calendar: &HolidayCalendar,
+    roll: RollConvention,
+    from: NaiveDateTime,
+    to: NaiveDateTime,
+) -> Vec<NaiveDateTime> {
+    let parse = |s: &str| NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").ok();
+    let (Some(mut at), Ok(rule)) = (parse(next_run_at), RecurrenceRule::parse(rule_text)) else {
+        return Vec::new();
+    };
+    let ends = ends_at.and_then(parse);
+
+    let mut dates = Vec::new();
+    let mut run = occurrences_run;
+    let mut steps = 0;
+    while steps < MAX_OCCURRENCES_PER_ITEM && !schedule_finished(at, ends, max_occurrences, run) {
+        let when = calendar.roll_datetime(at, roll);
+        if when >= to {
+            break;
+        }
+        if when >= from {
+            dates.push(when);
+        }
+        run += 1;
+        steps += 1;
+        match rule.next_after(at) {
+            Some(next) => at = next,
+            None => break,
+        }
+    }
+    dates
+}
+
+/// Every occurrence of the active items between `from` (inclusive) and `to` (exclusive),
+/// sorted by time. Overdue items that haven't been posted yet still count against their
+/// occurrence limit but only appear if they fall inside the window. Each item is rolled
+/// using its owner's entry in `calendars`; owners without one only skip weekends.
+pub fn expand_occurrences(
+    transactions: &[RecurringTransaction],
+    transfers: &[RecurringTransfer],
+    calendars: &HashMap<i32, HolidayCalendar>,
+    from: NaiveDateTime,
+    to: NaiveDateTime,
+) -> Vec<Occurrence> {
+    let weekends_only = HolidayCalendar::default();
+    let calendar_of = |owner: i32| calendars.get(&owner).unwrap_or(&weekends_only);
+    let mut out = Vec::new();
+
+    for item in transactions.iter().filter(|i| i.active) {
+        let dates = item_dates(
+            &item.next_run_at,
+            &item.recurrence_rule,
+            item.ends_at.as_deref(),
+            item.max_occurrences,
+            item.occurrences_run,
+            calendar_of(item.user_id),
+            RollConvention::from_str(&item.roll_convention),
+            from,
+            to,
+        );
+        out.extend(dates.into_iter().map(|at| Occurrence {
+            at,
+            source: OccurrenceSource::Transaction(item.id),
+            account_id: item.account_id,
+            to_account_id: None,
+            amount: item.amount,
+            category: item.category.clone(),
+        }));
+    }
+
+    for item in transfers.iter().filter(|i| i.active) {
+        let dates = item_dates(
+            &item.next_run_at,
+            &item.recurrence_rule,
+            item.ends_at.as_deref(),
+            item.max_occurrences,
+            item.occurrences_run,
+            calendar_of(item.user_id),
+            RollConvention::from_str(&item.roll_convention),
+            from,
+            to,
+        );
+        out.extend(dates.into_iter().map(|at| Occurrence {
+            at,
+            source: OccurrenceSource::Transfer(item.id),
+            account_id: item.from_account_id,
+            to_account_id: Some(item.to_account_id),
+            amount: item.amount,
+            category: "Transfer".to_string(),
+        }));
+    }
+
+    out.sort_by(|a, b| a.at.cmp(&b.at).then(a.category.cmp(&b.category)));
+    out
+}
+
+pub fn daily_totals(occurrences: &[Occurrence]) -> BTreeMap<NaiveDate, DayTotals> {
+    let mut totals: BTreeMap<NaiveDate, DayTotals> = BTreeMap::new();
+    for occ in occurrences {
+        let day = totals.entry(occ.at.date()).or_default();
+        if occ.is_transfer() {
+            day.transfers += occ.amount;
+        } else if occ.amount >= 0.0 {
+            day.income += occ.amount;
+        } else {
+            day.expenses += -occ.amount;
+        }
+    }
+    totals
+}
+
+/// An iCalendar (RFC 5545) file with one all-day event per occurrence.
+/// `summary` names each event; `stamp` is written as the DTSTAMP of every event.
+pub fn to_ics(occurrences: &[Occurrence], summary: impl Fn(&Occurrence) -> String, stamp: NaiveDateTime) -> String {
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//FinanceR//Upcoming//EN".to_string(),
+        "CALSCALE:GREGORIAN".to_string(),
+    ];
+    for occ in occurrences {
+        let (kind, id) = match occ.source {
+            OccurrenceSource::Transaction(id) => ("tx", id),
+            OccurrenceSource::Transfer(id) => ("transfer", id),
+        };
+        let day = occ.at.date();
+        lines.push("BEGIN:VEVENT".to_string());
+        lines.push(format!("UID:{}-{}-{}@financer", kind, id, day.format("%Y%m%d")));
+        lines.push(format!("DTSTAMP:{}", stamp.format("%Y%m%dT%H%M%S")));
+        lines.push(format!("DTSTART;VALUE=DATE:{}", day.format("%Y%m%d")));
+        lines.push(format!("DTEND;VALUE=DATE:{}", day.succ_opt().unwrap_or(day).format("%Y%m%d")));
+        lines.push(format!("SUMMARY:{}", escape_text(&summary(occ))));
+        lines.push("TRANSP:TRANSPARENT".to_string());
+        lines.push("END:VEVENT".to_string());
+    }
+    lines.push("END:VCALENDAR".to_string());
+
+    let mut out = String::new();
+    for line in lines {
+        out.push_str(&fold_line(&line));
+        out.push_str("\r\n");
+    }
+    out
+}
+
+fn escape_text(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+/// Content lines longer than 75 octets continue on the next line after a space.
+fn fold_line(line: &str) -> String {
+    let mut out = String::new();
+    let mut used = 0;
+    for ch in line.chars() {
+        if used + ch.len_utf8() > 75 {
+            out.push_str("\r\n ");
+            used = 1;
+        }
+        out.push(ch);
+        used += ch.len_utf8();
+    }
+    out
+}
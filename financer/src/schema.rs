@@ -11,6 +11,47 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    envelope_assignments (id) {
+        id -> Integer,
+        envelope_id -> Integer,
+        month -> Text,
+        amount_cents -> Integer,
+        created_at -> Text,
+    }
+}
+
+diesel::table! {
+    envelopes (id) {
+        id -> Integer,
+        user_id -> Integer,
+        category -> Text,
+        starts_on -> Text,
+    }
+}
+
+diesel::table! {
+    holidays (id) {
+        id -> Integer,
+        user_id -> Integer,
+        holiday_date -> Text,
+        name -> Text,
+    }
+}
+
+diesel::table! {
+    recurring_runs (id) {
+        id -> Integer,
+        item_kind -> Text,
+        item_id -> Integer,
+        scheduled_for -> Text,
+        status -> Text,
+        amount -> Float,
+        decided_by -> Nullable<Integer>,
+        decided_at -> Nullable<Text>,
+    }
+}
+
 diesel::table! {
     recurring_transactions (id) {
         id -> Integer,
@@ -22,6 +63,12 @@ diesel::table! {
         next_run_at -> Text,
         frequency -> Text,
         active -> Bool,
+        recurrence_rule -> Text,
+        ends_at -> Nullable<Text>,
+        max_occurrences -> Nullable<Integer>,
+        occurrences_run -> Integer,
+        requires_approval -> Bool,
+        roll_convention -> Text,
     }
 }
 
@@ -35,6 +82,34 @@ diesel::table! {
         next_run_at -> Text,
         frequency -> Text,
         active -> Bool,
+        recurrence_rule -> Text,
+        ends_at -> Nullable<Text>,
+        max_occurrences -> Nullable<Integer>,
+        occurrences_run -> Integer,
+        requires_approval -> Bool,
+        roll_convention -> Text,
+    }
+}
+
+diesel::table! {
+    budget_template_items (id) {
+        id -> Integer,
+        template_id -> Integer,
+        name -> Nullable<Text>,
+        category -> Text,
+        extra_categories -> Text,
+        limit_cents -> Integer,
+        period -> Text,
+        target_type -> Text,
+    }
+}
+
+diesel::table! {
+    budget_templates (id) {
+        id -> Integer,
+        user_id -> Integer,
+        name -> Text,
+        created_at -> Text,
     }
 }
 
@@ -48,6 +123,44 @@ diesel::table! {
         target_type -> Text,
         active -> Bool,
         updated_at -> Timestamp,
+        rollover_mode -> Text,
+        rollover_cap_cents -> Nullable<Integer>,
+        alert_thresholds -> Text,
+        name -> Nullable<Text>,
+        include_subcategories -> Bool,
+        starts_on -> Nullable<Text>,
+        ends_on -> Nullable<Text>,
+        created_at -> Text,
+    }
+}
+
+diesel::table! {
+    balance_snapshots (account_id, day) {
+        account_id -> Integer,
+        day -> Text,
+        balance_cents -> Integer,
+    }
+}
+
+diesel::table! {
+    budget_accounts (budget_id, account_id) {
+        budget_id -> Integer,
+        account_id -> Integer,
+    }
+}
+
+diesel::table! {
+    budget_carries (budget_id, period_start) {
+        budget_id -> Integer,
+        period_start -> Text,
+        carried_cents -> BigInt,
+    }
+}
+
+diesel::table! {
+    budget_categories (budget_id, category) {
+        budget_id -> Integer,
+        category -> Text,
     }
 }
 
@@ -59,6 +172,25 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    savings_goal_accounts (goal_id, account_id) {
+        goal_id -> Integer,
+        account_id -> Integer,
+    }
+}
+
+diesel::table! {
+    savings_goals (id) {
+        id -> Integer,
+        user_id -> Integer,
+        name -> Text,
+        target_cents -> Integer,
+        target_date -> Text,
+        expected_rate -> Double,
+        created_at -> Text,
+    }
+}
+
 diesel::table! {
     transactions (id) {
         id -> Integer,
@@ -69,6 +201,18 @@ diesel::table! {
         date -> Text,
         amount_cents -> Integer,
         balance_after -> Float,
+        created_by -> Nullable<Integer>,
+        recurring_id -> Nullable<Integer>,
+        recurring_kind -> Nullable<Text>,
+        is_transfer -> Bool,
+    }
+}
+
+diesel::table! {
+    account_members (account_id, user_id) {
+        account_id -> Integer,
+        user_id -> Integer,
+        role -> Text,
     }
 }
 
@@ -78,24 +222,120 @@ diesel::table! {
         username -> Text,
         password_hash -> Text,
         email -> Nullable<Text>,
+        low_balance_threshold -> Float,
+    }
+}
+
+diesel::table! {
+    login_attempts (username) {
+        username -> Text,
+        failed_count -> Integer,
+        last_failed_at -> Nullable<Text>,
+        locked_until -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    login_events (id) {
+        id -> Integer,
+        username -> Text,
+        user_id -> Nullable<Integer>,
+        succeeded -> Bool,
+        reason -> Text,
+        occurred_at -> Text,
+    }
+}
+
+diesel::table! {
+    user_totp (user_id) {
+        user_id -> Integer,
+        key_salt -> Text,
+        nonce -> Text,
+        ciphertext -> Text,
+        enabled -> Bool,
+        last_used_step -> Nullable<BigInt>,
+        created_at -> Text,
+    }
+}
+
+diesel::table! {
+    notifications (id) {
+        id -> Integer,
+        user_id -> Integer,
+        kind -> Text,
+        budget_id -> Nullable<Integer>,
+        period_start -> Nullable<Text>,
+        threshold -> Nullable<Integer>,
+        message -> Text,
+        created_at -> Text,
+        is_read -> Bool,
+        dismissed -> Bool,
+    }
+}
+
+diesel::table! {
+    recovery_codes (id) {
+        id -> Integer,
+        user_id -> Integer,
+        code_hash -> Text,
+        used_at -> Nullable<Text>,
     }
 }
 
+diesel::joinable!(account_members -> accounts (account_id));
+diesel::joinable!(account_members -> users (user_id));
 diesel::joinable!(accounts -> users (user_id));
+diesel::joinable!(balance_snapshots -> accounts (account_id));
+diesel::joinable!(budget_accounts -> accounts (account_id));
+diesel::joinable!(budget_accounts -> budgets (budget_id));
+diesel::joinable!(budget_carries -> budgets (budget_id));
+diesel::joinable!(budget_categories -> budgets (budget_id));
+diesel::joinable!(budget_template_items -> budget_templates (template_id));
+diesel::joinable!(budget_templates -> users (user_id));
 diesel::joinable!(budgets -> users (user_id));
 diesel::joinable!(contacts -> users (user));
+diesel::joinable!(envelope_assignments -> envelopes (envelope_id));
+diesel::joinable!(envelopes -> users (user_id));
+diesel::joinable!(holidays -> users (user_id));
+diesel::joinable!(login_events -> users (user_id));
+diesel::joinable!(notifications -> budgets (budget_id));
+diesel::joinable!(notifications -> users (user_id));
+diesel::joinable!(recovery_codes -> users (user_id));
+diesel::joinable!(recurring_runs -> users (decided_by));
 diesel::joinable!(recurring_transactions -> users (user_id));
 diesel::joinable!(recurring_transactions -> accounts (account_id));
 diesel::joinable!(recurring_transfers -> users (user_id));
+diesel::joinable!(savings_goal_accounts -> accounts (account_id));
+diesel::joinable!(savings_goal_accounts -> savings_goals (goal_id));
+diesel::joinable!(savings_goals -> users (user_id));
 diesel::joinable!(transactions -> accounts (user_account_id));
 diesel::joinable!(transactions -> contacts (contact_id));
+diesel::joinable!(user_totp -> users (user_id));
 
 diesel::allow_tables_to_appear_in_same_query!(
+    account_members,
     accounts,
+    balance_snapshots,
+    budget_accounts,
+    budget_carries,
+    budget_categories,
+    budget_template_items,
+    budget_templates,
     budgets,
     contacts,
+    envelope_assignments,
+    envelopes,
+    holidays,
+    login_attempts,
+    login_events,
+    notifications,
+    recovery_codes,
+    recurring_runs,
     recurring_transactions,
     recurring_transfers,
+    savings_goal_accounts,
+    savings_goals,
     transactions,
+    user_totp,
     users,
 );
@@ -0,0 +1,184 @@
+//! RFC 6238 time-based one-time passwords, secret encryption and recovery codes.
+//! Everything here is pure and clock-free so it can be tested with fixed timestamps.
+
+use argon2::Argon2;
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use data_encoding::{BASE32_NOPAD, BASE64};
+use hmac::{Hmac, Mac};
+use rand::{Rng, RngCore};
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+
+use crate::error::FinancerError;
+
+pub const STEP_SECS: i64 = 30;
+pub const DIGITS: u32 = 6;
+/// Codes from one step either side of the current one are accepted, to allow for clock drift.
+pub const ALLOWED_SKEW_STEPS: i64 = 1;
+pub const RECOVERY_CODE_COUNT: usize = 10;
+const ISSUER: &str = "FinanceR";
+
+/// A fresh 160-bit secret, base32 encoded as authenticator apps expect.
+pub fn generate_secret() -> String {
+    let mut bytes = [0u8; 20];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    BASE32_NOPAD.encode(&bytes)
+}
+
+fn decode_secret(secret_base32: &str) -> Result<Vec<u8>, FinancerError> {
+    BASE32_NOPAD
+        .decode(secret_base32.trim_end_matches('=').as_bytes())
+        .map_err(|e| FinancerError::CorruptData(format!("TOTP secret is not valid base32: {}", e)))
+}
+
+pub fn time_step(unix_time: i64) -> i64 {
+    unix_time.div_euclid(STEP_SECS)
+}
+
+/// HOTP (RFC 4226) value for a counter, truncated to `DIGITS` digits.
+fn hotp(key: &[u8], counter: i64) -> String {
+    let mut mac = <Hmac<Sha1> as Mac>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(&(counter as u64).to_be_bytes());
+    let digest = mac.finalize().into_bytes();
+
+    let offset = (digest[digest.len() - 1] & 0x0f) as usize;
+    let binary = u32::from_be_bytes([digest[offset] & 0x7f, digest[offset + 1], digest[offset + 2], digest[offset + 3]]);
+    format!("{:0width$}", binary % 10u32.pow(DIGITS), width = DIGITS as usize)
+}
+
+#[allow(dead_code)]
+pub fn code_at(secret_base32: &str, unix_time: i64) -> Result<String, FinancerError> {
+    Ok(hotp(&decode_secret(secret_base32)?, time_step(unix_time)))
+}
+
+/// Returns the matching time step when `code` is valid at `unix_time`, so callers can
+/// refuse a code that has already been used.
+pub fn verify_code(secret_base32: &str, code: &str, unix_time: i64) -> Result<Option<i64>, FinancerError> {
+    if !looks_like_totp_code(code) {
+        return Ok(None);
+    }
+    let code = code.trim();
+    let key = decode_secret(secret_base32)?;
+    let current = time_step(unix_time);
+    Ok((-ALLOWED_SKEW_STEPS..=ALLOWED_SKEW_STEPS)
+        .map(|skew| current + skew)
+        .find(|step| hotp(&key, *step) == code))
+}
+
+pub fn looks_like_totp_code(code: &str) -> bool {
+    let code = code.trim();
+    code.len() == DIGITS as usize && code.chars().all(|c| c.is_ascii_digit())
+}
+
+/// `otpauth://` URI for authenticator apps; the QR code on the enrollment screen encodes it.
+pub fn provisioning_uri(secret_base32: &str, account_name: &str) -> String {
+    format!(
+        "otpauth://totp/{issuer}:{account}?secret={secret}&issuer={issuer}&algorithm=SHA1&digits={digits}&period={period}",
+        issuer = ISSUER,
+        account = percent_encode(account_name),
+        secret = secret_base32,
+        digits = DIGITS,
+        period = STEP_SECS,
+    )
+}
+
+fn percent_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+/// Dark/light modules of a QR code for `data`, row by row, with the side length.
+pub fn qr_modules(data: &str) -> Option<(usize, Vec<bool>)> {
+    let code = qrcode::QrCode::new(data.as_bytes()).ok()?;
+    let width = code.width();
+    let modules = code
+        .to_colors()
+        .into_iter()
+        .map(|c| c == qrcode::Color::Dark)
+        .collect();
+    Some((width, modules))
+}
+
+/// A TOTP secret encrypted under a key derived from the user's password.
+pub struct EncryptedSecret {
+    pub key_salt: String,
+    pub nonce: String,
+    pub ciphertext: String,
+}
+
+fn derive_key(password: &str, salt: &[u8]) -> Result<[u8; 32], FinancerError> {
+    let mut key = [0u8; 32];
+    Argon2::default()
+        .hash_password_into(password.as_bytes(), salt, &mut key)
+        .map_err(|e| FinancerError::Internal(format!("Encryption key could not be derived: {}", e)))?;
+    Ok(key)
+}
+
+pub fn encrypt_secret(secret_base32: &str, password: &str) -> Result<EncryptedSecret, FinancerError> {
+    let mut salt = [0u8; 16];
+    let mut nonce = [0u8; 24];
+    rand::thread_rng().fill_bytes(&mut salt);
+    rand::thread_rng().fill_bytes(&mut nonce);
+
+    let cipher = XChaCha20Poly1305::new(&derive_key(password, &salt)?.into());
+    let ciphertext = cipher
+        .encrypt(XNonce::from_slice(&nonce), secret_base32.as_bytes())
+        .map_err(|_| FinancerError::Internal("TOTP secret could not be encrypted".to_string()))?;
+
+    Ok(EncryptedSecret {
+        key_salt: BASE64.encode(&salt),
+        nonce: BASE64.encode(&nonce),
+        ciphertext: BASE64.encode(&ciphertext),
+    })
+}
+
+/// Fails with `CorruptData` when the stored blob is unreadable, or when `password` is not
+/// the one it was encrypted with.
+pub fn decrypt_secret(encrypted: &EncryptedSecret, password: &str) -> Result<String, FinancerError> {
+    let decode = |field: &str| {
+        BASE64
+            .decode(field.as_bytes())
+            .map_err(|e| FinancerError::CorruptData(format!("TOTP secret is not valid base64: {}", e)))
+    };
+    let salt = decode(&encrypted.key_salt)?;
+    let nonce = decode(&encrypted.nonce)?;
+    let ciphertext = decode(&encrypted.ciphertext)?;
+    if nonce.len() != 24 {
+        return Err(FinancerError::CorruptData("TOTP nonce has the wrong length".to_string()));
+    }
+
+    let cipher = XChaCha20Poly1305::new(&derive_key(password, &salt)?.into());
+    let plaintext = cipher
+        .decrypt(XNonce::from_slice(&nonce), ciphertext.as_ref())
+        .map_err(|_| FinancerError::CorruptData("TOTP secret could not be decrypted".to_string()))?;
+    String::from_utf8(plaintext).map_err(|_| FinancerError::CorruptData("TOTP secret is not UTF-8".to_string()))
+}
+
+/// Recovery codes look like `7KQ2-M9XD`; ambiguous characters (0/O, 1/I) are left out.
+pub fn generate_recovery_codes() -> Vec<String> {
+    const ALPHABET: &[u8] = b"23456789ABCDEFGHJKLMNPQRSTUVWXYZ";
+    let mut rng = rand::thread_rng();
+    (0..RECOVERY_CODE_COUNT)
+        .map(|_| {
+            let chars: String = (0..8).map(|_| ALPHABET[rng.gen_range(0..ALPHABET.len())] as char).collect();
+            format!("{}-{}", &chars[..4], &chars[4..])
+        })
+        .collect()
+}
+
+/// Recovery codes are random enough that a plain SHA-256 is sufficient for storage.
+/// Case and separators are ignored so `7kq2 m9xd` matches `7KQ2-M9XD`.
+pub fn hash_recovery_code(code: &str) -> String {
+    let normalized: String = code
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+    BASE64.encode(&Sha256::digest(normalized.as_bytes()))
+}
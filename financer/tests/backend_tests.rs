@@ -1,5 +1,10 @@
 // Unit tests for db.rs backend functions
 
+// Some older tests re-import diesel traits that the prelude already provides, and the
+// connection smoke test asserts a constant once setup has succeeded.
+#![allow(unused_imports)]
+#![allow(clippy::assertions_on_constants)]
+
 #[cfg(test)]
 mod tests {
     use diesel::sqlite::SqliteConnection;
@@ -8,6 +13,7 @@ mod tests {
     use diesel::prelude::*;
     use diesel::ExpressionMethods;
     use financer::db::*;
+    use financer::error::FinancerError;
     use financer::models::*;
     use financer::schema::contacts::dsl::*;
     use chrono::NaiveDate;
@@ -114,6 +120,13 @@ mod tests {
                 limit_cents: 50000,
                 period: "Monthly".to_string(),
                 target_type: "Expense".to_string(),
+                rollover_mode: "none".to_string(),
+                rollover_cap_cents: None,
+                alert_thresholds: "80,100".to_string(),
+                name: None,
+                include_subcategories: false,
+                starts_on: None,
+                ends_on: None,
             },
         )
         .unwrap();
@@ -181,6 +194,11 @@ mod tests {
                 category: "Food".to_string(),
                 next_run_at: past.format("%Y-%m-%d %H:%M:%S").to_string(),
                 frequency: Period::Daily.to_str().to_string(),
+                recurrence_rule: None,
+                ends_at: None,
+                max_occurrences: None,
+                requires_approval: false,
+                roll_convention: "none".to_string(),
             },
         )
         .unwrap();
@@ -227,6 +245,11 @@ mod tests {
                 amount: 25.0,
                 next_run_at: past.format("%Y-%m-%d %H:%M:%S").to_string(),
                 frequency: Period::Weekly.to_str().to_string(),
+                recurrence_rule: None,
+                ends_at: None,
+                max_occurrences: None,
+                requires_approval: false,
+                roll_convention: "none".to_string(),
             },
         )
         .unwrap();
@@ -343,6 +366,13 @@ mod tests {
             limit_cents: 20000, // $200.00
             period: "monthly".to_string(),
             target_type: "spending".to_string(),
+            rollover_mode: "none".to_string(),
+            rollover_cap_cents: None,
+            alert_thresholds: "80,100".to_string(),
+            name: None,
+            include_subcategories: false,
+            starts_on: None,
+            ends_on: None,
         };
         let budget = create_budget(&mut conn, new_budget).unwrap();
         assert_eq!(budget.category, "Groceries");
@@ -355,6 +385,13 @@ mod tests {
                 limit_cents: 25000, // $250.00
                 period: "monthly".to_string(),
                 target_type: "spending".to_string(),
+                rollover_mode: "none".to_string(),
+                rollover_cap_cents: None,
+                alert_thresholds: "80,100".to_string(),
+                name: None,
+                include_subcategories: false,
+                starts_on: None,
+                ends_on: None,
             },
         ).unwrap();
         assert_eq!(updated.limit_cents, 25000);
@@ -371,6 +408,13 @@ mod tests {
             limit_cents: 10000, // $100.00
             period: "monthly".to_string(),
             target_type: "spending".to_string(),
+            rollover_mode: "none".to_string(),
+            rollover_cap_cents: None,
+            alert_thresholds: "80,100".to_string(),
+            name: None,
+            include_subcategories: false,
+            starts_on: None,
+            ends_on: None,
         };
         let budget = create_budget(&mut conn, new_budget).unwrap();
         let res = delete_budget(&mut conn, budget.id.expect("budget should have id"));
@@ -434,6 +478,13 @@ mod tests {
             limit_cents: 10000,
             period: "monthly".to_string(),
             target_type: "spending".to_string(),
+            rollover_mode: "none".to_string(),
+            rollover_cap_cents: None,
+            alert_thresholds: "80,100".to_string(),
+            name: None,
+            include_subcategories: false,
+            starts_on: None,
+            ends_on: None,
         };
         create_budget(&mut conn, new_budget).unwrap();
         // Now get categories
@@ -488,4 +539,2257 @@ mod tests {
         let txs = get_user_transactions(&mut conn, user_obj.id).unwrap();
         assert!(!txs.iter().any(|t| t.id == tx_id));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_create_user_rejects_duplicate_and_invalid_email() {
+        let mut conn = get_test_connection();
+        create_user(&mut conn, "dupuser", "pass", None).unwrap();
+        let dup = create_user(&mut conn, "dupuser", "other", None);
+        assert!(matches!(dup, Err(FinancerError::Conflict(_))));
+        let bad_email = create_user(&mut conn, "emailuser", "pass", Some("not-an-email"));
+        assert!(matches!(bad_email, Err(FinancerError::Validation(_))));
+        let empty = create_user(&mut conn, "", "pass", None);
+        assert!(matches!(empty, Err(FinancerError::Validation(_))));
+    }
+
+    #[test]
+    fn test_verify_user_reports_corrupt_hash() {
+        use financer::schema::users;
+
+        let mut conn = get_test_connection();
+        create_user(&mut conn, "corruptuser", "pass", None).unwrap();
+        diesel::update(users::table.filter(users::username.eq("corruptuser")))
+            .set(users::password_hash.eq("not a phc string"))
+            .execute(&mut conn)
+            .unwrap();
+        let res = verify_user(&mut conn, "corruptuser", "pass");
+        assert!(matches!(res, Err(FinancerError::CorruptData(_))));
+        // Unknown users are still a plain failed login, not an error
+        assert!(!verify_user(&mut conn, "nobody", "pass").unwrap());
+    }
+
+    #[test]
+    fn test_delete_account_of_other_user_is_permission_denied() {
+        let mut conn = get_test_connection();
+        create_user(&mut conn, "owner", "pass", None).unwrap();
+        create_user(&mut conn, "intruder", "pass", None).unwrap();
+        let owner = get_userid_by_username(&mut conn, "owner").unwrap();
+        let intruder = get_userid_by_username(&mut conn, "intruder").unwrap();
+        create_account(&mut conn, "Checking", "bank", 100.0, owner.id).unwrap();
+        let account = get_user_accounts(&mut conn, owner.id).unwrap().pop().unwrap();
+
+        let res = delete_account(&mut conn, intruder.id, account.id);
+        assert!(matches!(res, Err(FinancerError::PermissionDenied(_))));
+        let missing = delete_account(&mut conn, owner.id, account.id + 100);
+        assert!(matches!(missing, Err(FinancerError::NotFound(_))));
+        assert_eq!(get_user_accounts(&mut conn, owner.id).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_missing_records_are_not_found() {
+        let mut conn = get_test_connection();
+        create_user(&mut conn, "missinguser", "pass", None).unwrap();
+        let user_obj = get_userid_by_username(&mut conn, "missinguser").unwrap();
+        create_account(&mut conn, "Main", "bank", 100.0, user_obj.id).unwrap();
+        let account = get_user_accounts(&mut conn, user_obj.id).unwrap().pop().unwrap();
+
+        let res = update_transaction(&mut conn, 999, account.id, 10.0, "Food".to_string(), "2025-12-01".to_string());
+        assert!(matches!(res, Err(FinancerError::NotFound(_))));
+        assert!(matches!(delete_transaction(&mut conn, 999), Err(FinancerError::NotFound(_))));
+        assert!(matches!(delete_budget(&mut conn, 999), Err(FinancerError::NotFound(_))));
+        assert!(matches!(get_userid_by_username(&mut conn, "ghost"), Err(FinancerError::NotFound(_))));
+    }
+
+    #[test]
+    fn test_ensure_sufficient_funds() {
+        let mut conn = get_test_connection();
+        create_user(&mut conn, "fundsuser", "pass", None).unwrap();
+        let user_obj = get_userid_by_username(&mut conn, "fundsuser").unwrap();
+        create_account(&mut conn, "Main", "bank", 50.0, user_obj.id).unwrap();
+        let account = get_user_accounts(&mut conn, user_obj.id).unwrap().pop().unwrap();
+
+        assert!(ensure_sufficient_funds(&mut conn, account.id, 50.0).is_ok());
+        match ensure_sufficient_funds(&mut conn, account.id, 75.0) {
+            Err(FinancerError::InsufficientFunds { available, required, .. }) => {
+                assert!((available - 50.0).abs() < 1e-6);
+                assert!((required - 75.0).abs() < 1e-6);
+            }
+            other => panic!("expected InsufficientFunds, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_register_user_enforces_password_policy() {
+        use financer::auth::PasswordPolicy;
+
+        let mut conn = get_test_connection();
+        let policy = PasswordPolicy {
+            min_length: 10,
+            require_mixed_case: true,
+            require_digit: true,
+            require_symbol: true,
+        };
+        let weak = register_user(&mut conn, "weakuser", "password", None, &policy);
+        assert!(matches!(weak, Err(FinancerError::Validation(_))));
+        assert!(get_userid_by_username(&mut conn, "weakuser").is_err());
+        register_user(&mut conn, "stronguser", "Str0ng!Passw", None, &policy).unwrap();
+        assert!(verify_user(&mut conn, "stronguser", "Str0ng!Passw").unwrap());
+    }
+
+    #[test]
+    fn test_change_password_requires_current_password() {
+        use financer::auth::PasswordPolicy;
+
+        let mut conn = get_test_connection();
+        create_user(&mut conn, "pwuser", "oldpass1", None).unwrap();
+        let user_obj = get_userid_by_username(&mut conn, "pwuser").unwrap();
+        let policy = PasswordPolicy::default();
+
+        let wrong = change_password(&mut conn, user_obj.id, "not-it", "newpass12", &policy);
+        assert!(matches!(wrong, Err(FinancerError::Validation(_))));
+        let weak = change_password(&mut conn, user_obj.id, "oldpass1", "short", &policy);
+        assert!(matches!(weak, Err(FinancerError::Validation(_))));
+
+        change_password(&mut conn, user_obj.id, "oldpass1", "newpass12", &policy).unwrap();
+        assert!(!verify_user(&mut conn, "pwuser", "oldpass1").unwrap());
+        assert!(verify_user(&mut conn, "pwuser", "newpass12").unwrap());
+    }
+
+    #[test]
+    fn test_change_username_and_email() {
+        let mut conn = get_test_connection();
+        create_user(&mut conn, "renameme", "pass", Some("a@example.com")).unwrap();
+        create_user(&mut conn, "taken", "pass", Some("b@example.com")).unwrap();
+        let user_obj = get_userid_by_username(&mut conn, "renameme").unwrap();
+
+        let clash = change_username(&mut conn, user_obj.id, "taken");
+        assert!(matches!(clash, Err(FinancerError::Conflict(_))));
+        change_username(&mut conn, user_obj.id, "renamed").unwrap();
+        assert_eq!(get_userid_by_username(&mut conn, "renamed").unwrap().id, user_obj.id);
+        assert!(verify_user(&mut conn, "renamed", "pass").unwrap());
+
+        let bad = change_email(&mut conn, user_obj.id, Some("nope"));
+        assert!(matches!(bad, Err(FinancerError::Validation(_))));
+        let dup = change_email(&mut conn, user_obj.id, Some("b@example.com"));
+        assert!(matches!(dup, Err(FinancerError::Conflict(_))));
+        change_email(&mut conn, user_obj.id, Some("c@example.com")).unwrap();
+        let reloaded = get_userid_by_username(&mut conn, "renamed").unwrap();
+        assert_eq!(reloaded.email.as_deref(), Some("c@example.com"));
+    }
+
+    #[test]
+    fn test_verify_user_upgrades_outdated_hash() {
+        use argon2::{Algorithm, Argon2, Params, PasswordHasher, Version};
+        use financer::auth::needs_rehash;
+        use financer::schema::users;
+        use password_hash::SaltString;
+
+        let mut conn = get_test_connection();
+        create_user(&mut conn, "legacy", "pass", None).unwrap();
+
+        let weak_params = Params::new(1024, 1, 1, None).unwrap();
+        let salt = SaltString::generate(&mut rand::thread_rng());
+        let legacy_hash = Argon2::new(Algorithm::Argon2i, Version::V0x13, weak_params)
+            .hash_password(b"pass", &salt)
+            .unwrap()
+            .to_string();
+        assert!(needs_rehash(&legacy_hash));
+        diesel::update(users::table.filter(users::username.eq("legacy")))
+            .set(users::password_hash.eq(&legacy_hash))
+            .execute(&mut conn)
+            .unwrap();
+
+        assert!(!verify_user(&mut conn, "legacy", "wrong").unwrap());
+        let unchanged = get_userid_by_username(&mut conn, "legacy").unwrap();
+        assert_eq!(unchanged.password_hash, legacy_hash);
+
+        assert!(verify_user(&mut conn, "legacy", "pass").unwrap());
+        let upgraded = get_userid_by_username(&mut conn, "legacy").unwrap();
+        assert_ne!(upgraded.password_hash, legacy_hash);
+        assert!(!needs_rehash(&upgraded.password_hash));
+        assert!(verify_user(&mut conn, "legacy", "pass").unwrap());
+    }
+
+    #[test]
+    fn test_outdated_hash_kept_until_second_factor_passes() {
+        use argon2::{Algorithm, Argon2, Params, PasswordHasher, Version};
+        use financer::auth::{needs_rehash, LoginThrottlePolicy};
+        use financer::schema::users;
+        use financer::totp::code_at;
+        use password_hash::SaltString;
+
+        let mut conn = get_test_connection();
+        create_user(&mut conn, "legacy2fa", "pass", None).unwrap();
+        let uid = get_userid_by_username(&mut conn, "legacy2fa").unwrap().id;
+        let policy = LoginThrottlePolicy::default();
+        let t0 = NaiveDate::from_ymd_opt(2025, 3, 1).unwrap().and_hms_opt(9, 0, 0).unwrap();
+        let secret = begin_totp_enrollment(&mut conn, uid, "pass", t0).unwrap();
+        let code = code_at(&secret, unix_time(t0)).unwrap();
+        confirm_totp_enrollment(&mut conn, uid, "pass", &code, t0).unwrap();
+
+        let weak_params = Params::new(1024, 1, 1, None).unwrap();
+        let salt = SaltString::generate(&mut rand::thread_rng());
+        let legacy_hash = Argon2::new(Algorithm::Argon2i, Version::V0x13, weak_params)
+            .hash_password(b"pass", &salt)
+            .unwrap()
+            .to_string();
+        diesel::update(users::table.filter(users::id.eq(uid)))
+            .set(users::password_hash.eq(&legacy_hash))
+            .execute(&mut conn)
+            .unwrap();
+
+        // The password alone must not write anything to the user row.
+        let t1 = t0 + Duration::seconds(60);
+        assert!(matches!(
+            verify_user_at(&mut conn, "legacy2fa", "pass", None, t1, &policy),
+            Err(FinancerError::TwoFactorRequired)
+        ));
+        assert!(!verify_user_at(&mut conn, "legacy2fa", "pass", Some("000000"), t1, &policy).unwrap());
+        assert_eq!(get_userid_by_username(&mut conn, "legacy2fa").unwrap().password_hash, legacy_hash);
+
+        let code = code_at(&secret, unix_time(t1)).unwrap();
+        assert!(verify_user_at(&mut conn, "legacy2fa", "pass", Some(&code), t1, &policy).unwrap());
+        assert!(!needs_rehash(&get_userid_by_username(&mut conn, "legacy2fa").unwrap().password_hash));
+    }
+
+    #[test]
+    fn test_login_throttling_grows_and_resets() {
+        use financer::auth::LoginThrottlePolicy;
+
+        let mut conn = get_test_connection();
+        create_user(&mut conn, "alice", "pass", None).unwrap();
+        let policy = LoginThrottlePolicy::default();
+        let t0 = NaiveDate::from_ymd_opt(2025, 3, 1).unwrap().and_hms_opt(9, 0, 0).unwrap();
+
+        // The free attempts fail without any delay.
+        for _ in 0..policy.free_attempts - 1 {
+            assert!(!verify_user_at(&mut conn, "alice", "wrong", None, t0, &policy).unwrap());
+        }
+        assert!(!verify_user_at(&mut conn, "alice", "wrong", None, t0, &policy).unwrap());
+
+        // The last free failure starts the delay, so even the right password is refused.
+        let first_delay = policy.delay_after(policy.free_attempts);
+        assert!(first_delay > 0);
+        match verify_user_at(&mut conn, "alice", "pass", None, t0, &policy) {
+            Err(FinancerError::LoginThrottled { retry_after_secs }) => assert_eq!(retry_after_secs, first_delay),
+            other => panic!("expected LoginThrottled, got {:?}", other),
+        }
+
+        // After waiting, another failure doubles the delay.
+        let t1 = t0 + Duration::seconds(first_delay);
+        assert!(!verify_user_at(&mut conn, "alice", "wrong", None, t1, &policy).unwrap());
+        assert_eq!(policy.delay_after(policy.free_attempts + 1), first_delay * 2);
+        assert!(matches!(
+            verify_user_at(&mut conn, "alice", "pass", None, t1 + Duration::seconds(first_delay), &policy),
+            Err(FinancerError::LoginThrottled { .. })
+        ));
+
+        // A success once the delay has passed clears the counter.
+        let t2 = t1 + Duration::seconds(first_delay * 2);
+        assert!(verify_user_at(&mut conn, "alice", "pass", None, t2, &policy).unwrap());
+        assert!(!verify_user_at(&mut conn, "alice", "wrong", None, t2, &policy).unwrap());
+        assert!(verify_user_at(&mut conn, "alice", "pass", None, t2, &policy).unwrap());
+    }
+
+    #[test]
+    fn test_login_lockout_after_threshold() {
+        use financer::auth::LoginThrottlePolicy;
+
+        let mut conn = get_test_connection();
+        create_user(&mut conn, "bob", "pass", None).unwrap();
+        let policy = LoginThrottlePolicy {
+            free_attempts: 1,
+            base_delay_secs: 1,
+            max_delay_secs: 1,
+            lockout_threshold: 3,
+            lockout_secs: 600,
+        };
+        let mut now = NaiveDate::from_ymd_opt(2025, 3, 1).unwrap().and_hms_opt(9, 0, 0).unwrap();
+
+        for _ in 0..policy.lockout_threshold {
+            assert!(!verify_user_at(&mut conn, "bob", "wrong", None, now, &policy).unwrap());
+            now += Duration::seconds(policy.max_delay_secs);
+        }
+        match verify_user_at(&mut conn, "bob", "pass", None, now, &policy) {
+            Err(FinancerError::LoginThrottled { retry_after_secs }) => {
+                assert_eq!(retry_after_secs, policy.lockout_secs - policy.max_delay_secs)
+            }
+            other => panic!("expected LoginThrottled, got {:?}", other),
+        }
+
+        let after_lockout = now + Duration::seconds(policy.lockout_secs);
+        assert!(verify_user_at(&mut conn, "bob", "pass", None, after_lockout, &policy).unwrap());
+    }
+
+    #[test]
+    fn test_login_events_are_recorded() {
+        use financer::auth::LoginThrottlePolicy;
+
+        let mut conn = get_test_connection();
+        create_user(&mut conn, "carol", "pass", None).unwrap();
+        let uid = get_userid_by_username(&mut conn, "carol").unwrap().id;
+        let policy = LoginThrottlePolicy::default();
+        let t0 = NaiveDate::from_ymd_opt(2025, 3, 1).unwrap().and_hms_opt(9, 0, 0).unwrap();
+
+        verify_user_at(&mut conn, "carol", "wrong", None, t0, &policy).unwrap();
+        verify_user_at(&mut conn, "carol", "pass", None, t0 + Duration::seconds(5), &policy).unwrap();
+        verify_user_at(&mut conn, "nobody", "pass", None, t0, &policy).unwrap();
+
+        let events = get_login_events(&mut conn, uid, 10).unwrap();
+        assert_eq!(events.len(), 2);
+        assert!(events[0].succeeded);
+        assert_eq!(events[0].occurred_at, "2025-03-01 09:00:05");
+        assert!(!events[1].succeeded);
+        assert_eq!(events[1].reason, "bad_credentials");
+
+        delete_user_and_all_data(&mut conn, uid).unwrap();
+        assert!(get_login_events(&mut conn, uid, 10).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_auto_lock_policy_and_secret_wipe() {
+        use financer::auth::{wipe_secret, AutoLockPolicy};
+        use std::time::Duration as StdDuration;
+
+        let policy = AutoLockPolicy {
+            idle_timeout: Some(StdDuration::from_secs(300)),
+            lock_on_minimize: true,
+        };
+        assert!(!policy.should_lock(StdDuration::from_secs(299), false));
+        assert!(policy.should_lock(StdDuration::from_secs(300), false));
+        assert!(policy.should_lock(StdDuration::from_secs(0), true));
+
+        let never = AutoLockPolicy { idle_timeout: None, lock_on_minimize: false };
+        assert!(!never.should_lock(StdDuration::from_secs(86_400), true));
+
+        let mut secret = String::from("hunter2");
+        wipe_secret(&mut secret);
+        assert!(secret.is_empty());
+    }
+
+    #[test]
+    fn test_locking_keeps_totp_setup_in_progress() {
+        use financer::auth::TotpSetupForm;
+
+        let mut form = TotpSetupForm {
+            password: "pass".to_string(),
+            pending_secret: Some("JBSWY3DPEHPK3PXP".to_string()),
+            confirm_code: "123456".to_string(),
+            new_recovery_codes: vec!["7KQ2-M9XD".to_string(), "ABCD-EFGH".to_string()],
+        };
+
+        form.wipe_typed_secrets();
+        assert!(form.password.is_empty());
+        assert!(form.confirm_code.is_empty());
+        assert_eq!(form.pending_secret.as_deref(), Some("JBSWY3DPEHPK3PXP"));
+        assert_eq!(form.new_recovery_codes, vec!["7KQ2-M9XD", "ABCD-EFGH"]);
+
+        form.clear();
+        assert!(form.pending_secret.is_none());
+        assert!(form.new_recovery_codes.is_empty());
+    }
+
+    #[test]
+    fn test_totp_matches_rfc6238_vectors() {
+        use financer::totp::{code_at, verify_code};
+
+        // RFC 6238 appendix B, SHA-1 key "12345678901234567890", last six digits.
+        let secret = "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ";
+        assert_eq!(code_at(secret, 59).unwrap(), "287082");
+        assert_eq!(code_at(secret, 1111111109).unwrap(), "081804");
+        assert_eq!(code_at(secret, 1234567890).unwrap(), "005924");
+        assert_eq!(code_at(secret, 20000000000).unwrap(), "353130");
+
+        // One step of drift either way is accepted, two is not.
+        assert_eq!(verify_code(secret, "005924", 1234567890 + 30).unwrap(), Some(1234567890 / 30));
+        assert_eq!(verify_code(secret, "005924", 1234567890 + 60).unwrap(), None);
+        assert_eq!(verify_code(secret, "abcdef", 1234567890).unwrap(), None);
+    }
+
+    #[test]
+    fn test_totp_secret_round_trips_only_with_password() {
+        use financer::totp::{decrypt_secret, encrypt_secret, generate_secret, provisioning_uri};
+
+        let secret = generate_secret();
+        let encrypted = encrypt_secret(&secret, "pass1234").unwrap();
+        assert!(!encrypted.ciphertext.contains(&secret));
+        assert_eq!(decrypt_secret(&encrypted, "pass1234").unwrap(), secret);
+        assert!(matches!(decrypt_secret(&encrypted, "other"), Err(FinancerError::CorruptData(_))));
+
+        let uri = provisioning_uri(&secret, "jane doe");
+        assert!(uri.starts_with("otpauth://totp/FinanceR:jane%20doe?secret="));
+        assert!(uri.contains(&secret));
+    }
+
+    #[test]
+    fn test_login_with_totp_and_recovery_codes() {
+        use financer::auth::LoginThrottlePolicy;
+        use financer::totp::code_at;
+
+        let mut conn = get_test_connection();
+        create_user(&mut conn, "dana", "pass", None).unwrap();
+        let uid = get_userid_by_username(&mut conn, "dana").unwrap().id;
+        let policy = LoginThrottlePolicy::default();
+        let t0 = NaiveDate::from_ymd_opt(2025, 3, 1).unwrap().and_hms_opt(9, 0, 0).unwrap();
+
+        let secret = begin_totp_enrollment(&mut conn, uid, "pass", t0).unwrap();
+        // Not active until confirmed.
+        assert!(!is_totp_enabled(&mut conn, uid).unwrap());
+        assert!(verify_user_at(&mut conn, "dana", "pass", None, t0, &policy).unwrap());
+
+        assert!(matches!(
+            confirm_totp_enrollment(&mut conn, uid, "pass", "000000", t0),
+            Err(FinancerError::Validation(_))
+        ));
+        let enrolled_code = code_at(&secret, unix_time(t0)).unwrap();
+        let recovery = confirm_totp_enrollment(&mut conn, uid, "pass", &enrolled_code, t0).unwrap();
+        assert_eq!(recovery.len(), 10);
+        assert!(is_totp_enabled(&mut conn, uid).unwrap());
+
+        // The password alone is no longer enough.
+        let t1 = t0 + Duration::seconds(60);
+        assert!(matches!(
+            verify_user_at(&mut conn, "dana", "pass", None, t1, &policy),
+            Err(FinancerError::TwoFactorRequired)
+        ));
+        let code = code_at(&secret, unix_time(t1)).unwrap();
+        assert!(verify_user_at(&mut conn, "dana", "pass", Some(&code), t1, &policy).unwrap());
+        // The same code can't be used twice.
+        assert!(!verify_user_at(&mut conn, "dana", "pass", Some(&code), t1, &policy).unwrap());
+        // The code doesn't help with a wrong password.
+        let t2 = t1 + Duration::seconds(30);
+        let code = code_at(&secret, unix_time(t2)).unwrap();
+        assert!(!verify_user_at(&mut conn, "dana", "wrong", Some(&code), t2, &policy).unwrap());
+
+        // Recovery codes work once each, in any case.
+        let lowercase = recovery[0].to_lowercase();
+        assert!(verify_user_at(&mut conn, "dana", "pass", Some(&lowercase), t2, &policy).unwrap());
+        assert!(!verify_user_at(&mut conn, "dana", "pass", Some(&recovery[0]), t2, &policy).unwrap());
+        assert_eq!(count_unused_recovery_codes(&mut conn, uid).unwrap(), 9);
+
+        let events = get_login_events(&mut conn, uid, 10).unwrap();
+        assert!(events.iter().any(|e| e.reason == "recovery_code"));
+        assert!(events.iter().any(|e| e.reason == "bad_2fa_code"));
+
+        disable_totp(&mut conn, uid, "pass").unwrap();
+        assert!(verify_user_at(&mut conn, "dana", "pass", None, t2, &policy).unwrap());
+    }
+
+    #[test]
+    fn test_change_password_keeps_totp_secret_readable() {
+        use financer::auth::{LoginThrottlePolicy, PasswordPolicy};
+        use financer::totp::code_at;
+
+        let mut conn = get_test_connection();
+        create_user(&mut conn, "erin", "pass", None).unwrap();
+        let uid = get_userid_by_username(&mut conn, "erin").unwrap().id;
+        let t0 = NaiveDate::from_ymd_opt(2025, 3, 1).unwrap().and_hms_opt(9, 0, 0).unwrap();
+        let secret = begin_totp_enrollment(&mut conn, uid, "pass", t0).unwrap();
+        let code = code_at(&secret, unix_time(t0)).unwrap();
+        confirm_totp_enrollment(&mut conn, uid, "pass", &code, t0).unwrap();
+
+        change_password(&mut conn, uid, "pass", "NewPass123", &PasswordPolicy::default()).unwrap();
+
+        let t1 = t0 + Duration::seconds(90);
+        let code = code_at(&secret, unix_time(t1)).unwrap();
+        assert!(verify_user_at(&mut conn, "erin", "NewPass123", Some(&code), t1, &LoginThrottlePolicy::default()).unwrap());
+    }
+
+    #[test]
+    fn test_shared_account_visible_to_members_with_roles() {
+        let mut conn = get_test_connection();
+        create_user(&mut conn, "sam", "pass", None).unwrap();
+        create_user(&mut conn, "alex", "pass", None).unwrap();
+        create_user(&mut conn, "kid", "pass", None).unwrap();
+        let sam = get_userid_by_username(&mut conn, "sam").unwrap().id;
+        let alex = get_userid_by_username(&mut conn, "alex").unwrap().id;
+        let kid = get_userid_by_username(&mut conn, "kid").unwrap().id;
+        create_account(&mut conn, "Joint", "Chequing", 500.0, sam).unwrap();
+        let joint = get_user_accounts(&mut conn, sam).unwrap()[0].id;
+
+        assert!(get_user_accounts(&mut conn, alex).unwrap().is_empty());
+        assert!(matches!(
+            create_transaction_as(&mut conn, alex, joint, 0, -10.0, "Food".to_string(), "2025-12-01 00:00:00".to_string(), at("2025-12-01")),
+            Err(FinancerError::PermissionDenied(_))
+        ));
+
+        add_account_member(&mut conn, sam, joint, "alex", AccountRole::Editor).unwrap();
+        add_account_member(&mut conn, sam, joint, "kid", AccountRole::Viewer).unwrap();
+        assert!(matches!(
+            add_account_member(&mut conn, sam, joint, "alex", AccountRole::Viewer),
+            Err(FinancerError::Conflict(_))
+        ));
+        // Only owners can share.
+        assert!(matches!(
+            add_account_member(&mut conn, alex, joint, "kid", AccountRole::Editor),
+            Err(FinancerError::PermissionDenied(_))
+        ));
+
+        assert_eq!(get_user_accounts(&mut conn, alex).unwrap().len(), 1);
+        assert_eq!(get_user_accounts(&mut conn, kid).unwrap().len(), 1);
+
+        create_transaction_as(&mut conn, alex, joint, 0, -40.0, "Food".to_string(), "2025-12-02 00:00:00".to_string(), at("2025-12-02")).unwrap();
+        create_transaction_as(&mut conn, sam, joint, 0, -10.0, "Food".to_string(), "2025-12-03 00:00:00".to_string(), at("2025-12-03")).unwrap();
+        assert!(matches!(
+            create_transaction_as(&mut conn, kid, joint, 0, -5.0, "Food".to_string(), "2025-12-04 00:00:00".to_string(), at("2025-12-04")),
+            Err(FinancerError::PermissionDenied(_))
+        ));
+
+        // Every member sees the same history, with the author of each entry.
+        for member in [sam, alex, kid] {
+            let txs = get_user_transactions(&mut conn, member).unwrap();
+            assert_eq!(txs.len(), 2);
+            let start = NaiveDate::from_ymd_opt(2025, 12, 1).unwrap().and_hms_opt(0, 0, 0).unwrap();
+            let end = NaiveDate::from_ymd_opt(2026, 1, 1).unwrap().and_hms_opt(0, 0, 0).unwrap();
+            assert_eq!(get_spend_for_category_period(&mut conn, member, "Food", start, end).unwrap(), -5000);
+        }
+        let txs = get_user_transactions(&mut conn, kid).unwrap();
+        assert_eq!(txs.iter().find(|t| t.amount == -40.0).unwrap().created_by, Some(alex));
+        assert_eq!(txs.iter().find(|t| t.amount == -10.0).unwrap().created_by, Some(sam));
+
+        let members = get_account_members(&mut conn, kid, joint).unwrap();
+        assert_eq!(members.len(), 3);
+        assert_eq!(members[0].1, "sam");
+
+        // Editors can't delete the account; the last owner can't leave.
+        assert!(matches!(delete_account(&mut conn, alex, joint), Err(FinancerError::PermissionDenied(_))));
+        assert!(matches!(remove_account_member(&mut conn, sam, joint, sam), Err(FinancerError::Validation(_))));
+        remove_account_member(&mut conn, kid, joint, kid).unwrap();
+        assert!(get_user_accounts(&mut conn, kid).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_recurring_items_follow_account_membership() {
+        let mut conn = get_test_connection();
+        create_user(&mut conn, "sam", "pass", None).unwrap();
+        create_user(&mut conn, "alex", "pass", None).unwrap();
+        let sam = get_userid_by_username(&mut conn, "sam").unwrap().id;
+        let alex = get_userid_by_username(&mut conn, "alex").unwrap().id;
+        create_account(&mut conn, "Joint", "Chequing", 500.0, sam).unwrap();
+        let joint = get_user_accounts(&mut conn, sam).unwrap()[0].id;
+        add_account_member(&mut conn, sam, joint, "alex", AccountRole::Viewer).unwrap();
+
+        let rent = create_recurring_transaction(&mut conn, NewRecurringTransaction {
+            user_id: sam,
+            account_id: joint,
+            contact_id: 0,
+            amount: -100.0,
+            category: "Rent".to_string(),
+            next_run_at: "2025-12-01 00:00:00".to_string(),
+            frequency: Period::Monthly.to_str().to_string(),
+            recurrence_rule: None,
+            ends_at: None,
+            max_occurrences: None,
+            requires_approval: false,
+            roll_convention: "none".to_string(),
+        }).unwrap();
+        assert_eq!(get_user_recurring_transactions(&mut conn, alex).unwrap().len(), 1);
+        assert!(matches!(
+            delete_recurring_transaction(&mut conn, alex, rent.id),
+            Err(FinancerError::PermissionDenied(_))
+        ));
+
+        // Whoever logs in first posts the due item, credited to whoever scheduled it.
+        let now = NaiveDate::from_ymd_opt(2025, 12, 15).unwrap().and_hms_opt(0, 0, 0).unwrap();
+        assert_eq!(process_due_recurring(&mut conn, alex, now).unwrap(), 1);
+        assert_eq!(process_due_recurring(&mut conn, sam, now).unwrap(), 0);
+        let txs = get_user_transactions(&mut conn, sam).unwrap();
+        assert_eq!(txs.len(), 1);
+        assert_eq!(txs[0].created_by, Some(sam));
+
+        set_account_member_role(&mut conn, sam, joint, alex, AccountRole::Editor).unwrap();
+        delete_recurring_transaction(&mut conn, alex, rent.id).unwrap();
+    }
+
+    #[test]
+    fn test_deleting_user_hands_shared_accounts_to_remaining_member() {
+        let mut conn = get_test_connection();
+        create_user(&mut conn, "sam", "pass", None).unwrap();
+        create_user(&mut conn, "alex", "pass", None).unwrap();
+        let sam = get_userid_by_username(&mut conn, "sam").unwrap().id;
+        let alex = get_userid_by_username(&mut conn, "alex").unwrap().id;
+        create_account(&mut conn, "Joint", "Chequing", 500.0, sam).unwrap();
+        create_account(&mut conn, "Private", "Savings", 100.0, sam).unwrap();
+        let joint = get_user_accounts(&mut conn, sam).unwrap().iter().find(|a| a.name == "Joint").unwrap().id;
+        add_account_member(&mut conn, sam, joint, "alex", AccountRole::Editor).unwrap();
+        create_transaction_as(&mut conn, sam, joint, 0, -20.0, "Food".to_string(), "2025-12-02 00:00:00".to_string(), at("2025-12-02")).unwrap();
+
+        delete_user_and_all_data(&mut conn, sam).unwrap();
+
+        let remaining = get_user_accounts(&mut conn, alex).unwrap();
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(remaining[0].user_id, alex);
+        assert_eq!(get_account_role(&mut conn, alex, joint).unwrap(), Some(AccountRole::Owner));
+        let txs = get_user_transactions(&mut conn, alex).unwrap();
+        assert_eq!(txs.len(), 1);
+        assert_eq!(txs[0].created_by, None);
+    }
+
+    #[test]
+    fn test_deleting_user_keeps_schedules_on_shared_accounts() {
+        let mut conn = get_test_connection();
+        create_user(&mut conn, "sam", "pass", None).unwrap();
+        create_user(&mut conn, "alex", "pass", None).unwrap();
+        let sam = get_userid_by_username(&mut conn, "sam").unwrap().id;
+        let alex = get_userid_by_username(&mut conn, "alex").unwrap().id;
+        create_account(&mut conn, "Joint", "Chequing", 500.0, sam).unwrap();
+        create_account(&mut conn, "Private", "Savings", 100.0, sam).unwrap();
+        let sam_accounts = get_user_accounts(&mut conn, sam).unwrap();
+        let joint = sam_accounts.iter().find(|a| a.name == "Joint").unwrap().id;
+        let private = sam_accounts.iter().find(|a| a.name == "Private").unwrap().id;
+        add_account_member(&mut conn, sam, joint, "alex", AccountRole::Editor).unwrap();
+
+        for account in [joint, private] {
+            create_recurring_transaction(&mut conn, NewRecurringTransaction {
+                user_id: sam,
+                account_id: account,
+                contact_id: 0,
+                amount: -100.0,
+                category: "Rent".to_string(),
+                next_run_at: "2025-12-01 00:00:00".to_string(),
+                frequency: Period::Monthly.to_str().to_string(),
+                recurrence_rule: None,
+                ends_at: None,
+                max_occurrences: None,
+                requires_approval: false,
+                roll_convention: "none".to_string(),
+            }).unwrap();
+        }
+        // A transfer out of the private account goes with it.
+        create_recurring_transfer(&mut conn, NewRecurringTransfer {
+            user_id: sam,
+            from_account_id: private,
+            to_account_id: joint,
+            amount: 25.0,
+            next_run_at: "2025-12-01 00:00:00".to_string(),
+            frequency: Period::Weekly.to_str().to_string(),
+            recurrence_rule: None,
+            ends_at: None,
+            max_occurrences: None,
+            requires_approval: false,
+            roll_convention: "none".to_string(),
+        }).unwrap();
+
+        delete_user_and_all_data(&mut conn, sam).unwrap();
+
+        let schedules = get_user_recurring_transactions(&mut conn, alex).unwrap();
+        assert_eq!(schedules.len(), 1);
+        assert_eq!(schedules[0].account_id, joint);
+        assert_eq!(schedules[0].user_id, alex);
+        assert!(get_user_recurring_transfers(&mut conn, alex).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_viewer_cannot_edit_or_delete_shared_transactions() {
+        let mut conn = get_test_connection();
+        create_user(&mut conn, "sam", "pass", None).unwrap();
+        create_user(&mut conn, "kid", "pass", None).unwrap();
+        let sam = get_userid_by_username(&mut conn, "sam").unwrap().id;
+        let kid = get_userid_by_username(&mut conn, "kid").unwrap().id;
+        create_account(&mut conn, "Joint", "Chequing", 500.0, sam).unwrap();
+        let joint = get_user_accounts(&mut conn, sam).unwrap()[0].id;
+        add_account_member(&mut conn, sam, joint, "kid", AccountRole::Viewer).unwrap();
+        create_transaction_as(&mut conn, sam, joint, 0, -40.0, "Food".to_string(), "2025-12-02 00:00:00".to_string(), at("2025-12-02")).unwrap();
+        let tx_id = get_user_transactions(&mut conn, sam).unwrap()[0].id;
+
+        assert!(matches!(
+            update_transaction_as(&mut conn, kid, tx_id, joint, -1.0, "Food".to_string(), "2025-12-02 00:00:00".to_string(), at("2025-12-02")),
+            Err(FinancerError::PermissionDenied(_))
+        ));
+        assert!(matches!(delete_transaction_as(&mut conn, kid, tx_id), Err(FinancerError::PermissionDenied(_))));
+        let txs = get_user_transactions(&mut conn, sam).unwrap();
+        assert_eq!(txs.len(), 1);
+        assert_eq!(txs[0].amount, -40.0);
+
+        set_account_member_role(&mut conn, sam, joint, kid, AccountRole::Editor).unwrap();
+        update_transaction_as(&mut conn, kid, tx_id, joint, -30.0, "Food".to_string(), "2025-12-02 00:00:00".to_string(), at("2025-12-02")).unwrap();
+        delete_transaction_as(&mut conn, kid, tx_id).unwrap();
+        assert!(get_user_transactions(&mut conn, sam).unwrap().is_empty());
+    }
+
+    fn at(date_str: &str) -> chrono::NaiveDateTime {
+        chrono::NaiveDateTime::parse_from_str(&format!("{} 09:00:00", date_str), "%Y-%m-%d %H:%M:%S").unwrap()
+    }
+
+    fn next_dates(rule: &str, start: &str, count: usize) -> Vec<String> {
+        financer::recurrence::RecurrenceRule::parse(rule)
+            .unwrap()
+            .occurrences_after(at(start), count)
+            .iter()
+            .map(|d| d.format("%Y-%m-%d").to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_recurrence_rule_next_occurrences() {
+        // Bi-weekly keeps the weekday and skips a week.
+        assert_eq!(next_dates("FREQ=WEEKLY;INTERVAL=2", "2026-01-05", 2), ["2026-01-19", "2026-02-02"]);
+        // Semi-monthly picks the 1st and 15th, starting from a date in between.
+        assert_eq!(next_dates("FREQ=MONTHLY;BYMONTHDAY=1,15", "2026-01-10", 3), ["2026-01-15", "2026-02-01", "2026-02-15"]);
+        assert_eq!(next_dates("FREQ=MONTHLY;INTERVAL=3", "2026-01-31", 2), ["2026-04-30", "2026-07-30"]);
+        assert_eq!(
+            next_dates("FREQ=MONTHLY;BYDAY=MO,TU,WE,TH,FR;BYSETPOS=-1", "2026-01-01", 3),
+            ["2026-01-30", "2026-02-27", "2026-03-31"]
+        );
+        assert_eq!(next_dates("FREQ=MONTHLY;BYDAY=TU;BYSETPOS=2", "2026-01-13", 2), ["2026-02-10", "2026-03-10"]);
+        assert_eq!(next_dates("FREQ=MONTHLY;BYMONTHDAY=-1", "2026-01-31", 2), ["2026-02-28", "2026-03-31"]);
+        assert_eq!(next_dates("FREQ=WEEKLY;BYDAY=TU,TH", "2026-01-06", 3), ["2026-01-08", "2026-01-13", "2026-01-15"]);
+        assert_eq!(next_dates("FREQ=DAILY;BYDAY=MO,TU,WE,TH,FR", "2026-01-09", 1), ["2026-01-12"]);
+
+        // Plain monthly and yearly clamp to short months the way the old frequencies did.
+        assert_eq!(next_dates("FREQ=MONTHLY", "2026-01-31", 2), ["2026-02-28", "2026-03-28"]);
+        assert_eq!(next_dates("FREQ=YEARLY", "2028-02-29", 1), ["2029-02-28"]);
+        // Yearly BY parts cover every month of the year, as in RRULE.
+        assert_eq!(next_dates("FREQ=YEARLY;BYMONTHDAY=30", "2026-01-31", 3), ["2026-03-30", "2026-04-30", "2026-05-30"]);
+        assert_eq!(next_dates("FREQ=YEARLY;BYDAY=MO;BYSETPOS=1,-1", "2026-01-05", 2), ["2026-12-28", "2027-01-04"]);
+        assert_eq!(next_dates("FREQ=YEARLY;INTERVAL=2;BYMONTHDAY=-1;BYSETPOS=2", "2026-02-28", 2), ["2028-02-29", "2030-02-28"]);
+
+        // Time of day carries over, and a rule that can never fire again yields nothing.
+        let rule = financer::recurrence::RecurrenceRule::parse("FREQ=MONTHLY;INTERVAL=12;BYMONTHDAY=30").unwrap();
+        assert_eq!(rule.next_after(at("2026-02-01")), None);
+        let next = rule.next_after(at("2026-01-15")).unwrap();
+        assert_eq!(next, at("2026-01-30"));
+    }
+
+    #[test]
+    fn test_recurrence_rule_parse_and_format() {
+        use financer::recurrence::{presets, RecurrenceRule};
+
+        let rule = RecurrenceRule::parse("rrule:freq=monthly;interval=1;byday=tu;bysetpos=2").unwrap();
+        assert_eq!(rule.period, Period::Monthly);
+        assert_eq!(rule.to_rule_string(), "FREQ=MONTHLY;BYDAY=TU;BYSETPOS=2");
+        assert_eq!(rule.describe(), "Second Tuesday of the month");
+        assert_eq!(RecurrenceRule::parse("FREQ=WEEKLY;INTERVAL=3").unwrap().describe(), "Every 3 weeks");
+
+        for (_, preset) in presets() {
+            assert_eq!(RecurrenceRule::parse(&preset.to_rule_string()).unwrap(), preset);
+        }
+
+        for bad in ["", "INTERVAL=2", "FREQ=HOURLY", "FREQ=MONTHLY;BYMONTHDAY=0", "FREQ=MONTHLY;BYDAY=XX", "FREQ=DAILY;INTERVAL=0", "FREQ=DAILY;COUNT=3"] {
+            assert!(matches!(RecurrenceRule::parse(bad), Err(FinancerError::Validation(_))), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_existing_recurring_rows_migrate_to_rules() {
+        let mut conn = SqliteConnection::establish(":memory:").unwrap();
+        for migration in conn.pending_migrations(MIGRATIONS).unwrap() {
+            if migration.name().to_string().ends_with("add_recurrence_rules") {
+                break;
+            }
+            conn.run_migration(&migration).unwrap();
+        }
+        diesel::sql_query(
+            "INSERT INTO recurring_transactions (user_id, account_id, contact_id, amount, category, next_run_at, frequency, active)
+             VALUES (1, 1, 0, -5.0, 'Food', '2026-01-01 00:00:00', 'Weekly', 1),
+                    (1, 1, 0, -9.0, 'Gym', '2026-01-01 00:00:00', 'Yearly', 1)",
+        )
+        .execute(&mut conn)
+        .unwrap();
+        diesel::sql_query(
+            "INSERT INTO recurring_transfers (user_id, from_account_id, to_account_id, amount, next_run_at, frequency, active)
+             VALUES (1, 1, 2, 50.0, '2026-01-01 00:00:00', 'Daily', 1)",
+        )
+        .execute(&mut conn)
+        .unwrap();
+        conn.run_pending_migrations(MIGRATIONS).unwrap();
+
+        use financer::schema::{recurring_transactions, recurring_transfers};
+        let tx_rules: Vec<String> = recurring_transactions::table
+            .order(recurring_transactions::id.asc())
+            .select(recurring_transactions::recurrence_rule)
+            .load(&mut conn)
+            .unwrap();
+        assert_eq!(tx_rules, ["FREQ=WEEKLY", "FREQ=YEARLY"]);
+        let transfer_rules: Vec<String> = recurring_transfers::table
+            .select(recurring_transfers::recurrence_rule)
+            .load(&mut conn)
+            .unwrap();
+        assert_eq!(transfer_rules, ["FREQ=DAILY"]);
+    }
+
+    #[test]
+    fn test_process_due_recurring_follows_rule() {
+        let mut conn = get_test_connection();
+        create_user(&mut conn, "ruleuser", "pass", None).unwrap();
+        let uid = get_userid_by_username(&mut conn, "ruleuser").unwrap().id;
+        create_account(&mut conn, "Main", "bank", 0.0, uid).unwrap();
+        let account = get_user_accounts(&mut conn, uid).unwrap()[0].id;
+
+        let pay = create_recurring_transaction(&mut conn, NewRecurringTransaction {
+            user_id: uid,
+            account_id: account,
+            contact_id: 0,
+            amount: 1000.0,
+            category: "Salary".to_string(),
+            next_run_at: "2026-01-01 09:00:00".to_string(),
+            frequency: Period::Daily.to_str().to_string(),
+            recurrence_rule: Some("freq=monthly;bymonthday=15,1".to_string()),
+            ends_at: None,
+            max_occurrences: None,
+            requires_approval: false,
+            roll_convention: "none".to_string(),
+        })
+        .unwrap();
+        // The frequency label follows the rule's base period and the rule is stored canonically.
+        assert_eq!(pay.frequency, "Monthly");
+        assert_eq!(pay.recurrence_rule, "FREQ=MONTHLY;BYMONTHDAY=15,1");
+
+        assert_eq!(process_due_recurring(&mut conn, uid, at("2026-02-10")).unwrap(), 3);
+        let items = get_user_recurring_transactions(&mut conn, uid).unwrap();
+        assert_eq!(items[0].next_run_at, "2026-02-15 09:00:00");
+
+        let bad = create_recurring_transaction(&mut conn, NewRecurringTransaction {
+            user_id: uid,
+            account_id: account,
+            contact_id: 0,
+            amount: 5.0,
+            category: "Salary".to_string(),
+            next_run_at: "2026-01-01 09:00:00".to_string(),
+            frequency: Period::Monthly.to_str().to_string(),
+            recurrence_rule: Some("FREQ=FORTNIGHTLY".to_string()),
+            ends_at: None,
+            max_occurrences: None,
+            requires_approval: false,
+            roll_convention: "none".to_string(),
+        });
+        assert!(matches!(bad, Err(FinancerError::Validation(_))));
+    }
+
+    fn daily_item(uid: i32, account: i32, ends_at: Option<&str>, max_occurrences: Option<i32>) -> NewRecurringTransaction {
+        NewRecurringTransaction {
+            user_id: uid,
+            account_id: account,
+            contact_id: 0,
+            amount: -10.0,
+            category: "Coffee".to_string(),
+            next_run_at: "2026-01-01 09:00:00".to_string(),
+            frequency: Period::Daily.to_str().to_string(),
+            recurrence_rule: None,
+            ends_at: ends_at.map(str::to_string),
+            max_occurrences,
+            requires_approval: false,
+            roll_convention: "none".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_recurring_limits_deactivate_finished_items() {
+        let mut conn = get_test_connection();
+        create_user(&mut conn, "limits", "pass", None).unwrap();
+        let uid = get_userid_by_username(&mut conn, "limits").unwrap().id;
+        create_account(&mut conn, "Main", "bank", 100.0, uid).unwrap();
+        let account = get_user_accounts(&mut conn, uid).unwrap()[0].id;
+
+        let counted = create_recurring_transaction(&mut conn, daily_item(uid, account, None, Some(3))).unwrap();
+        let dated = create_recurring_transaction(&mut conn, daily_item(uid, account, Some("2026-01-02 23:59:59"), None)).unwrap();
+        assert_eq!(counted.remaining_occurrences(), Some(3));
+
+        assert_eq!(process_due_recurring(&mut conn, uid, at("2026-01-10")).unwrap(), 5);
+        let items = get_user_recurring_transactions(&mut conn, uid).unwrap();
+        let counted = items.iter().find(|i| i.id == counted.id).unwrap();
+        let dated = items.iter().find(|i| i.id == dated.id).unwrap();
+        assert!(!counted.active && !dated.active);
+        assert_eq!(counted.remaining_occurrences(), Some(0));
+        assert_eq!(dated.occurrences_run, 2);
+        assert_eq!(process_due_recurring(&mut conn, uid, at("2026-02-10")).unwrap(), 0);
+
+        // A finished item can't be resumed until its limits are extended.
+        assert!(matches!(
+            set_recurring_transaction_active(&mut conn, uid, counted.id, true, at("2026-01-10")),
+            Err(FinancerError::Validation(_))
+        ));
+        let mut extended = daily_item(uid, account, None, Some(4));
+        extended.next_run_at = counted.next_run_at.clone();
+        update_recurring_transaction(&mut conn, uid, counted.id, extended, false).unwrap();
+        assert_eq!(process_due_recurring(&mut conn, uid, at("2026-02-10")).unwrap(), 1);
+
+        assert!(matches!(
+            create_recurring_transaction(&mut conn, daily_item(uid, account, Some("2025-12-31 00:00:00"), None)),
+            Err(FinancerError::Validation(_))
+        ));
+        assert!(matches!(
+            create_recurring_transaction(&mut conn, daily_item(uid, account, None, Some(0))),
+            Err(FinancerError::Validation(_))
+        ));
+
+        // Edits are held to the same end date check as new items.
+        assert!(matches!(
+            update_recurring_transaction(&mut conn, uid, dated.id, daily_item(uid, account, Some("2025-12-31 00:00:00"), None), false),
+            Err(FinancerError::Validation(_))
+        ));
+        create_account(&mut conn, "Savings", "bank", 0.0, uid).unwrap();
+        let savings = get_user_accounts(&mut conn, uid).unwrap().into_iter().find(|a| a.name == "Savings").unwrap().id;
+        let transfer = |ends_at: Option<&str>| NewRecurringTransfer {
+            user_id: uid,
+            from_account_id: account,
+            to_account_id: savings,
+            amount: 5.0,
+            next_run_at: "2026-03-01 09:00:00".to_string(),
+            frequency: Period::Monthly.to_str().to_string(),
+            recurrence_rule: None,
+            ends_at: ends_at.map(str::to_string),
+            max_occurrences: None,
+            requires_approval: false,
+            roll_convention: "none".to_string(),
+        };
+        let monthly = create_recurring_transfer(&mut conn, transfer(None)).unwrap();
+        assert!(matches!(
+            update_recurring_transfer(&mut conn, uid, monthly.id, transfer(Some("2026-02-28 23:59:59")), false),
+            Err(FinancerError::Validation(_))
+        ));
+        assert_eq!(get_user_recurring_transfers(&mut conn, uid).unwrap()[0].ends_at, None);
+    }
+
+    #[test]
+    fn test_pause_and_resume_recurring_items() {
+        let mut conn = get_test_connection();
+        create_user(&mut conn, "pauser", "pass", None).unwrap();
+        let uid = get_userid_by_username(&mut conn, "pauser").unwrap().id;
+        create_account(&mut conn, "A", "bank", 100.0, uid).unwrap();
+        create_account(&mut conn, "B", "bank", 0.0, uid).unwrap();
+        let accounts = get_user_accounts(&mut conn, uid).unwrap();
+
+        let item = create_recurring_transaction(&mut conn, daily_item(uid, accounts[0].id, None, None)).unwrap();
+        set_recurring_transaction_active(&mut conn, uid, item.id, false, at("2026-01-01")).unwrap();
+        assert_eq!(process_due_recurring(&mut conn, uid, at("2026-01-05")).unwrap(), 0);
+
+        // Paused items stay listed, and editing one keeps it paused.
+        let paused = get_user_recurring_transactions(&mut conn, uid).unwrap().pop().unwrap();
+        assert!(!paused.active);
+        update_recurring_transaction(&mut conn, uid, item.id, daily_item(uid, accounts[0].id, None, Some(10)), false).unwrap();
+        assert!(!get_user_recurring_transactions(&mut conn, uid).unwrap()[0].active);
+
+        // Resuming skips what was missed while paused.
+        set_recurring_transaction_active(&mut conn, uid, item.id, true, at("2026-01-05")).unwrap();
+        let resumed = get_user_recurring_transactions(&mut conn, uid).unwrap().pop().unwrap();
+        assert!(resumed.active);
+        assert_eq!(resumed.next_run_at, "2026-01-05 09:00:00");
+        assert_eq!(process_due_recurring(&mut conn, uid, at("2026-01-05")).unwrap(), 1);
+
+        let transfer = create_recurring_transfer(&mut conn, NewRecurringTransfer {
+            user_id: uid,
+            from_account_id: accounts[0].id,
+            to_account_id: accounts[1].id,
+            amount: 5.0,
+            next_run_at: "2026-01-01 09:00:00".to_string(),
+            frequency: Period::Weekly.to_str().to_string(),
+            recurrence_rule: None,
+            ends_at: None,
+            max_occurrences: Some(2),
+            requires_approval: false,
+            roll_convention: "none".to_string(),
+        })
+        .unwrap();
+        set_recurring_transfer_active(&mut conn, uid, transfer.id, false, at("2026-01-01")).unwrap();
+        assert_eq!(process_due_recurring(&mut conn, uid, at("2026-01-05")).unwrap(), 0);
+        set_recurring_transfer_active(&mut conn, uid, transfer.id, true, at("2026-01-05")).unwrap();
+        assert_eq!(get_user_recurring_transfers(&mut conn, uid).unwrap()[0].next_run_at, "2026-01-08 09:00:00");
+    }
+
+    #[test]
+    fn test_expand_occurrences_for_window() {
+        use financer::schedule::{daily_totals, expand_occurrences, to_ics, OccurrenceSource};
+
+        let mut conn = get_test_connection();
+        create_user(&mut conn, "planner", "pass", None).unwrap();
+        let uid = get_userid_by_username(&mut conn, "planner").unwrap().id;
+        create_account(&mut conn, "A", "bank", 100.0, uid).unwrap();
+        create_account(&mut conn, "B", "bank", 0.0, uid).unwrap();
+        let accounts = get_user_accounts(&mut conn, uid).unwrap();
+
+        let mut rent = daily_item(uid, accounts[0].id, None, None);
+        rent.category = "Rent, monthly".to_string();
+        rent.amount = -1200.0;
+        rent.recurrence_rule = Some("FREQ=MONTHLY".to_string());
+        let rent = create_recurring_transaction(&mut conn, rent).unwrap();
+        let mut pay = daily_item(uid, accounts[0].id, None, Some(3));
+        pay.amount = 2000.0;
+        pay.category = "Salary".to_string();
+        pay.recurrence_rule = Some("FREQ=WEEKLY;INTERVAL=2".to_string());
+        create_recurring_transaction(&mut conn, pay).unwrap();
+        let paused = create_recurring_transaction(&mut conn, daily_item(uid, accounts[0].id, None, None)).unwrap();
+        set_recurring_transaction_active(&mut conn, uid, paused.id, false, at("2026-01-01")).unwrap();
+        create_recurring_transfer(&mut conn, NewRecurringTransfer {
+            user_id: uid,
+            from_account_id: accounts[0].id,
+            to_account_id: accounts[1].id,
+            amount: 50.0,
+            next_run_at: "2026-01-15 09:00:00".to_string(),
+            frequency: Period::Monthly.to_str().to_string(),
+            recurrence_rule: Some("FREQ=MONTHLY;BYMONTHDAY=1,15".to_string()),
+            ends_at: Some("2026-02-10 00:00:00".to_string()),
+            max_occurrences: None,
+            requires_approval: false,
+            roll_convention: "none".to_string(),
+        })
+        .unwrap();
+
+        let txs = get_user_recurring_transactions(&mut conn, uid).unwrap();
+        let transfers = get_user_recurring_transfers(&mut conn, uid).unwrap();
+        let before = get_user_transactions(&mut conn, uid).unwrap().len();
+        let occ = expand_occurrences(&txs, &transfers, &std::collections::HashMap::new(), at("2026-01-10"), at("2026-03-10"));
+        assert_eq!(get_user_transactions(&mut conn, uid).unwrap().len(), before);
+
+        let dates: Vec<String> = occ.iter().map(|o| format!("{} {}", o.at.format("%m-%d"), o.category)).collect();
+        // Salary's first run (01-01) is before the window but still uses one of its three runs.
+        assert_eq!(
+            dates,
+            [
+                "01-15 Salary",
+                "01-15 Transfer",
+                "01-29 Salary",
+                "02-01 Rent, monthly",
+                "02-01 Transfer",
+                "03-01 Rent, monthly",
+            ]
+        );
+        assert_eq!(occ[3].source, OccurrenceSource::Transaction(rent.id));
+        assert_eq!(occ[1].to_account_id, Some(accounts[1].id));
+
+        let totals = daily_totals(&occ);
+        let feb_first = totals[&NaiveDate::from_ymd_opt(2026, 2, 1).unwrap()];
+        assert_eq!(feb_first.expenses, 1200.0);
+        assert_eq!(feb_first.transfers, 50.0);
+        assert_eq!(feb_first.net(), -1200.0);
+
+        let ics = to_ics(&occ, |o| o.category.clone(), at("2026-01-10"));
+        assert!(ics.starts_with("BEGIN:VCALENDAR\r\n"));
+        assert!(ics.ends_with("END:VCALENDAR\r\n"));
+        assert_eq!(ics.matches("BEGIN:VEVENT").count(), 6);
+        assert!(ics.contains("DTSTART;VALUE=DATE:20260201\r\nDTEND;VALUE=DATE:20260202\r\n"));
+        assert!(ics.contains("SUMMARY:Rent\\, monthly\r\n"));
+        assert!(ics.lines().all(|l| l.len() <= 75));
+    }
+
+    #[test]
+    fn test_approval_queue_confirm_edit_and_skip() {
+        let mut conn = get_test_connection();
+        create_user(&mut conn, "approver", "pass", None).unwrap();
+        let uid = get_userid_by_username(&mut conn, "approver").unwrap().id;
+        create_account(&mut conn, "Main", "bank", 100.0, uid).unwrap();
+        let account = get_user_accounts(&mut conn, uid).unwrap()[0].id;
+
+        let mut item = daily_item(uid, account, None, None);
+        item.requires_approval = true;
+        create_recurring_transaction(&mut conn, item).unwrap();
+
+        assert_eq!(process_due_recurring(&mut conn, uid, at("2026-01-03")).unwrap(), 3);
+        assert_eq!(process_due_recurring(&mut conn, uid, at("2026-01-03")).unwrap(), 0);
+        assert!(get_user_transactions(&mut conn, uid).unwrap().is_empty());
+        let pending = get_pending_recurring_runs(&mut conn, uid).unwrap();
+        assert_eq!(pending.len(), 3);
+        assert_eq!(pending[0].scheduled_for, "2026-01-01 09:00:00");
+
+        // Confirming with an edited amount keeps the expense sign and the scheduled date.
+        confirm_recurring_run(&mut conn, uid, pending[0].id, Some(12.5), at("2026-01-03")).unwrap();
+        skip_recurring_run(&mut conn, uid, pending[1].id, at("2026-01-03")).unwrap();
+        assert!(matches!(
+            confirm_recurring_run(&mut conn, uid, pending[0].id, None, at("2026-01-03")),
+            Err(FinancerError::Conflict(_))
+        ));
+
+        let txs = get_user_transactions(&mut conn, uid).unwrap();
+        assert_eq!(txs.len(), 1);
+        assert_eq!(txs[0].amount, -12.5);
+        assert_eq!(txs[0].date, "2026-01-01 09:00:00");
+        assert_eq!(get_user_accounts(&mut conn, uid).unwrap()[0].balance, 87.5);
+        assert_eq!(get_pending_recurring_runs(&mut conn, uid).unwrap().len(), 1);
+
+        create_user(&mut conn, "stranger", "pass", None).unwrap();
+        let stranger = get_userid_by_username(&mut conn, "stranger").unwrap().id;
+        assert!(get_pending_recurring_runs(&mut conn, stranger).unwrap().is_empty());
+        assert!(matches!(
+            skip_recurring_run(&mut conn, stranger, pending[2].id, at("2026-01-03")),
+            Err(FinancerError::PermissionDenied(_))
+        ));
+    }
+
+    #[test]
+    fn test_run_log_prevents_double_posting_and_skips_next() {
+        use financer::schema::recurring_transactions;
+
+        let mut conn = get_test_connection();
+        create_user(&mut conn, "runlog", "pass", None).unwrap();
+        let uid = get_userid_by_username(&mut conn, "runlog").unwrap().id;
+        create_account(&mut conn, "Main", "bank", 100.0, uid).unwrap();
+        let account = get_user_accounts(&mut conn, uid).unwrap()[0].id;
+        let item = create_recurring_transaction(&mut conn, daily_item(uid, account, None, None)).unwrap();
+
+        assert_eq!(process_due_recurring(&mut conn, uid, at("2026-01-02")).unwrap(), 2);
+        // Even if the schedule is wound back, logged occurrences are not posted again.
+        diesel::update(recurring_transactions::table.filter(recurring_transactions::id.eq(item.id)))
+            .set(recurring_transactions::next_run_at.eq("2026-01-01 09:00:00"))
+            .execute(&mut conn)
+            .unwrap();
+        assert_eq!(process_due_recurring(&mut conn, uid, at("2026-01-02")).unwrap(), 0);
+        assert_eq!(get_user_transactions(&mut conn, uid).unwrap().len(), 2);
+
+        skip_next_recurring_occurrence(&mut conn, uid, RecurringKind::Transaction, item.id, at("2026-01-02")).unwrap();
+        assert_eq!(get_user_recurring_transactions(&mut conn, uid).unwrap()[0].next_run_at, "2026-01-04 09:00:00");
+        assert_eq!(process_due_recurring(&mut conn, uid, at("2026-01-04")).unwrap(), 1);
+        let dates: Vec<String> = get_user_transactions(&mut conn, uid).unwrap().into_iter().map(|t| t.date).collect();
+        assert!(!dates.contains(&"2026-01-03 09:00:00".to_string()));
+
+        delete_recurring_transaction(&mut conn, uid, item.id).unwrap();
+        use financer::schema::recurring_runs;
+        let left: i64 = recurring_runs::table.count().get_result(&mut conn).unwrap();
+        assert_eq!(left, 0);
+    }
+
+    #[test]
+    fn test_generated_transactions_link_to_template_and_can_be_rewritten() {
+        let mut conn = get_test_connection();
+        create_user(&mut conn, "linker", "pass", None).unwrap();
+        let uid = get_userid_by_username(&mut conn, "linker").unwrap().id;
+        create_account(&mut conn, "A", "bank", 100.0, uid).unwrap();
+        create_account(&mut conn, "B", "bank", 0.0, uid).unwrap();
+        let accounts = get_user_accounts(&mut conn, uid).unwrap();
+        let (a, b) = (accounts[0].id, accounts[1].id);
+
+        create_transaction(&mut conn, a, 0, -1.0, "Snacks".to_string(), "2026-01-01 08:00:00".to_string()).unwrap();
+        let coffee = create_recurring_transaction(&mut conn, daily_item(uid, a, None, None)).unwrap();
+        let saving = create_recurring_transfer(&mut conn, NewRecurringTransfer {
+            user_id: uid,
+            from_account_id: a,
+            to_account_id: b,
+            amount: 20.0,
+            next_run_at: "2026-01-01 09:00:00".to_string(),
+            frequency: Period::Weekly.to_str().to_string(),
+            recurrence_rule: None,
+            ends_at: None,
+            max_occurrences: None,
+            requires_approval: false,
+            roll_convention: "none".to_string(),
+        })
+        .unwrap();
+        assert_eq!(process_due_recurring(&mut conn, uid, at("2026-01-02")).unwrap(), 3);
+
+        let txs = get_user_transactions(&mut conn, uid).unwrap();
+        let from_coffee: Vec<&Transaction> = txs.iter().filter(|t| t.recurring_source() == Some((RecurringKind::Transaction, coffee.id))).collect();
+        let from_saving: Vec<&Transaction> = txs.iter().filter(|t| t.recurring_source() == Some((RecurringKind::Transfer, saving.id))).collect();
+        assert_eq!(from_coffee.len(), 2);
+        assert_eq!(from_saving.len(), 2);
+        assert!(txs.iter().any(|t| t.category == "Snacks" && t.recurring_source().is_none()));
+
+        // Rewriting applies the edited template to past entries and keeps balances in step.
+        let mut pricier = daily_item(uid, a, None, None);
+        pricier.amount = -15.0;
+        pricier.category = "Cafe".to_string();
+        pricier.next_run_at = "2026-01-03 09:00:00".to_string();
+        update_recurring_transaction(&mut conn, uid, coffee.id, pricier, true).unwrap();
+
+        let mut bigger = saving.clone();
+        bigger.amount = 30.0;
+        update_recurring_transfer(&mut conn, uid, saving.id, NewRecurringTransfer {
+            user_id: uid,
+            from_account_id: a,
+            to_account_id: b,
+            amount: bigger.amount,
+            next_run_at: "2026-01-08 09:00:00".to_string(),
+            frequency: bigger.frequency,
+            recurrence_rule: Some(bigger.recurrence_rule),
+            ends_at: None,
+            max_occurrences: None,
+            requires_approval: false,
+            roll_convention: "none".to_string(),
+        }, true)
+        .unwrap();
+
+        let txs = get_user_transactions(&mut conn, uid).unwrap();
+        let cafe: Vec<&Transaction> = txs.iter().filter(|t| t.category == "Cafe").collect();
+        assert_eq!(cafe.len(), 2);
+        assert!(cafe.iter().all(|t| t.amount == -15.0 && t.date.ends_with("09:00:00")));
+        let balances = get_user_accounts(&mut conn, uid).unwrap();
+        assert_eq!(balances.iter().find(|x| x.id == a).unwrap().balance, 100.0 - 1.0 - 30.0 - 30.0);
+        assert_eq!(balances.iter().find(|x| x.id == b).unwrap().balance, 30.0);
+
+        // The running balance on each row still follows the history in date order.
+        let mut history: Vec<&Transaction> = txs.iter().filter(|t| t.user_account_id == a).collect();
+        history.sort_by(|x, y| (&x.date, x.id).cmp(&(&y.date, y.id)));
+        let mut running = 100.0;
+        for tx in history {
+            running += tx.amount;
+            assert_eq!(tx.balance_after, running);
+        }
+
+        // Deleting the template leaves the link on its history.
+        delete_recurring_transaction(&mut conn, uid, coffee.id).unwrap();
+        let txs = get_user_transactions(&mut conn, uid).unwrap();
+        assert_eq!(txs.iter().filter(|t| t.recurring_source() == Some((RecurringKind::Transaction, coffee.id))).count(), 2);
+    }
+
+    #[test]
+    fn test_failed_rewrite_keeps_the_template_unchanged() {
+        let mut conn = get_test_connection();
+        create_user(&mut conn, "host", "pass", None).unwrap();
+        let host = get_userid_by_username(&mut conn, "host").unwrap().id;
+        create_user(&mut conn, "helper", "pass", None).unwrap();
+        let helper = get_userid_by_username(&mut conn, "helper").unwrap().id;
+        create_account(&mut conn, "Joint", "bank", 100.0, host).unwrap();
+        let joint = get_user_accounts(&mut conn, host).unwrap()[0].id;
+        create_account(&mut conn, "Own", "bank", 100.0, helper).unwrap();
+        let own = get_user_accounts(&mut conn, helper).unwrap().into_iter().find(|a| a.name == "Own").unwrap().id;
+        add_account_member(&mut conn, host, joint, "helper", AccountRole::Editor).unwrap();
+
+        // Past entries stay on the joint account after the template moves, then the helper
+        // loses the right to edit them.
+        let item = create_recurring_transaction(&mut conn, daily_item(helper, joint, None, None)).unwrap();
+        process_due_recurring(&mut conn, helper, at("2026-01-02")).unwrap();
+        update_recurring_transaction(&mut conn, helper, item.id, daily_item(helper, own, None, None), false).unwrap();
+        set_account_member_role(&mut conn, host, joint, helper, AccountRole::Viewer).unwrap();
+
+        let mut pricier = daily_item(helper, own, None, None);
+        pricier.amount = -25.0;
+        assert!(matches!(update_recurring_transaction(&mut conn, helper, item.id, pricier, true), Err(FinancerError::PermissionDenied(_))));
+        let saved = get_user_recurring_transactions(&mut conn, helper).unwrap().into_iter().find(|r| r.id == item.id).unwrap();
+        assert_eq!(saved.amount, -10.0);
+    }
+
+    #[test]
+    fn test_holiday_calendar_rolls_and_parses_files() {
+        use financer::calendar::{parse_holiday_file, HolidayCalendar};
+
+        let d = |s: &str| NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap();
+        let calendar = HolidayCalendar::new([d("2026-01-01"), d("2026-12-25")]);
+        assert!(calendar.is_business_day(d("2026-01-05")));
+        assert!(!calendar.is_business_day(d("2026-01-03")));
+        assert!(!calendar.is_business_day(d("2026-01-01")));
+
+        // New Year's Day is a Thursday.
+        assert_eq!(calendar.roll(d("2026-01-01"), RollConvention::None), d("2026-01-01"));
+        assert_eq!(calendar.roll(d("2026-01-01"), RollConvention::Following), d("2026-01-02"));
+        assert_eq!(calendar.roll(d("2026-01-01"), RollConvention::Preceding), d("2025-12-31"));
+        assert_eq!(calendar.roll(d("2026-01-01"), RollConvention::ModifiedFollowing), d("2026-01-02"));
+        assert_eq!(calendar.roll(d("2026-01-05"), RollConvention::Preceding), d("2026-01-05"));
+        // 31 January is a Saturday: following crosses into February, modified following doesn't.
+        assert_eq!(calendar.roll(d("2026-01-31"), RollConvention::Following), d("2026-02-02"));
+        assert_eq!(calendar.roll(d("2026-01-31"), RollConvention::ModifiedFollowing), d("2026-01-30"));
+        // Christmas on a Friday, then the weekend.
+        assert_eq!(calendar.roll(d("2026-12-25"), RollConvention::Following), d("2026-12-28"));
+
+        let listed = parse_holiday_file("# 2026\n2026-12-25, Christmas Day\n\n2026-12-26\t# Boxing Day\n").unwrap();
+        assert_eq!(
+            listed,
+            vec![(d("2026-12-25"), "Christmas Day".to_string()), (d("2026-12-26"), "Holiday".to_string())]
+        );
+        assert!(matches!(parse_holiday_file("2026-13-01, Nope"), Err(FinancerError::Validation(_))));
+
+        let ics = "BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nDTSTART;VALUE=DATE:20260704\r\nSUMMARY:Independence\r\n  Day\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n";
+        assert_eq!(parse_holiday_file(ics).unwrap(), vec![(d("2026-07-04"), "Independence Day".to_string())]);
+    }
+
+    #[test]
+    fn test_recurring_items_roll_off_weekends_and_holidays() {
+        use financer::schedule::expand_occurrences;
+
+        let mut conn = get_test_connection();
+        create_user(&mut conn, "roller", "pass", None).unwrap();
+        create_user(&mut conn, "other", "pass", None).unwrap();
+        let uid = get_userid_by_username(&mut conn, "roller").unwrap().id;
+        let other = get_userid_by_username(&mut conn, "other").unwrap().id;
+        create_account(&mut conn, "Main", "bank", 1000.0, uid).unwrap();
+        let account = get_user_accounts(&mut conn, uid).unwrap()[0].id;
+
+        let new_year = NaiveDate::from_ymd_opt(2026, 1, 1).unwrap();
+        let holiday = add_holiday(&mut conn, uid, new_year, "New Year's Day").unwrap();
+        assert!(matches!(add_holiday(&mut conn, uid, new_year, "Again"), Err(FinancerError::Conflict(_))));
+        assert!(matches!(delete_holiday(&mut conn, other, holiday.id), Err(FinancerError::NotFound(_))));
+        assert_eq!(import_holidays(&mut conn, uid, "2026-01-01\n2026-04-03, Good Friday\n").unwrap(), 1);
+        assert_eq!(get_user_holidays(&mut conn, uid).unwrap().len(), 2);
+
+        let mut bad = daily_item(uid, account, None, None);
+        bad.roll_convention = "sideways".to_string();
+        assert!(matches!(create_recurring_transaction(&mut conn, bad), Err(FinancerError::Validation(_))));
+
+        let mut salary = daily_item(uid, account, None, None);
+        salary.amount = 500.0;
+        salary.category = "Salary".to_string();
+        salary.recurrence_rule = Some("FREQ=MONTHLY;BYMONTHDAY=1".to_string());
+        salary.roll_convention = RollConvention::Following.to_str().to_string();
+        let salary = create_recurring_transaction(&mut conn, salary).unwrap();
+
+        let mut rent = daily_item(uid, account, None, None);
+        rent.amount = -300.0;
+        rent.category = "Rent".to_string();
+        rent.next_run_at = "2026-01-31 09:00:00".to_string();
+        rent.recurrence_rule = Some("FREQ=MONTHLY;BYMONTHDAY=-1".to_string());
+        rent.roll_convention = RollConvention::Preceding.to_str().to_string();
+        let rent = create_recurring_transaction(&mut conn, rent).unwrap();
+
+        // The 1st is a holiday, so salary waits for Friday the 2nd.
+        assert_eq!(process_due_recurring(&mut conn, uid, at("2026-01-01")).unwrap(), 0);
+        assert_eq!(process_due_recurring(&mut conn, uid, at("2026-01-02")).unwrap(), 1);
+        // Rent for Saturday the 31st is paid on Friday the 30th, ahead of its scheduled date.
+        assert_eq!(process_due_recurring(&mut conn, uid, at("2026-01-30")).unwrap(), 1);
+
+        let txs = get_user_transactions(&mut conn, uid).unwrap();
+        let posted = |cat: &str| -> Vec<String> {
+            txs.iter().filter(|t| t.category == cat).map(|t| t.date.clone()).collect()
+        };
+        assert_eq!(posted("Salary"), vec!["2026-01-02 09:00:00".to_string()]);
+        assert_eq!(posted("Rent"), vec!["2026-01-30 09:00:00".to_string()]);
+
+        let items = get_user_recurring_transactions(&mut conn, uid).unwrap();
+        let next = |item_id: i32| items.iter().find(|i| i.id == item_id).unwrap().next_run_at.clone();
+        assert_eq!(next(salary.id), "2026-02-01 09:00:00");
+        assert_eq!(next(rent.id), "2026-02-28 09:00:00");
+
+        // The projection rolls the same way.
+        let calendars = get_holiday_calendars(&mut conn, &[uid]).unwrap();
+        let occ = expand_occurrences(&items, &[], &calendars, at("2026-01-31"), at("2026-05-01"));
+        let days: Vec<(String, String)> = occ
+            .iter()
+            .map(|o| (o.category.clone(), o.at.format("%Y-%m-%d").to_string()))
+            .collect();
+        assert_eq!(
+            days,
+            vec![
+                ("Salary".to_string(), "2026-02-02".to_string()),
+                ("Rent".to_string(), "2026-02-27".to_string()),
+                ("Salary".to_string(), "2026-03-02".to_string()),
+                ("Rent".to_string(), "2026-03-31".to_string()),
+                ("Salary".to_string(), "2026-04-01".to_string()),
+                ("Rent".to_string(), "2026-04-30".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_balance_forecast_with_schedule_and_typical_spending() {
+        use financer::forecast::forecast_balances;
+        use financer::schedule::expand_occurrences;
+
+        let mut conn = get_test_connection();
+        create_user(&mut conn, "forecaster", "pass", None).unwrap();
+        let uid = get_userid_by_username(&mut conn, "forecaster").unwrap().id;
+        create_account(&mut conn, "Checking", "bank", 1000.0, uid).unwrap();
+        create_account(&mut conn, "Savings", "bank", 0.0, uid).unwrap();
+        let accounts = get_user_accounts(&mut conn, uid).unwrap();
+        let (checking, savings) = (accounts[0].id, accounts[1].id);
+
+        // 90 days of history: 90 spent on groceries; transfers don't count as spending.
+        create_transaction(&mut conn, checking, 0, -60.0, "Groceries".to_string(), "2026-01-10 12:00:00".to_string()).unwrap();
+        create_transaction(&mut conn, checking, 0, -30.0, "Groceries".to_string(), "2026-02-10 12:00:00".to_string()).unwrap();
+        create_transaction(&mut conn, checking, 0, -50.0, "Transfer".to_string(), "2026-02-01 12:00:00".to_string()).unwrap();
+        // Already taken off the balance, but not due until the 5th.
+        create_transaction(&mut conn, checking, 0, -200.0, "Car".to_string(), "2026-04-05 12:00:00".to_string()).unwrap();
+
+        let estimates = get_spend_estimates(&mut conn, uid, at("2026-01-01") - Duration::hours(9), at("2026-04-01") - Duration::hours(9)).unwrap();
+        assert_eq!(estimates.len(), 1);
+        assert_eq!(estimates[0].category, "Groceries");
+        assert_eq!(estimates[0].daily_amount, 1.0);
+
+        let mut salary = daily_item(uid, checking, None, None);
+        salary.amount = 500.0;
+        salary.category = "Salary".to_string();
+        salary.next_run_at = "2026-04-10 09:00:00".to_string();
+        salary.recurrence_rule = Some("FREQ=MONTHLY".to_string());
+        create_recurring_transaction(&mut conn, salary).unwrap();
+        create_recurring_transfer(&mut conn, NewRecurringTransfer {
+            user_id: uid,
+            from_account_id: checking,
+            to_account_id: savings,
+            amount: 100.0,
+            next_run_at: "2026-04-03 09:00:00".to_string(),
+            frequency: Period::Monthly.to_str().to_string(),
+            recurrence_rule: None,
+            ends_at: None,
+            max_occurrences: None,
+            requires_approval: false,
+            roll_convention: "none".to_string(),
+        })
+        .unwrap();
+
+        let today = NaiveDate::from_ymd_opt(2026, 4, 1).unwrap();
+        let scheduled = get_scheduled_transactions(&mut conn, uid, at("2026-04-02") - Duration::hours(9)).unwrap();
+        assert_eq!(scheduled.len(), 1);
+        let occurrences = expand_occurrences(
+            &get_user_recurring_transactions(&mut conn, uid).unwrap(),
+            &get_user_recurring_transfers(&mut conn, uid).unwrap(),
+            &std::collections::HashMap::new(),
+            at("2026-04-01") - Duration::hours(9),
+            at("2026-04-12"),
+        );
+        let accounts = get_user_accounts(&mut conn, uid).unwrap();
+        let forecast = forecast_balances(&accounts, &scheduled, &occurrences, &estimates, today, 10);
+
+        let checking_days: Vec<f32> = forecast[0].points.iter().map(|p| p.balance).collect();
+        assert_eq!(forecast[0].points.len(), 11);
+        assert_eq!(checking_days[0], 860.0);
+        assert_eq!(checking_days[2], 758.0);
+        assert_eq!(checking_days[4], 556.0);
+        assert_eq!(checking_days[9], 1051.0);
+        assert_eq!(checking_days[10], 1050.0);
+        assert_eq!(forecast[1].points[2].balance, 100.0);
+
+        let low = NaiveDate::from_ymd_opt(2026, 4, 5).unwrap();
+        assert_eq!(forecast[0].first_below(600.0).map(|p| p.date), Some(low));
+        // Spending keeps dragging the balance down until payday.
+        let payday_eve = NaiveDate::from_ymd_opt(2026, 4, 9).unwrap();
+        assert_eq!(forecast[0].lowest().map(|p| (p.date, p.balance)), Some((payday_eve, 552.0)));
+        assert!(forecast[1].first_below(0.0).is_none());
+
+        // Without the spending estimate only scheduled items move the balance.
+        let flat = forecast_balances(&accounts, &scheduled, &occurrences, &[], today, 10);
+        assert_eq!(flat[0].points[10].balance, 1060.0);
+
+        assert_eq!(get_low_balance_threshold(&mut conn, uid).unwrap(), 0.0);
+        set_low_balance_threshold(&mut conn, uid, 250.0).unwrap();
+        assert_eq!(get_low_balance_threshold(&mut conn, uid).unwrap(), 250.0);
+        assert!(matches!(set_low_balance_threshold(&mut conn, uid, f32::NAN), Err(FinancerError::Validation(_))));
+    }
+
+    #[test]
+    fn test_budget_periods_and_rollover_rules() {
+        use financer::budgeting::{period_range, periods_between, rollover_after};
+
+        let d = |s: &str| NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap();
+        let mid = |s: &str| d(s).and_hms_opt(0, 0, 0).unwrap();
+        assert_eq!(period_range(Period::Monthly, d("2026-01-15"), -1), (mid("2025-12-01"), mid("2026-01-01")));
+        assert_eq!(period_range(Period::Monthly, d("2026-01-15"), 12), (mid("2027-01-01"), mid("2027-02-01")));
+        // 15 January 2026 is a Thursday.
+        assert_eq!(period_range(Period::Weekly, d("2026-01-15"), 0), (mid("2026-01-12"), mid("2026-01-19")));
+        assert_eq!(periods_between(Period::Monthly, d("2026-01-20"), mid("2026-04-01")).len(), 3);
+        assert!(periods_between(Period::Monthly, d("2026-01-20"), mid("2026-01-01")).is_empty());
+
+        let spent = [10000, 40000, 5000];
+        assert_eq!(rollover_after(RolloverMode::None, None, 20000, 0, &spent), 0);
+        assert_eq!(rollover_after(RolloverMode::Surplus, None, 20000, 0, &spent), 15000);
+        assert_eq!(rollover_after(RolloverMode::SurplusAndDeficit, None, 20000, 0, &spent), 5000);
+        assert_eq!(rollover_after(RolloverMode::Capped, Some(12000), 20000, 0, &spent), 12000);
+        assert_eq!(rollover_after(RolloverMode::Surplus, None, 20000, 0, &[]), 0);
+        // Continuing from a saved carry gives the same answer as the full history.
+        let first_two = rollover_after(RolloverMode::SurplusAndDeficit, None, 20000, 0, &spent[..2]);
+        assert_eq!(rollover_after(RolloverMode::SurplusAndDeficit, None, 20000, first_two, &spent[2..]), 5000);
+    }
+
+    #[test]
+    fn test_budget_rollover_from_history() {
+        let mut conn = get_test_connection();
+        create_user(&mut conn, "roll", "pass", None).unwrap();
+        let uid = get_userid_by_username(&mut conn, "roll").unwrap().id;
+        create_account(&mut conn, "Main", "bank", 5000.0, uid).unwrap();
+        let account = get_user_accounts(&mut conn, uid).unwrap()[0].id;
+
+        let groceries = |mode: RolloverMode, cap: Option<i32>| NewBudget {
+            user_id: uid,
+            category: "Groceries".to_string(),
+            limit_cents: 20000,
+            period: Period::Monthly.to_str().to_string(),
+            target_type: TargetType::Expense.to_str().to_string(),
+            rollover_mode: mode.to_str().to_string(),
+            rollover_cap_cents: cap,
+            alert_thresholds: "80,100".to_string(),
+            name: None,
+            include_subcategories: false,
+            starts_on: None,
+            ends_on: None,
+        };
+        assert!(matches!(create_budget(&mut conn, groceries(RolloverMode::Capped, None)), Err(FinancerError::Validation(_))));
+        let mut unknown = groceries(RolloverMode::None, None);
+        unknown.rollover_mode = "sometimes".to_string();
+        assert!(matches!(create_budget(&mut conn, unknown), Err(FinancerError::Validation(_))));
+
+        let created = NaiveDate::from_ymd_opt(2026, 1, 5).unwrap().and_hms_opt(10, 0, 0).unwrap();
+        let budget = create_budget_at(&mut conn, groceries(RolloverMode::SurplusAndDeficit, None), created).unwrap();
+        let budget_id = budget.id.unwrap();
+        for (amount, when) in [(-150.0, "2026-01-10"), (-300.0, "2026-02-10"), (-50.0, "2026-03-10"), (20.0, "2026-03-11")] {
+            create_transaction(&mut conn, account, 0, amount, "Groceries".to_string(), format!("{} 12:00:00", when)).unwrap();
+        }
+        create_transaction(&mut conn, account, 0, -999.0, "Rent".to_string(), "2026-01-10 12:00:00".to_string()).unwrap();
+
+        let mid = |s: &str| NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap().and_hms_opt(0, 0, 0).unwrap();
+        let load = |conn: &mut SqliteConnection| get_user_budgets(conn, uid).unwrap().into_iter().find(|b| b.id == Some(budget_id)).unwrap();
+        let b = load(&mut conn);
+        // Nothing carries into the first period; January leaves 50, February overspends by 50.
+        assert_eq!(get_budget_rollover(&mut conn, uid, &b, mid("2026-01-01")).unwrap(), 0);
+        assert_eq!(get_budget_rollover(&mut conn, uid, &b, mid("2026-02-01")).unwrap(), 5000);
+        assert_eq!(get_budget_rollover(&mut conn, uid, &b, mid("2026-03-01")).unwrap(), -5000);
+        // March nets 30 after a refund: 200 - 50 - 30 = 120.
+        assert_eq!(get_budget_rollover(&mut conn, uid, &b, mid("2026-04-01")).unwrap(), 12000);
+        // Future periods keep accumulating the untouched limit.
+        assert_eq!(get_budget_rollover(&mut conn, uid, &b, mid("2026-06-01")).unwrap(), 52000);
+
+        // A late entry for February replaces the carries saved for the periods after it.
+        create_transaction(&mut conn, account, 0, -10.0, "Groceries".to_string(), "2026-02-20 12:00:00".to_string()).unwrap();
+        assert_eq!(get_budget_rollover(&mut conn, uid, &b, mid("2026-01-01")).unwrap(), 0);
+        assert_eq!(get_budget_rollover(&mut conn, uid, &b, mid("2026-02-01")).unwrap(), 5000);
+        assert_eq!(get_budget_rollover(&mut conn, uid, &b, mid("2026-04-01")).unwrap(), 11000);
+        let late_id = get_user_transactions(&mut conn, uid).unwrap().into_iter().find(|t| t.amount == -10.0).unwrap().id;
+        delete_transaction(&mut conn, late_id).unwrap();
+        assert_eq!(get_budget_rollover(&mut conn, uid, &b, mid("2026-04-01")).unwrap(), 12000);
+
+        // Sharing an account between other users keeps this user's saved carries.
+        let saved = |conn: &mut SqliteConnection| {
+            use financer::schema::budget_carries;
+            budget_carries::table.filter(budget_carries::budget_id.eq(budget_id)).count().get_result::<i64>(conn).unwrap()
+        };
+        let before = saved(&mut conn);
+        assert!(before > 0);
+        create_user(&mut conn, "host", "pass", None).unwrap();
+        let host = get_userid_by_username(&mut conn, "host").unwrap().id;
+        create_user(&mut conn, "guest", "pass", None).unwrap();
+        create_account(&mut conn, "Shared", "bank", 0.0, host).unwrap();
+        let shared = get_user_accounts(&mut conn, host).unwrap()[0].id;
+        add_account_member(&mut conn, host, shared, "guest", AccountRole::Viewer).unwrap();
+        delete_account(&mut conn, host, shared).unwrap();
+        assert_eq!(saved(&mut conn), before);
+        // Joining one does not.
+        create_account(&mut conn, "Joint", "bank", 0.0, host).unwrap();
+        let joint = get_user_accounts(&mut conn, host).unwrap().into_iter().find(|a| a.name == "Joint").unwrap().id;
+        add_account_member(&mut conn, host, joint, "roll", AccountRole::Viewer).unwrap();
+        assert_eq!(saved(&mut conn), 0);
+
+        update_budget(&mut conn, budget_id, groceries(RolloverMode::Surplus, None)).unwrap();
+        let b = load(&mut conn);
+        // Editing keeps the history back to when the budget was created.
+        assert_eq!(b.created_at, "2026-01-05 10:00:00");
+        assert_eq!(get_budget_rollover(&mut conn, uid, &b, mid("2026-03-01")).unwrap(), 0);
+        assert_eq!(get_budget_rollover(&mut conn, uid, &b, mid("2026-04-01")).unwrap(), 17000);
+
+        update_budget(&mut conn, budget_id, groceries(RolloverMode::Capped, Some(8000))).unwrap();
+        let b = load(&mut conn);
+        assert_eq!(get_budget_rollover(&mut conn, uid, &b, mid("2026-04-01")).unwrap(), 8000);
+
+        let mut income = groceries(RolloverMode::Surplus, None);
+        income.target_type = TargetType::Income.to_str().to_string();
+        update_budget(&mut conn, budget_id, income).unwrap();
+        let b = load(&mut conn);
+        assert_eq!(get_budget_rollover(&mut conn, uid, &b, mid("2026-04-01")).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_envelope_assign_spend_move_and_available_to_assign() {
+        let mut conn = get_test_connection();
+        create_user(&mut conn, "env", "pass", None).unwrap();
+        let uid = get_userid_by_username(&mut conn, "env").unwrap().id;
+        create_account(&mut conn, "Main", "bank", 1000.0, uid).unwrap();
+        let account = get_user_accounts(&mut conn, uid).unwrap()[0].id;
+        let day = |s: &str| NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap();
+        let now = at("2026-02-01");
+
+        let groceries = create_envelope(&mut conn, uid, "Groceries", day("2026-02-14")).unwrap();
+        assert_eq!(groceries.starts_on, "2026-02-01");
+        let rent = create_envelope(&mut conn, uid, "Rent", day("2026-02-01")).unwrap();
+        assert!(matches!(create_envelope(&mut conn, uid, "Groceries", day("2026-03-01")), Err(FinancerError::Conflict(_))));
+        assert!(matches!(create_envelope(&mut conn, uid, "Transfer", day("2026-03-01")), Err(FinancerError::Validation(_))));
+        assert!(matches!(assign_to_envelope(&mut conn, uid, groceries.id, day("2026-02-01"), 0, now), Err(FinancerError::Validation(_))));
+        assert!(matches!(move_between_envelopes(&mut conn, uid, rent.id, rent.id, day("2026-02-01"), 100, now), Err(FinancerError::Validation(_))));
+        assert!(matches!(move_between_envelopes(&mut conn, uid, rent.id, groceries.id, day("2026-02-01"), -100, now), Err(FinancerError::Validation(_))));
+
+        create_user(&mut conn, "other", "pass", None).unwrap();
+        let other = get_userid_by_username(&mut conn, "other").unwrap().id;
+        assert!(matches!(assign_to_envelope(&mut conn, other, rent.id, day("2026-02-01"), 100, now), Err(FinancerError::NotFound(_))));
+
+        // January's spending predates the envelopes, so no envelope counts it.
+        for (amt, when) in [(-50.0, "2026-01-15"), (-120.0, "2026-02-10"), (-30.0, "2026-03-05")] {
+            create_transaction(&mut conn, account, 0, amt, "Groceries".to_string(), format!("{} 12:00:00", when)).unwrap();
+        }
+        assign_to_envelope(&mut conn, uid, groceries.id, day("2026-02-01"), 20000, now).unwrap();
+        assign_to_envelope(&mut conn, uid, rent.id, day("2026-02-01"), 50000, now).unwrap();
+        move_between_envelopes(&mut conn, uid, rent.id, groceries.id, day("2026-03-01"), 5000, now).unwrap();
+
+        let status = |conn: &mut SqliteConnection, month: &str, envelope: i32| {
+            get_envelope_statuses(conn, uid, day(month)).unwrap().into_iter().find(|s| s.envelope_id == envelope).unwrap()
+        };
+        let jan = status(&mut conn, "2026-01-01", groceries.id);
+        assert_eq!((jan.assigned, jan.activity, jan.available), (0, 0, 0));
+        let feb = status(&mut conn, "2026-02-01", groceries.id);
+        assert_eq!((feb.assigned, feb.activity, feb.available), (20000, -12000, 8000));
+        let mar = status(&mut conn, "2026-03-01", groceries.id);
+        assert_eq!((mar.assigned, mar.activity, mar.available), (5000, -3000, 10000));
+        let mar_rent = status(&mut conn, "2026-03-01", rent.id);
+        assert_eq!((mar_rent.assigned, mar_rent.available), (-5000, 45000));
+
+        // Only income since the envelopes started can be assigned; transfers aren't income.
+        create_account(&mut conn, "Savings", "bank", 0.0, uid).unwrap();
+        let savings = get_user_accounts(&mut conn, uid).unwrap().into_iter().find(|a| a.name == "Savings").unwrap().id;
+        for (amt, when) in [(400.0, "2026-01-31"), (1000.0, "2026-02-01"), (500.0, "2026-03-01")] {
+            create_transaction(&mut conn, account, 0, amt, "Salary".to_string(), format!("{} 12:00:00", when)).unwrap();
+        }
+        create_transfer(&mut conn, account, savings, 300.0, "2026-02-03 12:00:00".to_string()).unwrap();
+        assert_eq!(get_available_to_assign(&mut conn, uid, day("2026-01-10")).unwrap(), -70000);
+        // February's 1000 less 200 in Groceries and 500 in Rent; March adds 500 more.
+        assert_eq!(get_available_to_assign(&mut conn, uid, day("2026-02-10")).unwrap(), 30000);
+        assert_eq!(get_available_to_assign(&mut conn, uid, day("2026-03-10")).unwrap(), 80000);
+        delete_envelope(&mut conn, uid, rent.id).unwrap();
+        assert_eq!(get_available_to_assign(&mut conn, uid, day("2026-03-10")).unwrap(), 125000);
+        assert_eq!(get_user_envelopes(&mut conn, uid).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_budget_threshold_alerts_and_notifications() {
+        let mut conn = get_test_connection();
+        create_user(&mut conn, "alerts", "pass", None).unwrap();
+        let uid = get_userid_by_username(&mut conn, "alerts").unwrap().id;
+        create_user(&mut conn, "partner", "pass", None).unwrap();
+        let partner = get_userid_by_username(&mut conn, "partner").unwrap().id;
+        create_account(&mut conn, "Main", "bank", 1000.0, uid).unwrap();
+        let account = get_user_accounts(&mut conn, uid).unwrap()[0].id;
+        add_account_member(&mut conn, uid, account, "partner", AccountRole::Editor).unwrap();
+
+        let budget = |owner: i32, cat: &str, limit_cents: i32, thresholds: &str| NewBudget {
+            user_id: owner,
+            category: cat.to_string(),
+            limit_cents,
+            period: Period::Monthly.to_str().to_string(),
+            target_type: TargetType::Expense.to_str().to_string(),
+            rollover_mode: RolloverMode::None.to_str().to_string(),
+            rollover_cap_cents: None,
+            alert_thresholds: thresholds.to_string(),
+            name: None,
+            include_subcategories: false,
+            starts_on: None,
+            ends_on: None,
+        };
+        assert!(matches!(create_budget(&mut conn, budget(uid, "Groceries", 20000, "80,lots")), Err(FinancerError::Validation(_))));
+        assert!(matches!(create_budget(&mut conn, budget(uid, "Groceries", 20000, "0")), Err(FinancerError::Validation(_))));
+        let groceries = create_budget(&mut conn, budget(uid, "Groceries", 20000, "100, 50%, 80")).unwrap();
+        assert_eq!(groceries.alert_thresholds, "50,80,100");
+        // The partner's own budget on the shared account has alerts turned off.
+        create_budget(&mut conn, budget(partner, "Groceries", 10000, "")).unwrap();
+
+        let spend = |conn: &mut SqliteConnection, amt: f32, when: &str| {
+            create_transaction(conn, account, 0, amt, "Groceries".to_string(), format!("{} 12:00:00", when)).unwrap();
+        };
+        spend(&mut conn, -50.0, "2026-03-05");
+        assert!(get_notifications(&mut conn, uid).unwrap().is_empty());
+        spend(&mut conn, -60.0, "2026-03-06");
+        let notes = get_notifications(&mut conn, uid).unwrap();
+        assert_eq!(notes.len(), 1);
+        assert_eq!((notes[0].budget_id, notes[0].threshold), (groceries.id, Some(50)));
+
+        // The warning before saving only counts budgets the expense would take over the limit.
+        let over = budgets_exceeded_by(&mut conn, uid, account, "Groceries", -100.0, at("2026-03-07")).unwrap();
+        assert_eq!(over.len(), 1);
+        assert_eq!((over[0].1, over[0].2), (21000, 20000));
+        assert!(budgets_exceeded_by(&mut conn, uid, account, "Groceries", -80.0, at("2026-03-07")).unwrap().is_empty());
+        assert!(budgets_exceeded_by(&mut conn, uid, account, "Groceries", 500.0, at("2026-03-07")).unwrap().is_empty());
+
+        spend(&mut conn, -100.0, "2026-03-07");
+        spend(&mut conn, -5.0, "2026-03-08");
+        let mut thresholds: Vec<Option<i32>> = get_notifications(&mut conn, uid).unwrap().iter().map(|n| n.threshold).collect();
+        thresholds.sort();
+        assert_eq!(thresholds, vec![Some(50), Some(80), Some(100)]);
+        assert!(get_notifications(&mut conn, partner).unwrap().is_empty());
+
+        // Moving money out of the account is not spending, even under a budgeted category.
+        create_account(&mut conn, "Savings", "bank", 0.0, uid).unwrap();
+        let savings = get_user_accounts(&mut conn, uid).unwrap().into_iter().find(|a| a.name == "Savings").unwrap().id;
+        create_budget(&mut conn, budget(uid, "Transfer", 1000, "100")).unwrap();
+        create_transfer(&mut conn, account, savings, 500.0, "2026-03-09 12:00:00".to_string()).unwrap();
+        let leg = get_user_transactions(&mut conn, uid).unwrap().into_iter().find(|t| t.is_transfer && t.amount < 0.0).unwrap();
+        update_transaction(&mut conn, leg.id, account, -600.0, leg.category.clone(), leg.date.clone()).unwrap();
+        assert_eq!(get_notifications(&mut conn, uid).unwrap().len(), 3);
+
+        // Alerts are stamped with the time the caller passes in.
+        create_budget(&mut conn, budget(uid, "Books", 1000, "100")).unwrap();
+        create_transaction_as(&mut conn, uid, account, 0, -15.0, "Books".to_string(), "2026-03-10 12:00:00".to_string(), at("2026-03-11")).unwrap();
+        let books = get_notifications(&mut conn, uid).unwrap().into_iter().find(|n| n.message.starts_with("Books")).unwrap();
+        assert_eq!(books.created_at, "2026-03-11 09:00:00");
+        dismiss_notification(&mut conn, uid, books.id).unwrap();
+
+        // A new month alerts again; an edit that moves spending into it counts too.
+        spend(&mut conn, -10.0, "2026-04-02");
+        let april: Vec<Transaction> = get_user_transactions(&mut conn, uid).unwrap().into_iter().filter(|t| t.date.starts_with("2026-04")).collect();
+        update_transaction(&mut conn, april[0].id, account, -170.0, "Groceries".to_string(), "2026-04-02 12:00:00".to_string()).unwrap();
+        assert_eq!(get_notifications(&mut conn, uid).unwrap().len(), 5);
+        assert_eq!(count_unread_notifications(&mut conn, uid).unwrap(), 5);
+
+        let first = get_notifications(&mut conn, uid).unwrap()[0].id;
+        assert!(matches!(mark_notification_read(&mut conn, partner, first), Err(FinancerError::NotFound(_))));
+        mark_notification_read(&mut conn, uid, first).unwrap();
+        assert_eq!(count_unread_notifications(&mut conn, uid).unwrap(), 4);
+        dismiss_notification(&mut conn, uid, first).unwrap();
+        assert_eq!(get_notifications(&mut conn, uid).unwrap().len(), 4);
+        mark_all_notifications_read(&mut conn, uid).unwrap();
+        assert_eq!(count_unread_notifications(&mut conn, uid).unwrap(), 0);
+
+        // Recurring posts are checked the same way.
+        create_budget(&mut conn, budget(uid, "Coffee", 3000, "100")).unwrap();
+        create_recurring_transaction(&mut conn, daily_item(uid, account, None, Some(3))).unwrap();
+        process_due_recurring(&mut conn, uid, at("2026-01-05")).unwrap();
+        assert!(get_notifications(&mut conn, uid).unwrap().iter().any(|n| n.message.starts_with("Coffee budget reached 100%")));
+
+        delete_budget(&mut conn, groceries.id.unwrap()).unwrap();
+        assert_eq!(get_notifications(&mut conn, uid).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_budget_scopes_cover_categories_accounts_and_custom_ranges() {
+        use financer::budgeting::{budget_range, is_within};
+
+        let mut conn = get_test_connection();
+        create_user(&mut conn, "scoped", "pass", None).unwrap();
+        let uid = get_userid_by_username(&mut conn, "scoped").unwrap().id;
+        create_user(&mut conn, "stranger", "pass", None).unwrap();
+        let stranger = get_userid_by_username(&mut conn, "stranger").unwrap().id;
+        create_account(&mut conn, "Checking", "bank", 1000.0, uid).unwrap();
+        create_account(&mut conn, "Card", "credit", 500.0, uid).unwrap();
+        create_account(&mut conn, "Theirs", "bank", 100.0, stranger).unwrap();
+        let accounts = get_user_accounts(&mut conn, uid).unwrap();
+        let checking = accounts.iter().find(|a| a.name == "Checking").unwrap().id;
+        let card = accounts.iter().find(|a| a.name == "Card").unwrap().id;
+        let theirs = get_user_accounts(&mut conn, stranger).unwrap()[0].id;
+
+        assert!(is_within("Food:Groceries:Organic", "Food"));
+        assert!(!is_within("Foodie", "Food"));
+
+        for (acct, amt, cat, when) in [
+            (checking, -40.0, "Food:Groceries", "2026-06-05"),
+            (card, -25.0, "Food:Dining", "2026-06-06"),
+            (checking, -10.0, "Food", "2026-06-07"),
+            (checking, -99.0, "Foodie", "2026-06-07"),
+            (card, -300.0, "Travel", "2026-06-04"),
+            (card, -200.0, "Travel", "2026-06-20"),
+            (checking, -150.0, "Lodging", "2026-06-10"),
+        ] {
+            create_transaction(&mut conn, acct, 0, amt, cat.to_string(), format!("{} 12:00:00", when)).unwrap();
+        }
+
+        let new_budget = |cat: &str, limit_cents: i32| NewBudget {
+            user_id: uid,
+            category: cat.to_string(),
+            limit_cents,
+            period: Period::Monthly.to_str().to_string(),
+            target_type: TargetType::Expense.to_str().to_string(),
+            rollover_mode: RolloverMode::None.to_str().to_string(),
+            rollover_cap_cents: None,
+            alert_thresholds: "100".to_string(),
+            name: None,
+            include_subcategories: false,
+            starts_on: None,
+            ends_on: None,
+        };
+        let june = budget_range(&create_budget(&mut conn, new_budget("Misc", 100)).unwrap(), NaiveDate::from_ymd_opt(2026, 6, 15).unwrap(), 0);
+
+        let mut food = new_budget("Food", 10000);
+        food.include_subcategories = true;
+        let food = create_budget(&mut conn, food).unwrap();
+        assert_eq!(get_budget_spend(&mut conn, &food, june.0, june.1).unwrap(), -7500);
+        set_budget_scope(&mut conn, uid, food.id.unwrap(), &[], &[checking]).unwrap();
+        assert_eq!(get_budget_spend(&mut conn, &food, june.0, june.1).unwrap(), -5000);
+        assert!(matches!(set_budget_scope(&mut conn, uid, food.id.unwrap(), &[], &[theirs]), Err(FinancerError::PermissionDenied(_))));
+        assert!(matches!(set_budget_scope(&mut conn, stranger, food.id.unwrap(), &[], &[]), Err(FinancerError::NotFound(_))));
+
+        let mut half_dated = new_budget("Travel", 40000);
+        half_dated.starts_on = Some("2026-06-03".to_string());
+        assert!(matches!(create_budget(&mut conn, half_dated), Err(FinancerError::Validation(_))));
+        let mut backwards = new_budget("Travel", 40000);
+        backwards.starts_on = Some("2026-06-17".to_string());
+        backwards.ends_on = Some("2026-06-03".to_string());
+        assert!(matches!(create_budget(&mut conn, backwards), Err(FinancerError::Validation(_))));
+
+        let mut trip = new_budget("Travel", 40000);
+        trip.name = Some("Trip".to_string());
+        trip.starts_on = Some("2026-06-03".to_string());
+        trip.ends_on = Some("2026-06-17".to_string());
+        trip.rollover_mode = RolloverMode::Surplus.to_str().to_string();
+        assert!(matches!(create_budget(&mut conn, trip), Err(FinancerError::Validation(_))));
+
+        let mut trip = new_budget("Travel", 40000);
+        trip.name = Some("Trip".to_string());
+        trip.starts_on = Some("2026-06-03".to_string());
+        trip.ends_on = Some("2026-06-17".to_string());
+        let trip = create_budget(&mut conn, trip).unwrap();
+        assert_eq!(trip.display_name(), "Trip");
+        set_budget_scope(&mut conn, uid, trip.id.unwrap(), &["Lodging".to_string(), "Travel".to_string()], &[]).unwrap();
+        assert_eq!(get_budget_scope(&mut conn, &trip).unwrap().categories, vec!["Travel".to_string(), "Lodging".to_string()]);
+        // A budget with its own dates covers just them, whichever period is asked for.
+        let range = budget_range(&trip, NaiveDate::from_ymd_opt(2026, 9, 1).unwrap(), 3);
+        assert_eq!(range, (at("2026-06-03") - Duration::hours(9), at("2026-06-18") - Duration::hours(9)));
+        assert_eq!(get_budget_spend(&mut conn, &trip, range.0, range.1).unwrap(), -45000);
+
+        // Alerts and the pre-save warning follow the scope and the dates.
+        create_transaction(&mut conn, checking, 0, -10.0, "Lodging".to_string(), "2026-06-11 12:00:00".to_string()).unwrap();
+        create_transaction(&mut conn, checking, 0, -10.0, "Lodging".to_string(), "2026-06-25 12:00:00".to_string()).unwrap();
+        let notes = get_notifications(&mut conn, uid).unwrap();
+        assert_eq!(notes.iter().filter(|n| n.budget_id == trip.id).count(), 1);
+        assert!(notes.iter().any(|n| n.message.starts_with("Trip budget reached 100%")));
+        assert_eq!(budgets_exceeded_by(&mut conn, uid, card, "Lodging", -1.0, at("2026-06-12")).unwrap().len(), 1);
+        assert!(budgets_exceeded_by(&mut conn, uid, card, "Lodging", -1.0, at("2026-06-25")).unwrap().is_empty());
+
+        delete_budget(&mut conn, trip.id.unwrap()).unwrap();
+        delete_user_and_all_data(&mut conn, uid).unwrap();
+    }
+
+    #[test]
+    fn test_budget_history_variance_and_streak() {
+        use financer::budgeting::performance_streak;
+
+        let mut conn = get_test_connection();
+        create_user(&mut conn, "history", "pass", None).unwrap();
+        let uid = get_userid_by_username(&mut conn, "history").unwrap().id;
+        create_account(&mut conn, "Main", "bank", 5000.0, uid).unwrap();
+        let account = get_user_accounts(&mut conn, uid).unwrap()[0].id;
+
+        let new_budget = |cat: &str, limit_cents: i32, target: TargetType| NewBudget {
+            user_id: uid,
+            category: cat.to_string(),
+            limit_cents,
+            period: Period::Monthly.to_str().to_string(),
+            target_type: target.to_str().to_string(),
+            rollover_mode: RolloverMode::None.to_str().to_string(),
+            rollover_cap_cents: None,
+            alert_thresholds: String::new(),
+            name: None,
+            include_subcategories: false,
+            starts_on: None,
+            ends_on: None,
+        };
+        let groceries = create_budget(&mut conn, new_budget("Groceries", 20000, TargetType::Expense)).unwrap();
+        let salary = create_budget(&mut conn, new_budget("Salary", 100000, TargetType::Income)).unwrap();
+        for (amt, cat, when) in [
+            (-150.0, "Groceries", "2026-01-10"),
+            (-250.0, "Groceries", "2026-02-10"),
+            (-100.0, "Groceries", "2026-03-10"),
+            (-180.0, "Groceries", "2026-04-10"),
+            (-300.0, "Groceries", "2026-05-10"),
+            (1200.0, "Salary", "2026-04-01"),
+        ] {
+            create_transaction(&mut conn, account, 0, amt, cat.to_string(), format!("{} 12:00:00", when)).unwrap();
+        }
+
+        let today = NaiveDate::from_ymd_opt(2026, 5, 15).unwrap();
+        let history = get_budget_history(&mut conn, &groceries, today, 5).unwrap();
+        assert_eq!(history.len(), 5);
+        assert_eq!(history[0].start, at("2026-01-01") - Duration::hours(9));
+        let actual: Vec<i64> = history.iter().map(|p| p.actual).collect();
+        assert_eq!(actual, vec![15000, 25000, 10000, 18000, 30000]);
+        assert_eq!(history[0].variance(TargetType::Expense), 5000);
+        assert_eq!(history[1].variance(TargetType::Expense), -5000);
+        assert_eq!(history[1].variance_percent(TargetType::Expense), Some(-25.0));
+        assert!(!history[1].on_target(TargetType::Expense));
+
+        // May is still running, so the streak is March and April under budget.
+        assert_eq!(performance_streak(&history, TargetType::Expense, at("2026-05-15")), Some((true, 2)));
+        assert_eq!(performance_streak(&history[..2], TargetType::Expense, at("2026-05-15")), Some((false, 1)));
+        assert_eq!(performance_streak(&history, TargetType::Expense, at("2026-01-15")), None);
+
+        let income = get_budget_history(&mut conn, &salary, today, 2).unwrap();
+        assert_eq!((income[0].actual, income[0].variance(TargetType::Income)), (120000, 20000));
+        assert_eq!(income[1].variance(TargetType::Income), -100000);
+
+        let mut trip = new_budget("Groceries", 5000, TargetType::Expense);
+        trip.starts_on = Some("2026-03-01".to_string());
+        trip.ends_on = Some("2026-03-31".to_string());
+        let trip = create_budget(&mut conn, trip).unwrap();
+        let once = get_budget_history(&mut conn, &trip, today, 6).unwrap();
+        assert_eq!(once.len(), 1);
+        assert_eq!((once[0].budgeted, once[0].actual), (5000, 10000));
+    }
+
+    #[test]
+    fn test_budget_templates_and_planners() {
+        use financer::budgeting::{drafts_from_average_spend, fifty_thirty_twenty, recent_months, split_income, BudgetDraft};
+
+        let mut conn = get_test_connection();
+        create_user(&mut conn, "planner", "pass", None).unwrap();
+        let uid = get_userid_by_username(&mut conn, "planner").unwrap().id;
+        create_account(&mut conn, "Checking", "bank", 1000.0, uid).unwrap();
+        create_account(&mut conn, "Savings", "bank", 0.0, uid).unwrap();
+        let accounts_list = get_user_accounts(&mut conn, uid).unwrap();
+        let (checking, savings) = (accounts_list[0].id, accounts_list[1].id);
+
+        for (amt, cat, when) in [
+            (3000.0, "Salary", "2026-02-01"),
+            (3000.0, "Salary", "2026-03-01"),
+            (-250.0, "Groceries", "2026-02-10"),
+            (-301.0, "Groceries", "2026-03-10"),
+            (-40.0, "Dining", "2026-03-12"),
+            (25.0, "Refunds", "2026-03-15"),
+            (-999.0, "Groceries", "2026-04-02"),
+        ] {
+            create_transaction(&mut conn, checking, 0, amt, cat.to_string(), format!("{} 12:00:00", when)).unwrap();
+        }
+        create_transfer(&mut conn, checking, savings, 500.0, "2026-03-20 12:00:00".to_string()).unwrap();
+
+        // Two whole months before April: refunds count as income, transfers and spending don't.
+        let (start, end) = recent_months(NaiveDate::from_ymd_opt(2026, 4, 15).unwrap(), 2);
+        assert_eq!((start, end), (at("2026-02-01") - Duration::hours(9), at("2026-04-01") - Duration::hours(9)));
+        assert_eq!(get_income_for_period(&mut conn, uid, start, end).unwrap(), 602500);
+
+        let mut shares = fifty_thirty_twenty();
+        shares[0].categories = vec!["Groceries".to_string(), "Rent".to_string()];
+        shares[1].categories = vec!["Dining".to_string()];
+        let split = split_income(400000, &shares);
+        assert_eq!(split.len(), 2);
+        assert_eq!((split[0].categories.clone(), split[0].limit_cents), (vec!["Groceries".to_string(), "Rent".to_string()], 200000));
+        assert_eq!(split[1].limit_cents, 120000);
+
+        let spend = get_spend_by_category_period(&mut conn, uid, start, end).unwrap();
+        let averaged = drafts_from_average_spend(&spend, 2);
+        let limits: Vec<(String, i32)> = averaged.iter().map(|d| (d.categories[0].clone(), d.limit_cents)).collect();
+        assert_eq!(limits, vec![("Dining".to_string(), 2000), ("Groceries".to_string(), 27600)]);
+
+        // The 50/30/20 budgets are skipped because Groceries and Dining already have one.
+        assert_eq!(create_budgets_from_drafts(&mut conn, uid, &averaged).unwrap(), 2);
+        assert_eq!(create_budgets_from_drafts(&mut conn, uid, &split).unwrap(), 0);
+        let rent = BudgetDraft { categories: vec!["Rent".to_string(), "Utilities".to_string()], ..split[0].clone() };
+        assert_eq!(create_budgets_from_drafts(&mut conn, uid, &[rent]).unwrap(), 1);
+        let budgets_list = get_user_budgets(&mut conn, uid).unwrap();
+        let rent_budget = budgets_list.iter().find(|b| b.category == "Rent").unwrap();
+        assert_eq!(get_budget_scope(&mut conn, rent_budget).unwrap().categories, vec!["Rent".to_string(), "Utilities".to_string()]);
+
+        let now = at("2026-04-15");
+        assert!(matches!(save_budget_template(&mut conn, uid, "  ", &split, now), Err(FinancerError::Validation(_))));
+        assert!(matches!(save_budget_template(&mut conn, uid, "Empty", &[], now), Err(FinancerError::Validation(_))));
+        let current = get_budget_drafts(&mut conn, uid).unwrap();
+        assert_eq!(current.len(), 3);
+        let template = save_budget_template(&mut conn, uid, "Spring", &current, now).unwrap();
+        assert!(matches!(save_budget_template(&mut conn, uid, "Spring", &current, now), Err(FinancerError::Conflict(_))));
+        assert_eq!(get_budget_templates(&mut conn, uid).unwrap().len(), 1);
+
+        let mut restored = get_budget_template_drafts(&mut conn, uid, template.id).unwrap();
+        let mut expected = current.clone();
+        restored.sort_by(|a, b| a.categories.cmp(&b.categories));
+        expected.sort_by(|a, b| a.categories.cmp(&b.categories));
+        assert_eq!(restored, expected);
+
+        // Applying the template to a fresh user recreates every budget.
+        create_user(&mut conn, "other", "pass", None).unwrap();
+        let other = get_userid_by_username(&mut conn, "other").unwrap().id;
+        assert!(matches!(get_budget_template_drafts(&mut conn, other, template.id), Err(FinancerError::NotFound(_))));
+        assert!(matches!(delete_budget_template(&mut conn, other, template.id), Err(FinancerError::NotFound(_))));
+        assert_eq!(create_budgets_from_drafts(&mut conn, other, &restored).unwrap(), 3);
+
+        delete_budget_template(&mut conn, uid, template.id).unwrap();
+        assert!(get_budget_templates(&mut conn, uid).unwrap().is_empty());
+        save_budget_template(&mut conn, uid, "Again", &current, now).unwrap();
+        delete_user_and_all_data(&mut conn, uid).unwrap();
+        assert!(get_budget_templates(&mut conn, uid).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_budget_pace_projection() {
+        use financer::budgeting::{project_pace, PaceStatus};
+        use financer::schedule::expand_occurrences;
+        use std::collections::HashMap;
+
+        let mut conn = get_test_connection();
+        create_user(&mut conn, "pace", "pass", None).unwrap();
+        let uid = get_userid_by_username(&mut conn, "pace").unwrap().id;
+        create_account(&mut conn, "Main", "bank", 1000.0, uid).unwrap();
+        let account = get_user_accounts(&mut conn, uid).unwrap()[0].id;
+
+        create_recurring_transaction(&mut conn, daily_item(uid, account, None, None)).unwrap();
+        assert_eq!(process_due_recurring(&mut conn, uid, at("2026-01-10")).unwrap(), 10);
+        create_transaction(&mut conn, account, 0, -200.0, "Coffee".to_string(), "2026-01-05 12:00:00".to_string()).unwrap();
+        let budget = create_budget(&mut conn, NewBudget {
+            user_id: uid,
+            category: "Coffee".to_string(),
+            limit_cents: 100000,
+            period: Period::Monthly.to_str().to_string(),
+            target_type: TargetType::Expense.to_str().to_string(),
+            rollover_mode: RolloverMode::None.to_str().to_string(),
+            rollover_cap_cents: None,
+            alert_thresholds: String::new(),
+            name: None,
+            include_subcategories: false,
+            starts_on: None,
+            ends_on: None,
+        }).unwrap();
+
+        let (start, end) = (at("2026-01-01") - Duration::hours(9), at("2026-02-01") - Duration::hours(9));
+        assert_eq!(get_budget_spend(&mut conn, &budget, start, end).unwrap(), -30000);
+        assert_eq!(get_budget_recurring_spend(&mut conn, &budget, start, end).unwrap(), -10000);
+
+        // Ten days in, the coffee still due this month is known rather than extrapolated.
+        let now = at("2026-01-11") - Duration::hours(9);
+        let items = get_user_recurring_transactions(&mut conn, uid).unwrap();
+        let upcoming: Vec<_> = expand_occurrences(&items, &[], &HashMap::new(), now, end);
+        assert_eq!(upcoming.len(), 21);
+        let upcoming: i64 = upcoming.iter().map(|o| (-o.amount * 100.0).round() as i64).sum();
+
+        let pace = project_pace(30000, 10000, upcoming, 100000, (start, end), now).unwrap();
+        assert_eq!(pace.projected, 30000 + 42000 + 21000);
+        assert_eq!(pace.days_left, 21);
+        assert_eq!(pace.safe_daily, 49000 / 21);
+        assert_eq!(pace.status, PaceStatus::OnTrack);
+
+        assert_eq!(project_pace(30000, 10000, upcoming, 90000, (start, end), now).unwrap().status, PaceStatus::AtRisk);
+        let over = project_pace(30000, 10000, upcoming, 25000, (start, end), now).unwrap();
+        assert_eq!((over.status, over.safe_daily), (PaceStatus::Over, 0));
+
+        // Nothing to project outside the period.
+        assert!(project_pace(30000, 10000, 0, 100000, (start, end), end).is_none());
+        assert!(project_pace(0, 0, 0, 100000, (start, end), start - Duration::days(1)).is_none());
+    }
+
+    #[test]
+    fn test_monthly_savings_rate_excludes_transfers() {
+        use financer::budgeting::overall_savings_rate;
+
+        let mut conn = get_test_connection();
+        create_user(&mut conn, "saver", "pass", None).unwrap();
+        let uid = get_userid_by_username(&mut conn, "saver").unwrap().id;
+        create_account(&mut conn, "Checking", "bank", 0.0, uid).unwrap();
+        create_account(&mut conn, "Savings", "bank", 0.0, uid).unwrap();
+        let accounts_list = get_user_accounts(&mut conn, uid).unwrap();
+        let (checking, savings) = (accounts_list[0].id, accounts_list[1].id);
+
+        for (amt, cat, when) in [
+            (3000.0, "Salary", "2026-01-01"),
+            (-600.0, "Groceries", "2026-01-12"),
+            (2000.0, "Salary", "2026-02-01"),
+            (-2500.0, "Rent", "2026-02-03"),
+        ] {
+            create_transaction(&mut conn, checking, 0, amt, cat.to_string(), format!("{} 12:00:00", when)).unwrap();
+        }
+        create_transfer(&mut conn, checking, savings, 1000.0, "2026-01-20 12:00:00".to_string()).unwrap();
+        create_recurring_transfer(&mut conn, NewRecurringTransfer {
+            user_id: uid,
+            from_account_id: savings,
+            to_account_id: checking,
+            amount: 100.0,
+            next_run_at: "2026-02-10 09:00:00".to_string(),
+            frequency: Period::Monthly.to_str().to_string(),
+            recurrence_rule: None,
+            ends_at: None,
+            max_occurrences: Some(1),
+            requires_approval: false,
+            roll_convention: "none".to_string(),
+        }).unwrap();
+        assert_eq!(process_due_recurring(&mut conn, uid, at("2026-02-10")).unwrap(), 1);
+
+        // Both legs of both transfers are flagged; nothing else is.
+        let all = get_user_transactions(&mut conn, uid).unwrap();
+        assert_eq!(all.iter().filter(|t| t.is_transfer).count(), 4);
+        assert!(all.iter().filter(|t| t.is_transfer).all(|t| t.category == "Transfer"));
+
+        let report = get_monthly_savings(&mut conn, uid, NaiveDate::from_ymd_opt(2026, 3, 15).unwrap(), 3).unwrap();
+        let rows: Vec<(String, i64, i64)> = report.iter().map(|m| (m.month.to_string(), m.income, m.expenses)).collect();
+        assert_eq!(rows, vec![
+            ("2026-01-01".to_string(), 300000, 60000),
+            ("2026-02-01".to_string(), 200000, 250000),
+            ("2026-03-01".to_string(), 0, 0),
+        ]);
+        assert_eq!(report[0].rate(), Some(80.0));
+        assert_eq!((report[1].saved(), report[1].rate()), (-50000, Some(-25.0)));
+        assert_eq!(report[2].rate(), None);
+        assert_eq!(overall_savings_rate(&report), Some(38.0));
+        assert_eq!(overall_savings_rate(&[]), None);
+    }
+
+    #[test]
+    fn test_savings_goals_track_linked_accounts() {
+        use financer::goals::months_until;
+
+        let mut conn = get_test_connection();
+        create_user(&mut conn, "goals", "pass", None).unwrap();
+        let uid = get_userid_by_username(&mut conn, "goals").unwrap().id;
+        create_user(&mut conn, "stranger", "pass", None).unwrap();
+        let stranger = get_userid_by_username(&mut conn, "stranger").unwrap().id;
+        create_account(&mut conn, "Checking", "bank", 1000.0, uid).unwrap();
+        create_account(&mut conn, "Savings", "bank", 2000.0, uid).unwrap();
+        create_account(&mut conn, "Brokerage", "investment", 500.0, uid).unwrap();
+        create_account(&mut conn, "Theirs", "bank", 50.0, stranger).unwrap();
+        let mine = get_user_accounts(&mut conn, uid).unwrap();
+        let (checking, savings, brokerage) = (mine[0].id, mine[1].id, mine[2].id);
+        let theirs = get_user_accounts(&mut conn, stranger).unwrap()[0].id;
+
+        for month in ["2026-01-15", "2026-02-15", "2026-03-15"] {
+            create_transfer(&mut conn, checking, savings, 300.0, format!("{} 12:00:00", month)).unwrap();
+        }
+
+        let goal = |goal_name: &'static str, target_date: &'static str, expected_rate: f64| NewSavingsGoal {
+            user_id: uid,
+            name: goal_name,
+            target_cents: 1500000,
+            target_date,
+            expected_rate,
+            created_at: "2026-04-10 09:00:00",
+        };
+        assert!(matches!(create_savings_goal(&mut conn, goal(" ", "2027-06-30", 0.0), &[savings]), Err(FinancerError::Validation(_))));
+        assert!(matches!(create_savings_goal(&mut conn, goal("Trip", "June", 0.0), &[savings]), Err(FinancerError::Validation(_))));
+        assert!(matches!(create_savings_goal(&mut conn, goal("Trip", "2027-06-30", 1.5), &[savings]), Err(FinancerError::Validation(_))));
+        assert!(matches!(create_savings_goal(&mut conn, goal("Trip", "2027-06-30", 0.0), &[]), Err(FinancerError::Validation(_))));
+        assert!(matches!(create_savings_goal(&mut conn, goal("Trip", "2027-06-30", 0.0), &[theirs]), Err(FinancerError::PermissionDenied(_))));
+
+        let created = create_savings_goal(&mut conn, goal(" Emergency fund ", "2027-06-30", 0.0), &[savings, brokerage]).unwrap();
+        assert_eq!(created.name, "Emergency fund");
+        assert!(matches!(create_savings_goal(&mut conn, goal("Emergency fund", "2028-01-01", 0.0), &[savings]), Err(FinancerError::Conflict(_))));
+
+        // 2900 in savings after the transfers plus 500 in the brokerage; 300 a month went in.
+        let today = NaiveDate::from_ymd_opt(2026, 4, 10).unwrap();
+        let goals = get_savings_goals(&mut conn, uid, today).unwrap();
+        assert_eq!(goals.len(), 1);
+        let progress = &goals[0];
+        assert_eq!(progress.account_ids, vec![savings, brokerage]);
+        assert_eq!((progress.saved, progress.monthly_pace), (340000, 30000));
+        assert!(!progress.is_reached());
+
+        // 11600 still to save over 14 months.
+        assert_eq!(months_until(today, NaiveDate::from_ymd_opt(2027, 6, 30).unwrap()), 14);
+        assert_eq!(progress.required_monthly(today), Some(82858));
+        // At 300 a month that takes 39 months.
+        assert_eq!(progress.projected_completion(today), NaiveDate::from_ymd_opt(2029, 7, 10));
+
+        assert_eq!(months_until(NaiveDate::from_ymd_opt(2026, 1, 31).unwrap(), NaiveDate::from_ymd_opt(2026, 2, 28).unwrap()), 0);
+        assert_eq!(months_until(NaiveDate::from_ymd_opt(2026, 5, 1).unwrap(), NaiveDate::from_ymd_opt(2026, 3, 1).unwrap()), 0);
+
+        assert!(matches!(delete_savings_goal(&mut conn, stranger, created.id), Err(FinancerError::NotFound(_))));
+        delete_savings_goal(&mut conn, uid, created.id).unwrap();
+        assert!(get_savings_goals(&mut conn, uid, today).unwrap().is_empty());
+
+        create_savings_goal(&mut conn, goal("House", "2030-01-01", 0.05), &[savings]).unwrap();
+        delete_user_and_all_data(&mut conn, uid).unwrap();
+        assert!(get_savings_goals(&mut conn, uid, today).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_balance_snapshots_and_net_worth_history() {
+        use financer::net_worth::{month_ends, net_worth_by_day, reconstruct_daily_balances, year_ends};
+        use financer::schema::balance_snapshots;
+
+        let d = |s: &str| NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap();
+        // Working back from 1000 now: 01-05 hasn't been reached by 01-03, and 300 came in on 01-02.
+        assert_eq!(
+            reconstruct_daily_balances(1000, &[(d("2026-01-02"), 300), (d("2026-01-05"), -100)], d("2026-01-01"), d("2026-01-03")),
+            vec![(d("2026-01-01"), 800), (d("2026-01-02"), 1100), (d("2026-01-03"), 1100)]
+        );
+
+        let mut conn = get_test_connection();
+        create_user(&mut conn, "worth", "pass", None).unwrap();
+        let uid = get_userid_by_username(&mut conn, "worth").unwrap().id;
+        create_user(&mut conn, "other", "pass", None).unwrap();
+        let other = get_userid_by_username(&mut conn, "other").unwrap().id;
+        create_account(&mut conn, "Checking", "bank", 1000.0, uid).unwrap();
+        create_account(&mut conn, "Card", "credit", 0.0, uid).unwrap();
+        create_account(&mut conn, "Theirs", "bank", 75.0, other).unwrap();
+        let mine = get_user_accounts(&mut conn, uid).unwrap();
+        let (checking, card) = (mine[0].id, mine[1].id);
+        create_contact(&mut conn, "Shop", uid).unwrap();
+        let shop: i32 = contacts.filter(name.eq("Shop")).select(id).first(&mut conn).unwrap();
+
+        create_transaction(&mut conn, checking, shop, 500.0, "Salary".to_string(), "2025-12-20 12:00:00".to_string()).unwrap();
+        create_transaction(&mut conn, checking, shop, -200.0, "Rent".to_string(), "2026-01-05 12:00:00".to_string()).unwrap();
+        create_transaction(&mut conn, card, shop, -300.0, "Shopping".to_string(), "2026-01-10 12:00:00".to_string()).unwrap();
+        create_transaction(&mut conn, checking, shop, 100.0, "Refund".to_string(), "2026-01-10 15:00:00".to_string()).unwrap();
+        // Scheduled ahead, so already in the balance but not in any past day.
+        create_transaction(&mut conn, checking, shop, -50.0, "Gym".to_string(), "2026-02-01 09:00:00".to_string()).unwrap();
+
+        // Each account starts at its first transaction; the one without any just gets today.
+        let today = d("2026-01-15");
+        assert_eq!(update_balance_snapshots(&mut conn, today).unwrap(), 27 + 6 + 1);
+        let snapshots = get_balance_snapshots(&mut conn, uid, None).unwrap();
+        assert_eq!(snapshots.len(), 27 + 6);
+        assert!(snapshots.iter().all(|s| s.account_id == checking || s.account_id == card));
+        let balance_on = |snapshots: &[BalanceSnapshot], account: i32, day: &str| {
+            snapshots.iter().find(|s| s.account_id == account && s.day == day).map(|s| s.balance_cents)
+        };
+        assert_eq!(balance_on(&snapshots, checking, "2025-12-20"), Some(150000));
+        assert_eq!(balance_on(&snapshots, checking, "2026-01-05"), Some(130000));
+        assert_eq!(balance_on(&snapshots, checking, "2026-01-15"), Some(140000));
+        assert_eq!(balance_on(&snapshots, card, "2026-01-09"), None);
+        assert_eq!(balance_on(&snapshots, card, "2026-01-10"), Some(-30000));
+
+        let points = net_worth_by_day(&snapshots);
+        assert_eq!((points[0].day, points[0].assets, points[0].liabilities), (d("2025-12-20"), 150000, 0));
+        let latest = points.last().unwrap();
+        assert_eq!((latest.assets, latest.liabilities, latest.net()), (140000, 30000, 110000));
+        let months: Vec<(NaiveDate, i64)> = month_ends(&points).iter().map(|p| (p.day, p.net())).collect();
+        assert_eq!(months, vec![(d("2025-12-31"), 150000), (d("2026-01-15"), 110000)]);
+        let years: Vec<NaiveDate> = year_ends(&points).iter().map(|p| p.day).collect();
+        assert_eq!(years, vec![d("2025-12-31"), d("2026-01-15")]);
+
+        // The next day rewrites the last snapshot and adds the new one.
+        create_transaction(&mut conn, checking, shop, -25.0, "Lunch".to_string(), "2026-01-16 08:00:00".to_string()).unwrap();
+        assert_eq!(update_balance_snapshots(&mut conn, d("2026-01-16")).unwrap(), 2 * 3);
+        let recent = get_balance_snapshots(&mut conn, uid, Some(d("2026-01-15"))).unwrap();
+        assert_eq!(recent.len(), 4);
+        assert_eq!(balance_on(&recent, checking, "2026-01-15"), Some(140000));
+        assert_eq!(balance_on(&recent, checking, "2026-01-16"), Some(137500));
+
+        // Rebuilding replaces bad and stray snapshots.
+        diesel::update(
+            balance_snapshots::table
+                .filter(balance_snapshots::account_id.eq(checking))
+                .filter(balance_snapshots::day.eq("2025-12-25")),
+        )
+        .set(balance_snapshots::balance_cents.eq(0))
+        .execute(&mut conn)
+        .unwrap();
+        diesel::insert_into(balance_snapshots::table)
+            .values(&BalanceSnapshot { account_id: checking, day: "2025-01-01".to_string(), balance_cents: 1 })
+            .execute(&mut conn)
+            .unwrap();
+        assert_eq!(backfill_balance_snapshots(&mut conn, uid, d("2026-01-16")).unwrap(), 28 + 7);
+        let rebuilt = get_balance_snapshots(&mut conn, uid, None).unwrap();
+        assert_eq!(rebuilt.len(), 28 + 7);
+        assert_eq!(balance_on(&rebuilt, checking, "2025-12-25"), Some(150000));
+        assert_eq!(get_balance_snapshots(&mut conn, other, None).unwrap().len(), 2);
+
+        delete_user_and_all_data(&mut conn, uid).unwrap();
+        let left: i64 = balance_snapshots::table
+            .filter(balance_snapshots::account_id.eq_any([checking, card]))
+            .count()
+            .get_result(&mut conn)
+            .unwrap();
+        assert_eq!(left, 0);
+        assert_eq!(get_balance_snapshots(&mut conn, other, None).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_income_statement_and_category_trends() {
+        use financer::reports::percent_change;
+
+        let mut conn = get_test_connection();
+        create_user(&mut conn, "reports", "pass", None).unwrap();
+        let uid = get_userid_by_username(&mut conn, "reports").unwrap().id;
+        create_user(&mut conn, "stranger", "pass", None).unwrap();
+        let stranger = get_userid_by_username(&mut conn, "stranger").unwrap().id;
+        create_account(&mut conn, "Checking", "bank", 5000.0, uid).unwrap();
+        create_account(&mut conn, "Savings", "bank", 0.0, uid).unwrap();
+        create_account(&mut conn, "Theirs", "bank", 0.0, stranger).unwrap();
+        let mine = get_user_accounts(&mut conn, uid).unwrap();
+        let (checking, savings) = (mine[0].id, mine[1].id);
+        let theirs = get_user_accounts(&mut conn, stranger).unwrap()[0].id;
+        create_contact(&mut conn, "Shop", uid).unwrap();
+        let shop: i32 = contacts.filter(name.eq("Shop")).select(id).first(&mut conn).unwrap();
+
+        let add = |conn: &mut SqliteConnection, account: i32, amount: f32, cat: &str, when: &str| {
+            create_transaction(conn, account, shop, amount, cat.to_string(), format!("{} 12:00:00", when)).unwrap();
+        };
+        add(&mut conn, checking, 3000.0, "Salary", "2026-03-01");
+        add(&mut conn, checking, -1200.0, "Rent", "2026-03-02");
+        add(&mut conn, checking, -300.0, "Groceries", "2026-03-05");
+        add(&mut conn, checking, 500.0, "Freelance", "2026-03-10");
+        add(&mut conn, checking, -100.0, "Groceries", "2026-03-20");
+        add(&mut conn, checking, -50.0, "Transfer", "2026-03-21");
+        create_transfer(&mut conn, checking, savings, 400.0, "2026-03-15 12:00:00".to_string()).unwrap();
+        add(&mut conn, theirs, 777.0, "Salary", "2026-03-01");
+        add(&mut conn, checking, -500.0, "Groceries", "2026-02-10");
+        add(&mut conn, checking, -250.0, "Groceries", "2025-03-10");
+        add(&mut conn, checking, -999.0, "Groceries", "2026-04-01");
+
+        let today = NaiveDate::from_ymd_opt(2026, 3, 17).unwrap();
+        let statement = get_income_statement(&mut conn, uid, today).unwrap();
+        assert_eq!(statement.month, NaiveDate::from_ymd_opt(2026, 3, 1).unwrap());
+        assert_eq!(statement.income, vec![("Salary".to_string(), 300000), ("Freelance".to_string(), 50000)]);
+        assert_eq!(statement.expenses, vec![("Rent".to_string(), 120000), ("Groceries".to_string(), 40000)]);
+        assert_eq!((statement.total_income(), statement.total_expenses(), statement.net()), (350000, 160000, 190000));
+
+        let trends = get_category_trends(&mut conn, uid, today, 12).unwrap();
+        let order: Vec<(&str, bool)> = trends.iter().map(|t| (t.category.as_str(), t.is_income)).collect();
+        assert_eq!(order, vec![("Salary", true), ("Freelance", true), ("Rent", false), ("Groceries", false)]);
+        let groceries = &trends[3];
+        assert_eq!(groceries.amounts.len(), 12);
+        assert_eq!(&groceries.amounts[10..], &[50000, 40000]);
+        assert_eq!(groceries.amounts[..10].iter().sum::<i64>(), 0);
+        assert_eq!(groceries.year_ago.last(), Some(&25000));
+        assert_eq!(groceries.month_over_month(), Some(-10000));
+        assert_eq!(groceries.year_over_year(), Some(15000));
+        assert_eq!(percent_change(50000, -10000), Some(-20.0));
+        assert_eq!(percent_change(25000, 15000), Some(60.0));
+        assert_eq!(percent_change(0, 15000), None);
+        let salary = &trends[0];
+        assert_eq!((salary.latest(), salary.month_over_month(), salary.year_over_year()), (300000, Some(300000), Some(300000)));
+
+        // The month before only has the February groceries.
+        let february = get_income_statement(&mut conn, uid, NaiveDate::from_ymd_opt(2026, 2, 1).unwrap()).unwrap();
+        assert!(february.income.is_empty());
+        assert_eq!(february.net(), -50000);
+    }
+}
@@ -15,6 +15,12 @@ mod tests {
         compound_interest_future_value_with_contributions,
         ContributionFrequency,
         CompoundingFrequency,
+        required_monthly_contribution,
+        months_to_reach_target,
+        debt_payoff_order,
+        simulate_debt_payoff,
+        Debt,
+        PayoffStrategy,
     };
 
     #[test]
@@ -159,4 +165,98 @@ mod tests {
 
         assert!((fv - expected).abs() < 1e-9);
     }
+
+    #[test]
+    fn test_required_monthly_contribution_reaches_target() {
+        assert!((required_monthly_contribution(0.0, 1200.0, 0.0, 12) - 100.0).abs() < 1e-9);
+        assert_eq!(required_monthly_contribution(10000.0, 10000.0, 0.05, 12), 0.0);
+        assert_eq!(required_monthly_contribution(500.0, 1200.0, 0.05, 0), 700.0);
+
+        let pmt = required_monthly_contribution(1000.0, 10000.0, 0.05, 24);
+        let fv = compound_interest_future_value_with_contributions(
+            1000.0,
+            pmt,
+            ContributionFrequency::Monthly,
+            0.05,
+            CompoundingFrequency::Monthly,
+            2.0,
+        );
+        assert!((fv - 10000.0).abs() < 1e-6);
+        assert_eq!(months_to_reach_target(1000.0, 10000.0, pmt + 1e-6, 0.05, 1200), Some(24));
+    }
+
+    #[test]
+    fn test_months_to_reach_target() {
+        assert_eq!(months_to_reach_target(0.0, 1200.0, 100.0, 0.0, 1200), Some(12));
+        assert_eq!(months_to_reach_target(0.0, 1250.0, 100.0, 0.0, 1200), Some(13));
+        assert_eq!(months_to_reach_target(2000.0, 1000.0, 0.0, 0.0, 1200), Some(0));
+        assert_eq!(months_to_reach_target(0.0, 100.0, 0.0, 0.0, 1200), None);
+        assert_eq!(months_to_reach_target(0.0, 100.0, 10.0, 0.0, 5), None);
+    }
+
+    fn two_debts(small_apr: f64, large_apr: f64) -> Vec<Debt> {
+        vec![
+            Debt { name: "Card".to_string(), balance: 300.0, apr: small_apr, minimum_payment: 50.0 },
+            Debt { name: "Loan".to_string(), balance: 1000.0, apr: large_apr, minimum_payment: 50.0 },
+        ]
+    }
+
+    #[test]
+    fn test_debt_payoff_order_by_strategy() {
+        let debts = two_debts(0.10, 0.20);
+        assert_eq!(debt_payoff_order(&debts, PayoffStrategy::Snowball, &[]), vec![0, 1]);
+        assert_eq!(debt_payoff_order(&debts, PayoffStrategy::Avalanche, &[]), vec![1, 0]);
+        assert_eq!(debt_payoff_order(&debts, PayoffStrategy::Custom, &[1]), vec![1, 0]);
+        assert_eq!(debt_payoff_order(&debts, PayoffStrategy::Custom, &[5, 1, 1]), vec![1, 0]);
+    }
+
+    #[test]
+    fn test_snowball_rolls_freed_minimums_to_next_debt() {
+        let plan = simulate_debt_payoff(&two_debts(0.0, 0.0), 100.0, PayoffStrategy::Snowball, &[], 600);
+
+        // 200 a month: the card gets 150 until it's gone, then the loan gets all of it.
+        assert_eq!(plan.payoff_month, vec![Some(2), Some(7)]);
+        assert_eq!(plan.schedule.len(), 7);
+        assert_eq!(plan.schedule[0].payments[0].payment, 150.0);
+        assert_eq!(plan.schedule[2].payments.len(), 1);
+        assert_eq!(plan.schedule[2].payments[0].payment, 200.0);
+        assert_eq!(plan.schedule[6].payments[0].payment, 100.0);
+        assert_eq!(plan.total_interest(), 0.0);
+        assert_eq!(plan.total_paid(), 1300.0);
+        assert!(plan.is_complete());
+    }
+
+    #[test]
+    fn test_avalanche_pays_less_interest_than_snowball() {
+        let debts = two_debts(0.10, 0.20);
+        let snowball = simulate_debt_payoff(&debts, 100.0, PayoffStrategy::Snowball, &[], 600);
+        let avalanche = simulate_debt_payoff(&debts, 100.0, PayoffStrategy::Avalanche, &[], 600);
+        let custom = simulate_debt_payoff(&debts, 100.0, PayoffStrategy::Custom, &[1, 0], 600);
+
+        assert!(snowball.is_complete() && avalanche.is_complete());
+        assert!(avalanche.total_interest() < snowball.total_interest());
+        assert_eq!(custom, avalanche);
+        for plan in [&snowball, &avalanche] {
+            let principal: f64 = plan.schedule.iter().flat_map(|m| &m.payments).map(|p| p.principal).sum();
+            assert!((principal - 1300.0).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_debt_payoff_interest_and_unpayable_debt() {
+        let debt = Debt { name: "Card".to_string(), balance: 1000.0, apr: 0.12, minimum_payment: 100.0 };
+        let plan = simulate_debt_payoff(std::slice::from_ref(&debt), 0.0, PayoffStrategy::Avalanche, &[], 600);
+        let first = plan.schedule[0].payments[0];
+        assert!((first.interest - 10.0).abs() < 1e-9);
+        assert!((first.principal - 90.0).abs() < 1e-9);
+        assert!((first.remaining_balance - 910.0).abs() < 1e-9);
+        assert_eq!(plan.payoff_month, vec![Some(11)]);
+
+        // 2% a month on 1000 is more than the 10 minimum, so it never gets paid.
+        let stuck = Debt { apr: 0.24, minimum_payment: 10.0, ..debt };
+        let plan = simulate_debt_payoff(&[stuck], 0.0, PayoffStrategy::Snowball, &[], 120);
+        assert_eq!(plan.payoff_month, vec![None]);
+        assert_eq!(plan.schedule.len(), 120);
+        assert!(!plan.is_complete());
+    }
 }
\ No newline at end of file